@@ -0,0 +1,23 @@
+//! Embeds the version of rustc promptline is built with, for `--diagnose`
+
+use std::{env, process::Command};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=RUSTC");
+
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    // Without it the report just says the version is unknown
+    if let Some(version) = version {
+        println!(
+            "cargo:rustc-env=PROMPTLINE_RUSTC_VERSION={}",
+            version.trim()
+        );
+    }
+}
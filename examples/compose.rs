@@ -0,0 +1,42 @@
+//! A prompt of two segments, one of its own and promptline's cwd, rendered
+//! without the promptline binary. Run it with `cargo run --example compose`
+
+use std::sync::Arc;
+
+use promptline::{
+    registry, render, Color, Context, DecoratedString, RenderedSegment, Segment, SegmentError,
+};
+
+/// `hi ada`, greeting the user by `$USER`
+struct Greeting;
+
+impl Segment for Greeting {
+    fn name(&self) -> &str {
+        "greeting"
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        let user = ctx
+            .var("USER")
+            .ok_or_else(|| SegmentError::not_applicable(self.name(), "no $USER"))?;
+        let greeting = DecoratedString::new(format!("hi {user}")).colored(Color::Magenta);
+        Ok(greeting.into())
+    }
+}
+
+fn main() {
+    let ctx = Context::from_env();
+    let cwd = registry::default_segments(&ctx.config)
+        .into_iter()
+        .find(|segment| segment.name() == "cwd");
+
+    let mut segments: Vec<Arc<dyn Segment>> = vec![Arc::new(Greeting)];
+    segments.extend(cwd);
+
+    let shown: Vec<_> = segments
+        .iter()
+        .filter_map(|segment| segment.render(&ctx).ok())
+        .map(|rendered| render::ansi(&rendered))
+        .collect();
+    println!("[{}] $", shown.join("]-["));
+}
@@ -0,0 +1,602 @@
+use std::{error::Error, fmt};
+
+use crate::{decorated::Escaping, init};
+
+#[derive(Debug)]
+pub enum ArgsError {
+    MissingValue(String),
+    InvalidValue(String, String),
+    UnknownFlag(String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgsError::MissingValue(flag) => write!(f, "{flag} requires a value"),
+            ArgsError::InvalidValue(flag, value) => write!(f, "invalid value {value} for {flag}"),
+            ArgsError::UnknownFlag(flag) => write!(f, "unknown flag {flag}"),
+        }
+    }
+}
+
+impl Error for ArgsError {}
+
+#[derive(Debug, Default, PartialEq)]
+pub enum Command {
+    #[default]
+    Prompt,
+    /// Runs every segment and prints a table of how each one went
+    Explain,
+    /// Renders every segment repeatedly and reports timing statistics
+    Bench,
+    /// Prints the snippet that sets up the given shell's prompt
+    Init(String),
+    /// Lists the problems in the config file, exiting with 1 if there are any
+    CheckConfig,
+    /// Renders the prompt against sample data instead of the environment
+    Preview,
+    /// Prints the completion script for the given shell
+    Completions(String),
+    /// Prints `USAGE`
+    Help,
+    /// Prints the version
+    Version,
+    /// Prints a report on the environment, config and segments for bug reports
+    Diagnose,
+    /// Caches what the expensive segments need for the cwd, for the shell to
+    /// run in the background when it changes directory
+    Prefetch,
+    /// Prints only the prompt char, colored by the exit status, to replace a
+    /// prompt once its command is accepted
+    Transient,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Which segments to render, for shells that draw the prompt in two phases
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Phase {
+    /// Every segment, in one go
+    #[default]
+    All,
+    /// `--fast`, only the cheap segments, with the result of `--slow` from the
+    /// last prompt passed in through `--async-result`
+    Fast,
+    /// `--slow`, only the expensive segments, printed as a fragment to pass to
+    /// `--async-result`
+    Slow,
+}
+
+impl Phase {
+    pub fn includes(self, expensive: bool) -> bool {
+        match self {
+            Phase::All => true,
+            Phase::Fast => !expensive,
+            Phase::Slow => expensive,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: Command,
+    /// Exit status of the previous command, passed positionally by the shell
+    pub status: Option<String>,
+    /// Segments requested with `--segment`, in the order given
+    pub segments: Vec<String>,
+    pub color: bool,
+    /// Set by `--shell` and `--embedded`, for the shell the prompt is printed
+    /// into
+    pub escaping: Escaping,
+    /// The line editor's vi mode, e.g. zsh's `$KEYMAP`
+    pub vi_mode: Option<String>,
+    /// The shell's umask in octal, as printed by `umask`
+    pub umask: Option<String>,
+    pub phase: Phase,
+    /// Output of `--slow`, shown after the cheap segments
+    pub async_result: Option<String>,
+    pub list_segments: bool,
+    /// Set by `--profile`, in place of `$PROMPTLINE_PROFILE`
+    pub profile: Option<String>,
+    pub iterations: usize,
+    pub warmup: usize,
+    pub output: OutputFormat,
+}
+
+/// What the value of a flag or command can be, for completing it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueKind {
+    /// Anything, there is nothing to complete
+    Any,
+    OneOf(&'static [&'static str]),
+    /// The name of a segment
+    Segment,
+}
+
+/// A flag `Args::parse` accepts
+pub struct Flag {
+    pub long: &'static str,
+    pub short: Option<&'static str>,
+    /// The value the flag takes, if it takes one
+    pub value: Option<ValueKind>,
+    pub help: &'static str,
+}
+
+/// A command `Args::parse` accepts in place of the exit status
+pub struct Subcommand {
+    pub name: &'static str,
+    pub value: Option<ValueKind>,
+    pub help: &'static str,
+}
+
+/// Shells `--shell` escapes for, as `Escaping::from_shell` accepts them
+const PROMPT_SHELLS: &[&str] = &["bash", "zsh", "powershell", "pwsh"];
+
+const fn flag(long: &'static str, value: Option<ValueKind>, help: &'static str) -> Flag {
+    Flag {
+        long,
+        short: None,
+        value,
+        help,
+    }
+}
+
+/// Every flag, which is where the parser finds out whether a flag exists and
+/// the completion scripts get their flags from
+pub const FLAGS: &[Flag] = &[
+    flag(
+        "--shell",
+        Some(ValueKind::OneOf(PROMPT_SHELLS)),
+        "Escape colors for the shell's prompt",
+    ),
+    flag(
+        "--embedded",
+        None,
+        "Escape for a prompt set once to the output",
+    ),
+    flag(
+        "--segment",
+        Some(ValueKind::Segment),
+        "Print only this segment",
+    ),
+    flag("--list-segments", None, "Print the names of all segments"),
+    flag("--no-color", None, "Print without colors"),
+    flag("--profile", Some(ValueKind::Any), "Use this config profile"),
+    flag(
+        "--vi-mode",
+        Some(ValueKind::Any),
+        "The line editor's vi mode",
+    ),
+    flag(
+        "--umask",
+        Some(ValueKind::Any),
+        "The shell's umask in octal",
+    ),
+    flag("--fast", None, "Render only the cheap segments"),
+    flag("--slow", None, "Render only the expensive segments"),
+    flag(
+        "--async-result",
+        Some(ValueKind::Any),
+        "Output of --slow to show",
+    ),
+    flag(
+        "--transient",
+        Some(ValueKind::Any),
+        "Print only the prompt char",
+    ),
+    flag("--iterations", Some(ValueKind::Any), "Iterations for bench"),
+    flag(
+        "--warmup",
+        Some(ValueKind::Any),
+        "Untimed iterations for bench",
+    ),
+    flag("--diagnose", None, "Print a report for bug reports"),
+    flag(
+        "--output",
+        Some(ValueKind::OneOf(&["text", "json"])),
+        "Output of bench and --diagnose",
+    ),
+    Flag {
+        long: "--help",
+        short: Some("-h"),
+        value: None,
+        help: "Print the help",
+    },
+    Flag {
+        long: "--version",
+        short: Some("-V"),
+        value: None,
+        help: "Print the version",
+    },
+];
+
+pub const COMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "init",
+        value: Some(ValueKind::OneOf(init::SHELLS)),
+        help: "Print the snippet that sets up the prompt",
+    },
+    Subcommand {
+        name: "explain",
+        value: None,
+        help: "Show how each segment went",
+    },
+    Subcommand {
+        name: "bench",
+        value: None,
+        help: "Time rendering the segments",
+    },
+    Subcommand {
+        name: "preview",
+        value: None,
+        help: "Render the prompt from sample data",
+    },
+    Subcommand {
+        name: "prefetch",
+        value: None,
+        help: "Cache git status for the next prompt",
+    },
+    Subcommand {
+        name: "check-config",
+        value: None,
+        help: "List the problems in the config file",
+    },
+    Subcommand {
+        name: "completions",
+        value: Some(ValueKind::OneOf(init::SHELLS)),
+        help: "Print the completion script of a shell",
+    },
+];
+
+/// What `--help` prints
+pub const USAGE: &str = "\
+Usage: promptline [STATUS] [OPTIONS]
+       promptline <COMMAND> [OPTIONS]
+
+Prints a shell prompt. STATUS is the exit status of the previous command, or
+of each command of a pipeline joined by spaces or colons. $PROMPTLINE_STATUS is
+used when it is left out.
+
+Commands:
+  init <SHELL>          Print the snippet that sets up the prompt of SHELL
+                        (bash, zsh or fish)
+  explain               Show how each segment went and how long it took
+  bench                 Time rendering the segments
+  preview               Render the prompt from sample data
+  prefetch              Count the changed files of the git work tree for the
+                        next prompt, with prefetch = true in the config
+  check-config          List the problems in the config file
+  completions <SHELL>   Print the completion script of SHELL (bash, zsh or
+                        fish)
+
+Options:
+  --shell <SHELL>       Escape colors for SHELL's prompt
+  --embedded            Also escape what bash or zsh would expand, for a
+                        prompt set to the output once, as in
+                        PS1=\"$(promptline)\", rather than running it
+  --segment <NAME>      Print only the segment NAME, can be repeated
+  --list-segments       Print the names of all segments
+  --no-color            Print without colors
+  --profile <NAME>      Use the config profile NAME instead of
+                        $PROMPTLINE_PROFILE
+  --vi-mode <MODE>      The line editor's vi mode, e.g. zsh's $KEYMAP
+  --umask <UMASK>       The shell's umask in octal
+  --fast                Render only the cheap segments
+  --slow                Render only the expensive segments, for --async-result
+  --async-result <OUT>  Output of --slow to show after the cheap segments
+  --transient <STATUS>  Print only the prompt char, colored by STATUS
+  --iterations <N>      Iterations for bench, 20 by default
+  --warmup <N>          Untimed iterations before bench starts, 3 by default
+  --diagnose            Print the versions, environment, config and how each
+                        segment went, to paste into a bug report
+  --output <FORMAT>     Output of bench and --diagnose, text or json
+  -h, --help            Print this help
+  -V, --version         Print the version
+
+Exit status:
+  0  The prompt, or whatever else was asked for, was printed
+  1  check-config found problems, or none of the --segment could be shown
+  2  The arguments are wrong. A bare prompt is printed anyway
+  3  The output couldn't be written
+";
+
+fn parse_count(flag: &str, value: String) -> Result<usize, ArgsError> {
+    value
+        .parse()
+        .map_err(|_| ArgsError::InvalidValue(flag.to_string(), value))
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, ArgsError> {
+        let mut ret = Args {
+            color: true,
+            iterations: 20,
+            warmup: 3,
+            ..Default::default()
+        };
+        let mut embedded = false;
+
+        while let Some(arg) = args.next() {
+            // Flags taking a value accept both `--flag value` and `--flag=value`
+            let (flag, mut inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .take()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| ArgsError::MissingValue(flag.clone()))
+            };
+
+            let known = FLAGS
+                .iter()
+                .any(|known| known.long == flag || known.short == Some(flag.as_str()));
+            if !known && flag.starts_with("--") {
+                return Err(ArgsError::UnknownFlag(arg));
+            }
+
+            match flag.as_str() {
+                "--segment" => ret.segments.push(value()?),
+                "--iterations" => ret.iterations = parse_count(&flag, value()?)?,
+                "--warmup" => ret.warmup = parse_count(&flag, value()?)?,
+                "--output" => {
+                    ret.output = match value()?.as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        other => return Err(ArgsError::InvalidValue(flag, other.to_string())),
+                    }
+                }
+                "--shell" => {
+                    let shell = value()?;
+                    ret.escaping =
+                        Escaping::from_shell(&shell).ok_or(ArgsError::InvalidValue(flag, shell))?;
+                }
+                "--embedded" => embedded = true,
+                "--vi-mode" => ret.vi_mode = Some(value()?),
+                "--umask" => ret.umask = Some(value()?),
+                "--profile" => ret.profile = Some(value()?),
+                "--fast" => ret.phase = Phase::Fast,
+                "--slow" => ret.phase = Phase::Slow,
+                "--async-result" => ret.async_result = Some(value()?),
+                "--transient" => {
+                    ret.command = Command::Transient;
+                    ret.status = Some(value()?);
+                }
+                "--diagnose" => ret.command = Command::Diagnose,
+                "--no-color" => ret.color = false,
+                "--list-segments" => ret.list_segments = true,
+                // Whatever else is asked for, help is all that is printed
+                "-h" | "--help" => {
+                    ret.command = Command::Help;
+                    return Ok(ret);
+                }
+                "-V" | "--version" => {
+                    ret.command = Command::Version;
+                    return Ok(ret);
+                }
+                "explain" if ret.status.is_none() && ret.command == Command::Prompt => {
+                    ret.command = Command::Explain
+                }
+                "bench" if ret.status.is_none() && ret.command == Command::Prompt => {
+                    ret.command = Command::Bench
+                }
+                "preview" if ret.status.is_none() && ret.command == Command::Prompt => {
+                    ret.command = Command::Preview
+                }
+                "prefetch" if ret.status.is_none() && ret.command == Command::Prompt => {
+                    ret.command = Command::Prefetch
+                }
+                "check-config" if ret.status.is_none() && ret.command == Command::Prompt => {
+                    ret.command = Command::CheckConfig
+                }
+                "init" if ret.status.is_none() && ret.command == Command::Prompt => {
+                    let shell = value()?;
+                    if init::snippet(&shell).is_none() {
+                        return Err(ArgsError::InvalidValue(flag, shell));
+                    }
+                    ret.command = Command::Init(shell);
+                }
+                "completions" if ret.status.is_none() && ret.command == Command::Prompt => {
+                    let shell = value()?;
+                    if !init::SHELLS.contains(&shell.as_str()) {
+                        return Err(ArgsError::InvalidValue(flag, shell));
+                    }
+                    ret.command = Command::Completions(shell);
+                }
+                // Anything after the exit status is ignored, as it always has been
+                _ if ret.status.is_none() => ret.status = Some(arg),
+                _ => (),
+            }
+        }
+
+        // `--shell` can come after it
+        if embedded {
+            ret.escaping = ret.escaping.embedded();
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn command(args: &[&str]) -> Command {
+        parse(args).unwrap().command
+    }
+
+    #[test]
+    fn status_and_defaults() {
+        let args = parse(&["130", "ignored"]).unwrap();
+        assert_eq!(args.command, Command::Prompt);
+        assert_eq!(args.status.as_deref(), Some("130"));
+        assert!(args.color);
+        assert_eq!(args.escaping, Escaping::Raw);
+        assert_eq!((args.iterations, args.warmup), (20, 3));
+
+        assert_eq!(parse(&[]).unwrap().status, None);
+    }
+
+    #[test]
+    fn unknown_flag() {
+        let e = parse(&["0", "--rigth"]).unwrap_err();
+        assert!(matches!(&e, ArgsError::UnknownFlag(flag) if flag == "--rigth"));
+        assert_eq!(e.to_string(), "unknown flag --rigth");
+        assert!(matches!(
+            parse(&["--colour=no"]),
+            Err(ArgsError::UnknownFlag(flag)) if flag == "--colour=no"
+        ));
+    }
+
+    #[test]
+    fn missing_value() {
+        for flag in ["--segment", "--shell", "--transient", "--output"] {
+            let e = parse(&["0", flag]).unwrap_err();
+            assert!(matches!(&e, ArgsError::MissingValue(missing) if missing == flag));
+        }
+        assert_eq!(
+            parse(&["init"]).unwrap_err().to_string(),
+            "init requires a value"
+        );
+    }
+
+    #[test]
+    fn invalid_value() {
+        for args in [
+            &["--shell", "fish"][..],
+            &["--output", "xml"],
+            &["--iterations", "many"],
+            &["--warmup=-1"],
+            &["init", "tcsh"],
+            &["completions", "tcsh"],
+        ] {
+            let e = parse(args).unwrap_err();
+            assert!(matches!(e, ArgsError::InvalidValue(..)), "{:?}", args);
+        }
+        assert_eq!(
+            parse(&["--shell=fish"]).unwrap_err().to_string(),
+            "invalid value fish for --shell"
+        );
+    }
+
+    #[test]
+    fn subcommands() {
+        assert_eq!(command(&["explain"]), Command::Explain);
+        assert_eq!(command(&["bench"]), Command::Bench);
+        assert_eq!(command(&["preview"]), Command::Preview);
+        assert_eq!(command(&["prefetch"]), Command::Prefetch);
+        assert_eq!(command(&["check-config"]), Command::CheckConfig);
+        assert_eq!(command(&["init", "zsh"]), Command::Init("zsh".into()));
+        assert_eq!(
+            command(&["completions", "fish"]),
+            Command::Completions("fish".into())
+        );
+        assert_eq!(command(&["--diagnose"]), Command::Diagnose);
+
+        let args = parse(&["--transient", "1"]).unwrap();
+        assert_eq!(args.command, Command::Transient);
+        assert_eq!(args.status.as_deref(), Some("1"));
+
+        // After the exit status a command name is just ignored
+        let args = parse(&["0", "explain"]).unwrap();
+        assert_eq!(args.command, Command::Prompt);
+        assert_eq!(args.status.as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn help_and_version_win() {
+        for flag in ["-h", "--help"] {
+            assert_eq!(command(&["0", flag, "--rigth"]), Command::Help);
+        }
+        for flag in ["-V", "--version"] {
+            assert_eq!(command(&["explain", flag, "--shell"]), Command::Version);
+        }
+        // Errors before them still count
+        assert!(parse(&["--rigth", "--help"]).is_err());
+    }
+
+    #[test]
+    fn flags() {
+        let args = parse(&[
+            "--segment",
+            "git",
+            "--segment=cwd",
+            "--no-color",
+            "--vi-mode",
+            "vicmd",
+            "--umask=0027",
+            "--profile",
+            "work",
+            "--fast",
+            "--async-result",
+            "main",
+            "--list-segments",
+        ])
+        .unwrap();
+        assert_eq!(args.segments, ["git", "cwd"]);
+        assert!(!args.color);
+        assert_eq!(args.vi_mode.as_deref(), Some("vicmd"));
+        assert_eq!(args.umask.as_deref(), Some("0027"));
+        assert_eq!(args.profile.as_deref(), Some("work"));
+        assert_eq!(args.phase, Phase::Fast);
+        assert_eq!(args.async_result.as_deref(), Some("main"));
+        assert!(args.list_segments);
+
+        let args = parse(&[
+            "bench",
+            "--iterations",
+            "50",
+            "--warmup=0",
+            "--output",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!((args.iterations, args.warmup), (50, 0));
+        assert_eq!(args.output, OutputFormat::Json);
+        assert_eq!(parse(&["--slow"]).unwrap().phase, Phase::Slow);
+    }
+
+    #[test]
+    fn shell_escaping() {
+        assert_eq!(parse(&["--shell", "zsh"]).unwrap().escaping, Escaping::Zsh);
+        assert_eq!(
+            parse(&["--shell", "pwsh"]).unwrap().escaping,
+            Escaping::PowerShell
+        );
+        // `--embedded` applies whichever order the two come in
+        assert_eq!(
+            parse(&["--embedded", "--shell", "bash"]).unwrap().escaping,
+            Escaping::BashEmbedded
+        );
+        assert_eq!(
+            parse(&["--shell=zsh", "--embedded"]).unwrap().escaping,
+            Escaping::ZshEmbedded
+        );
+    }
+
+    #[test]
+    fn usage_lists_everything() {
+        for flag in FLAGS {
+            assert!(USAGE.contains(flag.long), "{}", flag.long);
+        }
+        for command in COMMANDS {
+            assert!(
+                USAGE.contains(&format!("  {} ", command.name)),
+                "{}",
+                command.name
+            );
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::{fmt::Write, sync::Arc, time::Duration};
+
+use crate::{
+    runner::{self, Registry},
+    Context,
+};
+
+pub struct Stats {
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+impl Stats {
+    fn from_samples(mut samples: Vec<Duration>) -> Stats {
+        samples.sort();
+        Stats {
+            min: samples.first().copied().unwrap_or_default(),
+            median: samples.get(samples.len() / 2).copied().unwrap_or_default(),
+            max: samples.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+pub struct Report {
+    pub iterations: usize,
+    pub segments: Vec<(String, Stats)>,
+    pub total: Stats,
+}
+
+/// Calls `run_once` `warmup` times without recording anything, then
+/// `iterations` times collecting the per segment and total durations it
+/// returns. Per segment durations must be in the same order as `names`
+pub fn run(
+    names: &[&str],
+    iterations: usize,
+    warmup: usize,
+    mut run_once: impl FnMut() -> (Vec<Duration>, Duration),
+) -> Report {
+    for _ in 0..warmup {
+        run_once();
+    }
+
+    let mut segment_samples = vec![Vec::with_capacity(iterations); names.len()];
+    let mut total_samples = Vec::with_capacity(iterations);
+
+    for _ in 0..iterations {
+        let (segments, total) = run_once();
+        for (samples, duration) in segment_samples.iter_mut().zip(segments) {
+            samples.push(duration);
+        }
+        total_samples.push(total);
+    }
+
+    Report {
+        iterations,
+        segments: names
+            .iter()
+            .map(|name| name.to_string())
+            .zip(segment_samples.into_iter().map(Stats::from_samples))
+            .collect(),
+        total: Stats::from_samples(total_samples),
+    }
+}
+
+/// Benchmarks rendering every enabled segment in `registry` together, with the
+/// iteration counts given on the command line
+pub fn run_registry(registry: &Registry, ctx: &Arc<Context>) -> Report {
+    let names: Vec<_> = registry
+        .enabled(&ctx.config)
+        .map(|segment| segment.name())
+        .collect();
+    run(&names, ctx.args.iterations, ctx.args.warmup, || {
+        let (results, total) =
+            runner::timed(|| runner::render_all(registry.enabled(&ctx.config), ctx));
+        let durations = results.into_iter().map(|(_, elapsed)| elapsed).collect();
+        (durations, total)
+    })
+}
+
+pub(crate) fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut ret = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(ret, "\\u{:04x}", c as u32);
+            }
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+fn json_stats(stats: &Stats) -> String {
+    format!(
+        "\"min_ms\":{:.3},\"median_ms\":{:.3},\"max_ms\":{:.3}",
+        ms(stats.min),
+        ms(stats.median),
+        ms(stats.max)
+    )
+}
+
+impl Report {
+    pub fn to_text(&self) -> String {
+        let name_width = self
+            .segments
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(Some("segment".len()))
+            .max()
+            .unwrap_or(0);
+
+        let mut ret = String::new();
+        let mut row = |name: &str, min: &str, median: &str, max: &str| {
+            let _ = writeln!(ret, "{name:name_width$}  {min:>9}  {median:>9}  {max:>9}");
+        };
+
+        let fmt_ms = |d| format!("{:.2}ms", ms(d));
+
+        row("segment", "min", "median", "max");
+        for (name, stats) in &self.segments {
+            row(
+                name,
+                &fmt_ms(stats.min),
+                &fmt_ms(stats.median),
+                &fmt_ms(stats.max),
+            );
+        }
+        row(
+            "total",
+            &fmt_ms(self.total.min),
+            &fmt_ms(self.total.median),
+            &fmt_ms(self.total.max),
+        );
+
+        let _ = write!(ret, "\n{} iterations", self.iterations);
+        ret
+    }
+
+    pub fn to_json(&self) -> String {
+        let segments = self
+            .segments
+            .iter()
+            .map(|(name, stats)| {
+                format!("{{\"name\":{},{}}}", json_string(name), json_stats(stats))
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"iterations\":{},\"segments\":[{}],\"total\":{{{}}}}}",
+            self.iterations,
+            segments,
+            json_stats(&self.total)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::{RenderedSegment, Segment, SegmentError},
+        testing, DecoratedString,
+    };
+
+    struct Fast(&'static str);
+
+    impl Segment for Fast {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+            Ok(DecoratedString::new(self.0.into()).into())
+        }
+    }
+
+    #[test]
+    fn stats_of_samples() {
+        let mut calls = 0;
+        let report = run(&["a", "b"], 3, 2, || {
+            calls += 1;
+            let ms = Duration::from_millis;
+            (vec![ms(calls), ms(10 * calls)], ms(100 * calls))
+        });
+        // The two warmup calls are left out
+        assert_eq!(calls, 5);
+        let (name, b) = &report.segments[1];
+        assert_eq!(name, "b");
+        assert_eq!(
+            (b.min, b.median, b.max),
+            (
+                Duration::from_millis(30),
+                Duration::from_millis(40),
+                Duration::from_millis(50)
+            )
+        );
+        assert_eq!(report.total.median, Duration::from_millis(400));
+    }
+
+    #[test]
+    fn reports_every_segment() {
+        let names = ["user", "cwd", "git"];
+        let registry = Registry::new(
+            names
+                .iter()
+                .map(|name| Arc::new(Fast(name)) as Arc<dyn Segment>)
+                .collect(),
+        );
+        let mut ctx = testing::context(&[], None);
+        ctx.args.iterations = 4;
+        ctx.args.warmup = 1;
+
+        let report = run_registry(&registry, &Arc::new(ctx));
+        let reported: Vec<_> = report.segments.iter().map(|(name, _)| name).collect();
+        assert_eq!(reported, names);
+
+        let text = report.to_text();
+        let rows: Vec<_> = text
+            .lines()
+            .map(|line| line.split_whitespace().next().unwrap_or_default())
+            .collect();
+        assert_eq!(rows, ["segment", "user", "cwd", "git", "total", "", "4"]);
+        assert!(text.ends_with("\n4 iterations"), "{}", text);
+
+        let json = report.to_json();
+        assert!(json.starts_with("{\"iterations\":4,\"segments\":[{\"name\":\"user\",\"min_ms\":"));
+        for name in names {
+            assert!(
+                json.contains(&format!("{{\"name\":\"{name}\",")),
+                "{}",
+                json
+            );
+        }
+        assert!(json.contains("],\"total\":{\"min_ms\":"), "{}", json);
+    }
+
+    #[test]
+    fn json_strings() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+    }
+}
@@ -0,0 +1,252 @@
+//! Small files in [`Context::cache_dir`] that keep the result of an expensive
+//! check for a while, so that not every prompt pays for it
+//!
+//! [`Context::cache_dir`]: crate::Context::cache_dir
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Contents of the cache file at `path`, if it was written less than
+/// `max_age` ago
+pub fn read(path: &Path, max_age: Duration) -> Option<String> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let age = SystemTime::now().duration_since(modified).ok()?;
+    if age > max_age {
+        return None;
+    }
+
+    fs::read_to_string(path).ok()
+}
+
+/// Writes `contents` to the cache file at `path`. Failing to cache only costs
+/// the next prompt another check, so most callers ignore errors. The file is
+/// written to the side first and renamed into place, so that a prompt reading
+/// it at the same time never sees half of it
+pub fn write(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension(process::id().to_string());
+    let written = fs::write(&tmp, contents).and_then(|_| fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// How long the roots found above a directory are trusted, unless the
+/// directory itself changes first
+const ROOTS_TTL: Duration = Duration::from_secs(10);
+
+fn unix_nanos(time: SystemTime) -> Option<u128> {
+    Some(time.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+/// The project roots found above one cwd, e.g. which directory has the
+/// `.git`, kept in a file so the next prompt in the same directory can skip
+/// walking up. Any trouble with the file just turns the cache off
+pub struct RootCache {
+    path: PathBuf,
+    /// When the entries were first found and the mtime of the cwd then, the
+    /// first line of the file along with the cwd itself
+    header: String,
+    /// Root for each `,` joined set of markers, `None` if there was none
+    roots: BTreeMap<String, Option<PathBuf>>,
+    disabled: bool,
+}
+
+impl RootCache {
+    /// The cache for `cwd` in `dir`. It starts out empty if the file is
+    /// missing, garbled, older than the TTL or from before the cwd changed
+    pub fn load(dir: &Path, cwd: &Path) -> RootCache {
+        let mut hasher = DefaultHasher::new();
+        cwd.hash(&mut hasher);
+        let path = dir.join(format!("roots-{:016x}", hasher.finish()));
+
+        let cwd_mtime = fs::metadata(cwd)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(unix_nanos);
+        let (cwd, cwd_mtime) = match (cwd.to_str(), cwd_mtime) {
+            (Some(cwd), Some(mtime)) => (cwd, mtime),
+            _ => {
+                return RootCache {
+                    path,
+                    header: String::new(),
+                    roots: BTreeMap::new(),
+                    disabled: true,
+                }
+            }
+        };
+
+        let now = unix_nanos(SystemTime::now()).unwrap_or_default();
+        let roots = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| Self::parse(&contents, cwd, cwd_mtime, now));
+        match roots {
+            Some((header, roots)) => RootCache {
+                path,
+                header,
+                roots,
+                disabled: false,
+            },
+            None => RootCache {
+                path,
+                header: format!("{now} {cwd_mtime}\n{cwd}"),
+                roots: BTreeMap::new(),
+                disabled: false,
+            },
+        }
+    }
+
+    fn parse(
+        contents: &str,
+        cwd: &str,
+        cwd_mtime: u128,
+        now: u128,
+    ) -> Option<(String, BTreeMap<String, Option<PathBuf>>)> {
+        let mut lines = contents.lines();
+        let times = lines.next()?;
+        let (created, mtime) = times.split_once(' ')?;
+        let created: u128 = created.parse().ok()?;
+        if mtime.parse::<u128>().ok()? != cwd_mtime
+            || now.saturating_sub(created) > ROOTS_TTL.as_nanos()
+            || lines.next()? != cwd
+        {
+            return None;
+        }
+
+        let roots = lines
+            .map(|line| {
+                let (markers, root) = line.split_once('\t')?;
+                let root = Some(PathBuf::from(root)).filter(|_| !root.is_empty());
+                Some((markers.to_string(), root))
+            })
+            .collect::<Option<_>>()?;
+        Some((format!("{times}\n{cwd}"), roots))
+    }
+
+    /// The cached root for `markers`, `Some(None)` if it is known there is
+    /// none. Roots may be gone since, so they need checking before use
+    pub fn get(&self, markers: &[&str]) -> Option<Option<PathBuf>> {
+        self.roots.get(&markers.join(",")).cloned()
+    }
+
+    /// Remembers `root` for `markers` and writes the cache out
+    pub fn insert(&mut self, markers: &[&str], root: Option<&Path>) {
+        if self.disabled {
+            return;
+        }
+
+        let root = match root.map(Path::to_str) {
+            Some(Some(root)) if !root.contains('\n') => Some(root),
+            None => None,
+            _ => return,
+        };
+        self.roots
+            .insert(markers.join(","), root.map(PathBuf::from));
+
+        let mut contents = self.header.clone();
+        for (markers, root) in &self.roots {
+            let root = root.as_deref().and_then(Path::to_str).unwrap_or("");
+            contents += &format!("\n{markers}\t{root}");
+        }
+
+        if write(&self.path, &contents).is_err() {
+            self.disabled = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    #[test]
+    fn write_replaces_whole_file() {
+        let dir = TempDir::new();
+        let path = dir.path().join("promptline/idle");
+        write(&path, "120").unwrap();
+        write(&path, "7").unwrap();
+        assert_eq!(read(&path, Duration::from_secs(60)).as_deref(), Some("7"));
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        // The dir can't be created under a file
+        assert!(write(&path.join("nested"), "1").is_err());
+    }
+
+    #[test]
+    fn roots_hit_and_miss() {
+        let dir = TempDir::new();
+        let cwd = dir.path().join("src");
+        fs::create_dir_all(&cwd).unwrap();
+        let cache_dir = dir.path().join("cache");
+
+        let mut cache = RootCache::load(&cache_dir, &cwd);
+        assert_eq!(cache.get(&[".git"]), None);
+        cache.insert(&[".git"], Some(dir.path()));
+        cache.insert(&[".hg"], None);
+
+        let cache = RootCache::load(&cache_dir, &cwd);
+        assert_eq!(cache.get(&[".git"]), Some(Some(dir.path().to_path_buf())));
+        assert_eq!(cache.get(&[".hg"]), Some(None));
+        assert_eq!(cache.get(&["Gemfile"]), None);
+    }
+
+    #[test]
+    fn roots_dropped_when_cwd_changes() {
+        let dir = TempDir::new();
+        let cwd = dir.path().join("src");
+        fs::create_dir_all(&cwd).unwrap();
+        let cache_dir = dir.path().join("cache");
+
+        RootCache::load(&cache_dir, &cwd).insert(&[".git"], None);
+        // Old enough that the new file changes the mtime on any filesystem
+        let past = SystemTime::now() - Duration::from_secs(5);
+        fs::File::open(&cwd).unwrap().set_modified(past).unwrap();
+        RootCache::load(&cache_dir, &cwd).insert(&[".git"], None);
+        fs::create_dir(cwd.join(".git")).unwrap();
+
+        assert_eq!(RootCache::load(&cache_dir, &cwd).get(&[".git"]), None);
+    }
+
+    #[test]
+    fn garbled_or_expired_roots() {
+        let now = 100 * ROOTS_TTL.as_nanos();
+        let created = now - ROOTS_TTL.as_nanos() / 2;
+        let contents = format!("{created} 7\n/src\n.git\t/\n.hg\t");
+        assert!(RootCache::parse(&contents, "/src", 7, now).is_some());
+        assert!(RootCache::parse(&contents, "/src", 8, now).is_none());
+        assert!(RootCache::parse(&contents, "/other", 7, now).is_none());
+        assert!(RootCache::parse(&contents, "/src", 7, now + ROOTS_TTL.as_nanos()).is_none());
+        assert!(RootCache::parse(&format!("{contents}\ngarbage"), "/src", 7, now).is_none());
+        assert!(RootCache::parse("", "/src", 7, now).is_none());
+    }
+
+    #[test]
+    fn stale_root_is_walked_again() {
+        let root = TempDir::new();
+        root.write("proj/.git/HEAD", "ref: refs/heads/main\n");
+        root.write("proj/src/main.rs", "");
+        let find = || {
+            testing::context(&[("XDG_RUNTIME_DIR", "/run")], Some("/proj/src"))
+                .with_root(root.path().to_path_buf())
+                .find_root(&[".git"])
+        };
+
+        assert_eq!(find(), Some(root.path().join("proj")));
+        let cached = fs::read_dir(root.path().join("run/promptline")).unwrap();
+        assert_eq!(cached.count(), 1);
+
+        fs::remove_dir_all(root.path().join("proj/.git")).unwrap();
+        assert_eq!(find(), None);
+    }
+}
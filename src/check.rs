@@ -0,0 +1,426 @@
+//! `promptline check-config`, which lists everything wrong with a config file
+//! along with where it is, and the `cfg!` marker the prompt shows in place of
+//! that list when the config is broken
+
+use std::fmt;
+
+use crate::{
+    config::{self, Config, ConfigError, Table, Value},
+    custom::CustomConfig,
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    static_text::StaticConfig,
+    Context,
+};
+
+/// Something wrong with the config, at the key it was found under
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    /// Line and column counted from 1
+    pub line: usize,
+    pub column: usize,
+    /// Dotted path to the key, with `[[custom]]` and `[[static]]` entries
+    /// numbered from 0, empty if there is no key to blame
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        if !self.key.is_empty() {
+            write!(f, "{}: ", self.key)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Where each key and table header in `source` is, by dotted path. Works line
+/// by line rather than off the parsed table, so it still has something to say
+/// about a file that fails to parse
+fn key_positions(source: &str) -> Vec<(String, usize, usize)> {
+    let mut positions = vec![];
+    let mut table = String::new();
+    let mut array_counts: Vec<(String, usize)> = vec![];
+
+    for (i, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let column = line.len() - trimmed.len() + 1;
+        let position = |path: String| (path, i + 1, column);
+
+        if let Some(header) = trimmed.strip_prefix("[[") {
+            let name = header.split("]]").next().unwrap_or("").trim().to_string();
+            let index = match array_counts.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                }
+                None => {
+                    array_counts.push((name.clone(), 0));
+                    0
+                }
+            };
+            table = format!("{name}.{index}");
+            positions.push(position(table.clone()));
+        } else if let Some(header) = trimmed.strip_prefix('[') {
+            table = header.split(']').next().unwrap_or("").trim().to_string();
+            positions.push(position(table.clone()));
+        } else if let Some((key, _)) = trimmed.split_once('=') {
+            let key = key.trim().trim_matches('"');
+            let is_key = !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+            if is_key {
+                let path = match table.as_str() {
+                    "" => key.to_string(),
+                    table => format!("{table}.{key}"),
+                };
+                positions.push(position(path));
+            }
+        }
+    }
+
+    positions
+}
+
+/// Collects problems, placing each at its key or, failing that, the closest
+/// table above it
+struct Problems {
+    positions: Vec<(String, usize, usize)>,
+    found: Vec<Problem>,
+}
+
+impl Problems {
+    fn add(&mut self, key: &str, message: impl Into<String>) {
+        let mut path = key;
+        let (line, column) = loop {
+            if let Some((_, line, column)) = self.positions.iter().find(|(p, ..)| p == path) {
+                break (*line, *column);
+            }
+            match path.rsplit_once('.') {
+                Some((parent, _)) => path = parent,
+                None => break (1, 1),
+            }
+        };
+        self.found.push(Problem {
+            line,
+            column,
+            key: key.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Adds the error from parsing a field of the table at `prefix`
+    fn add_error(&mut self, prefix: &str, e: ConfigError) {
+        match e {
+            ConfigError::InvalidField { field, expected } => {
+                // Entries name their fields as `custom.color` and the like
+                let field = field.rsplit('.').next().unwrap_or(&field);
+                let key = match prefix {
+                    "" => field.to_string(),
+                    prefix => format!("{prefix}.{field}"),
+                };
+                self.add(&key, format!("expected {expected}"));
+            }
+            e => self.add(prefix, e.to_string()),
+        }
+    }
+}
+
+/// Why `command` won't make it through `sh -c` in one piece, if it won't
+fn command_problem(command: &str) -> Option<&'static str> {
+    if command.trim().is_empty() {
+        return Some("command is empty");
+    }
+
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => (),
+            (_, '\\') if chars.next().is_none() => return Some("command ends in a backslash"),
+            (None, '\'' | '"') => quote = Some(c),
+            _ => (),
+        }
+    }
+
+    match quote {
+        Some('\'') => Some("command has an unterminated single quote"),
+        Some(_) => Some("command has an unterminated double quote"),
+        None => None,
+    }
+}
+
+/// Why `pattern` can't match an interface name. Only a trailing `*` is
+/// supported
+fn pattern_problem(pattern: &str) -> Option<&'static str> {
+    match pattern.strip_suffix('*').unwrap_or(pattern) {
+        "" if !pattern.is_empty() => None,
+        "" => Some("empty pattern"),
+        prefix if prefix.contains('*') => Some("only a trailing * is supported"),
+        _ => None,
+    }
+}
+
+fn check_entries(
+    table: &Table,
+    key: &str,
+    problems: &mut Problems,
+    check: impl Fn(&Table) -> Result<String, ConfigError>,
+) -> Vec<String> {
+    let entries = match config::get_tables(table, key) {
+        Ok(entries) => entries,
+        Err(e) => {
+            problems.add_error("", e);
+            return vec![];
+        }
+    };
+
+    let mut names = vec![];
+    for (i, entry) in entries.into_iter().enumerate() {
+        let prefix = format!("{key}.{i}");
+        match check(entry) {
+            Ok(name) => names.push(name),
+            Err(e) => problems.add_error(&prefix, e),
+        }
+
+        if key == "custom" {
+            if let Ok(Some(command)) = config::get_str(entry, "command") {
+                if let Some(problem) = command_problem(command) {
+                    problems.add(&format!("{prefix}.command"), problem);
+                }
+            }
+        }
+    }
+    names
+}
+
+fn check_segments(segments: &Table, known: &[String], problems: &mut Problems) {
+    for (name, value) in segments {
+        let key = format!("segments.{name}");
+        let table = match value {
+            Value::Table(table) => table,
+            _ => {
+                problems.add(&key, format!("expected table, found {}", value.type_name()));
+                continue;
+            }
+        };
+
+        if !known.contains(name) {
+            problems.add(&key, format!("unknown segment {name}"));
+        }
+
+        let mut wrapped = Table::new();
+        let mut inner = Table::new();
+        inner.insert(name.clone(), value.clone());
+        wrapped.insert("segments".into(), Value::Table(inner));
+        if let Err(e) = Config::from_table(&wrapped) {
+            problems.add_error(&key, e);
+        }
+
+        match config::get_strings(table, "interfaces") {
+            Ok(patterns) => {
+                for pattern in patterns.unwrap_or_default() {
+                    if let Some(problem) = pattern_problem(&pattern) {
+                        problems.add(
+                            &format!("{key}.interfaces"),
+                            format!("{pattern:?}: {problem}"),
+                        );
+                    }
+                }
+            }
+            Err(e) => problems.add_error(&key, e),
+        }
+    }
+}
+
+/// Everything wrong with the config in `source`, in the order found. Segment
+/// names are checked against `builtin` and the config's own custom and static
+/// entries
+pub fn check(source: &str, builtin: &[String]) -> Vec<Problem> {
+    let mut problems = Problems {
+        positions: key_positions(source),
+        found: vec![],
+    };
+
+    let mut table = match config::parse(source) {
+        Ok(table) => table,
+        Err(e) => {
+            let (line, column) = e.position();
+            let key = problems
+                .positions
+                .iter()
+                .rfind(|(_, l, c)| *l == line && *c <= column)
+                .map_or_else(String::new, |(key, ..)| key.clone());
+            problems.found.push(Problem {
+                line,
+                column,
+                key,
+                message: e.kind().to_string(),
+            });
+            return problems.found;
+        }
+    };
+
+    match table.remove("profiles") {
+        Some(Value::Table(_)) | None => (),
+        Some(_) => problems.add("profiles", "expected table"),
+    }
+
+    let mut known = builtin.to_vec();
+    known.extend(check_entries(&table, "custom", &mut problems, |entry| {
+        CustomConfig::from_table(entry).map(|custom| custom.name)
+    }));
+    known.extend(check_entries(&table, "static", &mut problems, |entry| {
+        StaticConfig::from_table(entry).map(|config| config.name)
+    }));
+
+    match config::get_table(&table, "segments") {
+        Ok(Some(segments)) => check_segments(segments, &known, &mut problems),
+        Ok(None) => (),
+        Err(e) => problems.add_error("", e),
+    }
+
+    if let Ok(Some(drop_order)) = config::get_strings(&table, "drop_order") {
+        for name in drop_order.iter().filter(|name| !known.contains(name)) {
+            problems.add("drop_order", format!("unknown segment {name}"));
+        }
+    }
+
+    for key in ["custom", "static", "segments"] {
+        table.remove(key);
+    }
+    if let Err(e) = Config::from_table(&table) {
+        problems.add_error("", e);
+    }
+
+    problems.found
+}
+
+const MARKER_NAME: &str = "config_error";
+
+/// Shows `cfg!` when the config file is broken and promptline fell back to
+/// its defaults. `promptline check-config` says what is wrong
+pub struct ConfigMarker;
+
+impl Segment for ConfigMarker {
+    fn name(&self) -> &str {
+        MARKER_NAME
+    }
+
+    fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        Ok(DecoratedString::new("cfg!".into())
+            .colored(Color::Yellow)
+            .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(source: &str) -> Vec<String> {
+        let builtin = vec!["cwd".to_string(), "git".to_string(), "vpn".to_string()];
+        check(source, &builtin)
+            .iter()
+            .map(Problem::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn valid_config() {
+        let source = r#"
+drop_order = ["git", "clock"]
+
+[segments.vpn]
+interfaces = ["tun*", "wg0"]
+
+[[custom]]
+name = "clock"
+command = "date '+%H:%M'"
+color = "cyan"
+"#;
+        assert!(problems(source).is_empty(), "{:?}", problems(source));
+    }
+
+    #[test]
+    fn parse_error() {
+        assert_eq!(
+            problems("prompt_char = \">\"\n\n[segments.git]\nenabled = \"yes\n"),
+            ["4:15: segments.git.enabled: unterminated string"]
+        );
+        assert_eq!(
+            problems("drop_order = [\"git\" \"cwd\"]\n"),
+            ["1:21: drop_order: unexpected character '\"'"]
+        );
+    }
+
+    #[test]
+    fn unknown_segments() {
+        assert_eq!(
+            problems("drop_order = [\"cwd\", \"gti\"]\n\n  [segments.gti]\nenabled = false\n"),
+            [
+                "3:3: segments.gti: unknown segment gti",
+                "1:1: drop_order: unknown segment gti",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_colors() {
+        let source = "[[custom]]\nname = \"a\"\ncommand = \"true\"\n\n[[static]]\ntext = \"PROD\"\ncolor = \"scarlet\"\n";
+        assert_eq!(
+            problems(source),
+            ["7:1: static.0.color: expected color name"]
+        );
+    }
+
+    #[test]
+    fn custom_commands() {
+        let source = r#"[[custom]]
+name = "a"
+command = "echo 'hi"
+
+[[custom]]
+name = "b"
+command = "echo hi \\"
+when = "always"
+
+[[custom]]
+command = "true"
+"#;
+        assert_eq!(
+            problems(source),
+            [
+                "3:1: custom.0.command: command has an unterminated single quote",
+                "8:1: custom.1.when: expected file_exists:<path> or env:<name>",
+                "7:1: custom.1.command: command ends in a backslash",
+                "10:1: custom.2.name: expected string",
+            ]
+        );
+    }
+
+    #[test]
+    fn interface_patterns() {
+        assert_eq!(
+            problems("[segments.vpn]\ninterfaces = [\"tun*\", \"*vpn\", \"\"]\n"),
+            [
+                "2:1: segments.vpn.interfaces: \"*vpn\": only a trailing * is supported",
+                "2:1: segments.vpn.interfaces: \"\": empty pattern",
+            ]
+        );
+    }
+
+    #[test]
+    fn field_types() {
+        assert_eq!(
+            problems("segment_budget_ms = \"fast\"\n[segments.git]\nbudget_ms = -1\n"),
+            [
+                "3:1: segments.git.budget_ms: expected positive integer",
+                "1:1: segment_budget_ms: expected integer",
+            ]
+        );
+    }
+}
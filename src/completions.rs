@@ -0,0 +1,350 @@
+//! Scripts printed by `promptline completions <shell>`, generated from the
+//! flags and commands `Args::parse` accepts and the names of the segments
+//! that are registered, so they stay in step with both
+
+use crate::args::{Flag, ValueKind, COMMANDS, FLAGS};
+
+/// `s` in single quotes for bash and zsh
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// `s` in single quotes for fish, which escapes quotes inside of them
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// Segment names that can go into a script as they are. Custom segments can
+/// be named anything, and the odd one with spaces or quotes is left out
+/// rather than quoted for every shell
+fn completable(segments: &[String]) -> Vec<&str> {
+    segments
+        .iter()
+        .map(String::as_str)
+        .filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+        })
+        .collect()
+}
+
+/// The words `kind` completes to, `None` when there's nothing to offer
+fn values(kind: ValueKind, segments: &[&str]) -> Option<Vec<String>> {
+    let values: Vec<_> = match kind {
+        ValueKind::Any => return None,
+        ValueKind::OneOf(values) => values.iter().map(|v| v.to_string()).collect(),
+        ValueKind::Segment => segments.iter().map(|v| v.to_string()).collect(),
+    };
+    Some(values)
+}
+
+/// Flag names as typed, the short one first
+fn names(flag: &Flag) -> Vec<&'static str> {
+    flag.short.into_iter().chain([flag.long]).collect()
+}
+
+fn bash(segments: &[&str]) -> String {
+    let mut cases = String::new();
+    let mut no_values = vec![];
+    let with_values = FLAGS
+        .iter()
+        .filter_map(|flag| Some((names(flag).join("|"), flag.value?)))
+        .chain(
+            COMMANDS
+                .iter()
+                .filter_map(|command| Some((command.name.to_string(), command.value?))),
+        );
+    for (pattern, kind) in with_values {
+        match values(kind, segments) {
+            Some(values) => cases += &format!(
+                "        {pattern})\n            COMPREPLY=($(compgen -W {} -- \"$cur\"))\n            return\n            ;;\n",
+                quote(&values.join(" "))
+            ),
+            None => no_values.push(pattern),
+        }
+    }
+    if !no_values.is_empty() {
+        cases += &format!(
+            "        {})\n            return\n            ;;\n",
+            no_values.join("|")
+        );
+    }
+
+    let flags: Vec<_> = FLAGS.iter().flat_map(names).collect();
+    let commands: Vec<_> = COMMANDS.iter().map(|command| command.name).collect();
+    format!(
+        r#"_promptline() {{
+    local cur=${{COMP_WORDS[COMP_CWORD]}} prev=${{COMP_WORDS[COMP_CWORD-1]}}
+    COMPREPLY=()
+    case $prev in
+{cases}    esac
+    local words={flags}
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        words+=" "{commands}
+    fi
+    COMPREPLY=($(compgen -W "$words" -- "$cur"))
+}}
+complete -F _promptline promptline
+"#,
+        flags = quote(&flags.join(" ")),
+        commands = quote(&commands.join(" ")),
+    )
+}
+
+/// `s` for the inside of the brackets and the messages of an `_arguments`
+/// spec, which end at `]` and `:`
+fn zsh_escape(s: &str) -> String {
+    s.replace('[', r"\[")
+        .replace(']', r"\]")
+        .replace(':', r"\:")
+}
+
+/// The action completing a value of `kind`
+fn zsh_action(kind: ValueKind, segments: &[&str]) -> String {
+    match values(kind, segments) {
+        Some(values) => format!("({})", values.join(" ")),
+        None => " ".to_string(),
+    }
+}
+
+fn zsh(segments: &[&str]) -> String {
+    let mut specs = vec![];
+    for flag in FLAGS {
+        let help = zsh_escape(flag.help);
+        // `--segment` is the one flag that can be given more than once
+        let repeat = if flag.value == Some(ValueKind::Segment) {
+            "*"
+        } else {
+            ""
+        };
+        let value = flag.value.map_or_else(String::new, |kind| {
+            format!(
+                ":{}:{}",
+                flag.long.trim_start_matches('-'),
+                zsh_action(kind, segments)
+            )
+        });
+        let equals = if flag.value.is_some() { "=" } else { "" };
+        let spec = match flag.short {
+            // Brace expansion gives the short and the long flag a spec each
+            Some(short) => format!(
+                "{}{{{short},{}}}{}",
+                quote(&format!("({short} {})", flag.long)),
+                flag.long,
+                quote(&format!("{equals}[{help}]{value}")),
+            ),
+            None => quote(&format!("{repeat}{}{equals}[{help}]{value}", flag.long)),
+        };
+        specs.push(spec);
+    }
+    specs.push(quote("1: :->command"));
+    specs.push(quote("2: :->value"));
+
+    let commands: Vec<_> = COMMANDS
+        .iter()
+        .map(|command| quote(&format!("{}[{}]", command.name, zsh_escape(command.help))))
+        .collect();
+    let mut values = String::new();
+    for command in COMMANDS {
+        if let Some(kind) = command.value {
+            values += &format!(
+                "                {}) _values {} {} ;;\n",
+                command.name,
+                quote(command.name),
+                zsh_values(kind, segments)
+            );
+        }
+    }
+
+    format!(
+        r#"#compdef promptline
+_promptline() {{
+    local state
+    _arguments -s \
+        {specs}
+    case $state in
+        command)
+            _values command \
+                {commands}
+            ;;
+        value)
+            case $words[2] in
+{values}            esac
+            ;;
+    esac
+}}
+compdef _promptline promptline
+"#,
+        specs = specs.join(" \\\n        "),
+        commands = commands.join(" \\\n                "),
+    )
+}
+
+/// Arguments to `_values` for the words `kind` completes to
+fn zsh_values(kind: ValueKind, segments: &[&str]) -> String {
+    values(kind, segments)
+        .unwrap_or_default()
+        .iter()
+        .map(|value| quote(value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn fish(segments: &[&str]) -> String {
+    let mut script = String::from("complete -c promptline -f\n");
+    for command in COMMANDS {
+        script += &format!(
+            "complete -c promptline -n __fish_use_subcommand -a {} -d {}\n",
+            command.name,
+            fish_quote(command.help)
+        );
+    }
+    for command in COMMANDS {
+        let values = command.value.and_then(|kind| values(kind, segments));
+        if let Some(values) = values {
+            script += &format!(
+                "complete -c promptline -n {} -a {}\n",
+                fish_quote(&format!("__fish_seen_subcommand_from {}", command.name)),
+                fish_quote(&values.join(" "))
+            );
+        }
+    }
+    for flag in FLAGS {
+        let mut line = String::from("complete -c promptline");
+        if let Some(short) = flag.short {
+            line += &format!(" -s {}", short.trim_start_matches('-'));
+        }
+        line += &format!(" -l {}", flag.long.trim_start_matches('-'));
+        if let Some(kind) = flag.value {
+            line += " -r";
+            if let Some(values) = values(kind, segments) {
+                line += &format!(" -a {}", fish_quote(&values.join(" ")));
+            }
+        }
+        script += &format!("{line} -d {}\n", fish_quote(flag.help));
+    }
+    script
+}
+
+/// The completion script for `shell`, offering `segments` to `--segment`
+pub fn script(shell: &str, segments: &[String]) -> Option<String> {
+    let segments = completable(segments);
+    match shell {
+        "bash" => Some(bash(&segments)),
+        "zsh" => Some(zsh(&segments)),
+        "fish" => Some(fish(&segments)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        args::{Args, ArgsError},
+        init, segments,
+    };
+
+    fn builtin_names() -> Vec<String> {
+        segments::builtin()
+            .iter()
+            .map(|segment| segment.name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn every_shell_has_a_script() {
+        for shell in init::SHELLS {
+            assert!(script(shell, &[]).is_some(), "{}", shell);
+        }
+    }
+
+    #[test]
+    fn scripts_list_every_segment() {
+        let mut names = builtin_names();
+        names.push("my_clock".into());
+        for shell in init::SHELLS {
+            let script = script(shell, &names).unwrap();
+            for name in &names {
+                assert!(script.contains(name.as_str()), "{} in {}", name, shell);
+            }
+        }
+    }
+
+    #[test]
+    fn scripts_list_every_flag_and_command() {
+        for shell in init::SHELLS {
+            let script = script(shell, &[]).unwrap();
+            for flag in FLAGS {
+                let long = flag.long.trim_start_matches('-');
+                assert!(script.contains(long), "{} in {}", flag.long, shell);
+            }
+            for command in COMMANDS {
+                assert!(
+                    script.contains(command.name),
+                    "{} in {}",
+                    command.name,
+                    shell
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn odd_custom_names_are_left_out() {
+        let names = vec!["it's".to_string(), "two words".to_string()];
+        for shell in init::SHELLS {
+            let script = script(shell, &names).unwrap();
+            assert!(!script.contains("it's") && !script.contains("two words"));
+        }
+    }
+
+    /// Keeps `FLAGS` and `COMMANDS` from naming something the parser doesn't
+    /// take, or describing it wrong
+    #[test]
+    fn table_matches_parser() {
+        let parse = |args: &[&str]| Args::parse(args.iter().map(|arg| arg.to_string()));
+        for flag in FLAGS {
+            let value = match flag.value {
+                Some(ValueKind::OneOf(values)) => vec![values[0]],
+                Some(_) => vec!["1"],
+                None => vec![],
+            };
+            for name in names(flag) {
+                let args: Vec<_> = [name].iter().chain(&value).copied().collect();
+                let parsed = parse(&args).unwrap();
+                assert_ne!(parsed.status.as_deref(), Some(name));
+
+                let missing = parse(&[name]);
+                assert_eq!(
+                    matches!(missing, Err(ArgsError::MissingValue(_))),
+                    flag.value.is_some(),
+                    "{}",
+                    name
+                );
+            }
+            assert!(crate::args::USAGE.contains(flag.long), "{}", flag.long);
+        }
+
+        for command in COMMANDS {
+            let parsed = match command.value {
+                Some(ValueKind::OneOf(values)) => parse(&[command.name, values[0]]),
+                _ => parse(&[command.name]),
+            };
+            assert_eq!(parsed.unwrap().status, None, "{}", command.name);
+            assert!(
+                crate::args::USAGE.contains(command.name),
+                "{}",
+                command.name
+            );
+        }
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(quote("the shell's"), r"'the shell'\''s'");
+        assert_eq!(fish_quote("the shell's"), r"'the shell\'s'");
+    }
+}
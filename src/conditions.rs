@@ -0,0 +1,160 @@
+use std::{error::Error, fmt};
+
+use crate::{
+    config::{self, ConfigError, Table},
+    Context,
+};
+
+#[derive(Debug)]
+pub enum ConditionFailed {
+    NotSsh,
+    EnvNotSet(String),
+    HiddenValue(String),
+}
+
+impl fmt::Display for ConditionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConditionFailed::NotSsh => write!(f, "not connected over ssh"),
+            ConditionFailed::EnvNotSet(name) => write!(f, "{name} is not set"),
+            ConditionFailed::HiddenValue(value) => write!(f, "value {value:?} is hidden"),
+        }
+    }
+}
+
+impl Error for ConditionFailed {}
+
+/// Visibility rules for a segment. Every condition that is set has to pass for
+/// the segment to be shown
+#[derive(Clone, Default)]
+pub struct Conditions {
+    pub only_ssh: bool,
+    pub only_when_env: Option<String>,
+    pub hide_values: Vec<String>,
+}
+
+impl Conditions {
+    /// Built in rules for segments that the user hasn't configured
+    pub fn default_for(segment: &str) -> Conditions {
+        match segment {
+            "hostname" => Conditions {
+                only_ssh: true,
+                ..Default::default()
+            },
+            _ => Conditions::default(),
+        }
+    }
+
+    /// Overrides any of the defaults in `self` that are set in `table`
+    pub fn merge_table(&mut self, table: &Table) -> Result<(), ConfigError> {
+        if let Some(only_ssh) = config::get_bool(table, "only_ssh")? {
+            self.only_ssh = only_ssh;
+        }
+
+        if let Some(name) = config::get_str(table, "only_when_env")? {
+            self.only_when_env = Some(name.to_string());
+        }
+
+        if let Some(values) = config::get_strings(table, "hide_values")? {
+            self.hide_values = values;
+        }
+
+        Ok(())
+    }
+
+    /// Conditions that can be decided without rendering the segment
+    pub fn check_context(&self, ctx: &Context) -> Result<(), ConditionFailed> {
+        if self.only_ssh && !is_ssh(ctx) {
+            return Err(ConditionFailed::NotSsh);
+        }
+
+        if let Some(name) = &self.only_when_env {
+            if ctx.var(name).is_none() {
+                return Err(ConditionFailed::EnvNotSet(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn check_value(&self, value: &str) -> Result<(), ConditionFailed> {
+        if self.hide_values.iter().any(|v| v == value) {
+            return Err(ConditionFailed::HiddenValue(value.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether promptline runs in an SSH session
+pub(crate) fn is_ssh(ctx: &Context) -> bool {
+    ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"]
+        .iter()
+        .any(|name| ctx.var(name).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn conditions(segment: &str, toml: &str) -> Conditions {
+        let mut conditions = Conditions::default_for(segment);
+        conditions
+            .merge_table(&config::parse(toml).unwrap())
+            .unwrap();
+        conditions
+    }
+
+    fn shown(conditions: &Conditions, vars: &[(&str, &str)], value: &str) -> bool {
+        let ctx = testing::context(vars, None);
+        conditions.check_context(&ctx).is_ok() && conditions.check_value(value).is_ok()
+    }
+
+    #[test]
+    fn hostname_only_over_ssh() {
+        let hostname = conditions("hostname", "");
+        assert!(!shown(&hostname, &[], "box"));
+        assert!(shown(
+            &hostname,
+            &[("SSH_CONNECTION", "10.0.0.2 51234 10.0.0.1 22")],
+            "box"
+        ));
+        assert!(shown(&hostname, &[("SSH_TTY", "/dev/pts/3")], "box"));
+
+        let hostname = conditions("hostname", "only_ssh = false\n");
+        assert!(shown(&hostname, &[], "box"));
+        assert!(!shown(&conditions("cwd", "only_ssh = true\n"), &[], "~"));
+    }
+
+    #[test]
+    fn only_when_env() {
+        let node = conditions("node", "only_when_env = \"NODE_ENV\"\n");
+        assert!(shown(&node, &[("NODE_ENV", "production")], "v20.11.0"));
+        assert!(shown(&node, &[("NODE_ENV", "")], "v20.11.0"));
+        assert!(!shown(&node, &[("NVM_DIR", "/home/ada/.nvm")], "v20.11.0"));
+    }
+
+    #[test]
+    fn hide_values() {
+        let conda = conditions("conda", "hide_values = [\"base\", \"root\"]\n");
+        assert!(!shown(&conda, &[], "base"));
+        assert!(!shown(&conda, &[], "root"));
+        assert!(shown(&conda, &[], "science"));
+        assert!(shown(&conda, &[], "base-old"));
+    }
+
+    #[test]
+    fn all_have_to_pass() {
+        let toml = "only_ssh = true\nonly_when_env = \"TMUX\"\nhide_values = [\"base\"]\n";
+        let conda = conditions("conda", toml);
+        let ssh = ("SSH_CLIENT", "10.0.0.2 51234 22");
+        let tmux = ("TMUX", "/tmp/tmux-1000/default,1234,0");
+
+        assert!(shown(&conda, &[ssh, tmux], "science"));
+        assert!(!shown(&conda, &[ssh, tmux], "base"));
+        assert!(!shown(&conda, &[ssh], "science"));
+        assert!(!shown(&conda, &[tmux], "science"));
+        assert!(!shown(&conda, &[], "science"));
+    }
+}
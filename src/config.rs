@@ -0,0 +1,1316 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    convert::TryFrom,
+    env,
+    error::Error,
+    fmt, fs, io,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+    time::Duration,
+};
+
+use crate::{
+    conditions::Conditions,
+    context,
+    custom::CustomConfig,
+    decorated::{Color, Style},
+    layout, platform,
+    static_text::StaticConfig,
+    theme::Theme,
+};
+
+pub type Table = BTreeMap<String, Value>;
+
+/// A value parsed out of the config file. Only the subset of TOML that
+/// promptline needs is supported: strings, integers, booleans, arrays and
+/// tables.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    UnexpectedChar(char),
+    UnexpectedEof,
+    UnterminatedString,
+    InvalidEscape(char),
+    InvalidNumber(String),
+    DuplicateKey(String),
+    NotATable(String),
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    line: usize,
+    column: usize,
+    kind: ParseErrorKind,
+}
+
+impl ParseError {
+    /// Line and column of the error, both counted from 1
+    pub fn position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of file"),
+            ParseErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            ParseErrorKind::InvalidEscape(c) => write!(f, "invalid escape \\{c}"),
+            ParseErrorKind::InvalidNumber(s) => write!(f, "invalid number {s:?}"),
+            ParseErrorKind::DuplicateKey(k) => write!(f, "duplicate key {k:?}"),
+            ParseErrorKind::NotATable(k) => write!(f, "{k:?} is not a table"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.kind
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, ParseError),
+    InvalidField {
+        field: String,
+        expected: &'static str,
+    },
+    /// A `.promptline.toml` owned by another user, or any on windows where the
+    /// owner isn't checked, which is ignored
+    Untrusted(PathBuf),
+    /// A `.promptline.toml` with custom commands outside of `trusted_dirs`.
+    /// The rest of it still applies
+    UntrustedCommands(PathBuf),
+    /// `$PROMPTLINE_PROFILE` names a profile the config doesn't have
+    UnknownProfile(String),
+}
+
+impl ConfigError {
+    pub fn invalid_field(field: &str, expected: &'static str) -> ConfigError {
+        ConfigError::InvalidField {
+            field: field.to_string(),
+            expected,
+        }
+    }
+
+    /// Whether the config file itself is broken, rather than partly refused
+    pub fn is_invalid(&self) -> bool {
+        matches!(
+            self,
+            ConfigError::Read(..) | ConfigError::Parse(..) | ConfigError::InvalidField { .. }
+        )
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, _) => write!(f, "failed to read {}", path.display()),
+            ConfigError::Parse(path, _) => write!(f, "failed to parse {}", path.display()),
+            ConfigError::InvalidField { field, expected } => {
+                write!(f, "invalid value for {field}, expected {expected}")
+            }
+            ConfigError::Untrusted(path) => {
+                let reason = if cfg!(windows) {
+                    "its owner can't be checked on windows"
+                } else {
+                    "it is owned by another user"
+                };
+                write!(f, "ignoring {}, {reason}", path.display())
+            }
+            ConfigError::UntrustedCommands(path) => write!(
+                f,
+                "ignoring custom commands in {}, its directory is not in trusted_dirs",
+                path.display()
+            ),
+            ConfigError::UnknownProfile(name) => {
+                write!(f, "no profile named {name}, using the base config")
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Read(_, e) => Some(e),
+            ConfigError::Parse(_, e) => Some(e),
+            ConfigError::InvalidField { .. }
+            | ConfigError::Untrusted(_)
+            | ConfigError::UntrustedCommands(_)
+            | ConfigError::UnknownProfile(_) => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// Where the next char is
+    line: usize,
+    column: usize,
+    /// Where the char last taken was, for errors about that char
+    previous: (usize, usize),
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Parser<'a> {
+        Parser {
+            chars: s.chars().peekable(),
+            line: 1,
+            column: 1,
+            previous: (1, 1),
+        }
+    }
+
+    /// An error at the next char
+    fn err(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+
+    /// An error at the char last taken
+    fn err_previous(&self, kind: ParseErrorKind) -> ParseError {
+        let (line, column) = self.previous;
+        ParseError { line, column, kind }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    /// Takes the next char, keeping track of where it was
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.previous = (self.line, self.column);
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn next(&mut self) -> Result<char, ParseError> {
+        self.bump()
+            .ok_or_else(|| self.err(ParseErrorKind::UnexpectedEof))
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(self.err_previous(ParseErrorKind::UnexpectedChar(c))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') = self.peek() {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() != Some('#') {
+            return;
+        }
+        while !matches!(self.peek(), Some('\n') | None) {
+            self.bump();
+        }
+    }
+
+    /// Skips whitespace, newlines and comments, as allowed between
+    /// statements and inside arrays
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') | Some('\r') => {
+                    let _ = self.next();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn expect_line_end(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        self.skip_comment();
+        match self.peek() {
+            None => Ok(()),
+            Some('\r') | Some('\n') => {
+                self.next()?;
+                Ok(())
+            }
+            Some(c) => Err(self.err(ParseErrorKind::UnexpectedChar(c))),
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some('"') => self.parse_basic_string(),
+            Some('\'') => self.parse_literal_string(),
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        break;
+                    }
+                    key.push(c);
+                    self.bump();
+                }
+
+                if key.is_empty() {
+                    let kind = match self.peek() {
+                        Some(c) => ParseErrorKind::UnexpectedChar(c),
+                        None => ParseErrorKind::UnexpectedEof,
+                    };
+                    return Err(self.err(kind));
+                }
+
+                Ok(key)
+            }
+        }
+    }
+
+    fn parse_key_path(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut path = vec![];
+        loop {
+            self.skip_whitespace();
+            path.push(self.parse_key()?);
+            self.skip_whitespace();
+            if self.peek() != Some('.') {
+                return Ok(path);
+            }
+            self.bump();
+        }
+    }
+
+    fn parse_basic_string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = self
+                .next()
+                .map_err(|_| self.err(ParseErrorKind::UnterminatedString))?;
+            match c {
+                '"' => return Ok(s),
+                '\n' => return Err(self.err_previous(ParseErrorKind::UnterminatedString)),
+                '\\' => {
+                    let escaped = match self.next()? {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        'e' => '\x1b',
+                        '"' => '"',
+                        '\\' => '\\',
+                        c => return Err(self.err_previous(ParseErrorKind::InvalidEscape(c))),
+                    };
+                    s.push(escaped);
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn parse_literal_string(&mut self) -> Result<String, ParseError> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            let c = self
+                .next()
+                .map_err(|_| self.err(ParseErrorKind::UnterminatedString))?;
+            match c {
+                '\'' => return Ok(s),
+                '\n' => return Err(self.err_previous(ParseErrorKind::UnterminatedString)),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn parse_word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+') {
+                break;
+            }
+            word.push(c);
+            self.bump();
+        }
+        word
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ParseError> {
+        self.expect('[')?;
+        let mut items = vec![];
+        loop {
+            self.skip_blank();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(Value::Array(items));
+            }
+
+            items.push(self.parse_value()?);
+
+            self.skip_blank();
+            match self.next()? {
+                ',' => (),
+                ']' => return Ok(Value::Array(items)),
+                c => return Err(self.err_previous(ParseErrorKind::UnexpectedChar(c))),
+            }
+        }
+    }
+
+    fn parse_inline_table(&mut self) -> Result<Value, ParseError> {
+        self.expect('{')?;
+        let mut table = Table::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some('}') {
+                self.bump();
+                return Ok(Value::Table(table));
+            }
+
+            self.parse_key_value(&mut table)?;
+
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => (),
+                '}' => return Ok(Value::Table(table)),
+                c => return Err(self.err_previous(ParseErrorKind::UnexpectedChar(c))),
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        match self.peek() {
+            Some('"') => self.parse_basic_string().map(Value::String),
+            Some('\'') => self.parse_literal_string().map(Value::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_inline_table(),
+            Some(_) => {
+                let word = self.parse_word();
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    "" => {
+                        let c = self.next()?;
+                        Err(self.err_previous(ParseErrorKind::UnexpectedChar(c)))
+                    }
+                    _ => word
+                        .replace('_', "")
+                        .parse()
+                        .map(Value::Integer)
+                        .map_err(|_| self.err(ParseErrorKind::InvalidNumber(word))),
+                }
+            }
+            None => Err(self.err(ParseErrorKind::UnexpectedEof)),
+        }
+    }
+
+    fn parse_key_value(&mut self, table: &mut Table) -> Result<(), ParseError> {
+        let path = self.parse_key_path()?;
+        self.skip_whitespace();
+        self.expect('=')?;
+        self.skip_whitespace();
+        let value = self.parse_value()?;
+
+        let (key, parents) = path.split_last().expect("key path is never empty");
+        let table = table_at(table, parents).map_err(|kind| self.err(kind))?;
+        if table.contains_key(key) {
+            return Err(self.err(ParseErrorKind::DuplicateKey(key.clone())));
+        }
+        table.insert(key.clone(), value);
+        Ok(())
+    }
+
+    fn parse_document(&mut self) -> Result<Table, ParseError> {
+        let mut root = Table::new();
+        // Path of the table that key/value pairs are currently written to.
+        // Arrays of tables along the path resolve to their last element
+        let mut current = vec![];
+
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.bump();
+                    let is_array = self.peek() == Some('[');
+                    if is_array {
+                        self.bump();
+                    }
+
+                    let path = self.parse_key_path()?;
+                    self.expect(']')?;
+                    if is_array {
+                        self.expect(']')?;
+                    }
+
+                    let (key, parents) = path.split_last().expect("key path is never empty");
+                    let parent = table_at(&mut root, parents).map_err(|kind| self.err(kind))?;
+                    if is_array {
+                        match parent
+                            .entry(key.clone())
+                            .or_insert_with(|| Value::Array(vec![]))
+                        {
+                            Value::Array(items) => items.push(Value::Table(Table::new())),
+                            _ => return Err(self.err(ParseErrorKind::NotATable(key.clone()))),
+                        }
+                    } else {
+                        table_at(parent, std::slice::from_ref(key))
+                            .map_err(|kind| self.err(kind))?;
+                    }
+
+                    current = path;
+                }
+                Some(_) => {
+                    let table = table_at(&mut root, &current).map_err(|kind| self.err(kind))?;
+                    self.parse_key_value(table)?;
+                }
+            }
+
+            self.expect_line_end()?;
+        }
+    }
+}
+
+/// Walks down `path` from `table`, creating empty tables for missing keys.
+/// Arrays of tables resolve to their most recently added element
+fn table_at<'t>(
+    mut table: &'t mut Table,
+    path: &[String],
+) -> Result<&'t mut Table, ParseErrorKind> {
+    for key in path {
+        let entry = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+
+        table = match entry {
+            Value::Table(t) => t,
+            Value::Array(items) => match items.last_mut() {
+                Some(Value::Table(t)) => t,
+                _ => return Err(ParseErrorKind::NotATable(key.clone())),
+            },
+            _ => return Err(ParseErrorKind::NotATable(key.clone())),
+        };
+    }
+
+    Ok(table)
+}
+
+pub fn parse(s: &str) -> Result<Table, ParseError> {
+    Parser::new(s).parse_document()
+}
+
+pub fn get_str<'t>(table: &'t Table, key: &str) -> Result<Option<&'t str>, ConfigError> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(ConfigError::invalid_field(key, "string")),
+    }
+}
+
+pub fn get_int(table: &Table, key: &str) -> Result<Option<i64>, ConfigError> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::Integer(i)) => Ok(Some(*i)),
+        Some(_) => Err(ConfigError::invalid_field(key, "integer")),
+    }
+}
+
+pub fn get_bool(table: &Table, key: &str) -> Result<Option<bool>, ConfigError> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::Boolean(b)) => Ok(Some(*b)),
+        Some(_) => Err(ConfigError::invalid_field(key, "boolean")),
+    }
+}
+
+pub fn get_strings(table: &Table, key: &str) -> Result<Option<Vec<String>>, ConfigError> {
+    let items = match table.get(key) {
+        None => return Ok(None),
+        Some(Value::Array(items)) => items,
+        Some(_) => return Err(ConfigError::invalid_field(key, "array of strings")),
+    };
+
+    items
+        .iter()
+        .map(|item| match item {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(ConfigError::invalid_field(key, "array of strings")),
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+pub fn get_duration_ms(table: &Table, key: &str) -> Result<Option<Duration>, ConfigError> {
+    match get_int(table, key)? {
+        Some(ms) if ms >= 0 => Ok(Some(Duration::from_millis(ms as u64))),
+        Some(_) => Err(ConfigError::invalid_field(key, "positive integer")),
+        None => Ok(None),
+    }
+}
+
+pub fn get_table<'t>(table: &'t Table, key: &str) -> Result<Option<&'t Table>, ConfigError> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::Table(t)) => Ok(Some(t)),
+        Some(_) => Err(ConfigError::invalid_field(key, "table")),
+    }
+}
+
+pub fn get_tables<'t>(table: &'t Table, key: &str) -> Result<Vec<&'t Table>, ConfigError> {
+    match table.get(key) {
+        None => Ok(vec![]),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::Table(t) => Ok(t),
+                other => Err(ConfigError::InvalidField {
+                    field: format!("{key} ({})", other.type_name()),
+                    expected: "array of tables",
+                }),
+            })
+            .collect(),
+        Some(_) => Err(ConfigError::invalid_field(key, "array of tables")),
+    }
+}
+
+/// A `[segments.<name>]` table
+#[derive(Clone)]
+pub struct SegmentConfig {
+    /// Whether the segment is part of the prompt, `None` for the segment's
+    /// default
+    pub enabled: Option<bool>,
+    pub conditions: Conditions,
+    /// Overrides `Config::budget` for this segment
+    pub budget: Option<Duration>,
+    /// From the `colors` table, the style each color the segment uses is
+    /// shown as instead, whatever the theme
+    pub colors: Vec<(Color, Style)>,
+    /// The whole table, for keys specific to the segment that it reads itself
+    pub options: Table,
+}
+
+impl SegmentConfig {
+    pub fn default_for(segment: &str) -> SegmentConfig {
+        SegmentConfig {
+            enabled: None,
+            conditions: Conditions::default_for(segment),
+            budget: None,
+            colors: vec![],
+            options: Table::new(),
+        }
+    }
+
+    fn from_table(segment: &str, table: &Table) -> Result<SegmentConfig, ConfigError> {
+        let mut config = SegmentConfig::default_for(segment);
+        config.enabled = get_bool(table, "enabled")?;
+        config.conditions.merge_table(table)?;
+        config.budget = get_duration_ms(table, "budget_ms")?;
+        if let Some(colors) = get_table(table, "colors")? {
+            config.colors = colors
+                .iter()
+                .map(|(name, style)| {
+                    let color = Color::from_name(name)
+                        .ok_or_else(|| ConfigError::invalid_field(name, "color name"))?;
+                    let style = match style {
+                        Value::String(words) => Style::from_words(words),
+                        _ => None,
+                    }
+                    .ok_or_else(|| {
+                        ConfigError::invalid_field(name, "colors and bold, dim or underline")
+                    })?;
+                    Ok((color, style))
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        config.options = table.clone();
+        Ok(config)
+    }
+}
+
+const DEFAULT_BUDGET: Duration = Duration::from_millis(100);
+pub(crate) const DEFAULT_PROMPT_CHAR: &str = ">";
+const DEFAULT_MAX_LANGUAGES: usize = 1;
+
+fn default_drop_order() -> Vec<String> {
+    layout::DEFAULT_DROP_ORDER
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Prompt chars used in place of `prompt_char` inside of a git work tree, by
+/// whether it has changes. A state without one keeps `prompt_char`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RepoPromptChars {
+    pub clean: Option<String>,
+    pub dirty: Option<String>,
+}
+
+/// Segments shown as a single component, from a `[[merge]]` entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeConfig {
+    /// Names of the segments, in the order they are joined
+    pub segments: Vec<String>,
+    /// Put between the members, in the color of the first one. A space unless
+    /// set
+    pub joiner: String,
+}
+
+impl MergeConfig {
+    fn from_table(table: &Table) -> Result<MergeConfig, ConfigError> {
+        let segments = get_strings(table, "segments")?
+            .filter(|segments| !segments.is_empty())
+            .ok_or_else(|| ConfigError::invalid_field("merge.segments", "array of strings"))?;
+        let joiner = get_str(table, "joiner")?.unwrap_or(" ").to_string();
+        Ok(MergeConfig { segments, joiner })
+    }
+}
+
+/// Which marks segments put in front of their text
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum IconMode {
+    /// Symbols like `☿`, some of them from Nerd Fonts
+    #[default]
+    Unicode,
+    /// Text labels like `hg:`, for fonts and terminals without the symbols
+    Ascii,
+}
+
+pub struct Config {
+    pub custom: Vec<CustomConfig>,
+    /// `[[static]]` entries, in the order they appear
+    pub statics: Vec<StaticConfig>,
+    pub segments: BTreeMap<String, SegmentConfig>,
+    /// How long a segment may take before the prompt is printed without it
+    pub budget: Duration,
+    /// Show a placeholder in place of segments that ran out of time
+    pub timeout_placeholder: bool,
+    /// Render segments even if their conditions would hide them
+    pub ignore_conditions: bool,
+    /// Don't scale back filesystem probes on network filesystems
+    pub force_full_probes: bool,
+    /// Names shown in place of host names, keyed by the lowercased host name
+    pub host_aliases: BTreeMap<String, String>,
+    /// Segments to drop first when the prompt is wider than the terminal
+    pub drop_order: Vec<String>,
+    /// Shorten the cwd to fit the terminal before dropping it
+    pub truncate_cwd_to_fit: bool,
+    /// Ends the prompt, and makes up all of the transient prompt
+    pub prompt_char: String,
+    /// How many project segments, like the ones for languages, show at once.
+    /// The ones whose project is closest to the cwd win
+    pub max_languages: usize,
+    /// From `icons`, `unicode` or `ascii`
+    pub icons: IconMode,
+    /// ASCII frame of the last line for zsh, which then gets no other
+    /// characters on that line
+    pub zsh_last_line_frame: Option<String>,
+    /// Segments on each line above the one the command is typed on. Empty for
+    /// all of them on one line
+    pub lines: Vec<Vec<String>>,
+    /// From the `[repo_prompt_chars]` table. The work tree is only checked
+    /// for changes when it is set
+    pub repo_prompt_chars: Option<RepoPromptChars>,
+    /// `[[merge]]` entries, applied in the order they appear
+    pub merges: Vec<MergeConfig>,
+    /// Let `promptline prefetch`, which the init snippets run whenever the
+    /// shell changes directory, count the changed files of a git work tree
+    /// ahead of the prompt
+    pub prefetch: bool,
+    /// Styles for the colors of the segments, unless `$PROMPTLINE_THEME` picks
+    /// another
+    pub theme: Theme,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            custom: vec![],
+            statics: vec![],
+            segments: BTreeMap::new(),
+            budget: DEFAULT_BUDGET,
+            timeout_placeholder: false,
+            ignore_conditions: false,
+            force_full_probes: false,
+            host_aliases: BTreeMap::new(),
+            drop_order: default_drop_order(),
+            truncate_cwd_to_fit: true,
+            prompt_char: DEFAULT_PROMPT_CHAR.to_string(),
+            max_languages: DEFAULT_MAX_LANGUAGES,
+            icons: IconMode::default(),
+            zsh_last_line_frame: None,
+            lines: vec![],
+            repo_prompt_chars: None,
+            merges: vec![],
+            prefetch: false,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_table(table: &Table) -> Result<Config, ConfigError> {
+        let custom = get_tables(table, "custom")?
+            .into_iter()
+            .map(CustomConfig::from_table)
+            .collect::<Result<_, _>>()?;
+
+        let statics = get_tables(table, "static")?
+            .into_iter()
+            .map(StaticConfig::from_table)
+            .collect::<Result<_, _>>()?;
+
+        let merges = get_tables(table, "merge")?
+            .into_iter()
+            .map(MergeConfig::from_table)
+            .collect::<Result<_, _>>()?;
+
+        let mut segments = BTreeMap::new();
+        if let Some(segment_tables) = get_table(table, "segments")? {
+            for (name, value) in segment_tables {
+                let segment_table = match value {
+                    Value::Table(t) => t,
+                    _ => return Err(ConfigError::invalid_field(name, "table")),
+                };
+                segments.insert(
+                    name.clone(),
+                    SegmentConfig::from_table(name, segment_table)?,
+                );
+            }
+        }
+
+        let budget = get_duration_ms(table, "segment_budget_ms")?.unwrap_or(DEFAULT_BUDGET);
+        let timeout_placeholder = get_bool(table, "timeout_placeholder")?.unwrap_or(false);
+        let force_full_probes = get_bool(table, "force_full_probes")?.unwrap_or(false);
+        let prefetch = get_bool(table, "prefetch")?.unwrap_or(false);
+        let theme = match get_str(table, "theme")? {
+            Some(name) => Theme::from_name(name).ok_or_else(|| {
+                ConfigError::invalid_field("theme", "default, light, colorblind or mono")
+            })?,
+            None => Theme::default(),
+        };
+
+        let drop_order = get_strings(table, "drop_order")?.unwrap_or_else(default_drop_order);
+        let truncate_cwd_to_fit = get_bool(table, "truncate_cwd_to_fit")?.unwrap_or(true);
+        let prompt_char = get_str(table, "prompt_char")?
+            .unwrap_or(DEFAULT_PROMPT_CHAR)
+            .to_string();
+        let max_languages = match get_int(table, "max_languages")? {
+            Some(max) => usize::try_from(max)
+                .map_err(|_| ConfigError::invalid_field("max_languages", "count"))?,
+            None => DEFAULT_MAX_LANGUAGES,
+        };
+        let icons = match get_str(table, "icons")? {
+            None | Some("unicode") => IconMode::Unicode,
+            Some("ascii") => IconMode::Ascii,
+            Some(_) => return Err(ConfigError::invalid_field("icons", "unicode or ascii")),
+        };
+        let zsh_last_line_frame = match get_str(table, "zsh_last_line_frame")? {
+            Some(frame) if frame.chars().all(|c| c.is_ascii_graphic() || c == ' ') => {
+                Some(frame.to_string())
+            }
+            Some(_) => {
+                return Err(ConfigError::invalid_field(
+                    "zsh_last_line_frame",
+                    "printable ASCII",
+                ))
+            }
+            None => None,
+        };
+
+        let invalid_lines = || ConfigError::invalid_field("lines", "array of arrays of strings");
+        let lines = match table.get("lines") {
+            None => vec![],
+            Some(Value::Array(lines)) => lines
+                .iter()
+                .map(|line| match line {
+                    Value::Array(names) => names
+                        .iter()
+                        .map(|name| match name {
+                            Value::String(name) => Ok(name.clone()),
+                            _ => Err(invalid_lines()),
+                        })
+                        .collect(),
+                    _ => Err(invalid_lines()),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(invalid_lines()),
+        };
+
+        let repo_prompt_chars = match get_table(table, "repo_prompt_chars")? {
+            Some(chars) => Some(RepoPromptChars {
+                clean: get_str(chars, "clean")?.map(str::to_string),
+                dirty: get_str(chars, "dirty")?.map(str::to_string),
+            }),
+            None => None,
+        };
+
+        let mut host_aliases = BTreeMap::new();
+        if let Some(aliases) = get_table(table, "host_aliases")? {
+            for (host, alias) in aliases {
+                let alias = match alias {
+                    Value::String(s) => s.clone(),
+                    _ => return Err(ConfigError::invalid_field(host, "string")),
+                };
+                host_aliases.insert(host.to_lowercase(), alias);
+            }
+        }
+
+        Ok(Config {
+            custom,
+            statics,
+            segments,
+            budget,
+            timeout_placeholder,
+            ignore_conditions: false,
+            force_full_probes,
+            host_aliases,
+            drop_order,
+            truncate_cwd_to_fit,
+            prompt_char,
+            max_languages,
+            icons,
+            zsh_last_line_frame,
+            lines,
+            repo_prompt_chars,
+            merges,
+            prefetch,
+            theme,
+        })
+    }
+
+    pub fn segment(&self, name: &str) -> Cow<'_, SegmentConfig> {
+        match self.segments.get(name) {
+            Some(config) => Cow::Borrowed(config),
+            None => Cow::Owned(SegmentConfig::default_for(name)),
+        }
+    }
+
+    /// Leaves the segment `name` out of the prompt, whatever the config says
+    pub fn disable_segment(&mut self, name: &str) {
+        self.segments
+            .entry(name.to_string())
+            .or_insert_with(|| SegmentConfig::default_for(name))
+            .enabled = Some(false);
+    }
+
+    pub fn budget_for(&self, name: &str) -> Duration {
+        self.segments
+            .get(name)
+            .and_then(|segment| segment.budget)
+            .unwrap_or(self.budget)
+    }
+
+    pub fn from_path(path: &Path) -> Result<Config, ConfigError> {
+        Config::from_table(&read_table(path)?)
+    }
+}
+
+fn read_table(path: &Path) -> Result<Table, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+    parse(&content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+}
+
+/// Per-directory config, looked up in the cwd and its ancestors
+pub const LOCAL_CONFIG_NAME: &str = ".promptline.toml";
+
+/// The closest `.promptline.toml` above the cwd
+pub fn local_config_path() -> Option<PathBuf> {
+    let cwd = context::logical_cwd(env::var("PWD").ok().as_deref())?;
+    let dir = context::find_ancestor_with(&cwd, LOCAL_CONFIG_NAME)?;
+    Some(dir.join(LOCAL_CONFIG_NAME))
+}
+
+/// The table in the `.promptline.toml` at `path`, unless it is owned by
+/// another user, who could otherwise plant one in a shared directory
+fn local_overrides(path: &Path, owned: bool) -> Result<Table, ConfigError> {
+    if !owned {
+        return Err(ConfigError::Untrusted(path.to_path_buf()));
+    }
+    read_table(path)
+}
+
+/// Merges `overrides` into `table`, key by key for tables. Any other value,
+/// arrays included, replaces the one in `table`
+fn merge(table: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(value)) => merge(existing, value),
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Applies the `[profiles.<profile>]` table of the config on top of the rest
+/// of it, leaving the base config alone if there is no such profile. The
+/// profiles themselves are dropped either way
+fn apply_profile(table: &mut Table, profile: Option<&str>) -> Result<(), ConfigError> {
+    let mut profiles = match table.remove("profiles") {
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => return Err(ConfigError::invalid_field("profiles", "table")),
+        None => Table::new(),
+    };
+
+    let name = match profile {
+        Some(name) if !name.is_empty() => name,
+        _ => return Ok(()),
+    };
+    match profiles.remove(name) {
+        Some(Value::Table(profile)) => {
+            merge(table, profile);
+            Ok(())
+        }
+        Some(_) => Err(ConfigError::invalid_field(
+            &format!("profiles.{name}"),
+            "table",
+        )),
+        None => Err(ConfigError::UnknownProfile(name.to_string())),
+    }
+}
+
+/// Applies the `local` overrides from the `.promptline.toml` at `path` on top
+/// of the `global` config. Custom commands in it are dropped unless its
+/// directory is listed in the global `trusted_dirs`, and only the global
+/// config can extend that list or define profiles
+fn apply_local(global: &mut Table, mut local: Table, path: &Path) -> Result<(), ConfigError> {
+    local.remove("trusted_dirs");
+    local.remove("profiles");
+
+    let dir = path.parent().unwrap_or(path);
+    let trusted = get_strings(global, "trusted_dirs")?
+        .unwrap_or_default()
+        .iter()
+        .any(|trusted| platform::same_file(Path::new(trusted), dir));
+    let refused_commands = !trusted && local.remove("custom").is_some();
+
+    merge(global, local);
+
+    if refused_commands {
+        return Err(ConfigError::UntrustedCommands(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// What `$PROMPTLINE_DISABLE` turns off, to get promptline out of the way
+/// without touching the shell's rc files
+#[derive(Debug, PartialEq)]
+pub enum Disable {
+    Nothing,
+    /// `1`, the whole prompt, leaving a bare `$ `
+    Everything,
+    /// A comma separated list of segment names
+    Segments(Vec<String>),
+}
+
+impl Disable {
+    pub fn from_env_value(value: Option<&str>) -> Disable {
+        match value.map(str::trim) {
+            None | Some("" | "0") => Disable::Nothing,
+            Some("1") => Disable::Everything,
+            Some(names) => Disable::Segments(
+                names
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// `$PROMPTLINE_CONFIG` if set, otherwise `promptline/config.toml` in the XDG
+/// config directory
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("PROMPTLINE_CONFIG") {
+        return Some(path.into());
+    }
+
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_dir.join("promptline/config.toml"))
+}
+
+/// Loads the user's config in three layers, each overriding the one before:
+/// the base config file, the profile in it named by `$PROMPTLINE_PROFILE`, and
+/// the closest `.promptline.toml` above the cwd. A missing config file is not
+/// an error, promptline just falls back to its defaults. Also returns why the
+/// config, or part of it, was left out: an invalid global config or profile
+/// leaves only the defaults, an unknown profile leaves the base config, and a
+/// refused or invalid `.promptline.toml` leaves the global config
+pub fn load() -> (Config, Option<ConfigError>) {
+    load_profile(env::var("PROMPTLINE_PROFILE").ok().as_deref())
+}
+
+/// Like [`load`], with `profile` in place of `$PROMPTLINE_PROFILE`
+pub fn load_profile(profile: Option<&str>) -> (Config, Option<ConfigError>) {
+    let global = match config_path() {
+        Some(path) if path.exists() => read_table(&path),
+        _ => Ok(Table::new()),
+    };
+    let mut profile_error = None;
+    let global = global.and_then(|mut table| {
+        match apply_profile(&mut table, profile) {
+            Err(e @ ConfigError::UnknownProfile(_)) => profile_error = Some(e),
+            result => result?,
+        }
+        Ok((Config::from_table(&table)?, table))
+    });
+    let (global, mut table) = match global {
+        Ok(global) => global,
+        Err(e) => return (Config::default(), Some(e)),
+    };
+
+    let path = match local_config_path() {
+        Some(path) => path,
+        None => return (global, profile_error),
+    };
+
+    let owned = platform::owned_by_current_user(&path);
+    let applied =
+        local_overrides(&path, owned).and_then(|local| apply_local(&mut table, local, &path));
+
+    match Config::from_table(&table) {
+        Ok(config) => (config, applied.err().or(profile_error)),
+        Err(e) => (global, Some(applied.err().unwrap_or(e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const GLOBAL: &str = r#"
+prompt_char = ">"
+
+[segments.git]
+enabled = false
+budget_ms = 50
+
+[[custom]]
+name = "global"
+command = "echo global"
+"#;
+
+    #[test]
+    fn local_overrides_global() {
+        let dir = TempDir::new();
+        let path = dir.path().join(LOCAL_CONFIG_NAME);
+        let mut table = parse(GLOBAL).unwrap();
+        let local = parse("prompt_char = \"$\"\n[segments.git]\nenabled = true\n").unwrap();
+
+        apply_local(&mut table, local, &path).unwrap();
+        let config = Config::from_table(&table).unwrap();
+        assert_eq!(config.prompt_char, "$");
+        assert_eq!(config.segment("git").enabled, Some(true));
+        // Keys the local file leaves out keep their global values
+        assert_eq!(config.budget_for("git"), Duration::from_millis(50));
+        assert_eq!(config.custom.len(), 1);
+    }
+
+    #[test]
+    fn refuses_files_of_other_users() {
+        let dir = TempDir::new();
+        dir.write(LOCAL_CONFIG_NAME, "prompt_char = \"$\"\n");
+        let path = dir.path().join(LOCAL_CONFIG_NAME);
+
+        assert!(matches!(
+            local_overrides(&path, false),
+            Err(ConfigError::Untrusted(_))
+        ));
+        assert!(local_overrides(&path, true).is_ok());
+    }
+
+    #[test]
+    fn refuses_commands_outside_trusted_dirs() {
+        let dir = TempDir::new();
+        let path = dir.path().join(LOCAL_CONFIG_NAME);
+        let local = r#"
+prompt_char = "$"
+trusted_dirs = ["/"]
+
+[[custom]]
+name = "local"
+command = "echo local"
+"#;
+
+        let mut table = parse(GLOBAL).unwrap();
+        let e = apply_local(&mut table, parse(local).unwrap(), &path).unwrap_err();
+        assert!(matches!(e, ConfigError::UntrustedCommands(_)));
+        let config = Config::from_table(&table).unwrap();
+        assert_eq!(config.prompt_char, "$");
+        assert_eq!(config.custom[0].name, "global");
+
+        let mut table = parse(GLOBAL).unwrap();
+        table.insert(
+            "trusted_dirs".into(),
+            Value::Array(vec![Value::String(dir.path().display().to_string())]),
+        );
+        apply_local(&mut table, parse(local).unwrap(), &path).unwrap();
+        let config = Config::from_table(&table).unwrap();
+        assert_eq!(config.custom[0].name, "local");
+    }
+
+    const PROFILES: &str = r#"
+[profiles.demo]
+prompt_char = "%"
+
+[profiles.demo.segments.git]
+budget_ms = 10
+
+[profiles.dev.segments.git]
+enabled = true
+"#;
+
+    #[test]
+    fn profile_overrides_base() {
+        let mut table = parse(&format!("{GLOBAL}{PROFILES}")).unwrap();
+        apply_profile(&mut table, Some("demo")).unwrap();
+        let config = Config::from_table(&table).unwrap();
+        assert_eq!(config.prompt_char, "%");
+        assert_eq!(config.budget_for("git"), Duration::from_millis(10));
+        assert_eq!(config.segment("git").enabled, Some(false));
+        assert!(!table.contains_key("profiles"));
+    }
+
+    #[test]
+    fn unknown_profile_keeps_base() {
+        let mut table = parse(&format!("{GLOBAL}{PROFILES}")).unwrap();
+        let e = apply_profile(&mut table, Some("talk")).unwrap_err();
+        assert!(matches!(e, ConfigError::UnknownProfile(name) if name == "talk"));
+        let config = Config::from_table(&table).unwrap();
+        assert_eq!(config.prompt_char, ">");
+
+        let mut table = parse(&format!("{GLOBAL}{PROFILES}")).unwrap();
+        apply_profile(&mut table, None).unwrap();
+        assert_eq!(Config::from_table(&table).unwrap().prompt_char, ">");
+    }
+
+    #[test]
+    fn base_then_profile_then_local() {
+        let dir = TempDir::new();
+        let path = dir.path().join(LOCAL_CONFIG_NAME);
+        let mut table = parse(&format!("{GLOBAL}{PROFILES}")).unwrap();
+        let local = "[segments.git]\nbudget_ms = 20\n\n[profiles.demo]\nprompt_char = \"!\"\n";
+
+        apply_profile(&mut table, Some("demo")).unwrap();
+        apply_local(&mut table, parse(local).unwrap(), &path).unwrap();
+        let config = Config::from_table(&table).unwrap();
+        // The profile wins over the base config
+        assert_eq!(config.prompt_char, "%");
+        // and the directory over both, but can't define profiles of its own
+        assert_eq!(config.budget_for("git"), Duration::from_millis(20));
+        assert_eq!(config.segment("git").enabled, Some(false));
+    }
+
+    #[test]
+    fn disable_values() {
+        assert_eq!(Disable::from_env_value(None), Disable::Nothing);
+        assert_eq!(Disable::from_env_value(Some("0")), Disable::Nothing);
+        assert_eq!(Disable::from_env_value(Some("1")), Disable::Everything);
+        assert_eq!(
+            Disable::from_env_value(Some("git, hg,,kube")),
+            Disable::Segments(vec!["git".into(), "hg".into(), "kube".into()])
+        );
+    }
+
+    #[test]
+    fn disabled_segment_overrides_config() {
+        let mut config = Config::from_table(&parse(GLOBAL).unwrap()).unwrap();
+        config.disable_segment("git");
+        config.disable_segment("cwd");
+        assert_eq!(config.segment("git").enabled, Some(false));
+        assert_eq!(config.budget_for("git"), Duration::from_millis(50));
+        assert_eq!(config.segment("cwd").enabled, Some(false));
+    }
+
+    #[test]
+    fn lines() {
+        let toml = "lines = [[\"time\", \"host\"], [], [\"cwd\"]]\n";
+        let config = Config::from_table(&parse(toml).unwrap()).unwrap();
+        assert_eq!(config.lines, [vec!["time", "host"], vec![], vec!["cwd"]]);
+
+        for toml in [
+            "lines = [\"time\"]\n",
+            "lines = [[1]]\n",
+            "lines = \"time\"\n",
+        ] {
+            assert!(Config::from_table(&parse(toml).unwrap()).is_err());
+        }
+    }
+
+    #[test]
+    fn merges() {
+        let toml = "[[merge]]\nsegments = [\"status\", \"duration\"]\njoiner = \" · \"\n\n[[merge]]\nsegments = [\"user\", \"hostname\"]\n";
+        let config = Config::from_table(&parse(toml).unwrap()).unwrap();
+        assert_eq!(
+            config.merges,
+            [
+                MergeConfig {
+                    segments: vec!["status".into(), "duration".into()],
+                    joiner: " · ".into(),
+                },
+                MergeConfig {
+                    segments: vec!["user".into(), "hostname".into()],
+                    joiner: " ".into(),
+                },
+            ]
+        );
+
+        for toml in [
+            "[[merge]]\njoiner = \"@\"\n",
+            "[[merge]]\nsegments = []\n",
+            "[[merge]]\nsegments = [\"user\"]\njoiner = 1\n",
+        ] {
+            assert!(Config::from_table(&parse(toml).unwrap()).is_err());
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use crate::{
+    args::Args,
+    cache::RootCache,
+    config::{self, Config},
+    fstype, platform,
+};
+
+/// Everything a segment needs to know about the environment promptline runs
+/// in. Built once per run and shared by all segments
+pub struct Context {
+    pub args: Args,
+    pub config: Config,
+    /// The cwd is on a network filesystem, where probes should be kept to a
+    /// minimum
+    pub network_fs: bool,
+    env: HashMap<String, String>,
+    cwd: Option<PathBuf>,
+    /// Directory that absolute paths read by segments are resolved under, `/`
+    /// outside of tests
+    root: PathBuf,
+    git_root: OnceLock<Option<PathBuf>>,
+    hg_root: OnceLock<Option<PathBuf>>,
+    /// Roots found by earlier prompts in the cwd, `None` without a cache dir
+    root_cache: OnceLock<Option<Mutex<RootCache>>>,
+}
+
+impl Context {
+    /// Snapshots the process environment and working directory
+    pub fn new(args: Args, config: Config) -> Context {
+        let env: HashMap<_, _> = env::vars_os()
+            .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+            .collect();
+
+        let cwd = logical_cwd(env.get("PWD").map(String::as_str));
+
+        let network_fs =
+            !config.force_full_probes && cwd.as_deref().map(fstype::is_network_fs).unwrap_or(false);
+
+        Context {
+            network_fs,
+            ..Context::with_env(args, config, env, cwd)
+        }
+    }
+
+    /// A context for the process's environment with the user's config file,
+    /// as the prompt would have it without any flags. A config file that
+    /// fails to load is left out
+    ///
+    /// ```
+    /// let ctx = promptline::Context::from_env();
+    /// assert_eq!(ctx.var("PATH"), std::env::var("PATH").ok().as_deref());
+    /// ```
+    pub fn from_env() -> Context {
+        let (config, _) = config::load();
+        let args = Args::parse(std::iter::empty()).unwrap_or_default();
+        Context::new(args, config)
+    }
+
+    /// A context with the given environment instead of the process's, for
+    /// rendering segments in tests
+    pub fn with_env(
+        args: Args,
+        config: Config,
+        env: HashMap<String, String>,
+        cwd: Option<PathBuf>,
+    ) -> Context {
+        Context {
+            args,
+            config,
+            network_fs: false,
+            env,
+            cwd,
+            root: PathBuf::from("/"),
+            git_root: OnceLock::new(),
+            hg_root: OnceLock::new(),
+            root_cache: OnceLock::new(),
+        }
+    }
+
+    /// Resolves absolute paths under `root` instead of `/`
+    pub fn with_root(self, root: PathBuf) -> Context {
+        Context { root, ..self }
+    }
+
+    /// Value of the environment variable `name` when promptline started
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.env.get(name).map(String::as_str)
+    }
+
+    /// Names of the environment variables when promptline started, in no
+    /// particular order
+    pub fn var_names(&self) -> impl Iterator<Item = &str> {
+        self.env.keys().map(String::as_str)
+    }
+
+    /// The working directory as the shell shows it, with symlinks unresolved
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    /// Where `path` is on the filesystem segments read from. Relative paths are
+    /// resolved against the cwd
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        let path = match self.cwd() {
+            Some(cwd) if path.is_relative() => cwd.join(path),
+            _ => path.to_path_buf(),
+        };
+
+        match path.strip_prefix("/") {
+            Ok(relative) => self.root.join(relative),
+            Err(_) => path,
+        }
+    }
+
+    pub fn exists(&self, path: impl AsRef<Path>) -> bool {
+        self.path(path).exists()
+    }
+
+    pub fn read_to_string(&self, path: impl AsRef<Path>) -> io::Result<String> {
+        fs::read_to_string(self.path(path))
+    }
+
+    /// Where segments cache results between prompts. Only `$XDG_RUNTIME_DIR`
+    /// is used, as no other user can write to it
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        let runtime_dir = self.var("XDG_RUNTIME_DIR")?;
+        Some(self.path(runtime_dir).join("promptline"))
+    }
+
+    /// Root of the git repository containing the cwd
+    pub fn git_root(&self) -> Option<&Path> {
+        self.git_root
+            .get_or_init(|| self.find_root(&[".git"]))
+            .as_deref()
+    }
+
+    /// Root of the mercurial repository containing the cwd
+    pub fn hg_root(&self) -> Option<&Path> {
+        self.hg_root
+            .get_or_init(|| self.find_root(&[".hg"]))
+            .as_deref()
+    }
+
+    /// Closest ancestor of the cwd, or the cwd itself, containing any of
+    /// `markers`, like the `pom.xml` of a Maven project
+    pub fn find_root(&self, markers: &[&str]) -> Option<PathBuf> {
+        let cwd = self.path(self.cwd()?);
+
+        let cache = self
+            .root_cache
+            .get_or_init(|| Some(Mutex::new(RootCache::load(&self.cache_dir()?, &cwd))))
+            .as_ref();
+        // A root cached by an earlier prompt may have been deleted since
+        match cache.and_then(|cache| cache.lock().ok()?.get(markers)) {
+            Some(Some(root)) if markers.iter().any(|marker| root.join(marker).exists()) => {
+                return Some(root)
+            }
+            Some(None) => return None,
+            _ => {}
+        }
+
+        let root = self.walk_to_root(&cwd, markers);
+        if let Some(Ok(mut cache)) = cache.map(Mutex::lock) {
+            cache.insert(markers, root.as_deref());
+        }
+        root
+    }
+
+    fn walk_to_root(&self, cwd: &Path, markers: &[&str]) -> Option<PathBuf> {
+        // The logical path is what the user sees, but `..` components of
+        // symlinked directories can lead elsewhere in the physical one
+        if let Some(root) = find_ancestor_with_any(cwd, markers) {
+            return Some(root);
+        }
+
+        // Resolving every component is a round trip each on a network filesystem
+        if self.network_fs {
+            return None;
+        }
+
+        let canonical_cwd = fs::canonicalize(cwd).ok()?;
+        find_ancestor_with_any(&canonical_cwd, markers)
+    }
+}
+
+/// `$PWD` if it refers to the actual working directory, the physical working
+/// directory otherwise
+pub(crate) fn logical_cwd(pwd: Option<&str>) -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+
+    let pwd = match pwd {
+        Some(pwd) => PathBuf::from(pwd),
+        None => return Some(cwd),
+    };
+
+    if pwd.is_absolute() && platform::same_file(&pwd, &cwd) {
+        Some(pwd)
+    } else {
+        Some(cwd)
+    }
+}
+
+/// Closest ancestor of `dir`, or `dir` itself, that contains `marker`
+pub(crate) fn find_ancestor_with(dir: &Path, marker: &str) -> Option<PathBuf> {
+    find_ancestor_with_any(dir, &[marker])
+}
+
+/// Closest ancestor of `dir`, or `dir` itself, that contains any of `markers`
+pub(crate) fn find_ancestor_with_any(dir: &Path, markers: &[&str]) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|dir| markers.iter().any(|marker| dir.join(marker).exists()))
+        .map(Path::to_path_buf)
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    /// `repo` with a git dir, and `link` pointing into it from outside
+    fn linked_repo() -> TempDir {
+        let root = TempDir::new();
+        root.write("repo/.git/HEAD", "ref: refs/heads/main\n");
+        root.write("repo/src/.keep", "");
+        symlink(root.path().join("repo/src"), root.path().join("link")).unwrap();
+        root
+    }
+
+    #[test]
+    fn root_through_symlink() {
+        let root = linked_repo();
+        let ctx = testing::context(&[], Some("/link")).with_root(root.path().to_path_buf());
+        assert_eq!(ctx.cwd(), Some(Path::new("/link")));
+        // Nothing above the link has a `.git`, the physical path does
+        let repo = fs::canonicalize(root.path().join("repo")).unwrap();
+        assert_eq!(ctx.git_root(), Some(repo.as_path()));
+
+        let mut ctx = testing::context(&[], Some("/link")).with_root(root.path().to_path_buf());
+        ctx.network_fs = true;
+        assert_eq!(ctx.git_root(), None);
+    }
+
+    #[test]
+    fn logical_root_first() {
+        let root = linked_repo();
+        // `up` is physically in the outer repo, but logically in the inner one
+        root.write("repo/src/inner/.git/HEAD", "ref: refs/heads/main\n");
+        symlink(
+            root.path().join("repo/src"),
+            root.path().join("repo/src/inner/up"),
+        )
+        .unwrap();
+
+        let ctx =
+            testing::context(&[], Some("/repo/src/inner/up")).with_root(root.path().to_path_buf());
+        assert_eq!(
+            ctx.git_root(),
+            Some(root.path().join("repo/src/inner").as_path())
+        );
+    }
+}
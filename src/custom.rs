@@ -0,0 +1,366 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read},
+    path::PathBuf,
+    process::{Command, ExitStatus, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{self, ConfigError, Table},
+    decorated::{Color, DecoratedString},
+    platform,
+    segment::{ErrorKind, RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Gate for a custom segment, evaluated before its command is spawned
+#[derive(Clone)]
+pub enum Condition {
+    /// `file_exists:<path>`, relative paths are resolved against the cwd
+    FileExists(PathBuf),
+    /// `env:<name>`, passes if the variable is set
+    EnvSet(String),
+}
+
+impl Condition {
+    fn parse(s: &str) -> Option<Condition> {
+        let (kind, arg) = s.split_once(':')?;
+        match kind {
+            "file_exists" => Some(Condition::FileExists(arg.into())),
+            "env" => Some(Condition::EnvSet(arg.to_string())),
+            _ => None,
+        }
+    }
+
+    fn is_met(&self, ctx: &Context) -> bool {
+        match self {
+            Condition::FileExists(path) => ctx.exists(path),
+            Condition::EnvSet(name) => ctx.var(name).is_some(),
+        }
+    }
+}
+
+/// A `[[custom]]` entry in the config file
+#[derive(Clone)]
+pub struct CustomConfig {
+    pub name: String,
+    pub command: String,
+    pub color: Option<Color>,
+    pub timeout: Duration,
+    pub when: Option<Condition>,
+    /// Index in the segment list to insert at, appended to the end if unset
+    pub position: Option<usize>,
+}
+
+impl CustomConfig {
+    pub fn from_table(table: &Table) -> Result<CustomConfig, ConfigError> {
+        let name = config::get_str(table, "name")?
+            .ok_or_else(|| ConfigError::invalid_field("custom.name", "string"))?
+            .to_string();
+
+        let command = config::get_str(table, "command")?
+            .ok_or_else(|| ConfigError::invalid_field("custom.command", "string"))?
+            .to_string();
+
+        let color = config::get_str(table, "color")?
+            .map(|s| {
+                Color::from_name(s)
+                    .ok_or_else(|| ConfigError::invalid_field("custom.color", "color name"))
+            })
+            .transpose()?;
+
+        let timeout = config::get_duration_ms(table, "timeout_ms")?.unwrap_or(DEFAULT_TIMEOUT);
+
+        let when = config::get_str(table, "when")?
+            .map(|s| {
+                Condition::parse(s).ok_or_else(|| {
+                    ConfigError::invalid_field("custom.when", "file_exists:<path> or env:<name>")
+                })
+            })
+            .transpose()?;
+
+        let position = match config::get_int(table, "position")? {
+            Some(i) if i >= 0 => Some(i as usize),
+            Some(_) => {
+                return Err(ConfigError::invalid_field(
+                    "custom.position",
+                    "positive integer",
+                ))
+            }
+            None => None,
+        };
+
+        Ok(CustomConfig {
+            name,
+            command,
+            color,
+            timeout,
+            when,
+            position,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum CustomError {
+    ConditionNotMet,
+    Spawn(io::Error),
+    Wait(io::Error),
+    ReadOutput(io::Error),
+    Timeout(Duration),
+    ExitStatus(ExitStatus, String),
+    EmptyOutput,
+}
+
+impl fmt::Display for CustomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CustomError::ConditionNotMet => write!(f, "condition not met"),
+            CustomError::Spawn(_) => write!(f, "failed to spawn command"),
+            CustomError::Wait(_) => write!(f, "failed to wait for command"),
+            CustomError::ReadOutput(_) => write!(f, "failed to read command output"),
+            CustomError::Timeout(timeout) => {
+                write!(f, "command timed out after {}ms", timeout.as_millis())
+            }
+            CustomError::ExitStatus(status, stderr) => {
+                write!(f, "command failed with {status}")?;
+                if !stderr.is_empty() {
+                    write!(f, ": {}", stderr.trim_end())?;
+                }
+                Ok(())
+            }
+            CustomError::EmptyOutput => write!(f, "command produced no output"),
+        }
+    }
+}
+
+impl Error for CustomError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CustomError::Spawn(e) => Some(e),
+            CustomError::Wait(e) => Some(e),
+            CustomError::ReadOutput(e) => Some(e),
+            CustomError::ConditionNotMet
+            | CustomError::Timeout(_)
+            | CustomError::ExitStatus(..)
+            | CustomError::EmptyOutput => None,
+        }
+    }
+}
+
+fn read_in_background<R: Read + Send + 'static>(
+    mut reader: R,
+) -> mpsc::Receiver<io::Result<String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        // Receiver may be gone if the command timed out, nothing to do then
+        let _ = tx.send(reader.read_to_string(&mut output).map(|_| output));
+    });
+    rx
+}
+
+pub(crate) struct CommandOutput {
+    pub(crate) status: ExitStatus,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+}
+
+/// Runs `command` through the system shell, killing it and anything it spawned
+/// if it hasn't exited and closed its output within `timeout`
+pub(crate) fn run_command(command: &str, timeout: Duration) -> Result<CommandOutput, CustomError> {
+    run_with_timeout(platform::shell_command(command), timeout)
+}
+
+/// Runs `command`, killing it and anything it spawned if it hasn't exited and
+/// closed its output within `timeout`
+pub(crate) fn run_with_timeout(
+    mut command: Command,
+    timeout: Duration,
+) -> Result<CommandOutput, CustomError> {
+    let deadline = Instant::now() + timeout;
+
+    platform::own_process_group(&mut command);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(CustomError::Spawn)?;
+
+    let kill = platform::kill_tree;
+
+    let stdout = read_in_background(child.stdout.take().expect("stdout is piped"));
+    let stderr = read_in_background(child.stderr.take().expect("stderr is piped"));
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => (),
+            Err(e) => {
+                kill(&mut child);
+                return Err(CustomError::Wait(e));
+            }
+        }
+
+        if Instant::now() >= deadline {
+            kill(&mut child);
+            return Err(CustomError::Timeout(timeout));
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    };
+
+    // A backgrounded grandchild can hold the pipes open after sh exits
+    let mut collect = |rx: mpsc::Receiver<io::Result<String>>| match rx
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+    {
+        Ok(output) => output.map_err(CustomError::ReadOutput),
+        Err(_) => {
+            kill(&mut child);
+            Err(CustomError::Timeout(timeout))
+        }
+    };
+
+    let stdout = collect(stdout)?;
+    let stderr = collect(stderr)?;
+
+    Ok(CommandOutput {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+pub fn get_custom(custom: &CustomConfig, ctx: &Context) -> Result<DecoratedString, CustomError> {
+    if let Some(condition) = &custom.when {
+        if !condition.is_met(ctx) {
+            return Err(CustomError::ConditionNotMet);
+        }
+    }
+
+    let output = run_command(&custom.command, custom.timeout)?;
+
+    if !output.status.success() {
+        return Err(CustomError::ExitStatus(output.status, output.stderr));
+    }
+
+    let text = output.stdout.trim_end();
+    if text.is_empty() {
+        return Err(CustomError::EmptyOutput);
+    }
+
+    let mut decorated = DecoratedString::new(text.to_string()).bold();
+    if let Some(color) = custom.color {
+        decorated = decorated.colored(color);
+    }
+
+    Ok(decorated)
+}
+
+/// Segment showing the output of a user configured command
+pub struct CustomSegment {
+    config: CustomConfig,
+}
+
+impl CustomSegment {
+    pub fn new(config: CustomConfig) -> CustomSegment {
+        CustomSegment { config }
+    }
+}
+
+impl Segment for CustomSegment {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_custom(&self.config, ctx).map(Into::into).map_err(|e| {
+            let kind = match e {
+                CustomError::ConditionNotMet => ErrorKind::NotApplicable,
+                _ => ErrorKind::Failed,
+            };
+            SegmentError::new(self.config.name.as_str(), kind).with_source(e)
+        })
+    }
+
+    /// The name in place of the output, as the command could do anything
+    fn preview(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        let mut text = DecoratedString::new(self.config.name.clone());
+        if let Some(color) = self.config.color {
+            text = text.colored(color);
+        }
+        Ok(text.into())
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    fn custom(command: &str) -> CustomConfig {
+        CustomConfig {
+            name: "test".to_string(),
+            command: command.to_string(),
+            color: None,
+            // Long enough for a loaded machine, only the timeout test hits it
+            timeout: Duration::from_secs(5),
+            when: None,
+            position: None,
+        }
+    }
+
+    fn shown(command: &str) -> Result<String, CustomError> {
+        let ctx = testing::context(&[], None);
+        get_custom(&custom(command), &ctx).map(|text| text.to_plain())
+    }
+
+    #[test]
+    fn timeout_kills_the_whole_group() {
+        let dir = TempDir::new();
+        let marker = dir.path().join("survived");
+        // The backgrounded subshell only leaves the marker if it outlives sh
+        let command = format!("(sleep 0.3; touch '{}') & sleep 5", marker.display());
+
+        let started = Instant::now();
+        let res = run_command(&command, Duration::from_millis(100));
+        assert!(matches!(res, Err(CustomError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        thread::sleep(Duration::from_millis(600));
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn failure_is_reported() {
+        match shown("echo partial; echo broken >&2; exit 3") {
+            Err(CustomError::ExitStatus(status, stderr)) => {
+                assert_eq!(status.code(), Some(3));
+                assert_eq!(stderr, "broken\n");
+            }
+            res => panic!("{:?}", res),
+        }
+    }
+
+    #[test]
+    fn output_is_trimmed() {
+        assert_eq!(shown("printf 'main\\n\\n'").unwrap(), "main");
+        assert!(matches!(shown("echo"), Err(CustomError::EmptyOutput)));
+    }
+
+    #[test]
+    fn stderr_is_left_out() {
+        assert_eq!(shown("echo shown; echo hidden >&2").unwrap(), "shown");
+    }
+}
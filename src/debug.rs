@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+
+use crate::segment::{Outcome, SegmentError};
+
+/// How much `DEBUG_PROMPTLINE` asks to print
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    Off,
+    /// `DEBUG_PROMPTLINE=1`, only genuine errors
+    Failures,
+    /// `DEBUG_PROMPTLINE=2`, also why segments with nothing to show were left
+    /// out
+    All,
+}
+
+impl Verbosity {
+    pub fn from_env_value(value: Option<&str>) -> Verbosity {
+        match value.and_then(|v| v.parse::<u32>().ok()) {
+            None | Some(0) => Verbosity::Off,
+            Some(1) => Verbosity::Failures,
+            Some(_) => Verbosity::All,
+        }
+    }
+
+    pub fn shows(self, outcome: Outcome) -> bool {
+        match outcome {
+            Outcome::Shown => false,
+            Outcome::NotApplicable => self >= Verbosity::All,
+            Outcome::Failed => self >= Verbosity::Failures,
+        }
+    }
+}
+
+/// Writes the errors `verbosity` asks for, one after the other
+pub fn write_errors(
+    w: &mut impl Write,
+    verbosity: Verbosity,
+    errors: &[SegmentError],
+) -> io::Result<()> {
+    for error in errors.iter().filter(|e| verbosity.shows(e.outcome())) {
+        writeln!(w, "{error}")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::ErrorKind;
+
+    fn errors() -> Vec<SegmentError> {
+        vec![
+            SegmentError::not_applicable("git", "not a git repo"),
+            SegmentError::failed("hg", "failed to get working directory"),
+            SegmentError::new("hostname", ErrorKind::Hidden).with_message("not connected over ssh"),
+            SegmentError::new("custom", ErrorKind::OverBudget).with_message("took longer than 5ms"),
+            SegmentError::not_applicable("conda", "no conda env var"),
+        ]
+    }
+
+    fn written(verbosity: Verbosity) -> String {
+        let mut out = vec![];
+        write_errors(&mut out, verbosity, &errors()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn parses_env_value() {
+        assert_eq!(Verbosity::from_env_value(None), Verbosity::Off);
+        assert_eq!(Verbosity::from_env_value(Some("0")), Verbosity::Off);
+        assert_eq!(Verbosity::from_env_value(Some("yes")), Verbosity::Off);
+        assert_eq!(Verbosity::from_env_value(Some("1")), Verbosity::Failures);
+        assert_eq!(Verbosity::from_env_value(Some("2")), Verbosity::All);
+    }
+
+    #[test]
+    fn off_writes_nothing() {
+        assert_eq!(written(Verbosity::Off), "");
+    }
+
+    #[test]
+    fn failures_only() {
+        assert_eq!(
+            written(Verbosity::Failures),
+            "failed to get hg info\nCaused by:\nfailed to get working directory\n\n\
+             custom skipped\nCaused by:\ntook longer than 5ms\n\n"
+        );
+    }
+
+    #[test]
+    fn everything() {
+        let out = written(Verbosity::All);
+        for expected in [
+            "git not applicable\nCaused by:\nnot a git repo\n",
+            "failed to get hg info\n",
+            "hostname hidden by config\nCaused by:\nnot connected over ssh\n",
+            "custom skipped\n",
+            "conda not applicable\nCaused by:\nno conda env var\n",
+        ] {
+            assert!(out.contains(expected), "{:?} not in {:?}", expected, out);
+        }
+    }
+}
@@ -0,0 +1,626 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+};
+
+use crate::width;
+
+/// Foreground colors supported in segment output
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    fn to_ansi(self) -> i32 {
+        match self {
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+            Color::White => 37,
+        }
+    }
+
+    /// Parses a lowercase color name as used in the config file
+    pub fn from_name(name: &str) -> Option<Color> {
+        let color = match name {
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "white" => Color::White,
+            _ => return None,
+        };
+        Some(color)
+    }
+}
+
+/// What a color is shown as instead, for themes and the `colors` of a
+/// segment's config. Everything off leaves the text unstyled
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    /// Only the color `color`
+    pub const fn color(color: Color) -> Style {
+        Style {
+            color: Some(color),
+            bold: false,
+            dim: false,
+            underline: false,
+        }
+    }
+
+    /// Parses space separated color names and `bold`, `dim` and `underline`,
+    /// e.g. `blue underline`. `none` is no style at all
+    pub fn from_words(words: &str) -> Option<Style> {
+        if words.trim() == "none" {
+            return Some(Style::default());
+        }
+        let mut style = Style::default();
+        for word in words.split_whitespace() {
+            match word {
+                "bold" => style.bold = true,
+                "dim" => style.dim = true,
+                "underline" => style.underline = true,
+                _ if style.color.is_none() => style.color = Some(Color::from_name(word)?),
+                _ => return None,
+            }
+        }
+        (style != Style::default()).then_some(style)
+    }
+
+    fn apply(self, mut text: DecoratedString) -> DecoratedString {
+        if let Some(color) = self.color {
+            text = text.colored(color);
+        }
+        if self.underline {
+            text = text.underline();
+        }
+        if self.dim {
+            text = text.dim();
+        }
+        if self.bold {
+            text = text.bold();
+        }
+        text
+    }
+}
+
+/// How escape sequences are marked as zero width, so that the shell's line
+/// editor works out the prompt's width correctly
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum Escaping {
+    /// Bare escape sequences
+    #[default]
+    Raw,
+    /// `\x01` and `\x02` around each sequence, which readline skips
+    Bash,
+    /// `%{` and `%}` around each sequence. zsh expands `%` escapes in the
+    /// output of the command it runs for the prompt, so `%` is written as `%%`
+    Zsh,
+    /// PSReadLine skips escape sequences by itself, so nothing is added
+    PowerShell,
+    /// Like `Bash`, for output put into `PS1` once, as with
+    /// `PS1="$(promptline)"`, rather than run from it on every prompt. Bash
+    /// then decodes and expands the text itself, so `\`, `$` and backticks are
+    /// escaped for both, and `!` is written as `\041`, which posix mode would
+    /// otherwise turn into the history number
+    BashEmbedded,
+    /// Like `Zsh`, for output put into `PROMPT` once. With `prompt_subst`
+    /// zsh expands the text, and it always expands `%` escapes
+    ZshEmbedded,
+}
+
+impl Escaping {
+    /// Parses a shell name as given to `--shell`
+    pub fn from_shell(name: &str) -> Option<Escaping> {
+        let escaping = match name {
+            "bash" => Escaping::Bash,
+            "zsh" => Escaping::Zsh,
+            "powershell" | "pwsh" => Escaping::PowerShell,
+            _ => return None,
+        };
+        Some(escaping)
+    }
+
+    /// The escaping for output embedded in the prompt rather than run from
+    /// it. Only bash and zsh expand what is embedded
+    pub fn embedded(self) -> Escaping {
+        match self {
+            Escaping::Bash => Escaping::BashEmbedded,
+            Escaping::Zsh => Escaping::ZshEmbedded,
+            escaping => escaping,
+        }
+    }
+
+    /// `text` escaped to be shown as it is, for output without styling
+    pub fn escape(self, text: &str) -> String {
+        let mut s = String::new();
+        self.write_text(&mut s, text).unwrap();
+        s
+    }
+
+    /// `\x1b[0m`, which turns off all styling, marked as zero width
+    pub fn reset(self) -> String {
+        let mut s = String::new();
+        self.write_sequence(&mut s, format_args!("\x1b[0m"))
+            .unwrap();
+        s
+    }
+
+    fn write_sequence(self, s: &mut String, sequence: fmt::Arguments) -> fmt::Result {
+        match self {
+            Escaping::Raw | Escaping::PowerShell => s.write_fmt(sequence),
+            Escaping::Bash | Escaping::BashEmbedded => write!(s, "\x01{sequence}\x02"),
+            Escaping::Zsh | Escaping::ZshEmbedded => write!(s, "%{{{sequence}%}}"),
+        }
+    }
+
+    /// Writes `text` to be shown as it is, sanitized and with whatever the
+    /// shell would expand in it escaped
+    fn write_text(self, s: &mut String, text: &str) -> fmt::Result {
+        let text = sanitize(text);
+        match self {
+            Escaping::Raw | Escaping::Bash | Escaping::PowerShell => s.push_str(&text),
+            Escaping::Zsh => s.push_str(&text.replace('%', "%%")),
+            Escaping::BashEmbedded => {
+                for c in text.chars() {
+                    match c {
+                        // Decoding turns `\\` into `\`, which then escapes the
+                        // char after it from expansion
+                        '\\' => s.push_str("\\\\\\\\"),
+                        '$' | '`' => {
+                            s.push_str("\\\\");
+                            s.push(c);
+                        }
+                        '!' => s.push_str("\\041"),
+                        c => s.push(c),
+                    }
+                }
+            }
+            Escaping::ZshEmbedded => {
+                for c in text.chars() {
+                    match c {
+                        '\\' | '$' | '`' => {
+                            s.push('\\');
+                            s.push(c);
+                        }
+                        '%' => s.push_str("%%"),
+                        c => s.push(c),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `s` with control characters replaced by their symbols from the Control
+/// Pictures block, e.g. `␛` for escape. Text from outside sources like branch
+/// names could otherwise restyle the terminal or break the prompt's layout
+fn sanitize(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(|c| c.is_ascii_control()) {
+        return Cow::Borrowed(s);
+    }
+
+    let picture = |c: char| match c {
+        '\x7f' => '␡',
+        c if c.is_ascii_control() => char::from_u32(0x2400 + c as u32).unwrap_or('�'),
+        c => c,
+    };
+    Cow::Owned(s.chars().map(picture).collect())
+}
+
+/// Text with terminal styling applied, built up by wrapping a plain string
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecoratedString {
+    Bold(Box<DecoratedString>),
+    Dim(Box<DecoratedString>),
+    Underline(Box<DecoratedString>),
+    Colored(Box<DecoratedString>, Color),
+    /// Prefixes the inner string with an icon or label that isn't part of its value
+    Icon(String, Box<DecoratedString>),
+    /// Follows the first string with the second, which isn't part of its value
+    Suffix(Box<DecoratedString>, Box<DecoratedString>),
+    /// Strings styled one by one, with the separator between them. The last
+    /// field is the joined value
+    Joined(Vec<DecoratedString>, String, String),
+    Default(String),
+}
+
+impl DecoratedString {
+    fn append_to_ansi(
+        val: &DecoratedString,
+        escaping: Escaping,
+        s: &mut String,
+    ) -> Result<(), fmt::Error> {
+        // https://gist.github.com/fnky/458719343aabd01cfb17a3a4f7296797
+        match val {
+            DecoratedString::Bold(inner) => {
+                escaping.write_sequence(s, format_args!("\x1b[1m"))?;
+                Self::append_to_ansi(inner, escaping, s)?;
+                escaping.write_sequence(s, format_args!("\x1b[22m"))?;
+            }
+            DecoratedString::Dim(inner) => {
+                escaping.write_sequence(s, format_args!("\x1b[2m"))?;
+                Self::append_to_ansi(inner, escaping, s)?;
+                escaping.write_sequence(s, format_args!("\x1b[22m"))?;
+            }
+            DecoratedString::Underline(inner) => {
+                escaping.write_sequence(s, format_args!("\x1b[4m"))?;
+                Self::append_to_ansi(inner, escaping, s)?;
+                escaping.write_sequence(s, format_args!("\x1b[24m"))?;
+            }
+            DecoratedString::Colored(inner, color) => {
+                escaping.write_sequence(s, format_args!("\x1b[{}m", color.to_ansi()))?;
+                Self::append_to_ansi(inner, escaping, s)?;
+                escaping.write_sequence(s, format_args!("\x1b[39m"))?;
+            }
+            DecoratedString::Icon(icon, inner) => {
+                escaping.write_text(s, icon)?;
+                write!(s, " ")?;
+                Self::append_to_ansi(inner, escaping, s)?;
+            }
+            DecoratedString::Suffix(inner, suffix) => {
+                Self::append_to_ansi(inner, escaping, s)?;
+                write!(s, " ")?;
+                Self::append_to_ansi(suffix, escaping, s)?;
+            }
+            DecoratedString::Joined(parts, separator, _) => {
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        escaping.write_text(s, separator)?;
+                    }
+                    Self::append_to_ansi(part, escaping, s)?;
+                }
+            }
+            DecoratedString::Default(val) => {
+                escaping.write_text(s, val)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The undecorated text, without icons
+    pub fn value(&self) -> &str {
+        match self {
+            DecoratedString::Bold(inner)
+            | DecoratedString::Dim(inner)
+            | DecoratedString::Underline(inner)
+            | DecoratedString::Colored(inner, _)
+            | DecoratedString::Icon(_, inner)
+            | DecoratedString::Suffix(inner, _) => inner.value(),
+            DecoratedString::Joined(_, _, val) | DecoratedString::Default(val) => val,
+        }
+    }
+
+    /// The color the text starts out in, if it has one
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            DecoratedString::Colored(_, color) => Some(*color),
+            DecoratedString::Bold(inner)
+            | DecoratedString::Dim(inner)
+            | DecoratedString::Underline(inner)
+            | DecoratedString::Icon(_, inner)
+            | DecoratedString::Suffix(inner, _) => inner.color(),
+            DecoratedString::Joined(parts, _, _) => parts.first()?.color(),
+            DecoratedString::Default(_) => None,
+        }
+    }
+
+    /// The text with ANSI escape sequences for its styling
+    pub fn to_ansi(&self) -> String {
+        self.to_ansi_escaped(Escaping::Raw)
+    }
+
+    /// Like `to_ansi`, with the escape sequences marked as zero width for a
+    /// shell
+    pub fn to_ansi_escaped(&self, escaping: Escaping) -> String {
+        let mut ret = String::new();
+        Self::append_to_ansi(self, escaping, &mut ret).unwrap();
+        ret
+    }
+
+    /// The text as it would be displayed, without any escape sequences
+    pub fn to_plain(&self) -> String {
+        match self {
+            DecoratedString::Bold(inner)
+            | DecoratedString::Dim(inner)
+            | DecoratedString::Underline(inner)
+            | DecoratedString::Colored(inner, _) => inner.to_plain(),
+            DecoratedString::Icon(icon, inner) => format!("{icon} {}", inner.to_plain()),
+            DecoratedString::Suffix(inner, suffix) => {
+                format!("{} {}", inner.to_plain(), suffix.to_plain())
+            }
+            DecoratedString::Joined(parts, separator, _) => {
+                let parts: Vec<_> = parts.iter().map(DecoratedString::to_plain).collect();
+                parts.join(&sanitize(separator))
+            }
+            DecoratedString::Default(val) => sanitize(val).into_owned(),
+        }
+    }
+
+    /// Columns the text takes up on the terminal, see `width::visible_width`
+    pub fn visible_width(&self) -> usize {
+        width::visible_width(&self.to_plain())
+    }
+
+    /// Shortens the text to `width` columns, including icons and suffixes, by
+    /// replacing its start with `…`
+    pub fn truncate_start(&mut self, width: usize) {
+        let excess = self.visible_width().saturating_sub(width);
+        if excess > 0 {
+            self.remove_start(excess + 1);
+        }
+    }
+
+    fn remove_start(&mut self, count: usize) {
+        match self {
+            DecoratedString::Bold(inner)
+            | DecoratedString::Dim(inner)
+            | DecoratedString::Underline(inner)
+            | DecoratedString::Colored(inner, _)
+            | DecoratedString::Icon(_, inner)
+            | DecoratedString::Suffix(inner, _) => inner.remove_start(count),
+            DecoratedString::Joined(parts, separator, val) => {
+                // Everything that goes becomes part of the `…` in the first
+                // part that is left
+                let mut count = count;
+                while parts.len() > 1 {
+                    let taken = parts[0].to_plain().chars().count() + separator.chars().count();
+                    if taken > count {
+                        break;
+                    }
+                    parts.remove(0);
+                    count -= taken;
+                }
+                parts[0].remove_start(count);
+                *val = parts
+                    .iter()
+                    .map(|part| part.value().to_string())
+                    .collect::<Vec<_>>()
+                    .join(separator);
+            }
+            DecoratedString::Default(val) => {
+                let rest: String = val.chars().skip(count).collect();
+                *val = format!("…{rest}");
+            }
+        }
+    }
+
+    pub fn bold(self) -> DecoratedString {
+        DecoratedString::Bold(Box::new(self))
+    }
+
+    pub fn dim(self) -> DecoratedString {
+        DecoratedString::Dim(Box::new(self))
+    }
+
+    pub fn underline(self) -> DecoratedString {
+        DecoratedString::Underline(Box::new(self))
+    }
+
+    pub fn colored(self, color: Color) -> DecoratedString {
+        DecoratedString::Colored(Box::new(self), color)
+    }
+
+    /// The text with each color shown as the style `restyle` gives for it
+    /// instead, if it gives one
+    pub fn restyled(self, restyle: &dyn Fn(Color) -> Option<Style>) -> DecoratedString {
+        let boxed = |inner: Box<DecoratedString>| Box::new(inner.restyled(restyle));
+        match self {
+            DecoratedString::Bold(inner) => DecoratedString::Bold(boxed(inner)),
+            DecoratedString::Dim(inner) => DecoratedString::Dim(boxed(inner)),
+            DecoratedString::Underline(inner) => DecoratedString::Underline(boxed(inner)),
+            DecoratedString::Colored(inner, color) => match restyle(color) {
+                Some(style) => style.apply(inner.restyled(restyle)),
+                None => DecoratedString::Colored(boxed(inner), color),
+            },
+            DecoratedString::Icon(icon, inner) => DecoratedString::Icon(icon, boxed(inner)),
+            DecoratedString::Suffix(inner, suffix) => {
+                DecoratedString::Suffix(boxed(inner), boxed(suffix))
+            }
+            DecoratedString::Joined(parts, separator, value) => DecoratedString::Joined(
+                parts
+                    .into_iter()
+                    .map(|part| part.restyled(restyle))
+                    .collect(),
+                separator,
+                value,
+            ),
+            DecoratedString::Default(val) => DecoratedString::Default(val),
+        }
+    }
+
+    pub fn with_icon(self, icon: &str) -> DecoratedString {
+        DecoratedString::Icon(icon.to_string(), Box::new(self))
+    }
+
+    pub fn with_suffix(self, suffix: DecoratedString) -> DecoratedString {
+        DecoratedString::Suffix(Box::new(self), Box::new(suffix))
+    }
+
+    /// `parts` one after the other, with `separator` between them
+    pub fn join(parts: Vec<DecoratedString>, separator: &str) -> DecoratedString {
+        let value = parts
+            .iter()
+            .map(DecoratedString::value)
+            .collect::<Vec<_>>()
+            .join(separator);
+        DecoratedString::Joined(parts, separator.to_string(), value)
+    }
+
+    pub fn new(s: String) -> DecoratedString {
+        DecoratedString::Default(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decorated() -> DecoratedString {
+        DecoratedString::new("main".into())
+            .colored(Color::Green)
+            .bold()
+    }
+
+    #[test]
+    fn raw_escapes() {
+        assert_eq!(decorated().to_ansi(), "\x1b[1m\x1b[32mmain\x1b[39m\x1b[22m");
+        assert_eq!(
+            decorated().to_ansi_escaped(Escaping::PowerShell),
+            decorated().to_ansi()
+        );
+    }
+
+    #[test]
+    fn bash_escapes() {
+        assert_eq!(
+            decorated().to_ansi_escaped(Escaping::Bash),
+            "\x01\x1b[1m\x02\x01\x1b[32m\x02main\x01\x1b[39m\x02\x01\x1b[22m\x02"
+        );
+    }
+
+    #[test]
+    fn zsh_escapes() {
+        assert_eq!(
+            decorated().to_ansi_escaped(Escaping::Zsh),
+            "%{\x1b[1m%}%{\x1b[32m%}main%{\x1b[39m%}%{\x1b[22m%}"
+        );
+    }
+
+    #[test]
+    fn replaces_control_characters_in_text() {
+        let decorated = DecoratedString::new("evil\x1b[41m".into()).bold();
+        assert_eq!(decorated.to_ansi(), "\x1b[1mevil␛[41m\x1b[22m");
+        assert_eq!(decorated.to_plain(), "evil␛[41m");
+
+        let decorated = DecoratedString::new("a\nb\rc\td\x07\x7f".into());
+        assert_eq!(decorated.to_plain(), "a␊b␍c␉d␇␡");
+    }
+
+    #[test]
+    fn visible_width_matches_plain_text() {
+        let decorated = decorated().with_icon("⎇");
+        assert_eq!(decorated.visible_width(), 6);
+        assert_eq!(
+            width::strip_ansi(&decorated.to_ansi()),
+            decorated.to_plain()
+        );
+    }
+
+    #[test]
+    fn truncates_start() {
+        let mut decorated = DecoratedString::new("~/src/promptline".into()).bold();
+        decorated.truncate_start(11);
+        assert_eq!(decorated.to_ansi(), "\x1b[1m…promptline\x1b[22m");
+
+        let mut decorated = DecoratedString::new("science".into()).with_icon("🐍");
+        decorated.truncate_start(6);
+        assert_eq!(decorated.to_plain(), "🐍 …ce");
+    }
+
+    #[test]
+    fn joined_parts_keep_their_styles() {
+        let mut joined = DecoratedString::join(
+            vec![
+                DecoratedString::new("0".into()).colored(Color::Green),
+                DecoratedString::new("141".into()).colored(Color::Red),
+            ],
+            "|",
+        );
+        assert_eq!(joined.value(), "0|141");
+        assert_eq!(joined.to_ansi(), "\x1b[32m0\x1b[39m|\x1b[31m141\x1b[39m");
+
+        joined.truncate_start(3);
+        assert_eq!(joined.to_ansi(), "\x1b[31m…41\x1b[39m");
+        assert_eq!(joined.value(), "…41");
+    }
+
+    #[test]
+    fn reset_is_wrapped() {
+        assert_eq!(Escaping::Raw.reset(), "\x1b[0m");
+        assert_eq!(Escaping::Bash.reset(), "\x01\x1b[0m\x02");
+        assert_eq!(Escaping::Zsh.reset(), "%{\x1b[0m%}");
+    }
+
+    #[test]
+    fn suffix_is_not_part_of_value() {
+        let decorated = DecoratedString::new("bash".into())
+            .bold()
+            .with_suffix(DecoratedString::new("⇲3".into()).dim());
+        assert_eq!(decorated.value(), "bash");
+        assert_eq!(decorated.to_plain(), "bash ⇲3");
+        assert_eq!(decorated.to_ansi(), "\x1b[1mbash\x1b[22m \x1b[2m⇲3\x1b[22m");
+    }
+
+    fn branch(name: &str) -> DecoratedString {
+        DecoratedString::new(format!("{name} 0123456"))
+            .colored(Color::Green)
+            .with_icon("\u{e0a0}")
+    }
+
+    #[test]
+    fn bash_embedded_escapes_text() {
+        let escaping = Escaping::Bash.embedded();
+        assert_eq!(
+            branch("fix$PATH-handling").to_ansi_escaped(escaping),
+            "\u{e0a0} \x01\x1b[32m\x02fix\\\\$PATH-handling 0123456\x01\x1b[39m\x02"
+        );
+        assert_eq!(
+            branch("wip!`id`").to_ansi_escaped(escaping),
+            "\u{e0a0} \x01\x1b[32m\x02wip\\041\\\\`id\\\\` 0123456\x01\x1b[39m\x02"
+        );
+        assert_eq!(
+            DecoratedString::new("C:\\new".into()).to_ansi_escaped(escaping),
+            "C:\\\\\\\\new"
+        );
+        assert_eq!(escaping.escape("base!"), "base\\041");
+    }
+
+    #[test]
+    fn zsh_embedded_escapes_text() {
+        let escaping = Escaping::Zsh.embedded();
+        assert_eq!(
+            branch("fix$PATH-100%").to_ansi_escaped(escaping),
+            "\u{e0a0} %{\x1b[32m%}fix\\$PATH-100%% 0123456%{\x1b[39m%}"
+        );
+        assert_eq!(escaping.escape("`id`\\!"), "\\`id\\`\\\\!");
+    }
+
+    #[test]
+    fn escaping_leaves_text_alone_unless_embedded() {
+        for escaping in [Escaping::Raw, Escaping::Bash] {
+            assert_eq!(escaping.escape("fix$PATH!`%\\"), "fix$PATH!`%\\");
+        }
+        // Except for `%`, which zsh expands either way
+        assert_eq!(Escaping::Zsh.escape("fix$PATH!`%\\"), "fix$PATH!`%%\\");
+        assert_eq!(
+            branch("a%Bb").to_ansi_escaped(Escaping::Zsh),
+            "\u{e0a0} %{\x1b[32m%}a%%Bb 0123456%{\x1b[39m%}"
+        );
+        assert_eq!(Escaping::PowerShell.embedded(), Escaping::PowerShell);
+    }
+}
@@ -0,0 +1,643 @@
+//! `promptline --diagnose`, a report to paste into a bug report about the
+//! prompt: the versions, the environment, the config and how every segment
+//! went. Values of variables and options whose names look like they hold a
+//! secret are masked, also where a segment shows them as `NAME=value`
+
+use std::{
+    fmt::Write,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    bench::{json_string, ms},
+    config::{self, Value},
+    runner::{self, Registry},
+    theme,
+    trace::SegmentTrace,
+    Context,
+};
+
+/// Variables about the terminal, shell and locale that change how the prompt
+/// looks. Those of promptline itself are added to them
+const VARS: &[&str] = &[
+    "TERM",
+    "COLORTERM",
+    "NO_COLOR",
+    "COLUMNS",
+    "SHELL",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "XDG_RUNTIME_DIR",
+];
+
+/// Parts of a name that mark a variable or option as holding a secret
+const SECRET_MARKERS: &[&str] = &["TOKEN", "KEY", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL"];
+
+/// Shown in place of a secret
+pub const REDACTED: &str = "<redacted>";
+
+/// Whether `name` looks like it holds a secret, like `GITHUB_TOKEN` or
+/// `api_key`
+pub fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+/// `value`, or `REDACTED` if `name` looks like it holds a secret
+pub fn redact<'a>(name: &str, value: &'a str) -> &'a str {
+    if is_secret(name) {
+        REDACTED
+    } else {
+        value
+    }
+}
+
+/// `value` with what follows `NAME=` masked wherever `NAME` looks like it
+/// holds a secret, like the `GITHUB_TOKEN=ghp_…` env_watch shows. The mask
+/// runs up to the next `NAME=`, as a value may have spaces in it
+fn redact_pairs(value: &str) -> String {
+    let is_var = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let mut words = vec![];
+    let mut hiding = false;
+    for word in value.split(' ') {
+        match word.split_once('=') {
+            Some((name, _)) if is_var(name) => {
+                hiding = is_secret(name);
+                if hiding {
+                    words.push(format!("{name}={REDACTED}"));
+                } else {
+                    words.push(word.to_string());
+                }
+            }
+            _ if hiding => {}
+            _ => words.push(word.to_string()),
+        }
+    }
+    words.join(" ")
+}
+
+/// A config value written as TOML, with the values of keys that look like
+/// they hold a secret masked all the way down
+fn format_value(key: &str, value: &Value) -> String {
+    if is_secret(key) {
+        return format!("\"{REDACTED}\"");
+    }
+    match value {
+        Value::String(s) => json_string(s),
+        Value::Integer(i) => i.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Array(items) => {
+            let items: Vec<_> = items.iter().map(|item| format_value(key, item)).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Table(table) => {
+            let entries: Vec<_> = table
+                .iter()
+                .map(|(key, value)| format!("{key} = {}", format_value(key, value)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+    }
+}
+
+/// What the report says that doesn't come from the [`Context`]
+pub struct Setup {
+    pub stdout_tty: bool,
+    /// The global config file, whether or not it exists
+    pub config_path: Option<PathBuf>,
+    pub local_config_path: Option<PathBuf>,
+    /// Why the config, or part of it, was left out
+    pub config_error: Option<String>,
+}
+
+impl Setup {
+    /// Looks up the config files and stdout of this process
+    pub fn detect(config_error: Option<String>) -> Setup {
+        Setup {
+            stdout_tty: io::stdout().is_terminal(),
+            config_path: config::config_path(),
+            local_config_path: config::local_config_path(),
+            config_error,
+        }
+    }
+}
+
+/// How one segment went
+pub struct SegmentReport {
+    pub name: String,
+    pub enabled: bool,
+    pub expensive: bool,
+    /// As in `PROMPTLINE_TRACE`, or `disabled` if it wasn't rendered
+    pub outcome: &'static str,
+    /// What it showed, without styling
+    pub value: Option<String>,
+    /// Why it wasn't shown, followed by each of the causes
+    pub errors: Vec<String>,
+    pub elapsed: Option<Duration>,
+}
+
+pub struct Report {
+    pub version: &'static str,
+    /// The rustc promptline was built with, if the build could tell
+    pub rustc: Option<&'static str>,
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// `VARS` and those of promptline, `None` for unset ones
+    pub vars: Vec<(String, Option<String>)>,
+    pub setup: Setup,
+    /// The config as parsed, as `key = value` in TOML
+    pub config: Vec<(String, String)>,
+    pub segments: Vec<SegmentReport>,
+    pub total: Duration,
+}
+
+/// The variables in the report, redacted
+fn vars(ctx: &Context) -> Vec<(String, Option<String>)> {
+    let mut own: Vec<_> = ctx
+        .var_names()
+        .filter(|name| name.starts_with("PROMPTLINE_") || *name == "DEBUG_PROMPTLINE")
+        .collect();
+    own.sort_unstable();
+
+    VARS.iter()
+        .copied()
+        .chain(own)
+        .map(|name| {
+            let value = ctx.var(name).map(|value| redact(name, value).to_string());
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// The settings of `ctx.config`, as `key = value` with TOML values
+fn config_entries(ctx: &Context) -> Vec<(String, String)> {
+    let config = &ctx.config;
+    let strings =
+        |items: &[String]| Value::Array(items.iter().cloned().map(Value::String).collect());
+    let mut entries = vec![
+        ("prompt_char", Value::String(config.prompt_char.clone())),
+        (
+            "segment_budget_ms",
+            Value::Integer(config.budget.as_millis() as i64),
+        ),
+        (
+            "timeout_placeholder",
+            Value::Boolean(config.timeout_placeholder),
+        ),
+        ("max_languages", Value::Integer(config.max_languages as i64)),
+        // The one in effect, which may come from `$PROMPTLINE_THEME`
+        (
+            "theme",
+            Value::String(theme::current(ctx).name().to_string()),
+        ),
+        ("drop_order", strings(&config.drop_order)),
+        (
+            "lines",
+            Value::Array(config.lines.iter().map(|line| strings(line)).collect()),
+        ),
+    ];
+    if let Some(profile) = &ctx.args.profile {
+        entries.push(("profile", Value::String(profile.clone())));
+    }
+    let mut entries: Vec<_> = entries
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), format_value(key, &value)))
+        .collect();
+
+    let custom: Vec<_> = config
+        .custom
+        .iter()
+        .map(|custom| custom.name.clone())
+        .collect();
+    if !custom.is_empty() {
+        // Only the names, a command can have a secret in any part of it
+        entries.push((
+            "custom".to_string(),
+            format_value("custom", &strings(&custom)),
+        ));
+    }
+    for (name, segment) in &config.segments {
+        for (key, value) in &segment.options {
+            entries.push((format!("segments.{name}.{key}"), format_value(key, value)));
+        }
+    }
+    entries
+}
+
+/// Renders every segment of `registry` that is enabled and puts together the
+/// report
+pub fn report(registry: &Registry, ctx: &Arc<Context>, setup: Setup) -> Report {
+    let enabled: Vec<_> = registry.enabled(&ctx.config).collect();
+    let (results, total) = runner::timed(|| runner::render_all(enabled.clone(), ctx));
+    let mut results = enabled
+        .iter()
+        .map(|segment| segment.name())
+        .zip(results)
+        .peekable();
+
+    let segments = registry
+        .iter()
+        .map(|segment| {
+            let name = segment.name().to_string();
+            let expensive = segment.is_expensive();
+            let (result, elapsed) = match results.next_if(|(rendered, _)| *rendered == name) {
+                Some((_, result)) => result,
+                None => {
+                    return SegmentReport {
+                        name,
+                        enabled: false,
+                        expensive,
+                        outcome: "disabled",
+                        value: None,
+                        errors: vec![],
+                        elapsed: None,
+                    }
+                }
+            };
+
+            let outcome = SegmentTrace::new(&name, &result, elapsed).outcome;
+            let (value, errors) = match result {
+                Ok(rendered) => (Some(redact_pairs(&rendered.to_plain())), vec![]),
+                Err(e) => {
+                    let errors = e
+                        .to_string()
+                        .lines()
+                        .filter(|line| *line != "Caused by:")
+                        .map(str::to_string)
+                        .collect();
+                    (None, errors)
+                }
+            };
+            SegmentReport {
+                name,
+                enabled: true,
+                expensive,
+                outcome,
+                value,
+                errors,
+                elapsed: Some(elapsed),
+            }
+        })
+        .collect();
+
+    Report {
+        version: env!("CARGO_PKG_VERSION"),
+        rustc: option_env!("PROMPTLINE_RUSTC_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        vars: vars(ctx),
+        setup,
+        config: config_entries(ctx),
+        segments,
+        total,
+    }
+}
+
+fn format_ms(duration: Duration) -> String {
+    format!("{:.2}ms", ms(duration))
+}
+
+fn json_option(value: Option<&str>) -> String {
+    value.map_or_else(|| "null".to_string(), json_string)
+}
+
+fn json_path(path: &Option<PathBuf>) -> String {
+    json_option(path.as_ref().map(|path| path.to_string_lossy()).as_deref())
+}
+
+impl Report {
+    pub fn to_text(&self) -> String {
+        let mut ret = String::new();
+        let _ = writeln!(ret, "promptline {}", self.version);
+        let _ = writeln!(ret, "rustc: {}", self.rustc.unwrap_or("unknown"));
+        let _ = writeln!(ret, "os: {} {}", self.os, self.arch);
+        let tty = if self.setup.stdout_tty { "yes" } else { "no" };
+        let _ = writeln!(ret, "stdout is a tty: {tty}");
+
+        let _ = writeln!(ret, "\nenvironment:");
+        for (name, value) in &self.vars {
+            match value {
+                Some(value) => {
+                    let _ = writeln!(ret, "  {name}={value}");
+                }
+                None => {
+                    let _ = writeln!(ret, "  {name} unset");
+                }
+            }
+        }
+
+        let _ = writeln!(ret, "\nconfig:");
+        let path = |path: &Option<PathBuf>| match path {
+            Some(path) if path.exists() => path.display().to_string(),
+            Some(path) => format!("{} (missing)", path.display()),
+            None => "none".to_string(),
+        };
+        let _ = writeln!(ret, "  file: {}", path(&self.setup.config_path));
+        let _ = writeln!(ret, "  local file: {}", path(&self.setup.local_config_path));
+        if let Some(e) = &self.setup.config_error {
+            let _ = writeln!(ret, "  error: {e}");
+        }
+        for (key, value) in &self.config {
+            let _ = writeln!(ret, "  {key} = {value}");
+        }
+
+        let _ = writeln!(ret, "\nsegments:");
+        let name_width = self
+            .segments
+            .iter()
+            .map(|segment| segment.name.len())
+            .max()
+            .unwrap_or(0);
+        for segment in &self.segments {
+            let elapsed = segment.elapsed.map(format_ms).unwrap_or_default();
+            let expensive = if segment.expensive { "  expensive" } else { "" };
+            let row = format!(
+                "  {:name_width$}  {:14}  {elapsed:>9}{expensive}",
+                segment.name, segment.outcome
+            );
+            let _ = writeln!(ret, "{}", row.trim_end());
+            if let Some(value) = &segment.value {
+                let _ = writeln!(ret, "    {value}");
+            }
+            for error in &segment.errors {
+                let _ = writeln!(ret, "    {error}");
+            }
+        }
+        let _ = write!(ret, "\ntotal: {}", format_ms(self.total));
+        ret
+    }
+
+    pub fn to_json(&self) -> String {
+        let vars = self
+            .vars
+            .iter()
+            .map(|(name, value)| format!("{}:{}", json_string(name), json_option(value.as_deref())))
+            .collect::<Vec<_>>()
+            .join(",");
+        let config = self
+            .config
+            .iter()
+            .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| {
+                let errors = segment
+                    .errors
+                    .iter()
+                    .map(|error| json_string(error))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let elapsed = segment
+                    .elapsed
+                    .map_or_else(|| "null".to_string(), |elapsed| format!("{:.3}", ms(elapsed)));
+                format!(
+                    "{{\"name\":{},\"enabled\":{},\"expensive\":{},\"outcome\":{},\"value\":{},\"errors\":[{}],\"ms\":{}}}",
+                    json_string(&segment.name),
+                    segment.enabled,
+                    segment.expensive,
+                    json_string(segment.outcome),
+                    json_option(segment.value.as_deref()),
+                    errors,
+                    elapsed
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"version\":{},\"rustc\":{},\"os\":{},\"arch\":{},\"stdout_tty\":{},\"env\":{{{}}},\"config_path\":{},\"local_config_path\":{},\"config_error\":{},\"config\":{{{}}},\"segments\":[{}],\"total_ms\":{:.3}}}",
+            json_string(self.version),
+            json_option(self.rustc),
+            json_string(self.os),
+            json_string(self.arch),
+            self.setup.stdout_tty,
+            vars,
+            json_path(&self.setup.config_path),
+            json_path(&self.setup.local_config_path),
+            json_option(self.setup.config_error.as_deref()),
+            config,
+            segments,
+            ms(self.total)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        segment::{RenderedSegment, Segment, SegmentError},
+        testing, DecoratedString,
+    };
+
+    #[test]
+    fn secrets_are_masked() {
+        assert!(is_secret("GITHUB_TOKEN"));
+        assert!(is_secret("AWS_SECRET_ACCESS_KEY"));
+        assert!(is_secret("api_key"));
+        assert!(is_secret("PGPASSWORD"));
+        assert!(!is_secret("TERM"));
+        assert!(!is_secret("PROMPTLINE_PROFILE"));
+
+        assert_eq!(redact("OPENAI_API_KEY", "sk-abc"), REDACTED);
+        assert_eq!(redact("COLORTERM", "truecolor"), "truecolor");
+
+        let value = config::parse("a = [\"x\", { token = \"t\", ok = 1 }]\n").unwrap();
+        assert_eq!(
+            format_value("options", &Value::Table(value)),
+            "{ a = [\"x\", { ok = 1, token = \"<redacted>\" }] }"
+        );
+        assert_eq!(
+            format_value("password", &Value::Integer(1234)),
+            "\"<redacted>\""
+        );
+
+        assert_eq!(
+            redact_pairs("GITHUB_TOKEN=ghp_1 2 EDITOR=vi x=1=2"),
+            "GITHUB_TOKEN=<redacted> EDITOR=vi x=1=2"
+        );
+        assert_eq!(redact_pairs("main ✗2 a=b"), "main ✗2 a=b");
+    }
+
+    #[test]
+    fn segment_values_are_masked() {
+        let registry = Registry::new(vec![Arc::new(crate::segments::env_watch::EnvWatch)]);
+        let vars = [("GITHUB_TOKEN", "ghp_123"), ("EDITOR", "vi")];
+        let mut ctx = testing::context(&vars, Some("/"));
+        let toml = "[segments.env_watch]\nvars = [\"GITHUB_TOKEN\", \"EDITOR\"]\n";
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        let setup = Setup {
+            stdout_tty: false,
+            config_path: None,
+            local_config_path: None,
+            config_error: None,
+        };
+        let report = report(&registry, &Arc::new(ctx), setup);
+
+        assert_eq!(
+            report.segments[0].value.as_deref(),
+            Some("GITHUB_TOKEN=<redacted> EDITOR=vi")
+        );
+        assert!(!report.to_text().contains("ghp_123"));
+        assert!(!report.to_json().contains("ghp_123"));
+    }
+
+    struct Shown;
+
+    impl Segment for Shown {
+        fn name(&self) -> &str {
+            "shown"
+        }
+
+        fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+            Ok(DecoratedString::new("hi".into()).bold().into())
+        }
+    }
+
+    struct Failing;
+
+    impl Segment for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn is_expensive(&self) -> bool {
+            true
+        }
+
+        fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+            let source = io::Error::new(io::ErrorKind::NotFound, "no such file");
+            Err(SegmentError::failed("failing", "failed to read it").with_source(source))
+        }
+    }
+
+    struct Off;
+
+    impl Segment for Off {
+        fn name(&self) -> &str {
+            "off"
+        }
+
+        fn enabled_by_default(&self) -> bool {
+            false
+        }
+
+        fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+            unreachable!("disabled segments aren't rendered")
+        }
+    }
+
+    fn sample_report() -> Report {
+        let registry = Registry::new(vec![Arc::new(Shown), Arc::new(Off), Arc::new(Failing)]);
+        let vars = [
+            ("TERM", "xterm-256color"),
+            ("PROMPTLINE_GITHUB_TOKEN", "ghp_123"),
+            ("PROMPTLINE_PROFILE", "work"),
+            ("HOME", "/home/ada"),
+        ];
+        let mut ctx = testing::context(&vars, Some("/"));
+        let toml = "segment_budget_ms = 50\n[segments.weather]\napi_key = \"k\"\ncity = \"Oslo\"\n";
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        let setup = Setup {
+            stdout_tty: false,
+            config_path: None,
+            local_config_path: None,
+            config_error: Some("invalid prompt_char".to_string()),
+        };
+        report(&registry, &Arc::new(ctx), setup)
+    }
+
+    #[test]
+    fn assembles_report() {
+        let report = sample_report();
+
+        assert_eq!(
+            report.vars,
+            [
+                ("TERM".to_string(), Some("xterm-256color".to_string())),
+                ("COLORTERM".to_string(), None),
+                ("NO_COLOR".to_string(), None),
+                ("COLUMNS".to_string(), None),
+                ("SHELL".to_string(), None),
+                ("LANG".to_string(), None),
+                ("LC_ALL".to_string(), None),
+                ("LC_CTYPE".to_string(), None),
+                ("XDG_RUNTIME_DIR".to_string(), None),
+                (
+                    "PROMPTLINE_GITHUB_TOKEN".to_string(),
+                    Some(REDACTED.to_string())
+                ),
+                ("PROMPTLINE_PROFILE".to_string(), Some("work".to_string())),
+            ]
+        );
+
+        let config: Vec<_> = report
+            .config
+            .iter()
+            .map(|(key, value)| format!("{key} = {value}"))
+            .collect();
+        assert!(config.contains(&"segment_budget_ms = 50".to_string()));
+        assert!(config.contains(&"segments.weather.api_key = \"<redacted>\"".to_string()));
+        assert!(config.contains(&"segments.weather.city = \"Oslo\"".to_string()));
+
+        let segments: Vec<_> = report
+            .segments
+            .iter()
+            .map(|s| (s.name.as_str(), s.enabled, s.outcome, s.value.as_deref()))
+            .collect();
+        assert_eq!(
+            segments,
+            [
+                ("shown", true, "shown", Some("hi")),
+                ("off", false, "disabled", None),
+                ("failing", true, "failed", None),
+            ]
+        );
+        assert!(report.segments[1].elapsed.is_none());
+        assert!(report.segments[2].expensive);
+        assert_eq!(
+            report.segments[2].errors,
+            [
+                "failed to get failing info",
+                "failed to read it",
+                "no such file"
+            ]
+        );
+    }
+
+    #[test]
+    fn text_and_json() {
+        let report = sample_report();
+
+        let text = report.to_text();
+        assert!(text.starts_with(&format!("promptline {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(text.contains("\n  PROMPTLINE_GITHUB_TOKEN=<redacted>\n"));
+        assert!(text.contains("\n  COLORTERM unset\n"));
+        assert!(text.contains("\n  error: invalid prompt_char\n"));
+        assert!(text.contains("\n    failed to read it\n"));
+        assert!(!text.contains("ghp_123"));
+
+        let json = report.to_json();
+        assert!(json.starts_with("{\"version\":"));
+        assert!(json.contains("\"PROMPTLINE_GITHUB_TOKEN\":\"<redacted>\""));
+        assert!(json.contains("\"COLORTERM\":null"));
+        assert!(json.contains(
+            "{\"name\":\"off\",\"enabled\":false,\"expensive\":false,\"outcome\":\"disabled\",\"value\":null,\"errors\":[],\"ms\":null}"
+        ));
+        assert!(json.contains(
+            "\"errors\":[\"failed to get failing info\",\"failed to read it\",\"no such file\"]"
+        ));
+        assert!(!json.contains("ghp_123"));
+        assert!(json.ends_with('}'));
+    }
+}
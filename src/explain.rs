@@ -0,0 +1,164 @@
+use std::{fmt::Write, sync::Arc, time::Duration};
+
+use crate::{
+    runner::{self, Registry},
+    segment::Outcome,
+    Context,
+};
+
+/// Runs every enabled segment in `registry` and returns a table with its status, value
+/// or error, and how long it took
+pub fn explain(registry: &Registry, ctx: &Arc<Context>) -> String {
+    let (results, total) = runner::timed(|| runner::render_all(registry.enabled(&ctx.config), ctx));
+
+    let rows: Vec<_> = registry
+        .enabled(&ctx.config)
+        .zip(results)
+        .map(|(segment, (result, elapsed))| {
+            let status = match Outcome::of(&result) {
+                Outcome::Shown => "shown",
+                Outcome::NotApplicable => "hidden",
+                Outcome::Failed => "failed",
+            };
+            let detail = match result {
+                Ok(rendered) => rendered.to_plain(),
+                Err(e) => e.to_single_line(),
+            };
+            (segment.name(), status, detail, format_ms(elapsed))
+        })
+        .collect();
+
+    let name_width = rows
+        .iter()
+        .map(|row| row.0.len())
+        .chain(Some("segment".len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut table = String::new();
+
+    let _ = writeln!(
+        table,
+        "{:name_width$}  {:6}  {:>9}  value",
+        "segment", "status", "time"
+    );
+
+    for (name, status, detail, elapsed) in rows {
+        let _ = writeln!(
+            table,
+            "{name:name_width$}  {status:6}  {elapsed:>9}  {detail}"
+        );
+    }
+
+    let _ = write!(
+        table,
+        "\n{:name_width$}  {:6}  {:>9}",
+        "total",
+        "",
+        format_ms(total)
+    );
+
+    table
+}
+
+fn format_ms(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        config::{self, Config},
+        segment::{RenderedSegment, Segment, SegmentError},
+        testing, DecoratedString,
+    };
+
+    /// A segment that renders with `render`, named `name`
+    struct Fake {
+        name: &'static str,
+        render: fn(&str) -> Result<RenderedSegment, SegmentError>,
+    }
+
+    impl Segment for Fake {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+            (self.render)(self.name)
+        }
+    }
+
+    fn registry() -> Registry {
+        let fake = |name, render| Arc::new(Fake { name, render }) as Arc<dyn Segment>;
+        Registry::new(vec![
+            fake("shown", |_| Ok(DecoratedString::new("main".into()).into())),
+            fake("conditional", |_| {
+                Ok(DecoratedString::new("base".into()).into())
+            }),
+            fake("absent", |name| {
+                Err(SegmentError::not_applicable(name, "not a repo"))
+            }),
+            fake("slow", |_| {
+                thread::sleep(Duration::from_secs(5));
+                Ok(DecoratedString::new("late".into()).into())
+            }),
+            fake("broken", |name| {
+                Err(SegmentError::failed(name, "unexpected git content"))
+            }),
+            fake(
+                "disabled",
+                |_| Ok(DecoratedString::new("off".into()).into()),
+            ),
+        ])
+    }
+
+    #[test]
+    fn lists_every_enabled_segment() {
+        let toml = "segment_budget_ms = 50\n\
+                    [segments.conditional]\nhide_values = [\"base\"]\n\
+                    [segments.disabled]\nenabled = false\n";
+        let mut ctx = testing::context(&[], None);
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+
+        let table = explain(&registry(), &Arc::new(ctx));
+        let rows: Vec<_> = table
+            .lines()
+            .map(|line| {
+                let columns: Vec<_> = line.split_whitespace().collect();
+                // Without the time, which varies
+                let detail = columns.get(3..).unwrap_or_default().join(" ");
+                (columns.first().copied(), columns.get(1).copied(), detail)
+            })
+            .collect();
+
+        let row = |name: &'static str, status: &'static str, detail: &str| {
+            (Some(name), Some(status), detail.to_string())
+        };
+        assert_eq!(
+            rows[..6],
+            [
+                row("segment", "status", "value"),
+                row("shown", "shown", "main"),
+                row(
+                    "conditional",
+                    "hidden",
+                    "conditional hidden by config: value \"base\" is hidden"
+                ),
+                row("absent", "hidden", "absent not applicable: not a repo"),
+                row("slow", "failed", "slow skipped: took longer than 50ms"),
+                row(
+                    "broken",
+                    "failed",
+                    "failed to get broken info: unexpected git content"
+                ),
+            ]
+        );
+        assert_eq!(rows[6], (None, None, String::new()));
+        assert_eq!(rows[7].0, Some("total"));
+        assert_eq!(rows.len(), 8);
+    }
+}
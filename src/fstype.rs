@@ -0,0 +1,154 @@
+use std::path::Path;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use nix::sys::statfs::{self, FsType, NFS_SUPER_MAGIC, SMB_SUPER_MAGIC};
+
+    // Magic numbers from statfs(2) that nix doesn't provide
+    const CIFS_MAGIC_NUMBER: i64 = 0xff53_4d42;
+    const SMB2_MAGIC_NUMBER: i64 = 0xfe53_4d42;
+    const FUSE_SUPER_MAGIC: i64 = 0x6573_5546;
+
+    /// `field` of the mount table with the octal escapes the kernel writes for
+    /// spaces, tabs, newlines and backslashes, like `\040`, turned back
+    fn unescape(field: &str) -> String {
+        let mut ret = String::new();
+        let mut rest = field;
+        while let Some(i) = rest.find('\\') {
+            ret.push_str(&rest[..i]);
+            let code = rest
+                .get(i + 1..i + 4)
+                .and_then(|code| u8::from_str_radix(code, 8).ok());
+            match code {
+                Some(code) => {
+                    ret.push(char::from(code));
+                    rest = &rest[i + 4..];
+                }
+                None => {
+                    ret.push('\\');
+                    rest = &rest[i + 1..];
+                }
+            }
+        }
+        ret.push_str(rest);
+        ret
+    }
+
+    /// The mount point and filesystem type of each line of a
+    /// `/proc/self/mountinfo`. The type follows the `-` that ends the optional
+    /// fields, of which there can be any number
+    fn parse_mountinfo(mountinfo: &str) -> Vec<(PathBuf, &str)> {
+        mountinfo
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(' ');
+                let mount_point = fields.nth(4)?;
+                let fs_type = fields.skip_while(|field| *field != "-").nth(1)?;
+                Some((PathBuf::from(unescape(mount_point)), fs_type))
+            })
+            .collect()
+    }
+
+    /// Type of the filesystem `path` is on, from the mount closest to it. Of
+    /// mounts on the same point the last one is on top
+    fn fs_type_of<'a>(mounts: &[(PathBuf, &'a str)], path: &Path) -> Option<&'a str> {
+        mounts
+            .iter()
+            .filter(|(mount_point, _)| path.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+            .map(|(_, fs_type)| *fs_type)
+    }
+
+    /// FUSE is used for plenty of local filesystems too, so check the mount
+    /// table to see whether `path` is actually on sshfs
+    fn is_sshfs(path: &Path) -> bool {
+        let mountinfo = match fs::read_to_string("/proc/self/mountinfo") {
+            Ok(mountinfo) => mountinfo,
+            Err(_) => return false,
+        };
+
+        fs_type_of(&parse_mountinfo(&mountinfo), path) == Some("fuse.sshfs")
+    }
+
+    pub fn is_network_fs(path: &Path) -> bool {
+        let fs_type = match statfs::statfs(path) {
+            Ok(stat) => stat.filesystem_type(),
+            Err(_) => return false,
+        };
+
+        let FsType(magic) = fs_type;
+        // fs_type_t differs between libcs and architectures
+        #[allow(clippy::unnecessary_cast)]
+        match magic as i64 {
+            _ if fs_type == NFS_SUPER_MAGIC || fs_type == SMB_SUPER_MAGIC => true,
+            CIFS_MAGIC_NUMBER | SMB2_MAGIC_NUMBER => true,
+            FUSE_SUPER_MAGIC => is_sshfs(path),
+            _ => false,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Trimmed from a laptop with a home dir on sshfs, a project mounted
+        /// over it from another host and a dir with spaces in its name
+        const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+23 22 0:21 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw
+45 22 0:45 / /home/ada/remote rw,nosuid,nodev,relatime shared:30 - fuse.sshfs ada@build:/home/ada rw,user_id=1000,group_id=1000
+46 45 0:46 / /home/ada/remote/shop rw,nosuid,nodev,relatime - fuse.rclone shop: rw,user_id=1000,group_id=1000
+47 22 0:47 / /mnt/Team\\040Drive rw,relatime shared:31 master:2 - fuse.sshfs ada@nas:/team rw,user_id=1000
+";
+
+        fn fs_type(path: &str) -> Option<&'static str> {
+            fs_type_of(&parse_mountinfo(MOUNTINFO), Path::new(path))
+        }
+
+        #[test]
+        fn parses_mountinfo() {
+            let mounts = parse_mountinfo(MOUNTINFO);
+            assert_eq!(mounts.len(), 5);
+            assert_eq!(mounts[2], (PathBuf::from("/home/ada/remote"), "fuse.sshfs"));
+            assert_eq!(mounts[4], (PathBuf::from("/mnt/Team Drive"), "fuse.sshfs"));
+            assert!(parse_mountinfo("22 1 259:2 / / rw\n").is_empty());
+        }
+
+        #[test]
+        fn closest_mount() {
+            assert_eq!(fs_type("/home/ada/remote/src"), Some("fuse.sshfs"));
+            // Mounted inside the sshfs one
+            assert_eq!(fs_type("/home/ada/remote/shop/web"), Some("fuse.rclone"));
+            // Only whole components count
+            assert_eq!(fs_type("/home/ada/remote-old"), Some("ext4"));
+            assert_eq!(fs_type("/mnt/Team Drive/plans"), Some("fuse.sshfs"));
+            assert_eq!(fs_type("/mnt/Team"), Some("ext4"));
+            assert_eq!(fs_type_of(&[], Path::new("/")), None);
+        }
+
+        #[test]
+        fn unescapes() {
+            assert_eq!(unescape("/mnt/a\\040b\\011c"), "/mnt/a b\tc");
+            assert_eq!(unescape("/mnt/back\\134slash"), "/mnt/back\\slash");
+            assert_eq!(unescape("/mnt/odd\\"), "/mnt/odd\\");
+            assert_eq!(unescape("/mnt/odd\\9x"), "/mnt/odd\\9x");
+        }
+    }
+}
+
+/// Whether `path` lives on a network filesystem (NFS, CIFS/SMB or sshfs),
+/// where every stat is a round trip and probes should be kept to a minimum
+#[cfg(target_os = "linux")]
+pub fn is_network_fs(path: &Path) -> bool {
+    linux::is_network_fs(path)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_network_fs(_path: &Path) -> bool {
+    false
+}
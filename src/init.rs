@@ -0,0 +1,126 @@
+//! Snippets printed by `promptline init <shell>` that set the shell's prompt
+//! to promptline's output. Source them from the shell's rc file, e.g.
+//! `eval "$(promptline init zsh)"`
+
+/// `$PIPESTATUS` has the status of every command of the last pipeline, and is
+/// saved first thing in `PROMPT_COMMAND` before anything there replaces it.
+/// Bash has no hook for changing directory, so `PROMPT_COMMAND` compares the
+/// cwd with the last prompt's to start `promptline prefetch` in the
+/// background. A plain `$ ` stands in when promptline fails or isn't
+/// installed
+const BASH: &str = r#"_promptline_ps1() {
+    local prompt
+    if prompt=$(promptline "$1" --shell bash); then
+        printf '%s' "$prompt"
+    else
+        printf '$ '
+    fi
+}
+_promptline_precmd() {
+    _promptline_status="${PIPESTATUS[*]}"
+    if [[ $PWD != "$_promptline_pwd" ]]; then
+        _promptline_pwd=$PWD
+        (promptline prefetch >/dev/null 2>&1 &)
+    fi
+}
+PROMPT_COMMAND="_promptline_precmd${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+PS1='$(_promptline_ps1 "$_promptline_status")'
+"#;
+
+/// `zle-keymap-select` runs whenever vi mode switches keymaps, and redrawing
+/// there re-runs promptline with the new `$KEYMAP`. The expensive segments are
+/// rendered by `--slow` in the background, and once it is done `zle -F` hands
+/// its output to the `--fast` prompt and redraws it. Accepting a line first
+/// redraws its prompt as the `--transient` one, leaving only the prompt char
+/// in the scrollback. Changing directory starts `promptline prefetch` in the
+/// background. A plain `%# ` stands in when promptline fails or isn't
+/// installed
+const ZSH: &str = r#"setopt prompt_subst
+_promptline() {
+    local prompt
+    if prompt=$(promptline "$@"); then
+        print -rn -- "$prompt"
+    else
+        print -rn -- '%# '
+    fi
+}
+_promptline_status=0
+_promptline_slow=
+_promptline_precmd() {
+    _promptline_status="${pipestatus[*]}"
+    _promptline_slow=
+    if [[ -n $_promptline_fd ]]; then
+        zle -F $_promptline_fd
+        exec {_promptline_fd}<&-
+    fi
+    exec {_promptline_fd}< <(promptline --shell zsh --slow)
+    zle -F $_promptline_fd _promptline_async_done
+}
+_promptline_async_done() {
+    IFS= read -rd '' -u $1 _promptline_slow
+    zle -F $1
+    exec {_promptline_fd}<&-
+    _promptline_fd=
+    zle reset-prompt
+}
+precmd_functions+=(_promptline_precmd)
+_promptline_chpwd() {
+    promptline prefetch >/dev/null 2>&1 &!
+}
+chpwd_functions+=(_promptline_chpwd)
+_promptline_prompt='$(_promptline "$_promptline_status" --shell zsh --vi-mode "${KEYMAP:-main}" --fast --async-result "$_promptline_slow")'
+PROMPT=$_promptline_prompt
+_promptline_accept_line() {
+    PROMPT='$(_promptline --transient "$_promptline_status" --shell zsh)'
+    zle reset-prompt
+    PROMPT=$_promptline_prompt
+    zle .accept-line
+}
+zle -N accept-line _promptline_accept_line
+_promptline_keymap_select() { zle reset-prompt }
+zle -N zle-keymap-select _promptline_keymap_select
+"#;
+
+/// fish repaints the prompt by itself when `$fish_bind_mode` changes. The
+/// empty `fish_mode_prompt` replaces fish's own mode indicator. `--slow` runs
+/// in a background fish that hands its output back through a universal
+/// variable named after this shell's pid, which triggers the repaint. A change
+/// of `$PWD` starts `promptline prefetch` in the background. A plain `> `
+/// stands in when promptline prints nothing, e.g. when it isn't installed
+const FISH: &str = r#"function fish_prompt
+    set -l prompt (promptline $status --vi-mode $fish_bind_mode --fast --async-result "$__promptline_slow" | string collect)
+    and printf '%s' $prompt
+    or printf '> '
+end
+function fish_mode_prompt
+end
+function __promptline_slow_start --on-event fish_prompt
+    set -g __promptline_slow
+    fish --private --command "set -U __promptline_slow_$fish_pid (promptline --slow)" &
+    disown
+end
+function __promptline_slow_done --on-variable __promptline_slow_$fish_pid
+    set -l var __promptline_slow_$fish_pid
+    set -q $var; or return
+    set -g __promptline_slow $$var
+    set -e $var
+    commandline -f repaint
+end
+function __promptline_prefetch --on-variable PWD
+    promptline prefetch >/dev/null 2>&1 &
+    disown
+end
+"#;
+
+/// The shells `promptline init` has a snippet for
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// The snippet for `shell`, as given to `promptline init`
+pub fn snippet(shell: &str) -> Option<&'static str> {
+    match shell {
+        "bash" => Some(BASH),
+        "zsh" => Some(ZSH),
+        "fish" => Some(FISH),
+        _ => None,
+    }
+}
@@ -0,0 +1,373 @@
+//! Splitting the prompt into lines and fitting them into the terminal
+
+use crate::{config::MergeConfig, segment::RenderedSegment};
+
+/// Segments dropped first when the prompt is too wide, ahead of those later in
+/// the list. Segments that aren't listed go before any of these
+pub const DEFAULT_DROP_ORDER: &[&str] = &[
+    "time",
+    "shell",
+    "shlvl",
+    "vi_mode",
+    "docker",
+    "nix_shell",
+    "conda",
+    "venv",
+    "locale",
+    "mail",
+    "unpushed",
+    "user",
+    "hostname",
+    "hg",
+    "git",
+    "cwd",
+    "status",
+];
+
+/// Frame of the prompt's last line, in front of the prompt char
+pub const LAST_LINE_FRAME: &str = "└";
+
+/// The lines of the prompt above the last one, `┌[a]-[b]` for the first and
+/// `├[c]` for the ones after it. Lines without components are left out
+pub fn content_lines(lines: &[Vec<String>]) -> Vec<String> {
+    lines
+        .iter()
+        .filter(|components| !components.is_empty())
+        .enumerate()
+        .map(|(i, components)| {
+            let frame = if i == 0 { "┌" } else { "├" };
+            format!("{frame}[{}]", components.join("]-["))
+        })
+        .collect()
+}
+
+/// The last line of the prompt, up to where the command line starts
+pub fn last_line(frame: &str, prompt_char: &str) -> String {
+    format!("{frame}{prompt_char} ")
+}
+
+/// Shortest the cwd is truncated to before it is dropped too
+const MIN_CWD_WIDTH: usize = 8;
+
+/// A rendered segment, along with the name of the segment it came from
+pub struct Component {
+    pub name: String,
+    pub segment: RenderedSegment,
+}
+
+fn component_width(component: &Component) -> usize {
+    component.segment.visible_width()
+}
+
+/// Combines the components of each of `merges` into one, joined in the order
+/// the merge lists them. It takes the place and the name of the member that
+/// comes first in the prompt, so `lines` and `drop_order` refer to it by that
+/// name. Members without text are left out of the prompt, and a merge with
+/// only one member left keeps it as it is
+pub fn merge(mut components: Vec<Component>, merges: &[MergeConfig]) -> Vec<Component> {
+    for merge in merges {
+        components.retain(|component| {
+            !merge.segments.contains(&component.name) || !component.segment.value().is_empty()
+        });
+        let positions: Vec<_> = components
+            .iter()
+            .enumerate()
+            .filter(|(_, component)| merge.segments.contains(&component.name))
+            .map(|(i, _)| i)
+            .collect();
+        let first = match positions.as_slice() {
+            [first, _, ..] => *first,
+            _ => continue,
+        };
+
+        let name = components[first].name.clone();
+        let mut members: Vec<_> = positions
+            .iter()
+            .rev()
+            .map(|&i| components.remove(i))
+            .collect();
+        members.sort_by_key(|member| merge.segments.iter().position(|name| *name == member.name));
+        let members = members.into_iter().map(|member| member.segment).collect();
+        let segment = RenderedSegment::merge(members, &merge.joiner);
+        components.insert(first, Component { name, segment });
+    }
+    components
+}
+
+/// Splits `components` into the lines the config assigns them to, in the
+/// order given there. Components no line names go on the last line, in the
+/// order they came in. Lines left without components are left out
+pub fn assign_lines(components: Vec<Component>, lines: &[Vec<String>]) -> Vec<Vec<Component>> {
+    let mut assigned: Vec<Vec<Component>> = lines.iter().map(|_| vec![]).collect();
+    let mut rest = vec![];
+    for component in components {
+        match lines.iter().position(|line| line.contains(&component.name)) {
+            Some(i) => assigned[i].push(component),
+            None => rest.push(component),
+        }
+    }
+    for (line, names) in assigned.iter_mut().zip(lines) {
+        line.sort_by_key(|component| names.iter().position(|name| *name == component.name));
+    }
+
+    match assigned.last_mut() {
+        Some(last) => last.extend(rest),
+        None => assigned.push(rest),
+    }
+    assigned.retain(|line| !line.is_empty());
+    assigned
+}
+
+/// Width of a line of the prompt, `┌[a]-[b]` for two components
+fn line_width(components: &[Component]) -> usize {
+    if components.is_empty() {
+        return 0;
+    }
+
+    let frame = 3 * components.len();
+    components.iter().map(component_width).sum::<usize>() + frame
+}
+
+/// Index of the component to drop next. Unlisted ones go first, from the end of
+/// the prompt backwards
+fn next_to_drop(components: &[Component], drop_order: &[String]) -> Option<usize> {
+    let rank = |component: &Component| {
+        drop_order
+            .iter()
+            .position(|name| *name == component.name)
+            .map_or(0, |i| i + 1)
+    };
+
+    components
+        .iter()
+        .enumerate()
+        .rev()
+        .min_by_key(|(_, component)| rank(component))
+        .map(|(i, _)| i)
+}
+
+/// Drops components in `drop_order` until the line fits in `width` columns.
+/// If `truncate_cwd` is set, the cwd is shortened from the start before it is
+/// dropped
+pub fn fit_to_width(
+    components: &mut Vec<Component>,
+    width: usize,
+    drop_order: &[String],
+    truncate_cwd: bool,
+) {
+    while line_width(components) > width {
+        let i = match next_to_drop(components, drop_order) {
+            Some(i) => i,
+            None => return,
+        };
+
+        if truncate_cwd && components[i].name == "cwd" {
+            let excess = line_width(components) - width;
+            let cwd_width = component_width(&components[i]);
+            if cwd_width.saturating_sub(excess) >= MIN_CWD_WIDTH {
+                components[i].segment.truncate_start(cwd_width - excess);
+                return;
+            }
+        }
+
+        components.remove(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decorated::{Color, DecoratedString};
+
+    fn components(names: &[(&str, &str)]) -> Vec<Component> {
+        names
+            .iter()
+            .map(|(name, text)| Component {
+                name: name.to_string(),
+                segment: DecoratedString::new(text.to_string()).bold().into(),
+            })
+            .collect()
+    }
+
+    fn default_order() -> Vec<String> {
+        DEFAULT_DROP_ORDER.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn plain(components: &[Component]) -> Vec<String> {
+        components.iter().map(|c| c.segment.to_plain()).collect()
+    }
+
+    fn prompt() -> Vec<Component> {
+        components(&[
+            ("time", "12:34"),
+            ("cwd", "~/src/promptline"),
+            ("shell", "zsh"),
+            ("status", "0"),
+            ("git", "main 0123456789abcd"),
+        ])
+    }
+
+    #[test]
+    fn fitting_prompt_is_untouched() {
+        let mut components = prompt();
+        let width = line_width(&components);
+        fit_to_width(&mut components, width, &default_order(), true);
+        assert_eq!(components.len(), 5);
+    }
+
+    #[test]
+    fn drops_time_and_shell_first() {
+        let mut components = prompt();
+        let width = line_width(&components) - 1;
+        fit_to_width(&mut components, width, &default_order(), true);
+        assert_eq!(
+            plain(&components),
+            ["~/src/promptline", "zsh", "0", "main 0123456789abcd"]
+        );
+
+        fit_to_width(&mut components, width - 8, &default_order(), true);
+        assert_eq!(
+            plain(&components),
+            ["~/src/promptline", "0", "main 0123456789abcd"]
+        );
+    }
+
+    #[test]
+    fn truncates_cwd_as_last_resort() {
+        let mut components = prompt();
+        fit_to_width(&mut components, 20, &default_order(), true);
+        assert_eq!(plain(&components), ["…c/promptline", "0"]);
+        assert_eq!(line_width(&components), 20);
+    }
+
+    #[test]
+    fn drops_cwd_without_truncation() {
+        let mut components = prompt();
+        fit_to_width(&mut components, 20, &default_order(), false);
+        assert_eq!(plain(&components), ["0"]);
+    }
+
+    #[test]
+    fn unlisted_dropped_first() {
+        let mut components = components(&[("cwd", "~"), ("custom", "x"), ("status", "0")]);
+        fit_to_width(&mut components, 8, &default_order(), true);
+        assert_eq!(plain(&components), ["~", "0"]);
+    }
+
+    fn names(lines: &[Vec<Component>]) -> Vec<Vec<&str>> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|c| c.name.as_str()).collect())
+            .collect()
+    }
+
+    fn config_lines(lines: &[&[&str]]) -> Vec<Vec<String>> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|name| name.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn one_line_by_default() {
+        let lines = assign_lines(prompt(), &[]);
+        assert_eq!(names(&lines), [["time", "cwd", "shell", "status", "git"]]);
+        assert!(assign_lines(vec![], &[]).is_empty());
+    }
+
+    #[test]
+    fn lines_in_config_order() {
+        let config = config_lines(&[&["shell", "time"], &["git", "cwd"]]);
+        let lines = assign_lines(prompt(), &config);
+        assert_eq!(
+            names(&lines),
+            [vec!["shell", "time"], vec!["git", "cwd", "status"]]
+        );
+    }
+
+    #[test]
+    fn empty_lines_collapse() {
+        let config = config_lines(&[&["user", "hostname"], &["time"], &["cwd"]]);
+        let lines = assign_lines(prompt(), &config);
+        assert_eq!(
+            names(&lines),
+            [vec!["time"], vec!["cwd", "shell", "status", "git"]]
+        );
+
+        let config = config_lines(&[&["time"], &["user"]]);
+        let lines = assign_lines(prompt(), &config);
+        assert_eq!(
+            names(&lines),
+            [vec!["time"], vec!["cwd", "shell", "status", "git"]]
+        );
+    }
+
+    #[test]
+    fn frames() {
+        let lines = config_lines(&[&["a", "b"], &[], &["c"], &["d"]]);
+        assert_eq!(content_lines(&lines), ["┌[a]-[b]", "├[c]", "├[d]"]);
+        assert!(content_lines(&[vec![]]).is_empty());
+    }
+
+    fn status_and_duration() -> Vec<MergeConfig> {
+        vec![MergeConfig {
+            segments: vec!["status".into(), "duration".into()],
+            joiner: " · ".into(),
+        }]
+    }
+
+    #[test]
+    fn merge_without_members() {
+        let merged = merge(prompt(), &status_and_duration());
+        assert_eq!(plain(&merged), plain(&prompt()));
+        assert!(merge(vec![], &status_and_duration()).is_empty());
+    }
+
+    #[test]
+    fn merge_with_one_member() {
+        let mut components = prompt();
+        components.retain(|component| component.name != "status");
+        components.extend(self::components(&[("duration", "12.4s")]));
+        let merged = merge(components, &status_and_duration());
+        assert_eq!(
+            plain(&merged),
+            [
+                "12:34",
+                "~/src/promptline",
+                "zsh",
+                "main 0123456789abcd",
+                "12.4s"
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_with_two_members() {
+        // The duration comes first in the prompt but second in the merge
+        let mut components = components(&[("duration", "12.4s"), ("cwd", "~")]);
+        components.push(Component {
+            name: "status".into(),
+            segment: DecoratedString::new("✘ 1".into())
+                .colored(Color::Red)
+                .bold()
+                .into(),
+        });
+        let merged = merge(components, &status_and_duration());
+        assert_eq!(
+            merged.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            ["duration", "cwd"]
+        );
+        assert_eq!(plain(&merged), ["✘ 1 · 12.4s", "~"]);
+        assert_eq!(
+            merged[0].segment.to_ansi(),
+            "\x1b[1m\x1b[31m✘ 1\x1b[39m\x1b[22m\x1b[31m · \x1b[39m\x1b[1m12.4s\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn merge_leaves_out_empty_members() {
+        let components = components(&[("status", ""), ("duration", "12.4s"), ("cwd", "~")]);
+        let merged = merge(components, &status_and_duration());
+        assert_eq!(plain(&merged), ["12.4s", "~"]);
+    }
+}
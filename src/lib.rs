@@ -0,0 +1,75 @@
+//! promptline renders a shell prompt out of segments, each showing one piece of
+//! information like the cwd or the git branch. Segments implement [`Segment`]
+//! and are rendered in parallel against a shared [`Context`]
+//!
+//! # Stable API
+//!
+//! Other programs can render promptline's segments themselves. These items
+//! follow semver, everything else is promptline's own and may change in any
+//! release:
+//!
+//! - [`Context::from_env`], the environment and config the prompt would see
+//! - [`registry::default_segments`], the segments of the prompt in order
+//! - [`Segment`], and [`Segment::render`] in particular
+//! - [`RenderedSegment`], its [`value`](RenderedSegment::value) and its
+//!   [`decorated`](RenderedSegment::decorated) text. That is a
+//!   [`DecoratedString`] rather than one text and one style, as parts of a
+//!   segment can be styled differently
+//! - [`render::ansi`] and [`render::plain`], the text to print
+//!
+//! Enums that will grow more variants, and [`SegmentError`], are
+//! `#[non_exhaustive]`.
+//!
+//! ```no_run
+//! use promptline::{registry, render, Context};
+//!
+//! let ctx = Context::from_env();
+//! let shown: Vec<_> = registry::default_segments(&ctx.config)
+//!     .iter()
+//!     .filter_map(|segment| segment.render(&ctx).ok())
+//!     .map(|rendered| render::ansi(&rendered))
+//!     .collect();
+//! println!("{}", shown.join(" "));
+//! ```
+//!
+//! `examples/compose.rs` puts a segment of its own next to a built in one.
+
+pub mod args;
+pub mod bench;
+pub mod cache;
+pub mod check;
+pub mod completions;
+pub mod conditions;
+pub mod config;
+pub mod context;
+pub mod custom;
+pub mod debug;
+pub mod decorated;
+pub mod diagnose;
+pub mod explain;
+pub mod fstype;
+pub mod init;
+pub mod layout;
+#[cfg(feature = "time")]
+pub mod localtime;
+pub mod platform;
+pub mod preview;
+pub mod prompt;
+pub mod registry;
+pub mod render;
+pub mod runner;
+pub mod segment;
+pub mod segments;
+pub mod size;
+pub mod static_text;
+pub mod theme;
+pub mod trace;
+pub mod width;
+
+#[cfg(test)]
+mod testing;
+
+pub use context::Context;
+pub use decorated::{Color, DecoratedString, Escaping};
+pub use segment::{RenderedSegment, Segment, SegmentError};
+pub use width::{strip_ansi, visible_width};
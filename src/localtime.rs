@@ -0,0 +1,183 @@
+//! Just enough local time support for the time segment, without pulling in
+//! chrono
+
+use std::{fmt::Write, mem, time::SystemTime};
+
+#[cfg(unix)]
+extern "C" {
+    // Not exposed by the libc crate on unix
+    fn tzset();
+}
+
+/// Breaks `time` down in the local time zone
+#[cfg(unix)]
+fn local_tm(time: libc::time_t) -> Option<libc::tm> {
+    // SAFETY: tm is plain data that localtime_r fills in, and both pointers are
+    // valid for the duration of the call
+    unsafe {
+        // localtime_r doesn't have to pick up changes to $TZ by itself
+        tzset();
+        let mut tm: libc::tm = mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            return None;
+        }
+        Some(tm)
+    }
+}
+
+/// Breaks `time` down in the local time zone
+#[cfg(windows)]
+fn local_tm(time: libc::time_t) -> Option<libc::tm> {
+    // SAFETY: tm is plain data that localtime_s fills in, and both pointers are
+    // valid for the duration of the call
+    unsafe {
+        libc::tzset();
+        let mut tm: libc::tm = mem::zeroed();
+        if libc::localtime_s(&mut tm, &time) != 0 {
+            return None;
+        }
+        Some(tm)
+    }
+}
+
+/// A broken down local time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LocalTime {
+    pub year: i32,
+    /// 1 to 12
+    pub month: u32,
+    /// 1 to 31
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl LocalTime {
+    pub fn now() -> Option<LocalTime> {
+        let since_epoch = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?;
+        LocalTime::from_timestamp(since_epoch.as_secs() as i64)
+    }
+
+    /// Converts seconds since the epoch to local time in the zone given by
+    /// `$TZ`, or the system zone if unset
+    pub fn from_timestamp(timestamp: i64) -> Option<LocalTime> {
+        let tm = local_tm(timestamp as libc::time_t)?;
+
+        Some(LocalTime {
+            year: tm.tm_year + 1900,
+            month: tm.tm_mon as u32 + 1,
+            day: tm.tm_mday as u32,
+            hour: tm.tm_hour as u32,
+            minute: tm.tm_min as u32,
+            second: tm.tm_sec as u32,
+        })
+    }
+
+    /// Converts seconds since the epoch to UTC, for when the local zone can't
+    /// be worked out
+    pub fn utc_from_timestamp(timestamp: i64) -> LocalTime {
+        let days = timestamp.div_euclid(86400);
+        let secs = timestamp.rem_euclid(86400) as u32;
+
+        // Howard Hinnant's days_from_civil, backwards
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        LocalTime {
+            year: year as i32,
+            month,
+            day,
+            hour: secs / 3600,
+            minute: secs / 60 % 60,
+            second: secs % 60,
+        }
+    }
+
+    /// Formats using a subset of strftime: `%H`, `%M`, `%S`, `%d`, `%m`, `%Y`,
+    /// `%p` and `%%`. Anything else is copied through unchanged
+    pub fn format(&self, format: &str) -> String {
+        let mut ret = String::new();
+        let mut chars = format.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                ret.push(c);
+                continue;
+            }
+
+            let _ = match chars.next() {
+                Some('H') => write!(ret, "{:02}", self.hour),
+                Some('M') => write!(ret, "{:02}", self.minute),
+                Some('S') => write!(ret, "{:02}", self.second),
+                Some('d') => write!(ret, "{:02}", self.day),
+                Some('m') => write!(ret, "{:02}", self.month),
+                Some('Y') => write!(ret, "{}", self.year),
+                Some('p') if self.hour < 12 => write!(ret, "AM"),
+                Some('p') => write!(ret, "PM"),
+                Some('%') => write!(ret, "%"),
+                Some(other) => write!(ret, "%{other}"),
+                None => write!(ret, "%"),
+            };
+        }
+
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> LocalTime {
+        LocalTime {
+            year: 2024,
+            month: 3,
+            day: 9,
+            hour,
+            minute,
+            second: 5,
+        }
+    }
+
+    #[test]
+    fn formats_directives() {
+        assert_eq!(time(7, 4).format("%H:%M"), "07:04");
+        assert_eq!(
+            time(7, 4).format("%Y-%m-%d %H:%M:%S %p"),
+            "2024-03-09 07:04:05 AM"
+        );
+        assert_eq!(time(19, 30).format("%p"), "PM");
+    }
+
+    #[test]
+    fn utc() {
+        assert_eq!(
+            LocalTime::utc_from_timestamp(0).format("%Y-%m-%d %H:%M:%S"),
+            "1970-01-01 00:00:00"
+        );
+        assert_eq!(LocalTime::utc_from_timestamp(1_709_982_245), time(11, 4));
+        assert_eq!(
+            LocalTime::utc_from_timestamp(951_825_600).format("%Y-%m-%d %H"),
+            "2000-02-29 12"
+        );
+        assert_eq!(
+            LocalTime::utc_from_timestamp(-1).format("%Y-%m-%d %H:%M:%S"),
+            "1969-12-31 23:59:59"
+        );
+    }
+
+    #[test]
+    fn copies_unknown_directives() {
+        assert_eq!(time(7, 4).format("100%% %Q %"), "100% %Q %");
+    }
+}
@@ -1,601 +1,552 @@
 use std::{
-    env,
-    error::Error,
-    fmt::{self, Write as FmtWrite},
-    fs::{self, File},
-    io::{self, Read, Write},
-    path::{Path, PathBuf},
+    env, fmt, fs,
+    io::{self, Write},
+    panic,
+    path::Path,
+    process,
+    sync::Arc,
+    time::{Instant, SystemTime},
 };
 
-#[allow(unused)]
-enum Color {
-    Red,
-    Green,
-    Yellow,
-    Blue,
-    Magenta,
-    Cyan,
-    White,
-}
+use promptline::{
+    args::{self, Args},
+    bench,
+    check::{self, ConfigMarker},
+    completions,
+    config::{self, Config, ConfigError, Disable},
+    debug::{self, Verbosity},
+    decorated::Escaping,
+    diagnose, explain, init,
+    layout::{self, Component},
+    platform, preview, prompt, registry,
+    runner::{self, Registry},
+    segment::{self, ErrorKind, Outcome},
+    segments,
+    trace::{self, SegmentTrace, Trace},
+    Context, DecoratedString, SegmentError,
+};
 
-impl Color {
-    fn to_ansi(&self) -> i32 {
-        match self {
-            Color::Red => 31,
-            Color::Green => 32,
-            Color::Yellow => 33,
-            Color::Blue => 34,
-            Color::Magenta => 35,
-            Color::Cyan => 36,
-            Color::White => 37,
+/// Exit statuses besides 0 and 1, as listed in `--help`
+const EXIT_USAGE: i32 = 2;
+const EXIT_IO: i32 = 3;
+
+/// Writes `output` to stdout, exiting with `EXIT_IO` if it can't be, e.g. when
+/// the shell has closed the pipe
+fn print_out(output: fmt::Arguments) {
+    let mut stdout = io::stdout().lock();
+    if stdout
+        .write_fmt(output)
+        .and_then(|()| stdout.flush())
+        .is_err()
+    {
+        process::exit(EXIT_IO);
+    }
+}
+
+/// Writes the prompt, with a line for each of `lines` that has components and
+/// `frame` and `prompt_char` on the last one, in one go. Without any components
+/// only the last line is left, escaped like the components already are. `end`
+/// follows the prompt, to reset the terminal's styling
+fn render_prompt(
+    lines: &[Vec<String>],
+    (frame, prompt_char): (&str, &str),
+    escaping: Escaping,
+    end: &str,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut prompt = layout::content_lines(lines);
+    prompt.push(layout::last_line(
+        &escaping.escape(frame),
+        &escaping.escape(prompt_char),
+    ));
+    let prompt = format!("{}{end}", prompt.join("\n"));
+
+    out.write_all(prompt.as_bytes())?;
+    out.flush()
+}
+
+/// Resets the terminal's styling after the prompt, so styling a segment failed
+/// to turn off doesn't bleed into the command line
+fn prompt_end(args: &Args) -> String {
+    if args.color {
+        args.escaping.reset()
+    } else {
+        String::new()
+    }
+}
+
+/// Prints each problem in the config file as `path:line:column: key: message`.
+/// Exits with 1 if there were any
+fn check_config() -> ! {
+    let path = match config::config_path() {
+        Some(path) => path,
+        None => {
+            print_out(format_args!(
+                "no config file, neither $PROMPTLINE_CONFIG nor $HOME is set\n"
+            ));
+            process::exit(0);
         }
-    }
-}
-
-enum DecoratedString {
-    Bold(Box<DecoratedString>),
-    Colored(Box<DecoratedString>, Color),
-    Default(String),
-}
+    };
 
-impl DecoratedString {
-    fn append_to_ansi(val: &DecoratedString, s: &mut String) -> Result<(), fmt::Error> {
-        // https://gist.github.com/fnky/458719343aabd01cfb17a3a4f7296797
-        match val {
-            DecoratedString::Bold(inner) => {
-                write!(s, "\x1b[1m")?;
-                Self::append_to_ansi(inner, s)?;
-                write!(s, "\x1b[22m")?;
-            }
-            DecoratedString::Colored(inner, color) => {
-                write!(s, "\x1b[{}m", color.to_ansi())?;
-                Self::append_to_ansi(inner, s)?;
-                write!(s, "\x1b[39m")?;
-            }
-            DecoratedString::Default(val) => {
-                write!(s, "{val}")?;
-            }
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            print_out(format_args!(
+                "{}: not found, using the defaults\n",
+                path.display()
+            ));
+            process::exit(0);
         }
+        Err(e) => {
+            print_out(format_args!("{}: {e}\n", path.display()));
+            process::exit(1);
+        }
+    };
 
-        Ok(())
-    }
-
-    fn to_ansi(&self) -> String {
-        let mut ret = String::new();
-        Self::append_to_ansi(self, &mut ret).unwrap();
-        ret
-    }
-
-    fn bold(self) -> DecoratedString {
-        DecoratedString::Bold(Box::new(self))
-    }
-
-    fn colored(self, color: Color) -> DecoratedString {
-        DecoratedString::Colored(Box::new(self), color)
+    let builtin: Vec<_> = segments::builtin()
+        .iter()
+        .map(|segment| segment.name().to_string())
+        .collect();
+    let problems = check::check(&source, &builtin);
+    if problems.is_empty() {
+        print_out(format_args!("{}: ok\n", path.display()));
+        process::exit(0);
     }
 
-    fn new(s: String) -> DecoratedString {
-        DecoratedString::Default(s)
+    for problem in &problems {
+        print_out(format_args!("{}:{problem}\n", path.display()));
     }
+    process::exit(1);
 }
 
-fn get_time() -> String {
-    let time = chrono::Local::now().time();
-    let formatted = format!("{}", time.format("%H:%M"));
-    DecoratedString::new(formatted)
-        .bold()
-        .colored(Color::Cyan)
-        .to_ansi()
-}
-
-#[derive(Debug)]
-enum UserError {
-    GetUser(nix::Error),
-    NoUser,
-}
-
-impl fmt::Display for UserError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            UserError::GetUser(_) => write!(f, "failed to get user"),
-            UserError::NoUser => write!(f, "no active user"),
+/// Prints the prompt as it looks with every enabled segment filled in from
+/// sample data, followed by a newline
+fn preview_prompt(registry: &Registry, args: Args, config: Config) -> ! {
+    let root = match preview::SampleRoot::create() {
+        Ok(root) => root,
+        Err(e) => {
+            let _ = writeln!(io::stderr(), "promptline: failed to set up the sample: {e}");
+            process::exit(EXIT_IO);
         }
+    };
+    let ctx = preview::context(args, config, &root);
+    let lines = preview::render(registry, &ctx);
+
+    let mut out = io::stdout().lock();
+    let end = prompt_end(&ctx.args);
+    let last_line = prompt::last_line(&ctx, None);
+    if render_prompt(&lines, last_line, ctx.args.escaping, &end, &mut out).is_err()
+        || writeln!(out).is_err()
+    {
+        process::exit(EXIT_IO);
     }
+    drop(root);
+    process::exit(0);
 }
 
-impl Error for UserError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            UserError::GetUser(e) => Some(e),
-            UserError::NoUser => None,
-        }
-    }
+fn describe_config_error(e: &ConfigError) -> String {
+    let mut message = String::from("failed to load config\nCaused by:\n");
+    let _ = segment::write_error_chain(&mut message, e);
+    message
 }
 
-fn get_user() -> Result<String, UserError> {
-    let user = nix::unistd::User::from_uid(nix::unistd::getuid())
-        .map_err(UserError::GetUser)?
-        .ok_or(UserError::NoUser)?;
-
-    let color_user = match user.name.as_str() {
-        "root" => DecoratedString::new(user.name).colored(Color::Red).bold(),
-        _ => DecoratedString::new(user.name)
-            .colored(Color::Magenta)
-            .bold(),
-    };
+fn print_errors(errors: &[SegmentError], config_error: Option<&str>) {
+    let verbosity = Verbosity::from_env_value(env::var("DEBUG_PROMPTLINE").ok().as_deref());
+    let _ = debug::write_errors(&mut io::stderr(), verbosity, errors);
 
-    Ok(color_user.to_ansi())
+    if let Some(e) = config_error {
+        if verbosity.shows(Outcome::Failed) {
+            let _ = writeln!(io::stderr(), "{e}");
+        }
+    }
 }
 
-#[derive(Debug)]
-enum HostnameError {
-    GetHostname(nix::Error),
-    GetHostnameString(std::str::Utf8Error),
-}
+/// Prints only the segments requested with `--segment`. Exits with 1 if none
+/// of them could be shown
+fn print_requested_segments(registry: &Registry, ctx: &Arc<Context>) -> ! {
+    let args = &ctx.args;
 
-impl fmt::Display for HostnameError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            HostnameError::GetHostname(_) => write!(f, "failed to get host name"),
-            HostnameError::GetHostnameString(_) => {
-                write!(f, "failed to convert hostname to string")
+    let mut requested = vec![];
+    for name in &args.segments {
+        match registry.get(name) {
+            Some(segment) => requested.push(segment),
+            None => {
+                let _ = writeln!(io::stderr(), "promptline: unknown segment {name}");
+                process::exit(EXIT_USAGE);
             }
         }
     }
-}
 
-impl Error for HostnameError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            HostnameError::GetHostname(e) => Some(e),
-            HostnameError::GetHostnameString(e) => Some(e),
+    let mut rendered = vec![];
+    let mut errors = vec![];
+
+    for (result, _) in runner::render_all(requested, ctx) {
+        match result {
+            Ok(segment) if args.color => rendered.push(segment.to_ansi_escaped(args.escaping)),
+            Ok(segment) => rendered.push(args.escaping.escape(&segment.to_plain())),
+            Err(e) => errors.push(e),
         }
     }
-}
 
-fn get_hostname() -> Result<String, HostnameError> {
-    let mut buf = [0u8; 64];
-    let res = nix::unistd::gethostname(&mut buf)
-        .map_err(HostnameError::GetHostname)?
-        .to_str()
-        .map_err(HostnameError::GetHostnameString)?;
+    print_errors(&errors, None);
 
-    let res = DecoratedString::new(res.to_string())
-        .colored(Color::Green)
-        .bold()
-        .to_ansi();
+    if rendered.is_empty() {
+        process::exit(1);
+    }
 
-    Ok(res)
+    print_out(format_args!("{}\n", rendered.join(" ")));
+    process::exit(0);
 }
 
-#[derive(Debug)]
-struct NoExitStatus;
-
-impl fmt::Display for NoExitStatus {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "no exit status")
+/// Keeps panics, which segments recover from, out of the shell unless
+/// `DEBUG_PROMPTLINE` asks for failures. Their messages are in the errors of
+/// the segments either way
+fn quiet_panics() {
+    let verbosity = Verbosity::from_env_value(env::var("DEBUG_PROMPTLINE").ok().as_deref());
+    if !verbosity.shows(Outcome::Failed) {
+        panic::set_hook(Box::new(|_| ()));
     }
 }
 
-impl Error for NoExitStatus {}
-
-fn get_status() -> Result<String, NoExitStatus> {
-    let status = env::args().nth(1).ok_or(NoExitStatus)?;
-
-    let color_status = match status.as_str() {
-        "0" => DecoratedString::new(status)
-            .colored(Color::Green)
-            .bold()
-            .to_ansi(),
-        _ => DecoratedString::new(status)
-            .colored(Color::Red)
-            .bold()
-            .to_ansi(),
+fn main() {
+    let started = Instant::now();
+    quiet_panics();
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            // A shell with a typo in its prompt setup still gets a prompt, if
+            // a bare one
+            let _ = writeln!(io::stderr(), "promptline: {e}, see promptline --help");
+            let last_line = (layout::LAST_LINE_FRAME, ">");
+            let _ = render_prompt(&[], last_line, Escaping::Raw, "", &mut io::stdout().lock());
+            process::exit(EXIT_USAGE);
+        }
     };
 
-    Ok(color_status)
-}
+    match args.command {
+        args::Command::Help => {
+            print_out(format_args!("{}", args::USAGE));
+            return;
+        }
+        args::Command::Version => {
+            print_out(format_args!("promptline {}\n", env!("CARGO_PKG_VERSION")));
+            return;
+        }
+        _ => (),
+    }
+
+    // Checked before anything else is looked at, as what is being avoided may
+    // be a hung network filesystem
+    let disable = Disable::from_env_value(env::var("PROMPTLINE_DISABLE").ok().as_deref());
+    if disable == Disable::Everything {
+        match args.command {
+            args::Command::Prompt if args.list_segments => (),
+            args::Command::Prompt if !args.segments.is_empty() => process::exit(1),
+            args::Command::Prompt if args.phase == args::Phase::Slow => return,
+            args::Command::Prefetch => return,
+            args::Command::Prompt | args::Command::Transient => {
+                print_out(format_args!("$ "));
+                return;
+            }
+            _ => (),
+        }
+    }
 
-fn get_cwd() -> String {
-    let cwd = env::var("PWD");
+    if let args::Command::Init(shell) = &args.command {
+        // The shell was checked when parsing the args
+        print_out(format_args!("{}", init::snippet(shell).unwrap_or_default()));
+        return;
+    }
 
-    if cwd.is_err() {
-        return DecoratedString::new("!!!".to_string())
-            .colored(Color::Red)
-            .bold()
-            .to_ansi();
+    if args.command == args::Command::CheckConfig {
+        check_config();
     }
 
-    let mut cwd = cwd.unwrap();
+    let (mut config, config_error) = match &args.profile {
+        Some(profile) => config::load_profile(Some(profile)),
+        None => config::load(),
+    };
+    let config_invalid = config_error.as_ref().is_some_and(ConfigError::is_invalid);
+    let config_error = config_error.map(|e| describe_config_error(&e));
 
-    if let Ok(home) = env::var("HOME") {
-        if cwd.starts_with(&home) {
-            cwd = cwd.replacen(&home, "~", 1);
+    if args.command == args::Command::Bench {
+        config.ignore_conditions = true;
+    }
+
+    let mut registry = registry::build(&config);
+    if let Disable::Segments(names) = &disable {
+        // The same list may be set on machines with other segments compiled
+        // in, so names that aren't segments here are skipped
+        for name in names.iter().filter(|name| registry.get(name).is_some()) {
+            config.disable_segment(name);
         }
     }
+    if config_invalid {
+        registry.insert(0, Arc::new(ConfigMarker));
+    }
 
-    DecoratedString::new(cwd)
-        .colored(Color::Blue)
-        .bold()
-        .to_ansi()
-}
+    if args.command == args::Command::Preview {
+        preview_prompt(&registry, args, config);
+    }
 
-#[derive(Debug)]
-enum HgError {
-    NoCwd(std::io::Error),
-    NotHg,
-}
+    if let args::Command::Completions(shell) = &args.command {
+        let names: Vec<_> = registry.iter().map(|s| s.name().to_string()).collect();
+        // The shell was checked when parsing the args
+        let script = completions::script(shell, &names).unwrap_or_default();
+        print_out(format_args!("{script}"));
+        return;
+    }
+
+    let ctx = Arc::new(Context::new(args, config));
+    let args = &ctx.args;
 
-impl fmt::Display for HgError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            HgError::NoCwd(_) => write!(f, "failed to get working directory"),
-            HgError::NotHg => write!(f, "working directory not in hg repo"),
+    if args.command == args::Command::Prefetch {
+        #[cfg(feature = "git")]
+        if ctx.config.prefetch {
+            segments::git::prefetch(&ctx);
         }
+        return;
     }
-}
 
-impl Error for HgError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            HgError::NoCwd(e) => Some(e),
-            HgError::NotHg => None,
+    if args.command == args::Command::Transient {
+        if write!(io::stdout().lock(), "{}", prompt::transient(&ctx)).is_err() {
+            process::exit(EXIT_IO);
         }
+        return;
     }
-}
 
-fn get_mercurial_info() -> Result<String, HgError> {
-    let mut hg_root = env::current_dir().map_err(HgError::NoCwd)?;
-
-    loop {
-        if hg_root.join(".hg").exists() {
-            break;
+    if args.list_segments {
+        for segment in registry.iter() {
+            print_out(format_args!("{}\n", segment.name()));
         }
+        return;
+    }
 
-        if !hg_root.pop() {
-            return Err(HgError::NotHg);
+    if args.command == args::Command::Explain {
+        let table = explain::explain(&registry, &ctx);
+        if let Some(e) = config_error {
+            print_out(format_args!("{}\n\n", segment::single_line(&e)));
         }
+        print_out(format_args!("{table}\n"));
+        return;
     }
 
-    let mut hg_components = vec![];
-
-    {
-        let mut maybe_push_file = |path| {
-            // Don't care if this fails, just don't include it in the results
-            let _ = File::open(hg_root.join(path))
-                .and_then(|mut f| {
-                    let mut output = String::new();
-                    f.read_to_string(&mut output)?;
-                    Ok(output)
-                })
-                .map(|s| hg_components.push(s.trim().to_string()));
-        };
-
-        maybe_push_file(hg_root.join(".hg/bookmarks.current"));
-        maybe_push_file(hg_root.join(".hg/branch"));
+    if args.command == args::Command::Bench {
+        let report = bench::run_registry(&registry, &ctx);
+        match args.output {
+            args::OutputFormat::Text => print_out(format_args!("{}\n", report.to_text())),
+            args::OutputFormat::Json => print_out(format_args!("{}\n", report.to_json())),
+        }
+        return;
     }
 
-    let s = File::open(hg_root.join(".hg/dirstate"))
-        .iter()
-        .flat_map(|f| f.bytes())
-        .take(6)
-        .filter_map(|x| x.ok())
-        .map(|x| format!("{x:02x}"))
-        .collect::<String>();
-
-    hg_components.push(s);
-
-    let mut output = String::new();
-
-    for (i, component) in hg_components.iter().enumerate() {
-        if component.is_empty() {
-            continue;
-        }
-        if i != 0 {
-            output.push(' ')
+    if args.command == args::Command::Diagnose {
+        let setup = diagnose::Setup::detect(config_error);
+        let report = diagnose::report(&registry, &ctx, setup);
+        match args.output {
+            args::OutputFormat::Text => print_out(format_args!("{}\n", report.to_text())),
+            args::OutputFormat::Json => print_out(format_args!("{}\n", report.to_json())),
         }
-        output.push_str(component);
+        return;
     }
 
-    if output.is_empty() {
-        return Ok(output.as_str().into());
+    if !args.segments.is_empty() {
+        print_requested_segments(&registry, &ctx);
     }
 
-    let output = DecoratedString::new(output)
-        .colored(Color::Green)
-        .bold()
-        .to_ansi();
-    Ok(output)
-}
-
-#[derive(Debug)]
-enum GitError {
-    NoCwd(std::io::Error),
-    CanonicalCwd(std::io::Error),
-    ReadGitFile(std::io::Error),
-    ReadHead(std::io::Error),
-    NotGitRepo,
-    UnexpectedGitContent,
-    ReadRef(std::io::Error),
-    NoRefName,
-}
+    let mut components = vec![];
+    let mut errors = vec![];
+    let trace_path = ctx.var("PROMPTLINE_TRACE").filter(|path| !path.is_empty());
+    let mut traced = vec![];
 
-impl fmt::Display for GitError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            GitError::NoCwd(_) => write!(f, "failed to get cwd"),
-            GitError::CanonicalCwd(_) => write!(f, "failed to canonicalize cwd"),
-            GitError::ReadGitFile(_) => write!(f, "failed to read .git file"),
-            GitError::ReadHead(_) => write!(f, "failed to read git HEAD"),
-            GitError::NotGitRepo => write!(f, "not a git repo"),
-            GitError::UnexpectedGitContent => write!(f, "unexpected git content"),
-            GitError::ReadRef(_) => write!(f, "failed to read ref"),
-            GitError::NoRefName => write!(f, "failed to get ref name"),
+    let enabled: Vec<_> = registry
+        .enabled(&ctx.config)
+        .filter(|segment| args.phase.includes(segment.is_expensive()))
+        .collect();
+    for (segment, (result, elapsed)) in enabled
+        .iter()
+        .zip(runner::render_all(enabled.clone(), &ctx))
+    {
+        let name = segment.name().to_string();
+        if trace_path.is_some() {
+            traced.push(SegmentTrace::new(&name, &result, elapsed));
         }
-    }
-}
-
-impl Error for GitError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            GitError::NoCwd(e) => Some(e),
-            GitError::CanonicalCwd(e) => Some(e),
-            GitError::ReadGitFile(e) => Some(e),
-            GitError::ReadHead(e) => Some(e),
-            GitError::NotGitRepo => None,
-            GitError::UnexpectedGitContent => None,
-            GitError::ReadRef(e) => Some(e),
-            GitError::NoRefName => None,
+        match result {
+            Ok(segment) => components.push(Component { name, segment }),
+            Err(e) => {
+                if e.kind == ErrorKind::OverBudget && ctx.config.timeout_placeholder {
+                    let segment = DecoratedString::new("⌛".into()).into();
+                    components.push(Component { name, segment });
+                }
+                errors.push(e);
+            }
         }
     }
-}
-
-fn get_git_info() -> Result<String, GitError> {
-    let cwd = env::current_dir().map_err(GitError::NoCwd)?;
-    let canonical_cwd = fs::canonicalize(cwd).map_err(GitError::CanonicalCwd)?;
 
-    let mut dir_iter = Some(&canonical_cwd as &Path);
-    while let Some(dir) = dir_iter {
-        if dir.join(".git").exists() {
-            break;
+    // The expensive segments come last in the fast prompt, so the cheap ones
+    // stay where they are when the slow result fills in. Only the fast prompt
+    // is fitted to the terminal, line by line, with room left for the slow
+    // result on the last line. The slow result is a single fragment, so it
+    // fills in on the last line whichever line its segments are assigned to
+    let async_result = match args.phase {
+        args::Phase::Fast => args.async_result.as_deref().filter(|s| !s.is_empty()),
+        _ => None,
+    };
+    let reserved = async_result.map_or(0, |s| promptline::visible_width(s) + 3);
+
+    let columns = platform::terminal_width()
+        .or_else(|| ctx.var("COLUMNS").and_then(|columns| columns.parse().ok()));
+    let components = layout::merge(components, &ctx.config.merges);
+    let mut lines = match args.phase {
+        args::Phase::Slow => vec![components],
+        _ => layout::assign_lines(components, &ctx.config.lines),
+    };
+    if let (Some(columns), false) = (columns, args.phase == args::Phase::Slow) {
+        let last = lines.len().saturating_sub(1);
+        for (i, line) in lines.iter_mut().enumerate() {
+            let reserved = if i == last { reserved } else { 0 };
+            layout::fit_to_width(
+                line,
+                columns.saturating_sub(reserved),
+                &ctx.config.drop_order,
+                ctx.config.truncate_cwd_to_fit,
+            );
         }
-
-        dir_iter = dir.parent();
     }
 
-    let repo = dir_iter.ok_or(GitError::NotGitRepo)?;
-
-    // if .git has gitdir:.... we have to follow the link
-
-    let mut git_dir = repo.join(".git");
-    if git_dir.is_file() {
-        let git_content = fs::read_to_string(git_dir).map_err(GitError::ReadGitFile)?;
-
-        const PREFIX: &str = "gitdir: ";
+    let mut lines: Vec<Vec<_>> = lines
+        .into_iter()
+        .map(|line| {
+            line.into_iter()
+                .map(|Component { segment, .. }| {
+                    if args.color {
+                        segment.to_ansi_escaped(args.escaping)
+                    } else {
+                        args.escaping.escape(&segment.to_plain())
+                    }
+                })
+                .collect()
+        })
+        .collect();
 
-        match git_content.strip_prefix(PREFIX) {
-            Some(v) => git_dir = v.trim().into(),
-            None => return Err(GitError::UnexpectedGitContent),
+    if let Some(async_result) = async_result {
+        match lines.last_mut() {
+            Some(last) => last.push(async_result.to_string()),
+            None => lines.push(vec![async_result.to_string()]),
         }
     }
 
-    let head_content = fs::read_to_string(git_dir.join("HEAD")).map_err(GitError::ReadHead)?;
-
-    const REF_PREFIX: &str = "ref: ";
-    let output = match head_content.strip_prefix(REF_PREFIX) {
-        Some(refs_path) => {
-            let refs_path = Path::new(refs_path.trim());
+    print_errors(&errors, config_error.as_deref());
 
-            let commit_hash =
-                fs::read_to_string(git_dir.join(refs_path)).map_err(GitError::ReadRef)?;
-
-            let short_hash = &commit_hash[..14];
-            let ref_name = refs_path
-                .file_name()
-                .ok_or(GitError::NoRefName)?
-                .to_string_lossy();
+    if let Some(path) = trace_path {
+        let trace = Trace {
+            time: SystemTime::now(),
+            cwd: ctx.cwd().map(Path::to_path_buf),
+            total: started.elapsed(),
+            segments: traced,
+        };
+        trace::append(Path::new(path), &trace);
+    }
 
-            format!("{ref_name} {short_hash}")
+    if args.phase == args::Phase::Slow {
+        // A fragment for `--async-result`, without the frame
+        let fragment = lines.concat().join("]-[");
+        if write!(io::stdout().lock(), "{fragment}").is_err() {
+            process::exit(EXIT_IO);
         }
-        None => head_content[..14].to_string(),
-    };
-
-    Ok(DecoratedString::new(output)
-        .colored(Color::Green)
-        .bold()
-        .to_ansi())
-}
-
-#[derive(Debug)]
-struct NoCondaEnv;
+        return;
+    }
 
-impl fmt::Display for NoCondaEnv {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "no conda env var")
+    let repo = prompt::repo_state(&ctx);
+    let last_line = prompt::last_line(&ctx, repo);
+    let end = prompt_end(args);
+    // The shell may have stopped reading, e.g. when interrupted mid prompt
+    let mut out = io::stdout().lock();
+    if render_prompt(&lines, last_line, args.escaping, &end, &mut out).is_err() {
+        process::exit(EXIT_IO);
     }
 }
 
-impl Error for NoCondaEnv {}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn get_conda_info() -> Result<String, NoCondaEnv> {
-    let conda_env = std::env::var("CONDA_DEFAULT_ENV").map_err(|_| NoCondaEnv)?;
-    Ok(DecoratedString::new(format!("🐍 {conda_env}"))
-        .bold()
-        .to_ansi())
-}
+    const LAST_LINE: (&str, &str) = (layout::LAST_LINE_FRAME, ">");
 
-#[derive(Debug)]
-struct NotDockerContainer;
-
-impl fmt::Display for NotDockerContainer {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "not docker container")
+    fn rendered(n: usize) -> String {
+        let components: Vec<_> = (0..n).map(|i| i.to_string()).collect();
+        let mut out = vec![];
+        render_prompt(&[components], LAST_LINE, Escaping::Raw, "", &mut out).unwrap();
+        String::from_utf8(out).unwrap()
     }
-}
 
-impl Error for NotDockerContainer {}
-
-fn get_docker_env() -> Result<String, NotDockerContainer> {
-    match std::fs::metadata("/.dockerenv") {
-        Ok(_) => Ok("🐳".into()),
-        Err(_) => Err(NotDockerContainer),
+    #[test]
+    fn no_components() {
+        assert_eq!(rendered(0), "└> ");
     }
-}
-
-#[derive(Debug)]
-enum ShellError {
-    EnvNotSet,
-    NoShellName,
-}
 
-impl fmt::Display for ShellError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            ShellError::EnvNotSet => write!(f, "shell env not set"),
-            ShellError::NoShellName => write!(f, "failed to get shell name"),
-        }
+    #[test]
+    fn one_component() {
+        assert_eq!(rendered(1), "┌[0]\n└> ");
     }
-}
-
-impl Error for ShellError {}
-
-fn get_shell() -> Result<String, ShellError> {
-    let shell: PathBuf = std::env::var("SHELL")
-        .map_err(|_| ShellError::EnvNotSet)?
-        .into();
-
-    let name = shell
-        .file_name()
-        .ok_or(ShellError::NoShellName)?
-        .to_string_lossy();
 
-    Ok(DecoratedString::new(name.to_string()).bold().to_ansi())
-}
-
-#[derive(Debug)]
-struct NotInNixShell;
-
-impl fmt::Display for NotInNixShell {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "not in nix shell")
+    #[test]
+    fn two_components() {
+        assert_eq!(rendered(2), "┌[0]-[1]\n└> ");
     }
-}
 
-impl Error for NotInNixShell {}
-
-fn show_nix_shell() -> Result<String, NotInNixShell> {
-    std::env::var("IN_NIX_SHELL").map_err(|_| NotInNixShell)?;
-
-    let shell_name = std::env::var("name").unwrap_or("nix-shell".to_string());
-
-    Ok(DecoratedString::new(format!("nix: {shell_name}"))
-        .bold()
-        .to_ansi())
-}
-
-fn do_print(mut components: Vec<String>) {
-    components.insert(0, "┌[".into());
-    for i in 1..components.len() - 1 {
-        components.insert(2 * i, "]-[".into());
+    #[test]
+    fn ends_with_reset() {
+        let mut out = vec![];
+        let lines = [vec!["0".to_string()]];
+        render_prompt(&lines, LAST_LINE, Escaping::Raw, "\x1b[0m", &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "┌[0]\n└> \x1b[0m");
     }
-    components.push("]\n└> ".into());
-    for component in components {
-        print!("{component}");
+
+    #[test]
+    fn ten_components() {
+        assert_eq!(
+            rendered(10),
+            "┌[0]-[1]-[2]-[3]-[4]-[5]-[6]-[7]-[8]-[9]\n└> "
+        );
     }
-}
 
-#[derive(Debug)]
-enum MainError {
-    Docker(NotDockerContainer),
-    User(UserError),
-    Hostname(HostnameError),
-    Shell(ShellError),
-    Status(NoExitStatus),
-    Mercurial(HgError),
-    Git(GitError),
-    Conda(NoCondaEnv),
-    NixShell(NotInNixShell),
-}
+    #[test]
+    fn three_lines() {
+        let lines = [
+            vec!["0".to_string(), "1".to_string()],
+            vec![],
+            vec!["2".to_string()],
+        ];
+        let mut out = vec![];
+        render_prompt(&lines, LAST_LINE, Escaping::Raw, "", &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "┌[0]-[1]\n├[2]\n└> ");
+    }
 
-impl fmt::Display for MainError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let source: &dyn Error = match self {
-            MainError::Docker(e) => {
-                writeln!(f, "failed to get docker info")?;
-                e
-            }
-            MainError::User(e) => {
-                writeln!(f, "failed to get user info")?;
-                e
-            }
-            MainError::Hostname(e) => {
-                writeln!(f, "failed to get hostname info")?;
-                e
-            }
-            MainError::Shell(e) => {
-                writeln!(f, "failed to get shell info")?;
-                e
-            }
-            MainError::Status(e) => {
-                writeln!(f, "failed to get exit status")?;
-                e
-            }
-            MainError::Mercurial(e) => {
-                writeln!(f, "failed to get mercurial info")?;
-                e
-            }
-            MainError::Git(e) => {
-                writeln!(f, "failed to get git info")?;
-                e
-            }
-            MainError::Conda(e) => {
-                writeln!(f, "failed to get conda info")?;
-                e
-            }
-            MainError::NixShell(e) => {
-                writeln!(f, "failed to get nix shell info")?;
-                e
-            }
-        };
+    #[test]
+    fn last_line_is_escaped() {
+        let mut out = vec![];
+        render_prompt(&[], ("%", "%#"), Escaping::Zsh, "", &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "%%%%# ");
+    }
 
-        writeln!(f, "Caused by:")?;
+    /// A reader that has gone away, like a shell closing the pipe early
+    struct ClosedPipe;
 
-        let mut source = Some(source);
-        while let Some(err) = source {
-            writeln!(f, "{err}")?;
-            source = err.source();
+    impl Write for ClosedPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
         }
 
-        Ok(())
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
     }
-}
 
-fn main() {
-    let (oks, errors): (Vec<Result<_, MainError>>, Vec<_>) = vec![
-        Ok(get_time()),
-        get_docker_env().map_err(MainError::Docker),
-        get_user().map_err(MainError::User),
-        get_hostname().map_err(MainError::Hostname),
-        Ok(get_cwd()),
-        get_shell().map_err(MainError::Shell),
-        get_status().map_err(MainError::Status),
-        get_mercurial_info().map_err(MainError::Mercurial),
-        get_git_info().map_err(MainError::Git),
-        get_conda_info().map_err(MainError::Conda),
-        show_nix_shell().map_err(MainError::NixShell),
-    ]
-    .into_iter()
-    .partition(Result::is_ok);
-
-    let components: Vec<_> = oks
-        .into_iter()
-        .map(|x| x.expect("Invalid result"))
-        .collect();
-
-    if Ok("1") == env::var("DEBUG_PROMPTLINE").as_ref().map(|s| s.as_str()) {
-        for error in errors.into_iter().map(|e| e.unwrap_err()) {
-            let _ = writeln!(io::stderr(), "{error}");
-        }
+    #[test]
+    fn closed_pipe_is_an_error() {
+        let lines = [vec!["0".to_string()]];
+        let res = render_prompt(&lines, LAST_LINE, Escaping::Raw, "", &mut ClosedPipe);
+        assert!(res.is_err());
     }
-    do_print(components);
 }
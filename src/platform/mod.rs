@@ -0,0 +1,27 @@
+//! The few things that need OS specific code, with the same functions
+//! implemented for unix and windows
+
+use std::{error::Error, net::Ipv4Addr};
+
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+pub use self::unix::*;
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+pub use self::windows::*;
+
+pub type PlatformError = Box<dyn Error + Send + Sync>;
+
+/// A network interface with the addresses promptline cares about
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub up: bool,
+    /// Whether it has any IP address, v4 or v6
+    pub has_address: bool,
+    /// Its IPv4 addresses, in the order the system lists them
+    pub ipv4: Vec<Ipv4Addr>,
+}
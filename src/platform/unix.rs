@@ -0,0 +1,292 @@
+use std::{
+    convert::TryFrom,
+    ffi::{CStr, OsStr, OsString},
+    fs,
+    mem::MaybeUninit,
+    net::IpAddr,
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::MetadataExt,
+        process::CommandExt,
+    },
+    path::Path,
+    process::{Child, Command},
+    ptr,
+};
+
+use nix::{
+    errno::Errno,
+    ifaddrs,
+    net::if_::InterfaceFlags,
+    sys::{signal, socket::SockAddr},
+    unistd::{self, Pid, SysconfVar},
+};
+
+use super::{NetworkInterface, PlatformError};
+use crate::Context;
+
+/// Used if the system doesn't say how long host names can be. Matches
+/// `MAXHOSTNAMELEN` on macOS and the BSDs, which allow longer names than Linux
+const FALLBACK_HOST_NAME_MAX: usize = 255;
+
+/// Room for the longest host name the system allows plus the nul terminator.
+/// A fixed 64 byte buffer truncates the long `.local` names mDNS hands out on
+/// macOS
+fn hostname_buffer_len() -> usize {
+    let max = unistd::sysconf(SysconfVar::HOST_NAME_MAX)
+        .ok()
+        .flatten()
+        .and_then(|max| usize::try_from(max).ok())
+        .unwrap_or(FALLBACK_HOST_NAME_MAX);
+    max.max(FALLBACK_HOST_NAME_MAX) + 1
+}
+
+/// Gives up on passwd entries longer than this
+const MAX_PASSWD_BUFFER: usize = 1024 * 1024;
+
+/// Name of the user promptline runs as, `None` if it has no passwd entry. Looked
+/// up with `getpwuid_r` directly, as nix replaces invalid UTF-8 in the name
+pub fn user_name(_ctx: &Context) -> Result<Option<OsString>, PlatformError> {
+    let uid = unistd::getuid().as_raw();
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+
+    loop {
+        let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+        let mut result = ptr::null_mut();
+        let res = unsafe {
+            libc::getpwuid_r(
+                uid,
+                passwd.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            )
+        };
+
+        if res == libc::ERANGE && buf.len() < MAX_PASSWD_BUFFER {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if res != 0 {
+            return Err(Errno::from_i32(res).into());
+        }
+        if result.is_null() {
+            return Ok(None);
+        }
+
+        // `result` points at `passwd`, whose strings live in `buf`
+        let name = unsafe { CStr::from_ptr((*result).pw_name) };
+        return Ok(Some(OsStr::from_bytes(name.to_bytes()).to_os_string()));
+    }
+}
+
+/// Whether promptline runs with root's privileges, whatever the account is
+/// called
+pub fn is_root() -> bool {
+    unistd::geteuid().is_root()
+}
+
+/// Effective uid and gid of promptline
+pub fn user_ids() -> Option<(u32, u32)> {
+    Some((unistd::geteuid().as_raw(), unistd::getegid().as_raw()))
+}
+
+/// Gives up on host names longer than this, whatever the system claims
+const MAX_HOST_NAME_BUFFER: usize = 64 * 1024;
+
+/// Calls `gethostname` with buffers of `len` bytes, doubling it for as long as
+/// the name doesn't fit. Some systems fail with `ENAMETOOLONG` then, others
+/// truncate the name and leave out the nul terminator
+fn read_host_name(
+    mut len: usize,
+    mut gethostname: impl FnMut(&mut [u8]) -> Result<(), Errno>,
+) -> Result<OsString, PlatformError> {
+    loop {
+        let mut buf = vec![0u8; len];
+        match gethostname(&mut buf) {
+            Ok(()) => {
+                if let Some(end) = buf.iter().position(|&b| b == 0) {
+                    buf.truncate(end);
+                    return Ok(OsString::from_vec(buf));
+                }
+            }
+            Err(Errno::ENAMETOOLONG) => (),
+            Err(e) => return Err(e.into()),
+        }
+
+        if len >= MAX_HOST_NAME_BUFFER {
+            return Err("host name too long".into());
+        }
+        len *= 2;
+    }
+}
+
+pub fn host_name(_ctx: &Context) -> Result<OsString, PlatformError> {
+    read_host_name(hostname_buffer_len(), |buf| {
+        // nix's wrapper overwrites the last byte with a nul, which would hide
+        // a truncated name
+        let res = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(Errno::last())
+        }
+    })
+}
+
+/// The terminal on stdin, e.g. `/dev/pts/3`
+pub fn tty_name() -> Option<String> {
+    let tty = unistd::ttyname(0).ok()?;
+    Some(tty.to_string_lossy().into_owned())
+}
+
+/// Columns of the terminal on stderr or stdin. stdout is never a terminal, the
+/// shell reads the prompt from it
+pub fn terminal_width() -> Option<usize> {
+    [2, 0].iter().find_map(|&fd| {
+        let mut size = MaybeUninit::<libc::winsize>::zeroed();
+        let res = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, size.as_mut_ptr()) };
+        let size = unsafe { size.assume_init() };
+        (res == 0 && size.ws_col > 0).then(|| usize::from(size.ws_col))
+    })
+}
+
+/// Process that started promptline
+pub fn parent_pid() -> Option<u32> {
+    Some(unistd::getppid().as_raw() as u32)
+}
+
+/// Whether `a` and `b` are the same directory, even if reached through
+/// different symlinks
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Whether the file at `path` belongs to the user promptline runs as
+pub fn owned_by_current_user(path: &Path) -> bool {
+    fs::metadata(path)
+        .map(|metadata| metadata.uid() == unistd::geteuid().as_raw())
+        .unwrap_or(false)
+}
+
+/// The system's network interfaces, one entry per interface rather than per
+/// address like `getifaddrs` returns them
+pub fn network_interfaces() -> Result<Vec<NetworkInterface>, PlatformError> {
+    let mut interfaces: Vec<NetworkInterface> = vec![];
+    for address in ifaddrs::getifaddrs()? {
+        let ip = match address.address {
+            Some(SockAddr::Inet(inet)) => Some(inet.to_std().ip()),
+            _ => None,
+        };
+
+        let index = match interfaces
+            .iter()
+            .position(|interface| interface.name == address.interface_name)
+        {
+            Some(index) => index,
+            None => {
+                interfaces.push(NetworkInterface {
+                    name: address.interface_name,
+                    up: address.flags.contains(InterfaceFlags::IFF_UP),
+                    has_address: false,
+                    ipv4: vec![],
+                });
+                interfaces.len() - 1
+            }
+        };
+
+        let interface = &mut interfaces[index];
+        interface.has_address |= ip.is_some();
+        if let Some(IpAddr::V4(ip)) = ip {
+            interface.ipv4.push(ip);
+        }
+    }
+    Ok(interfaces)
+}
+
+/// `command` run through the system shell
+pub fn shell_command(command: &str) -> Command {
+    let mut ret = Command::new("/bin/sh");
+    ret.arg("-c").arg(command);
+    ret
+}
+
+/// Starts `command` in a process group of its own, so that `kill_tree` can
+/// take out its children too
+pub fn own_process_group(command: &mut Command) {
+    command.process_group(0);
+}
+
+/// Kills a child started after `own_process_group` along with anything it
+/// spawned
+pub fn kill_tree(child: &mut Child) {
+    let _ = signal::killpg(Pid::from_raw(child.id() as i32), signal::Signal::SIGKILL);
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hostname_buffer_fits_long_names() {
+        assert!(hostname_buffer_len() > 70);
+    }
+
+    /// Writes `name` into the buffer like `gethostname` does, failing if it
+    /// doesn't fit with its nul when `fail` is set and truncating it otherwise
+    fn fake_gethostname(name: &str, fail: bool) -> impl FnMut(&mut [u8]) -> Result<(), Errno> + '_ {
+        move |buf| {
+            let name = name.as_bytes();
+            if name.len() >= buf.len() {
+                if fail {
+                    return Err(Errno::ENAMETOOLONG);
+                }
+                let len = buf.len();
+                buf.copy_from_slice(&name[..len]);
+                return Ok(());
+            }
+            buf[..name.len()].copy_from_slice(name);
+            buf[name.len()] = 0;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn retries_on_enametoolong() {
+        let name = "a".repeat(100);
+        let calls = std::cell::Cell::new(0);
+        let mut fake = fake_gethostname(&name, true);
+        let res = read_host_name(16, |buf| {
+            calls.set(calls.get() + 1);
+            fake(buf)
+        });
+        assert_eq!(res.unwrap(), *name);
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[test]
+    fn retries_when_truncated() {
+        let name = "b".repeat(100);
+        assert_eq!(
+            read_host_name(16, fake_gethostname(&name, false)).unwrap(),
+            *name
+        );
+    }
+
+    #[test]
+    fn name_filling_the_buffer_keeps_last_character() {
+        let name = "c".repeat(16);
+        assert_eq!(
+            read_host_name(16, fake_gethostname(&name, false)).unwrap(),
+            *name
+        );
+        assert_eq!(
+            read_host_name(17, fake_gethostname(&name, true)).unwrap(),
+            *name
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::Path,
+    process::{Child, Command},
+};
+
+use super::{NetworkInterface, PlatformError};
+use crate::Context;
+
+/// Name of the user promptline runs as, from `%USERNAME%`
+pub fn user_name(ctx: &Context) -> Result<Option<OsString>, PlatformError> {
+    Ok(ctx.var("USERNAME").map(OsString::from))
+}
+
+/// Whether promptline runs elevated. Not checked on windows, which would need
+/// the process token
+pub fn is_root() -> bool {
+    false
+}
+
+/// Effective uid and gid of promptline. Windows has SIDs instead
+pub fn user_ids() -> Option<(u32, u32)> {
+    None
+}
+
+/// Name of the machine, from `%COMPUTERNAME%`
+pub fn host_name(ctx: &Context) -> Result<OsString, PlatformError> {
+    ctx.var("COMPUTERNAME")
+        .map(OsString::from)
+        .ok_or_else(|| "COMPUTERNAME is not set".into())
+}
+
+/// The terminal on stdin. Windows consoles have no name to tell them apart
+pub fn tty_name() -> Option<String> {
+    None
+}
+
+/// Columns of the console. Not looked up on windows, `$COLUMNS` is used
+/// instead
+pub fn terminal_width() -> Option<usize> {
+    None
+}
+
+/// Process that started promptline. Not looked up on windows, where there's no
+/// `/proc` to ask for its name anyway
+pub fn parent_pid() -> Option<u32> {
+    None
+}
+
+/// Whether `a` and `b` are the same directory, even if reached through
+/// different links
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether the file at `path` belongs to the user promptline runs as. Always
+/// false on windows, where ownership is part of the file's ACL, which isn't
+/// read yet. Local config files are ignored rather than trusted blindly
+pub fn owned_by_current_user(_path: &Path) -> bool {
+    false
+}
+
+/// The system's network interfaces. Not listed on windows, which would need
+/// `GetAdaptersAddresses`
+pub fn network_interfaces() -> Result<Vec<NetworkInterface>, PlatformError> {
+    Err("listing network interfaces is not supported on windows".into())
+}
+
+/// `command` run through `cmd.exe`
+pub fn shell_command(command: &str) -> Command {
+    let mut ret = Command::new("cmd");
+    ret.arg("/C").arg(command);
+    ret
+}
+
+/// Nothing to do, as `kill_tree` only kills the child itself
+pub fn own_process_group(_command: &mut Command) {}
+
+/// Kills a child started after `own_process_group`. Grandchildren are left
+/// running, that would need a job object
+pub fn kill_tree(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
@@ -0,0 +1,135 @@
+//! `promptline preview`, which renders the prompt against made up data so the
+//! colors of every segment can be seen at once, e.g. when tweaking a theme
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    args::Args,
+    config::Config,
+    layout::{self, Component},
+    runner::{self, Registry},
+    Context,
+};
+
+/// The sample environment. The shell is in a git repo over SSH, inside a conda
+/// env and a nix shell
+const SAMPLE_VARS: &[(&str, &str)] = &[
+    ("HOME", "/home/ada"),
+    ("PWD", "/home/ada/src/shop"),
+    ("USER", "ada"),
+    ("SHELL", "/usr/bin/zsh"),
+    ("PROMPTLINE_SHOW_SHELL", "1"),
+    ("SHLVL", "2"),
+    ("PROMPTLINE_FAKE_TIME", "12:34"),
+    ("SSH_CONNECTION", "203.0.113.7 52144 198.51.100.2 22"),
+    ("CONDA_DEFAULT_ENV", "ml"),
+    ("IN_NIX_SHELL", "impure"),
+    ("name", "shop-env"),
+    ("PATH", "/usr/local/bin:/usr/bin:/bin"),
+    ("LANG", "en_US.UTF-8"),
+];
+
+/// Files of the sample filesystem, relative to its root
+const SAMPLE_FILES: &[(&str, &str)] = &[
+    (
+        "home/ada/src/shop/.git/HEAD",
+        "ref: refs/heads/feature/foo\n",
+    ),
+    (
+        "home/ada/src/shop/.git/refs/heads/feature/foo",
+        "3f9a1c2e7b4d5a6f8e9d0c1b2a3f4e5d6c7b8a9f\n",
+    ),
+    (
+        "home/ada/src/shop/Gemfile",
+        "source \"https://rubygems.org\"\n",
+    ),
+    ("home/ada/src/shop/.ruby-version", "3.2.2\n"),
+    ("home/ada/src/shop/rust-toolchain", "nightly\n"),
+    ("proc/uptime", "273600.00 1000.00\n"),
+    (
+        "proc/net/route",
+        "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+         wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\n",
+    ),
+];
+
+/// The exit status the sample's last command failed with
+const SAMPLE_STATUS: &str = "1";
+
+/// A scratch directory holding the sample filesystem, removed on drop
+pub struct SampleRoot {
+    path: PathBuf,
+}
+
+impl SampleRoot {
+    pub fn create() -> io::Result<SampleRoot> {
+        let path = std::env::temp_dir().join(format!("promptline-preview-{}", std::process::id()));
+        let root = SampleRoot { path };
+        for (relative, contents) in SAMPLE_FILES {
+            let path = root.path.join(relative);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)?;
+        }
+        Ok(root)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for SampleRoot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A context with the sample environment, reading files from `root` instead
+/// of the real filesystem
+pub fn context(mut args: Args, config: Config, root: &SampleRoot) -> Context {
+    args.status = Some(SAMPLE_STATUS.to_string());
+
+    let env: HashMap<_, _> = SAMPLE_VARS
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let cwd = env.get("PWD").map(PathBuf::from);
+
+    Context::with_env(args, config, env, cwd).with_root(root.path().to_path_buf())
+}
+
+/// The samples of the segments enabled in the config, split into lines and
+/// styled as `args` asks
+pub fn render(registry: &Registry, ctx: &Context) -> Vec<Vec<String>> {
+    let args = &ctx.args;
+    let enabled: Vec<_> = registry.enabled(&ctx.config).collect();
+    let components = enabled
+        .iter()
+        .zip(runner::preview_all(enabled.clone(), ctx))
+        .filter_map(|(segment, (result, _))| {
+            let name = segment.name().to_string();
+            result.ok().map(|segment| Component { name, segment })
+        })
+        .collect();
+    let components = layout::merge(components, &ctx.config.merges);
+    layout::assign_lines(components, &ctx.config.lines)
+        .into_iter()
+        .map(|line| {
+            line.into_iter()
+                .map(|Component { segment, .. }| {
+                    if args.color {
+                        segment.to_ansi_escaped(args.escaping)
+                    } else {
+                        segment.to_plain()
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,206 @@
+//! The prompt char that ends the prompt, shared by the full prompt and the
+//! transient one that replaces it once a command is accepted
+
+use crate::{
+    config::DEFAULT_PROMPT_CHAR,
+    decorated::{Color, DecoratedString, Escaping},
+    layout::LAST_LINE_FRAME,
+    theme, Context,
+};
+
+/// The exit statuses of each command of a pipeline, as passed in by the shell
+/// joined by spaces or colons, e.g. `"${PIPESTATUS[*]}"`. `None` if any of
+/// them isn't a number
+pub fn pipe_statuses(status: &str) -> Option<Vec<&str>> {
+    let statuses: Vec<_> = status
+        .split([' ', ':'])
+        .filter(|status| !status.is_empty())
+        .collect();
+    let numeric = !statuses.is_empty()
+        && statuses
+            .iter()
+            .all(|status| status.chars().all(|c| c.is_ascii_digit()));
+    numeric.then_some(statuses)
+}
+
+/// Color for an exit status as passed in by the shell, green if every command
+/// succeeded and red for anything else
+pub fn status_color(status: &str) -> Color {
+    match pipe_statuses(status) {
+        Some(statuses) if statuses.iter().all(|status| *status == "0") => Color::Green,
+        _ => Color::Red,
+    }
+}
+
+/// The exit status of the previous command, passed as an argument or, for
+/// shells where exporting is easier, in `$PROMPTLINE_STATUS`. The argument
+/// wins if there are both. A blank status counts as none
+pub fn exit_status(ctx: &Context) -> Option<&str> {
+    let not_blank = |status: &&str| !status.trim().is_empty();
+    ctx.args
+        .status
+        .as_deref()
+        .filter(not_blank)
+        .or_else(|| ctx.var("PROMPTLINE_STATUS").filter(not_blank))
+}
+
+/// The configured prompt char, colored by the exit status if there is one
+pub fn prompt_char(ctx: &Context) -> DecoratedString {
+    let prompt_char = DecoratedString::new(ctx.config.prompt_char.clone());
+    match exit_status(ctx) {
+        Some(status) => {
+            let prompt_char = prompt_char.colored(status_color(status)).bold();
+            theme::restyle(ctx, "prompt_char", prompt_char)
+        }
+        None => prompt_char,
+    }
+}
+
+/// Whether the git work tree the cwd is in has changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RepoState {
+    Clean,
+    Dirty,
+}
+
+/// State of the git work tree the cwd is in, `None` outside of one, on a
+/// network filesystem or if git couldn't tell in time. Only checked when `repo_prompt_chars` is set, and
+/// regardless of whether the git segment is enabled
+pub fn repo_state(ctx: &Context) -> Option<RepoState> {
+    ctx.config.repo_prompt_chars.as_ref()?;
+    #[cfg(feature = "git")]
+    {
+        crate::segments::git::is_dirty(ctx).map(|dirty| {
+            if dirty {
+                RepoState::Dirty
+            } else {
+                RepoState::Clean
+            }
+        })
+    }
+    #[cfg(not(feature = "git"))]
+    {
+        None
+    }
+}
+
+/// The frame and prompt char of the prompt's last line. The prompt char is the
+/// one `repo_prompt_chars` has for `repo` if there is one. zsh works out the
+/// width of that line itself, which can go wrong for anything but ASCII and
+/// misplace the cursor, so with `zsh_last_line_frame` set both are kept to
+/// ASCII there. A prompt char that isn't is replaced with the default one
+pub fn last_line(ctx: &Context, repo: Option<RepoState>) -> (&str, &str) {
+    let chars = ctx.config.repo_prompt_chars.as_ref();
+    let repo_char = match (repo, chars) {
+        (Some(RepoState::Clean), Some(chars)) => chars.clean.as_deref(),
+        (Some(RepoState::Dirty), Some(chars)) => chars.dirty.as_deref(),
+        _ => None,
+    };
+    let prompt_char = repo_char.unwrap_or(&ctx.config.prompt_char);
+    match &ctx.config.zsh_last_line_frame {
+        Some(frame) if matches!(ctx.args.escaping, Escaping::Zsh | Escaping::ZshEmbedded) => {
+            let prompt_char = if prompt_char.is_ascii() {
+                prompt_char
+            } else {
+                DEFAULT_PROMPT_CHAR
+            };
+            (frame, prompt_char)
+        }
+        _ => (LAST_LINE_FRAME, prompt_char),
+    }
+}
+
+/// The whole transient prompt, escaped for the shell and followed by a reset
+/// when color is on
+pub fn transient(ctx: &Context) -> String {
+    let args = &ctx.args;
+    if args.color {
+        format!(
+            "{} {}",
+            prompt_char(ctx).to_ansi_escaped(args.escaping),
+            args.escaping.reset()
+        )
+    } else {
+        format!("{} ", args.escaping.escape(&prompt_char(ctx).to_plain()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{args::Args, config, config::Config, layout, width};
+
+    fn context(args: &[&str], config: &str) -> Context {
+        let args = Args::parse(args.iter().map(|arg| arg.to_string())).unwrap();
+        let config = Config::from_table(&config::parse(config).unwrap()).unwrap();
+        Context::with_env(args, config, Default::default(), None)
+    }
+
+    #[test]
+    fn colored_by_status() {
+        let ctx = context(&["--transient", "0"], "");
+        assert_eq!(transient(&ctx), "\x1b[1m\x1b[32m>\x1b[39m\x1b[22m \x1b[0m");
+
+        let ctx = context(&["--transient", "130", "--shell", "zsh"], "");
+        assert_eq!(
+            transient(&ctx),
+            "%{\x1b[1m%}%{\x1b[31m%}>%{\x1b[39m%}%{\x1b[22m%} %{\x1b[0m%}"
+        );
+        assert_eq!(ctx.args.escaping, Escaping::Zsh);
+    }
+
+    #[test]
+    fn pipeline_colors() {
+        assert_eq!(pipe_statuses("0 1:141"), Some(vec!["0", "1", "141"]));
+        assert_eq!(pipe_statuses("0 x"), None);
+        assert_eq!(pipe_statuses(""), None);
+        assert_eq!(status_color("0 0"), Color::Green);
+        assert_eq!(status_color("0 1"), Color::Red);
+        assert_eq!(status_color("junk"), Color::Red);
+    }
+
+    #[test]
+    fn ascii_last_line_for_zsh() {
+        let config = "prompt_char = \"❯\"\nzsh_last_line_frame = \"`-\"";
+        let ctx = context(&["0", "--shell", "zsh"], config);
+        let (frame, prompt_char) = last_line(&ctx, None);
+        let line = layout::last_line(frame, prompt_char);
+        assert_eq!(line, "`-> ");
+        // zsh counts each character as a column, which is right for ASCII
+        assert!(line.is_ascii());
+        assert_eq!(width::visible_width(&line), line.chars().count());
+
+        // Other shells keep the usual line
+        let ctx = context(&["0", "--shell", "bash"], config);
+        let (frame, prompt_char) = last_line(&ctx, None);
+        assert_eq!(layout::last_line(frame, prompt_char), "└❯ ");
+
+        let ctx = context(&["0", "--shell", "zsh"], "prompt_char = \"❯\"");
+        assert_eq!(last_line(&ctx, None), ("└", "❯"));
+    }
+
+    #[test]
+    fn configured_char() {
+        let ctx = context(&["--transient", "1", "--no-color"], "prompt_char = \"❯\"");
+        assert_eq!(transient(&ctx), "❯ ");
+    }
+
+    #[test]
+    fn char_by_repo_state() {
+        let config = "prompt_char = \"❯\"\n[repo_prompt_chars]\ndirty = \"±\"\n";
+        let ctx = context(&["0"], config);
+        assert_eq!(last_line(&ctx, Some(RepoState::Dirty)), ("└", "±"));
+        assert_eq!(last_line(&ctx, Some(RepoState::Clean)), ("└", "❯"));
+        assert_eq!(last_line(&ctx, None), ("└", "❯"));
+
+        let config = "[repo_prompt_chars]\nclean = \"$\"\ndirty = \"±\"\n";
+        let ctx = context(&["0"], config);
+        assert_eq!(last_line(&ctx, Some(RepoState::Clean)), ("└", "$"));
+        assert_eq!(last_line(&ctx, None), ("└", ">"));
+
+        // Off unless configured, without even looking for a repo
+        let ctx = context(&["0"], "");
+        assert_eq!(repo_state(&ctx), None);
+        assert_eq!(last_line(&ctx, Some(RepoState::Dirty)), ("└", ">"));
+    }
+}
@@ -0,0 +1,49 @@
+//! The segments that make up the prompt
+
+use std::sync::Arc;
+
+use crate::{
+    config::Config, custom::CustomSegment, runner::Registry, segments, static_text::StaticSegment,
+    Segment,
+};
+
+/// Built in segments followed by the custom and static ones from the config,
+/// each at its configured position. Static segments go in last, so they can be
+/// placed after a custom one
+pub fn build(config: &Config) -> Registry {
+    let mut registry = Registry::new(segments::builtin());
+
+    for custom in &config.custom {
+        let position = custom.position.unwrap_or(registry.len());
+        registry.insert(position, Arc::new(CustomSegment::new(custom.clone())));
+    }
+
+    registry.place_all(
+        config
+            .statics
+            .iter()
+            .map(|config| {
+                let segment: Arc<dyn Segment> = Arc::new(StaticSegment::new(config.clone()));
+                (segment, config.placement.clone())
+            })
+            .collect(),
+    );
+
+    registry
+}
+
+/// The segments of the prompt `config` describes, in the order they are
+/// shown. Segments it disables, and those off by default, are left out
+///
+/// ```
+/// use promptline::{config::Config, registry};
+///
+/// let names: Vec<_> = registry::default_segments(&Config::default())
+///     .iter()
+///     .map(|segment| segment.name().to_string())
+///     .collect();
+/// assert!(!names.iter().any(|name| name == "users"));
+/// ```
+pub fn default_segments(config: &Config) -> Vec<Arc<dyn Segment>> {
+    build(config).enabled(config).cloned().collect()
+}
@@ -0,0 +1,22 @@
+//! Text of rendered segments, for printing them outside of a prompt
+//!
+//! ```
+//! use promptline::{render, Color, DecoratedString, RenderedSegment};
+//!
+//! let segment = RenderedSegment::from(DecoratedString::new("main".into()).colored(Color::Green));
+//! assert_eq!(render::ansi(&segment), "\x1b[32mmain\x1b[39m");
+//! assert_eq!(render::plain(&segment), "main");
+//! ```
+
+use crate::RenderedSegment;
+
+/// The segment with ANSI escape sequences for its styling. Use
+/// `RenderedSegment::to_ansi_escaped` for text printed into a shell's prompt
+pub fn ansi(segment: &RenderedSegment) -> String {
+    segment.to_ansi()
+}
+
+/// The segment as it would be displayed, without any escape sequences
+pub fn plain(segment: &RenderedSegment) -> String {
+    segment.to_plain()
+}
@@ -0,0 +1,512 @@
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::Config,
+    segment::{ErrorKind, RenderedSegment, Segment, SegmentError},
+    theme, Context,
+};
+
+/// Where a segment from the config goes relative to the others
+#[derive(Clone, Debug, PartialEq)]
+pub enum Placement {
+    First,
+    Last,
+    /// Right after the named segment, or last if there is no such segment
+    After(String),
+}
+
+/// The segments that make up the prompt, in the order they are printed
+#[derive(Clone, Default)]
+pub struct Registry {
+    segments: Vec<Arc<dyn Segment>>,
+}
+
+impl Registry {
+    pub fn new(segments: Vec<Arc<dyn Segment>>) -> Registry {
+        Registry { segments }
+    }
+
+    /// Inserts `segment` at `position`, or at the end if that is past the last
+    /// segment
+    pub fn insert(&mut self, position: usize, segment: Arc<dyn Segment>) {
+        let position = position.min(self.segments.len());
+        self.segments.insert(position, segment);
+    }
+
+    pub fn push(&mut self, segment: Arc<dyn Segment>) {
+        self.segments.push(segment);
+    }
+
+    /// Adds `segments` where their placements say. Segments with the same
+    /// placement keep their order
+    pub fn place_all(&mut self, segments: Vec<(Arc<dyn Segment>, Placement)>) {
+        let segments: Vec<_> = segments
+            .into_iter()
+            .map(|(segment, placement)| match placement {
+                Placement::After(name) if self.position(&name).is_none() => {
+                    (segment, Placement::Last)
+                }
+                placement => (segment, placement),
+            })
+            .collect();
+
+        // Inserting at the same index in reverse keeps them in order
+        for (segment, placement) in segments.iter().rev() {
+            let position = match placement {
+                Placement::First => 0,
+                Placement::After(name) => self.position(name).map_or(0, |i| i + 1),
+                Placement::Last => continue,
+            };
+            self.insert(position, Arc::clone(segment));
+        }
+
+        for (segment, placement) in segments {
+            if placement == Placement::Last {
+                self.push(segment);
+            }
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.segments
+            .iter()
+            .position(|segment| segment.name() == name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Segment>> {
+        self.segments.iter().find(|segment| segment.name() == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Segment>> {
+        self.segments.iter()
+    }
+
+    /// The segments that make up the prompt, leaving out those disabled in
+    /// `config` or by default
+    pub fn enabled<'a>(&'a self, config: &'a Config) -> impl Iterator<Item = &'a Arc<dyn Segment>> {
+        self.segments.iter().filter(move |segment| {
+            config
+                .segment(segment.name())
+                .enabled
+                .unwrap_or_else(|| segment.enabled_by_default())
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+/// Renders `segment`, unless the conditions configured for it hide it
+pub fn render(segment: &dyn Segment, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+    render_with(segment, ctx, |ctx| segment.render(ctx))
+}
+
+/// The message a panic was raised with
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("no message", String::as_str),
+    }
+}
+
+/// Renders with `render`, turning a panic into a failure so that one broken
+/// segment can't take the whole prompt down with it
+fn render_with(
+    segment: &dyn Segment,
+    ctx: &Context,
+    render: impl FnOnce(&Context) -> Result<RenderedSegment, SegmentError>,
+) -> Result<RenderedSegment, SegmentError> {
+    let render_styled = || {
+        check_and_render(segment, ctx, render)
+            .map(|rendered| rendered.restyled(&theme::styler(ctx, segment.name())))
+    };
+    panic::catch_unwind(AssertUnwindSafe(render_styled)).unwrap_or_else(|payload| {
+        Err(SegmentError::failed(
+            segment.name(),
+            format!("panicked: {}", panic_message(&*payload)),
+        ))
+    })
+}
+
+fn check_and_render(
+    segment: &dyn Segment,
+    ctx: &Context,
+    render: impl FnOnce(&Context) -> Result<RenderedSegment, SegmentError>,
+) -> Result<RenderedSegment, SegmentError> {
+    if ctx.config.ignore_conditions {
+        return render(ctx);
+    }
+
+    let name = segment.name();
+    let conditions = &ctx.config.segment(name).conditions;
+    let hidden = |e| SegmentError::new(name, ErrorKind::Hidden).with_source(e);
+
+    conditions.check_context(ctx).map_err(hidden)?;
+    let rendered = render(ctx)?;
+    conditions.check_value(rendered.value()).map_err(hidden)?;
+
+    Ok(rendered)
+}
+
+pub fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let ret = f();
+    (ret, start.elapsed())
+}
+
+type SegmentResult = (Result<RenderedSegment, SegmentError>, Duration);
+
+/// A segment being rendered on its own worker thread
+struct PendingSegment {
+    name: String,
+    rx: mpsc::Receiver<SegmentResult>,
+    started: Instant,
+    budget: Duration,
+}
+
+impl PendingSegment {
+    fn spawn(segment: &Arc<dyn Segment>, ctx: &Arc<Context>) -> PendingSegment {
+        let name = segment.name().to_string();
+
+        let mut budget = ctx.config.budget_for(&name);
+        if ctx.network_fs {
+            budget /= 2;
+        }
+
+        let (tx, rx) = mpsc::channel();
+
+        {
+            let segment = Arc::clone(segment);
+            let ctx = Arc::clone(ctx);
+            thread::spawn(move || {
+                let result = timed(|| render(&*segment, &ctx));
+                // The receiver is gone if we ran over budget, nothing to do then
+                let _ = tx.send(result);
+            });
+        }
+
+        PendingSegment {
+            name,
+            rx,
+            started: Instant::now(),
+            budget,
+        }
+    }
+
+    /// Waits for the result until the segment's budget, counted from when it
+    /// was spawned, runs out. An abandoned worker finishes in the background
+    /// and its result is dropped. Also returns how long the segment took
+    fn wait(self) -> SegmentResult {
+        let deadline = self.started + self.budget;
+        match self
+            .rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => (
+                Err(SegmentError::new(self.name, ErrorKind::OverBudget)
+                    .with_message(format!("took longer than {}ms", self.budget.as_millis()))),
+                self.started.elapsed(),
+            ),
+            Err(RecvTimeoutError::Disconnected) => (
+                Err(SegmentError::failed(
+                    self.name,
+                    "worker exited without a result",
+                )),
+                self.started.elapsed(),
+            ),
+        }
+    }
+}
+
+/// Which project segments, like the ones for languages, are shown when the
+/// cwd is in several projects at once
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProjectSegments {
+    All,
+    /// The `max_languages` closest to the cwd
+    Nearest,
+    None,
+}
+
+impl ProjectSegments {
+    /// From `$PROMPTLINE_PROJECT_SEGMENTS`, nearest unless it says otherwise
+    pub fn from_env(ctx: &Context) -> ProjectSegments {
+        match ctx.var("PROMPTLINE_PROJECT_SEGMENTS") {
+            Some("all") => ProjectSegments::All,
+            Some("none") => ProjectSegments::None,
+            _ => ProjectSegments::Nearest,
+        }
+    }
+}
+
+/// Hides the project segments among `results` that `mode` leaves out. Of
+/// projects at the same depth, the segment printed first wins
+fn limit_projects(
+    results: &mut [SegmentResult],
+    names: &[String],
+    mode: ProjectSegments,
+    nearest: usize,
+) {
+    let mut projects: Vec<_> = results
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (result, _))| Some((result.as_ref().ok()?.project_depth()?, i)))
+        .collect();
+    projects.sort();
+
+    let keep = match mode {
+        ProjectSegments::All => projects.len(),
+        ProjectSegments::Nearest => nearest,
+        ProjectSegments::None => 0,
+    };
+    for &(_, i) in projects.iter().skip(keep) {
+        let message = match mode {
+            ProjectSegments::None => "project segments are turned off",
+            _ => "closer project segments are shown",
+        };
+        results[i].0 = Err(SegmentError::not_applicable(names[i].clone(), message));
+    }
+}
+
+/// Renders all `segments` in parallel, each within its time budget, returning
+/// the results in the same order along with how long each one took. Segments
+/// share process state like the cwd and environment, which is fine as long as
+/// they only ever read it
+pub fn render_all<'a>(
+    segments: impl IntoIterator<Item = &'a Arc<dyn Segment>>,
+    ctx: &Arc<Context>,
+) -> Vec<SegmentResult> {
+    let pending: Vec<_> = segments
+        .into_iter()
+        .map(|segment| PendingSegment::spawn(segment, ctx))
+        .collect();
+    let names: Vec<_> = pending.iter().map(|pending| pending.name.clone()).collect();
+
+    let mut results: Vec<_> = pending.into_iter().map(PendingSegment::wait).collect();
+    limit_projects(
+        &mut results,
+        &names,
+        ProjectSegments::from_env(ctx),
+        ctx.config.max_languages,
+    );
+    results
+}
+
+/// Renders the sample of each of `segments` one after the other, for
+/// `promptline preview`. There are no budgets, as nothing waits on the samples
+pub fn preview_all<'a>(
+    segments: impl IntoIterator<Item = &'a Arc<dyn Segment>>,
+    ctx: &Context,
+) -> Vec<SegmentResult> {
+    let mut names = vec![];
+    let mut results = vec![];
+    for segment in segments {
+        names.push(segment.name().to_string());
+        results.push(timed(|| {
+            render_with(&**segment, ctx, |ctx| segment.preview(ctx))
+        }));
+    }
+
+    limit_projects(
+        &mut results,
+        &names,
+        ProjectSegments::from_env(ctx),
+        ctx.config.max_languages,
+    );
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecoratedString;
+
+    struct Named(&'static str);
+
+    impl Segment for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+            Ok(DecoratedString::new(self.0.into()).into())
+        }
+    }
+
+    fn registry(names: &[&'static str]) -> Registry {
+        Registry::new(
+            names
+                .iter()
+                .map(|&name| Arc::new(Named(name)) as Arc<dyn Segment>)
+                .collect(),
+        )
+    }
+
+    fn placed(placements: &[(&'static str, Placement)]) -> Vec<String> {
+        let mut registry = registry(&["user", "cwd", "git"]);
+        registry.place_all(
+            placements
+                .iter()
+                .map(|(name, placement)| {
+                    (Arc::new(Named(name)) as Arc<dyn Segment>, placement.clone())
+                })
+                .collect(),
+        );
+        registry.iter().map(|s| s.name().to_string()).collect()
+    }
+
+    #[test]
+    fn first_and_last_keep_config_order() {
+        assert_eq!(
+            placed(&[
+                ("a", Placement::First),
+                ("z", Placement::Last),
+                ("b", Placement::First),
+                ("y", Placement::Last),
+            ]),
+            ["a", "b", "user", "cwd", "git", "z", "y"]
+        );
+    }
+
+    #[test]
+    fn after_named_segment() {
+        assert_eq!(
+            placed(&[
+                ("a", Placement::After("cwd".into())),
+                ("b", Placement::After("cwd".into())),
+                ("c", Placement::After("git".into())),
+            ]),
+            ["user", "cwd", "a", "b", "git", "c"]
+        );
+    }
+
+    #[test]
+    fn after_unknown_segment_goes_last() {
+        assert_eq!(
+            placed(&[
+                ("a", Placement::After("kube".into())),
+                ("b", Placement::Last),
+            ]),
+            ["user", "cwd", "git", "a", "b"]
+        );
+    }
+
+    fn shown_projects(
+        depths: &[Option<usize>],
+        mode: ProjectSegments,
+        nearest: usize,
+    ) -> Vec<usize> {
+        let mut results: Vec<SegmentResult> = depths
+            .iter()
+            .map(|depth| {
+                let rendered = RenderedSegment::new(DecoratedString::new("x".into()));
+                let rendered = match depth {
+                    Some(depth) => rendered.with_project_depth(*depth),
+                    None => rendered,
+                };
+                (Ok(rendered), Duration::ZERO)
+            })
+            .collect();
+        let names: Vec<_> = (0..depths.len()).map(|i| i.to_string()).collect();
+        limit_projects(&mut results, &names, mode, nearest);
+        (0..depths.len())
+            .filter(|&i| results[i].0.is_ok())
+            .collect()
+    }
+
+    struct Panicking;
+
+    impl Segment for Panicking {
+        fn name(&self) -> &str {
+            "panicking"
+        }
+
+        fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+            let empty: &[u8] = &[];
+            panic!("index out of bounds: {}", empty.len());
+        }
+    }
+
+    #[test]
+    fn panic_fails_only_its_segment() {
+        let mut registry = registry(&["user", "cwd"]);
+        registry.insert(1, Arc::new(Panicking));
+        let ctx = Arc::new(crate::testing::context(&[], None));
+
+        let results = render_all(registry.iter(), &ctx);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0.as_ref().unwrap().value(), "user");
+        assert_eq!(results[2].0.as_ref().unwrap().value(), "cwd");
+
+        let e = results[1].0.as_ref().unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Failed);
+        assert_eq!(
+            e.to_single_line(),
+            "failed to get panicking info: panicked: index out of bounds: 0"
+        );
+    }
+
+    /// Takes far longer than any budget
+    struct Slow;
+
+    impl Segment for Slow {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+            thread::sleep(Duration::from_secs(5));
+            Ok(DecoratedString::new("slow".into()).into())
+        }
+    }
+
+    #[test]
+    fn slow_segment_is_cut_off() {
+        let mut registry = registry(&["user", "cwd"]);
+        registry.insert(1, Arc::new(Slow));
+        let mut ctx = crate::testing::context(&[], None);
+        ctx.config.budget = Duration::from_millis(50);
+        let ctx = Arc::new(ctx);
+
+        let (results, took) = timed(|| render_all(registry.iter(), &ctx));
+        assert!(took < Duration::from_secs(1), "{:?}", took);
+        assert_eq!(results[0].0.as_ref().unwrap().value(), "user");
+        assert_eq!(results[2].0.as_ref().unwrap().value(), "cwd");
+
+        let e = results[1].0.as_ref().unwrap_err();
+        assert_eq!(e.kind, ErrorKind::OverBudget);
+        assert_eq!(e.to_single_line(), "slow skipped: took longer than 50ms");
+    }
+
+    #[test]
+    fn nearest_project_segments() {
+        let depths = [Some(2), None, Some(0), Some(1), Some(0)];
+        assert_eq!(shown_projects(&depths, ProjectSegments::Nearest, 1), [1, 2]);
+        assert_eq!(
+            shown_projects(&depths, ProjectSegments::Nearest, 2),
+            [1, 2, 4]
+        );
+        assert_eq!(
+            shown_projects(&depths, ProjectSegments::All, 1),
+            [0, 1, 2, 3, 4]
+        );
+        assert_eq!(shown_projects(&depths, ProjectSegments::None, 1), [1]);
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{self, Write},
+};
+
+use crate::{
+    decorated::{Color, DecoratedString, Escaping, Style},
+    Context,
+};
+
+/// One piece of the prompt, e.g. the git branch or the current user
+pub trait Segment: Send + Sync {
+    /// Name used to refer to the segment in the config file and on the
+    /// command line
+    fn name(&self) -> &str;
+
+    /// Whether the segment is part of the prompt without `enabled = true` in
+    /// its config
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    /// Whether the segment is too slow to hold up the prompt for, and is left
+    /// to `--slow` when the shell renders the prompt in two phases
+    fn is_expensive(&self) -> bool {
+        false
+    }
+
+    /// Renders the segment, or explains why it can't be shown
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError>;
+
+    /// Renders the segment for `promptline preview`, against a context filled
+    /// with sample data. Segments that ask the machine itself rather than the
+    /// context make up their own sample instead
+    fn preview(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        self.render(ctx)
+    }
+}
+
+/// Output of a successfully rendered segment
+///
+/// This isn't a single text with a single style: parts of a segment can be
+/// styled differently, like the icon and counts of the git segment or the
+/// status of each command of a pipeline. The segment keeps them as a
+/// [`DecoratedString`] instead, which [`decorated`](Self::decorated) gives
+/// access to. [`value`](Self::value) is the text without icons, and
+/// [`render`](crate::render) turns the whole into text to print
+///
+/// ```
+/// use promptline::{render, Color, DecoratedString, RenderedSegment};
+///
+/// let counts = DecoratedString::join(
+///     vec![
+///         DecoratedString::new("+1".into()).colored(Color::Green),
+///         DecoratedString::new("~2".into()).colored(Color::Yellow),
+///     ],
+///     " ",
+/// );
+/// let segment = RenderedSegment::from(counts.with_icon("\u{e0a0}"));
+/// assert_eq!(segment.value(), "+1 ~2");
+/// assert_eq!(render::plain(&segment), "\u{e0a0} +1 ~2");
+/// assert_eq!(
+///     render::ansi(&segment),
+///     "\u{e0a0} \x1b[32m+1\x1b[39m \x1b[33m~2\x1b[39m"
+/// );
+/// ```
+#[derive(Debug)]
+pub struct RenderedSegment {
+    decorated: DecoratedString,
+    project_depth: Option<usize>,
+}
+
+impl RenderedSegment {
+    pub fn new(decorated: DecoratedString) -> RenderedSegment {
+        RenderedSegment {
+            decorated,
+            project_depth: None,
+        }
+    }
+
+    /// Marks the segment as one about the project the cwd is in, found
+    /// `depth` directories up from the cwd. Only the closest project segments
+    /// are shown when several apply
+    pub fn with_project_depth(self, depth: usize) -> RenderedSegment {
+        RenderedSegment {
+            project_depth: Some(depth),
+            ..self
+        }
+    }
+
+    pub fn project_depth(&self) -> Option<usize> {
+        self.project_depth
+    }
+
+    /// `members` as one segment, with `joiner` between them in the color of
+    /// the first member. Keeps the first member's project depth
+    pub fn merge(members: Vec<RenderedSegment>, joiner: &str) -> RenderedSegment {
+        let project_depth = members.first().and_then(|member| member.project_depth);
+        let color = members.first().and_then(|member| member.decorated.color());
+        let mut parts = vec![];
+        for (i, member) in members.into_iter().enumerate() {
+            if i > 0 {
+                let joiner = DecoratedString::new(joiner.to_string());
+                parts.push(match color {
+                    Some(color) => joiner.colored(color),
+                    None => joiner,
+                });
+            }
+            parts.push(member.decorated);
+        }
+        RenderedSegment {
+            decorated: DecoratedString::join(parts, ""),
+            project_depth,
+        }
+    }
+
+    /// The text along with its styling
+    pub fn decorated(&self) -> &DecoratedString {
+        &self.decorated
+    }
+
+    /// See `DecoratedString::restyled`
+    pub fn restyled(self, restyle: &dyn Fn(Color) -> Option<Style>) -> RenderedSegment {
+        RenderedSegment {
+            decorated: self.decorated.restyled(restyle),
+            ..self
+        }
+    }
+
+    /// The undecorated text, used for matching against config values
+    pub fn value(&self) -> &str {
+        self.decorated.value()
+    }
+
+    pub fn to_ansi(&self) -> String {
+        self.decorated.to_ansi()
+    }
+
+    /// Columns the segment takes up on the terminal
+    pub fn visible_width(&self) -> usize {
+        self.decorated.visible_width()
+    }
+
+    /// Shortens the segment to `width` chars by cutting off the start
+    pub fn truncate_start(&mut self, width: usize) {
+        self.decorated.truncate_start(width);
+    }
+
+    pub fn to_ansi_escaped(&self, escaping: Escaping) -> String {
+        self.decorated.to_ansi_escaped(escaping)
+    }
+
+    pub fn to_plain(&self) -> String {
+        self.decorated.to_plain()
+    }
+}
+
+impl From<DecoratedString> for RenderedSegment {
+    fn from(decorated: DecoratedString) -> RenderedSegment {
+        RenderedSegment::new(decorated)
+    }
+}
+
+/// Writes `err` followed by its chain of sources, one per line
+pub fn write_error_chain(f: &mut impl Write, err: &dyn Error) -> fmt::Result {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        writeln!(f, "{err}")?;
+        source = err.source();
+    }
+
+    Ok(())
+}
+
+/// How rendering a segment turned out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Shown,
+    /// The segment had nothing to show, which is expected and not worth
+    /// reporting
+    NotApplicable,
+    /// A genuine error
+    Failed,
+}
+
+impl Outcome {
+    pub fn of(result: &Result<RenderedSegment, SegmentError>) -> Outcome {
+        match result {
+            Ok(_) => Outcome::Shown,
+            Err(e) => e.outcome(),
+        }
+    }
+}
+
+/// Broad reason a segment isn't shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// There is nothing to show here, e.g. the cwd isn't in a git repo
+    NotApplicable,
+    /// Hidden by a condition in the config
+    Hidden,
+    /// Took longer than its time budget
+    OverBudget,
+    /// Something went wrong getting the segment's info
+    Failed,
+}
+
+/// Why a segment isn't shown
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SegmentError {
+    pub segment: String,
+    pub kind: ErrorKind,
+    pub message: Option<Cow<'static, str>>,
+    pub source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl SegmentError {
+    pub fn new(segment: impl Into<String>, kind: ErrorKind) -> SegmentError {
+        SegmentError {
+            segment: segment.into(),
+            kind,
+            message: None,
+            source: None,
+        }
+    }
+
+    pub fn not_applicable(
+        segment: impl Into<String>,
+        message: impl Into<Cow<'static, str>>,
+    ) -> SegmentError {
+        SegmentError::new(segment, ErrorKind::NotApplicable).with_message(message)
+    }
+
+    pub fn failed(
+        segment: impl Into<String>,
+        message: impl Into<Cow<'static, str>>,
+    ) -> SegmentError {
+        SegmentError::new(segment, ErrorKind::Failed).with_message(message)
+    }
+
+    pub fn with_message(self, message: impl Into<Cow<'static, str>>) -> SegmentError {
+        SegmentError {
+            message: Some(message.into()),
+            ..self
+        }
+    }
+
+    pub fn with_source(self, source: impl Into<Box<dyn Error + Send + Sync>>) -> SegmentError {
+        SegmentError {
+            source: Some(source.into()),
+            ..self
+        }
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        match self.kind {
+            ErrorKind::NotApplicable | ErrorKind::Hidden => Outcome::NotApplicable,
+            ErrorKind::OverBudget | ErrorKind::Failed => Outcome::Failed,
+        }
+    }
+
+    /// The error and its causes joined onto one line
+    pub fn to_single_line(&self) -> String {
+        single_line(&self.to_string())
+    }
+}
+
+/// Joins a multi-line error message, as written by `Display` impls that list
+/// their causes, onto one line
+pub fn single_line(message: &str) -> String {
+    message
+        .trim_end()
+        .replace("\nCaused by:\n", ": ")
+        .replace('\n', ": ")
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let segment = &self.segment;
+        match self.kind {
+            ErrorKind::NotApplicable => writeln!(f, "{segment} not applicable")?,
+            ErrorKind::Hidden => writeln!(f, "{segment} hidden by config")?,
+            ErrorKind::OverBudget => writeln!(f, "{segment} skipped")?,
+            ErrorKind::Failed => writeln!(f, "failed to get {segment} info")?,
+        }
+
+        if self.message.is_none() && self.source.is_none() {
+            return Ok(());
+        }
+
+        writeln!(f, "Caused by:")?;
+
+        if let Some(message) = &self.message {
+            writeln!(f, "{message}")?;
+        }
+
+        if let Some(source) = &self.source {
+            write_error_chain(f, source.as_ref())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for SegmentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+    }
+}
@@ -0,0 +1,136 @@
+use std::fs;
+
+use super::project_root;
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "bazel";
+
+/// Files marking the root of a Bazel workspace, in the order their names are
+/// looked for. Bzlmod's `MODULE.bazel` replaces `WORKSPACE`
+const MARKERS: &[&str] = &["MODULE.bazel", "WORKSPACE.bazel", "WORKSPACE"];
+
+/// The `name` given to the first `module(...)` or `workspace(...)` call in
+/// `contents`, which can span lines
+fn declared_name(contents: &str) -> Option<&str> {
+    ["module(", "workspace("].iter().find_map(|call| {
+        let start = contents
+            .match_indices(call)
+            .map(|(start, _)| start)
+            .find(|&start| {
+                // A call at the start of a line, not e.g. `bazel_workspace(`
+                contents[..start]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|c| c == '\n' || c == ' ' || c == '\t')
+            })?;
+        let args = contents[start + call.len()..].split(')').next()?;
+        args.match_indices("name").find_map(|(at, _)| {
+            let value = args[at + "name".len()..].trim_start().strip_prefix('=')?;
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+            value[1..]
+                .split(quote)
+                .next()
+                .filter(|name| !name.is_empty())
+        })
+    })
+}
+
+fn get_bazel(ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+    let (root, depth) = project_root(ctx, NAME, MARKERS)?;
+
+    let declared = MARKERS
+        .iter()
+        .filter_map(|marker| fs::read_to_string(root.join(marker)).ok())
+        .find_map(|contents| declared_name(&contents).map(str::to_string));
+    let name = match declared {
+        Some(name) => name,
+        None => root
+            .file_name()
+            .ok_or_else(|| SegmentError::failed(NAME, "failed to get workspace name"))?
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let bazel = DecoratedString::new(format!("bazel: {name}")).dim();
+    Ok(RenderedSegment::from(bazel).with_project_depth(depth))
+}
+
+/// `bazel: myws` inside Bazel workspaces, named by their `module` or
+/// `workspace` call, or else after their directory
+pub struct Bazel;
+
+impl Segment for Bazel {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_bazel(ctx)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    fn shown(root: &TempDir, cwd: &str) -> String {
+        let ctx = testing::context(&[], Some(cwd)).with_root(root.path().to_path_buf());
+        get_bazel(&ctx).unwrap().to_plain()
+    }
+
+    #[test]
+    fn module_bazel() {
+        let root = TempDir::new();
+        root.write(
+            "src/mono/MODULE.bazel",
+            "module(\n    name = \"my_module\",\n    version = \"1.0\",\n)\n\nbazel_dep(name = \"rules_go\", version = \"0.46.0\")\n",
+        );
+        root.write("src/mono/app/BUILD.bazel", "");
+        assert_eq!(shown(&root, "/src/mono/app"), "bazel: my_module");
+    }
+
+    #[test]
+    fn workspace_file() {
+        let root = TempDir::new();
+        root.write(
+            "src/mono/WORKSPACE",
+            "# The workspace\nworkspace(name = 'legacy_ws')\n\nload(\"@bazel_tools//tools/build_defs/repo:http.bzl\", \"http_archive\")\n",
+        );
+        assert_eq!(shown(&root, "/src/mono"), "bazel: legacy_ws");
+    }
+
+    #[test]
+    fn workspace_bazel_file() {
+        let root = TempDir::new();
+        root.write("src/mono/WORKSPACE.bazel", "workspace(name=\"new_ws\")\n");
+        assert_eq!(shown(&root, "/src/mono"), "bazel: new_ws");
+    }
+
+    #[test]
+    fn unnamed_workspace() {
+        let root = TempDir::new();
+        root.write("src/mono/WORKSPACE", "");
+        root.write(
+            "src/mono/MODULE.bazel",
+            "bazel_dep(name = \"rules_cc\", version = \"0.0.9\")\n",
+        );
+        assert_eq!(shown(&root, "/src/mono"), "bazel: mono");
+    }
+
+    #[test]
+    fn declared_names() {
+        assert_eq!(declared_name("module(name = \"m\")"), Some("m"));
+        assert_eq!(
+            declared_name("module(\n  compatibility_level = 1,\n  name = \"m\",\n)"),
+            Some("m")
+        );
+        assert_eq!(declared_name("my_workspace(name = \"no\")"), None);
+        assert_eq!(declared_name("workspace()\nfoo(name = \"no\")"), None);
+        assert_eq!(declared_name(""), None);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::project_root;
+use crate::{
+    config::{self, Table},
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "cargo";
+
+const MANIFEST: &str = "Cargo.toml";
+
+/// The manifest in `dir`, if it parses. Our TOML parser only knows a subset
+/// of the language, so a manifest it can't read is treated like a missing one
+fn manifest(dir: &Path) -> Option<Table> {
+    config::parse(&fs::read_to_string(dir.join(MANIFEST)).ok()?).ok()
+}
+
+fn package_name(manifest: &Table) -> Option<String> {
+    let package = config::get_table(manifest, "package").ok()??;
+    config::get_str(package, "name").ok()?.map(String::from)
+}
+
+fn dir_name(dir: &Path) -> String {
+    dir.file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+/// Whether a path from `members` or `exclude` matches `path`, both relative
+/// to the workspace root. As in cargo, `*` matches within a single directory
+fn matches(pattern: &str, path: &Path) -> bool {
+    let pattern = Path::new(pattern.trim_start_matches("./").trim_end_matches('/'));
+    pattern.components().count() == path.components().count()
+        && pattern.iter().zip(path).all(|(pattern, name)| {
+            let (pattern, name) = (pattern.to_string_lossy(), name.to_string_lossy());
+            match pattern.split_once('*') {
+                Some((prefix, suffix)) => {
+                    name.len() >= prefix.len() + suffix.len()
+                        && name.starts_with(prefix)
+                        && name.ends_with(suffix)
+                }
+                None => pattern == name,
+            }
+        })
+}
+
+/// Root and manifest of the workspace `member` belongs to: the closest
+/// directory above it with a `[workspace]`, as long as that lists `member`.
+/// Crates pulled in by path from outside of the workspace dir have none
+fn workspace(member: &Path) -> Option<(PathBuf, Table)> {
+    let (root, manifest) = member.ancestors().skip(1).find_map(|dir| {
+        let manifest = manifest(dir)?;
+        manifest
+            .contains_key("workspace")
+            .then(|| (dir.to_path_buf(), manifest))
+    })?;
+
+    let workspace = config::get_table(&manifest, "workspace").ok()??;
+    let listed = |key| config::get_strings(workspace, key).ok().flatten();
+    let relative = member.strip_prefix(&root).ok()?;
+    let is_member = listed("members")?
+        .iter()
+        .any(|pattern| matches(pattern, relative))
+        && !listed("exclude")
+            .unwrap_or_default()
+            .iter()
+            .any(|pattern| matches(pattern, relative));
+    is_member.then_some((root, manifest))
+}
+
+fn get_cargo(ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+    let (member, depth) = project_root(ctx, NAME, &[MANIFEST])?;
+    let manifest = manifest(&member);
+
+    let mut name = manifest
+        .as_ref()
+        .and_then(package_name)
+        .unwrap_or_else(|| dir_name(&member));
+    let is_workspace_root = manifest.is_some_and(|m| m.contains_key("workspace"));
+    if !is_workspace_root {
+        if let Some((root, manifest)) = workspace(&member) {
+            let workspace = package_name(&manifest).unwrap_or_else(|| dir_name(&root));
+            name = format!("{workspace}/{name}");
+        }
+    }
+
+    let text = DecoratedString::new(name);
+    let text = match super::icon(ctx, NAME, "🦀", "cargo:")? {
+        Some(icon) => text.with_icon(&icon),
+        None => text,
+    };
+    Ok(RenderedSegment::from(text.dim()).with_project_depth(depth))
+}
+
+/// `🦀` with the name of the crate the cwd is in, after the name of its
+/// workspace when it is a workspace member
+pub struct Cargo;
+
+impl Segment for Cargo {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_cargo(ctx)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    fn shown(root: &TempDir, cwd: &str) -> String {
+        let ctx = testing::context(&[], Some(cwd)).with_root(root.path().to_path_buf());
+        get_cargo(&ctx).unwrap().to_plain()
+    }
+
+    fn package(name: &str) -> String {
+        format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n\n[dependencies]\nlibc = {{ version = \"0.2\", optional = true }}\n")
+    }
+
+    #[test]
+    fn workspace_member() {
+        let root = TempDir::new();
+        root.write(
+            "src/shop/Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\", \"cli\"]\nexclude = [\"crates/old\"]\n",
+        );
+        root.write("src/shop/crates/api/Cargo.toml", &package("shop-api"));
+        root.write("src/shop/cli/Cargo.toml", &package("shop"));
+        root.write("src/shop/crates/old/Cargo.toml", &package("legacy"));
+
+        assert_eq!(shown(&root, "/src/shop/crates/api/src"), "🦀 shop/shop-api");
+        assert_eq!(shown(&root, "/src/shop/cli"), "🦀 shop/shop");
+        assert_eq!(shown(&root, "/src/shop/crates/old"), "🦀 legacy");
+        assert_eq!(shown(&root, "/src/shop"), "🦀 shop");
+    }
+
+    #[test]
+    fn single_crate() {
+        let root = TempDir::new();
+        root.write("src/tool/Cargo.toml", &package("tool"));
+        assert_eq!(shown(&root, "/src/tool/src"), "🦀 tool");
+
+        // A root package is its own workspace
+        let source = package("app") + "\n[workspace]\nmembers = [\"macros\"]\n";
+        root.write("src/app/Cargo.toml", &source);
+        root.write("src/app/macros/Cargo.toml", &package("app-macros"));
+        assert_eq!(shown(&root, "/src/app"), "🦀 app");
+        assert_eq!(shown(&root, "/src/app/macros"), "🦀 app/app-macros");
+    }
+
+    #[test]
+    fn path_dependency_outside_workspace() {
+        let root = TempDir::new();
+        root.write(
+            "src/shop/Cargo.toml",
+            "[workspace]\nmembers = [\"api\", \"../shared\"]\n",
+        );
+        root.write("src/shop/api/Cargo.toml", &package("api"));
+        root.write("src/shared/Cargo.toml", &package("shared"));
+        assert_eq!(shown(&root, "/src/shared"), "🦀 shared");
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::Path;
+
+use crate::{
+    config,
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "cmake";
+
+/// The value of `key` in a `CMakeCache.txt`, whose entries are lines of
+/// `KEY:TYPE=VALUE`. Comments and lines that aren't entries are skipped
+fn cache_entry<'a>(cache: &'a str, key: &str) -> Option<&'a str> {
+    cache.lines().find_map(|line| {
+        if line.starts_with("//") || line.starts_with('#') {
+            return None;
+        }
+        let (name, value) = line.split_once('=')?;
+        let (name, _type) = name.split_once(':')?;
+        (name.trim() == key).then(|| value.trim())
+    })
+}
+
+fn get_cmake(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let show_generator = config::get_bool(&ctx.config.segment(NAME).options, "show_generator")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .unwrap_or(false);
+
+    let cwd = ctx
+        .cwd()
+        .ok_or_else(|| SegmentError::failed(NAME, "failed to get cwd"))?;
+    // Only the build dir itself, a tree below it is usually a subproject's
+    let cache = ctx
+        .read_to_string(cwd.join("CMakeCache.txt"))
+        .map_err(|_| SegmentError::not_applicable(NAME, "not a cmake build dir"))?;
+
+    let source = cache_entry(&cache, "CMAKE_HOME_DIRECTORY")
+        .ok_or_else(|| SegmentError::failed(NAME, "no source dir in CMakeCache.txt"))?;
+    let project = Path::new(source)
+        .file_name()
+        .ok_or_else(|| SegmentError::failed(NAME, "failed to get project name"))?
+        .to_string_lossy();
+
+    let mut details = vec![];
+    details.extend(cache_entry(&cache, "CMAKE_BUILD_TYPE"));
+    if show_generator {
+        details.extend(cache_entry(&cache, "CMAKE_GENERATOR"));
+    }
+    let details: Vec<_> = details.into_iter().filter(|d| !d.is_empty()).collect();
+
+    let shown = match details.as_slice() {
+        [] => format!("cmake: {project}"),
+        details => format!("cmake: {project} [{}]", details.join(", ")),
+    };
+    Ok(DecoratedString::new(shown).dim())
+}
+
+/// `cmake: myproj [Debug]` in a CMake build dir, naming the source dir it was
+/// configured from and its build type. With `show_generator = true` the
+/// generator is shown too, as `[Debug, Ninja]`
+pub struct Cmake;
+
+impl Segment for Cmake {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_cmake(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    /// Trimmed from a cache written by CMake 3.28
+    const CACHE: &str = "\
+# This is the CMakeCache file.
+# For build in directory: /home/ada/src/myproj/build
+# It was generated by CMake: /usr/bin/cmake
+
+########################
+# EXTERNAL cache entries
+########################
+
+//Choose the type of build, options are: None Debug Release RelWithDebInfo
+// MinSizeRel ...
+CMAKE_BUILD_TYPE:STRING=Debug
+
+//Enable/Disable output of compile commands during generation.
+CMAKE_EXPORT_COMPILE_COMMANDS:BOOL=ON
+
+########################
+# INTERNAL cache entries
+########################
+
+//Name of generator.
+CMAKE_GENERATOR:INTERNAL=Ninja
+//Source directory with the top level CMakeLists.txt file for this
+// project
+CMAKE_HOME_DIRECTORY:INTERNAL=/home/ada/src/myproj
+this line is not an entry
+";
+
+    fn shown_with(root: &TempDir, toml: &str) -> Result<String, SegmentError> {
+        let mut ctx = testing::context(&[], Some("/home/ada/src/myproj/build"))
+            .with_root(root.path().to_path_buf());
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        get_cmake(&ctx).map(|cmake| cmake.to_plain())
+    }
+
+    fn build_dir(cache: &str) -> TempDir {
+        let root = TempDir::new();
+        root.write("home/ada/src/myproj/build/CMakeCache.txt", cache);
+        root
+    }
+
+    #[test]
+    fn build_dir_of_project() {
+        let root = build_dir(CACHE);
+        assert_eq!(shown_with(&root, "").unwrap(), "cmake: myproj [Debug]");
+        assert_eq!(
+            shown_with(&root, "[segments.cmake]\nshow_generator = true\n").unwrap(),
+            "cmake: myproj [Debug, Ninja]"
+        );
+    }
+
+    #[test]
+    fn no_build_type() {
+        let root =
+            build_dir(&CACHE.replace("CMAKE_BUILD_TYPE:STRING=Debug", "CMAKE_BUILD_TYPE:STRING="));
+        assert_eq!(shown_with(&root, "").unwrap(), "cmake: myproj");
+
+        let root = build_dir(&CACHE.replace("CMAKE_BUILD_TYPE", "// CMAKE_BUILD_TYPE"));
+        assert_eq!(shown_with(&root, "").unwrap(), "cmake: myproj");
+    }
+
+    #[test]
+    fn only_the_build_dir_itself() {
+        let root = TempDir::new();
+        root.write("home/ada/src/myproj/CMakeCache.txt", CACHE);
+        root.write("home/ada/src/myproj/build/.keep", "");
+        let res = shown_with(&root, "");
+        assert!(matches!(res, Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+
+    #[test]
+    fn entries() {
+        assert_eq!(cache_entry(CACHE, "CMAKE_GENERATOR"), Some("Ninja"));
+        assert_eq!(cache_entry(CACHE, "CMAKE_MAKE_PROGRAM"), None);
+        assert_eq!(
+            cache_entry("CMAKE_GENERATOR=Ninja\n", "CMAKE_GENERATOR"),
+            None
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "conda";
+
+fn get_conda_info(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let conda_env = ctx
+        .var("CONDA_DEFAULT_ENV")
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "no conda env var"))?;
+    // base is active in every shell once conda is set up, which says nothing
+    if conda_env == "base" && ctx.var("PROMPTLINE_SHOW_CONDA_BASE") != Some("1") {
+        return Err(SegmentError::not_applicable(NAME, "base env"));
+    }
+
+    let env = match super::icon(ctx, NAME, "🐍", "conda:")? {
+        Some(icon) => DecoratedString::new(conda_env.to_string()).with_icon(&icon),
+        None => DecoratedString::new(conda_env.to_string()),
+    }
+    .bold();
+    // Each `conda activate` without deactivating first stacks another env
+    let stacked = ctx
+        .var("CONDA_SHLVL")
+        .and_then(|level| level.parse::<u32>().ok())
+        .map_or(0, |level| level.saturating_sub(1));
+    if stacked > 0 {
+        Ok(env.with_suffix(DecoratedString::new(format!("(+{stacked})")).dim()))
+    } else {
+        Ok(env)
+    }
+}
+
+/// Active conda environment, as `🐍 myenv (+2)` with two more stacked
+/// beneath it. base is left out unless `$PROMPTLINE_SHOW_CONDA_BASE` is 1
+pub struct Conda;
+
+impl Segment for Conda {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_conda_info(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn shows_env() {
+        let ctx = testing::context(&[("CONDA_DEFAULT_ENV", "science")], None);
+        assert_eq!(get_conda_info(&ctx).unwrap().to_plain(), "🐍 science");
+    }
+
+    #[test]
+    fn stacked_envs() {
+        let shown = |level| {
+            let ctx = testing::context(
+                &[("CONDA_DEFAULT_ENV", "myenv"), ("CONDA_SHLVL", level)],
+                None,
+            );
+            get_conda_info(&ctx).unwrap().to_plain()
+        };
+        assert_eq!(shown("3"), "🐍 myenv (+2)");
+        assert_eq!(shown("1"), "🐍 myenv");
+        assert_eq!(shown("many"), "🐍 myenv");
+    }
+
+    #[test]
+    fn base_env() {
+        let ctx = testing::context(&[("CONDA_DEFAULT_ENV", "base")], None);
+        assert!(get_conda_info(&ctx).is_err());
+
+        let ctx = testing::context(
+            &[
+                ("CONDA_DEFAULT_ENV", "base"),
+                ("PROMPTLINE_SHOW_CONDA_BASE", "1"),
+            ],
+            None,
+        );
+        assert_eq!(get_conda_info(&ctx).unwrap().to_plain(), "🐍 base");
+    }
+
+    #[test]
+    fn no_env() {
+        let ctx = testing::context(&[], None);
+        assert!(get_conda_info(&ctx).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use std::{fs, path::PathBuf};
+
+use crate::{
+    config,
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "cpu_temp";
+
+/// `|` separated parts of the zone type to look for, the package sensor on
+/// Intel and the generic name most ARM boards use
+const DEFAULT_ZONE_TYPE: &str = "x86_pkg_temp|cpu";
+
+const DEFAULT_WARN_CELSIUS: i64 = 80;
+
+/// The `thermal_zone*` directories in sysfs, in the kernel's numbering.
+/// Sorted by number, as `thermal_zone10` comes before `thermal_zone2` by name
+fn thermal_zones(ctx: &Context) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(ctx.path("/sys/class/thermal")) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut zones: Vec<_> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let number: u32 = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("thermal_zone")?
+                .parse()
+                .ok()?;
+            Some((number, entry.path()))
+        })
+        .collect();
+    zones.sort();
+    zones.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Whether the zone `type` contains any of the `|` separated parts of
+/// `pattern`
+fn matches_type(zone_type: &str, pattern: &str) -> bool {
+    pattern
+        .split('|')
+        .any(|part| !part.is_empty() && zone_type.contains(part))
+}
+
+/// Temperature in degrees Celsius of the first zone whose type matches
+/// `pattern`, from millidegrees in its `temp` file
+fn zone_celsius(ctx: &Context, pattern: &str) -> Result<i64, SegmentError> {
+    let zone = thermal_zones(ctx)
+        .into_iter()
+        .find(|zone| {
+            fs::read_to_string(zone.join("type"))
+                .map(|zone_type| matches_type(zone_type.trim(), pattern))
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "no matching thermal zone"))?;
+
+    let temp = fs::read_to_string(zone.join("temp"))
+        .map_err(|e| SegmentError::not_applicable(NAME, "failed to read temp").with_source(e))?;
+    let millidegrees: i64 = temp
+        .trim()
+        .parse()
+        .map_err(|e| SegmentError::not_applicable(NAME, "temp is not a number").with_source(e))?;
+
+    Ok(millidegrees / 1000)
+}
+
+fn get_cpu_temp(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let options = &ctx.config.segment(NAME).options;
+    let invalid = |e| SegmentError::failed(NAME, "invalid config").with_source(e);
+    let pattern = config::get_str(options, "zone_type")
+        .map_err(invalid)?
+        .unwrap_or(DEFAULT_ZONE_TYPE);
+    let warn = config::get_int(options, "warn_celsius")
+        .map_err(invalid)?
+        .unwrap_or(DEFAULT_WARN_CELSIUS);
+
+    let celsius = zone_celsius(ctx, pattern)?;
+    if celsius <= warn {
+        return Err(SegmentError::not_applicable(NAME, "below warn_celsius"));
+    }
+
+    let temp = match super::icon(ctx, NAME, "🌡", "temp:")? {
+        Some(icon) => DecoratedString::new(format!("{celsius}°")).with_icon(&icon),
+        None => DecoratedString::new(format!("{celsius}°")),
+    };
+    Ok(temp.colored(Color::Red).bold())
+}
+
+/// Warns when the CPU runs hotter than `warn_celsius`. Off unless
+/// `enabled = true`
+pub struct CpuTemp;
+
+impl Segment for CpuTemp {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_cpu_temp(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    fn with_zones(zones: &[(u32, &str, &str)]) -> (TempDir, Context) {
+        let root = TempDir::new();
+        for (number, zone_type, temp) in zones {
+            let zone = format!("sys/class/thermal/thermal_zone{number}");
+            root.write(&format!("{zone}/type"), &format!("{zone_type}\n"));
+            root.write(&format!("{zone}/temp"), &format!("{temp}\n"));
+        }
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        (root, ctx)
+    }
+
+    fn hidden(ctx: &Context) -> bool {
+        matches!(get_cpu_temp(ctx), Err(e) if e.kind == ErrorKind::NotApplicable)
+    }
+
+    #[test]
+    fn warns_when_hot() {
+        let (_root, ctx) = with_zones(&[(0, "acpitz", "40000"), (1, "x86_pkg_temp", "94500")]);
+        assert_eq!(
+            get_cpu_temp(&ctx).unwrap().to_ansi(),
+            "\x1b[1m\x1b[31m🌡 94°\x1b[39m\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn hidden_when_cool() {
+        let (_root, ctx) = with_zones(&[(0, "x86_pkg_temp", "80000")]);
+        assert!(hidden(&ctx));
+    }
+
+    #[test]
+    fn first_matching_zone_by_number() {
+        let (_root, ctx) = with_zones(&[(10, "cpu-thermal", "99000"), (2, "cpu-thermal", "50000")]);
+        assert!(hidden(&ctx));
+    }
+
+    #[test]
+    fn hidden_without_sysfs_or_match() {
+        let (_root, ctx) = with_zones(&[]);
+        assert!(hidden(&ctx));
+
+        let (_root, ctx) = with_zones(&[(0, "acpitz", "99000")]);
+        assert!(hidden(&ctx));
+    }
+
+    #[test]
+    fn hidden_for_garbage() {
+        let (_root, ctx) = with_zones(&[(0, "x86_pkg_temp", "hot")]);
+        assert!(hidden(&ctx));
+    }
+
+    #[test]
+    fn type_pattern_alternatives() {
+        assert!(matches_type("x86_pkg_temp", DEFAULT_ZONE_TYPE));
+        assert!(matches_type("cpu-thermal", DEFAULT_ZONE_TYPE));
+        assert!(!matches_type("acpitz", DEFAULT_ZONE_TYPE));
+        assert!(!matches_type("acpitz", "cpu|"));
+    }
+}
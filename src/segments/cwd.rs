@@ -0,0 +1,115 @@
+use crate::{
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+/// `cwd` as Windows shells show it: backslashes, an upper case drive letter
+/// and no `\\?\` prefix, with `home` shortened to `~`. Windows paths are case
+/// insensitive, so the home directory is too
+fn windows_display(cwd: &str, home: Option<&str>) -> String {
+    let normalize = |path: &str| {
+        let mut path = path
+            .strip_prefix(r"\\?\")
+            .unwrap_or(path)
+            .replace('/', "\\");
+        if path.as_bytes().get(1) == Some(&b':') {
+            path[..1].make_ascii_uppercase();
+        }
+        path
+    };
+
+    let cwd = normalize(cwd);
+
+    if let Some(home) = home.map(normalize) {
+        let home = home.trim_end_matches('\\');
+        let rest = cwd
+            .get(..home.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(home))
+            .map(|_| &cwd[home.len()..]);
+
+        if let Some(rest) = rest {
+            if rest.is_empty() || rest.starts_with('\\') {
+                return format!("~{rest}");
+            }
+        }
+    }
+
+    cwd
+}
+
+fn get_cwd(ctx: &Context) -> DecoratedString {
+    let cwd = match (ctx.var("PWD"), ctx.cwd()) {
+        (Some(cwd), _) => {
+            let mut cwd = cwd.to_string();
+
+            if let Some(home) = ctx.var("HOME") {
+                if cwd.starts_with(home) {
+                    cwd = cwd.replacen(home, "~", 1);
+                }
+            }
+
+            cwd
+        }
+        // PowerShell and cmd don't set $PWD, unlike Git Bash
+        (None, Some(cwd)) if cfg!(windows) => {
+            windows_display(&cwd.to_string_lossy(), ctx.var("USERPROFILE"))
+        }
+        (None, _) => {
+            return DecoratedString::new("!!!".to_string())
+                .colored(Color::Red)
+                .bold()
+        }
+    };
+
+    DecoratedString::new(cwd).colored(Color::Blue).bold()
+}
+
+/// Working directory as the shell shows it, with `$HOME` shortened to `~`
+pub struct Cwd;
+
+impl Segment for Cwd {
+    fn name(&self) -> &str {
+        "cwd"
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        Ok(get_cwd(ctx).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn shortens_home() {
+        let ctx = testing::context(&[("PWD", "/home/me/src"), ("HOME", "/home/me")], None);
+        assert_eq!(get_cwd(&ctx).to_plain(), "~/src");
+    }
+
+    #[test]
+    fn outside_home() {
+        let ctx = testing::context(&[("PWD", "/etc"), ("HOME", "/home/me")], None);
+        assert_eq!(get_cwd(&ctx).to_plain(), "/etc");
+    }
+
+    #[test]
+    fn windows_paths() {
+        let home = Some(r"C:\Users\me");
+        assert_eq!(windows_display(r"C:\Users\me", home), "~");
+        assert_eq!(windows_display(r"c:\users\ME\src", home), r"~\src");
+        assert_eq!(windows_display(r"\\?\C:\Users\me\src", home), r"~\src");
+        assert_eq!(windows_display("C:/Users/me/src", home), r"~\src");
+        assert_eq!(windows_display(r"C:\Users\meg", home), r"C:\Users\meg");
+        assert_eq!(windows_display(r"d:\work", home), r"D:\work");
+        assert_eq!(windows_display(r"D:\work", None), r"D:\work");
+    }
+
+    #[test]
+    fn no_pwd() {
+        let ctx = testing::context(&[("HOME", "/home/me")], None);
+        assert_eq!(get_cwd(&ctx).to_plain(), "!!!");
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(not(windows))]
+use crate::config;
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "docker";
+
+#[cfg(windows)]
+fn get_docker_env(_ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    Err(SegmentError::not_applicable(
+        NAME,
+        "docker containers aren't detected on windows",
+    ))
+}
+
+/// Value of `key` in an os-release file, with the quotes taken off
+#[cfg(not(windows))]
+fn os_release_field<'a>(os_release: &'a str, key: &str) -> Option<&'a str> {
+    os_release.lines().find_map(|line| {
+        let value = line.trim().strip_prefix(key)?.strip_prefix('=')?;
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|&quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(value);
+        Some(value).filter(|value| !value.is_empty())
+    })
+}
+
+/// `debian 12` from the `ID` and `VERSION_ID` in `/etc/os-release`. Rolling
+/// releases have no `VERSION_ID`
+#[cfg(not(windows))]
+fn distro(ctx: &Context) -> Option<String> {
+    let os_release = ctx.read_to_string("/etc/os-release").ok()?;
+    let id = os_release_field(&os_release, "ID")?;
+    match os_release_field(&os_release, "VERSION_ID") {
+        Some(version) => Some(format!("{id} {version}")),
+        None => Some(id.to_string()),
+    }
+}
+
+/// Whether promptline runs inside a docker container
+#[cfg(not(windows))]
+pub(crate) fn in_container(ctx: &Context) -> bool {
+    ctx.exists("/.dockerenv")
+}
+
+#[cfg(not(windows))]
+fn get_docker_env(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    if !in_container(ctx) {
+        return Err(SegmentError::not_applicable(NAME, "not docker container"));
+    }
+
+    let show_distro = config::get_bool(&ctx.config.segment(NAME).options, "show_distro")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .unwrap_or(true);
+
+    super::with_icon(
+        ctx,
+        NAME,
+        "🐳",
+        "docker:",
+        distro(ctx).filter(|_| show_distro),
+    )
+}
+
+/// Marker shown inside docker containers, followed by the distro of the image
+/// unless `show_distro = false`
+pub struct Docker;
+
+impl Segment for Docker {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_docker_env(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        args::Args,
+        config::Config,
+        testing::{self, TempDir},
+    };
+
+    #[test]
+    fn in_container() {
+        let root = TempDir::new();
+        root.write(".dockerenv", "");
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        assert_eq!(get_docker_env(&ctx).unwrap().to_plain(), "🐳");
+    }
+
+    #[test]
+    fn not_in_container() {
+        let root = TempDir::new();
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        assert!(get_docker_env(&ctx).is_err());
+    }
+
+    #[test]
+    fn distro_after_whale() {
+        let root = TempDir::new();
+        root.write(".dockerenv", "");
+        root.write(
+            "etc/os-release",
+            "NAME=\"Alpine Linux\"\nID=alpine\nVERSION_ID=3.19.1\n",
+        );
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        assert_eq!(get_docker_env(&ctx).unwrap().to_plain(), "🐳 alpine 3.19.1");
+
+        let config = "[segments.docker]\nshow_distro = false\n";
+        let config = Config::from_table(&config::parse(config).unwrap()).unwrap();
+        let ctx = Context::with_env(Args::default(), config, Default::default(), None)
+            .with_root(root.path().to_path_buf());
+        assert_eq!(get_docker_env(&ctx).unwrap().to_plain(), "🐳");
+    }
+
+    #[test]
+    fn os_release_values() {
+        let os_release = "PRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\nVERSION_ID=\"12\"\nID=debian\nID_LIKE=''\n";
+        assert_eq!(os_release_field(os_release, "ID"), Some("debian"));
+        assert_eq!(os_release_field(os_release, "VERSION_ID"), Some("12"));
+        assert_eq!(os_release_field(os_release, "ID_LIKE"), None);
+        assert_eq!(os_release_field(os_release, "VERSION"), None);
+        assert_eq!(os_release_field("ID='arch'\n", "ID"), Some("arch"));
+    }
+}
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+
+use super::{json_string, json_value};
+use crate::{
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "docker_context";
+
+/// The context docker talks to the local daemon through
+const DEFAULT_CONTEXT: &str = "default";
+
+/// Where the docker CLI keeps its config, `$DOCKER_CONFIG` or `~/.docker`
+fn config_dir(ctx: &Context) -> Option<PathBuf> {
+    match ctx.var("DOCKER_CONFIG") {
+        Some(dir) => Some(dir.into()),
+        None => Some(Path::new(ctx.var("HOME")?).join(".docker")),
+    }
+}
+
+/// The `currentContext` of a docker `config.json`
+fn current_context(config: &str) -> Option<&str> {
+    json_string(json_value(config, "currentContext")?)
+}
+
+/// The daemon the docker CLI talks to, the way docker picks it: `$DOCKER_HOST`
+/// over `$DOCKER_CONTEXT` over the context last switched to
+fn target(ctx: &Context) -> Option<String> {
+    if let Some(host) = ctx.var("DOCKER_HOST") {
+        return Some(host.to_string());
+    }
+    if let Some(context) = ctx.var("DOCKER_CONTEXT") {
+        return Some(context.to_string());
+    }
+    let config = ctx
+        .read_to_string(config_dir(ctx)?.join("config.json"))
+        .ok()?;
+    current_context(&config).map(str::to_string)
+}
+
+fn get_docker_context(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let target = target(ctx)
+        .filter(|target| !target.is_empty() && target != DEFAULT_CONTEXT)
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "local docker daemon"))?;
+
+    let color = if target.contains("prod") {
+        Color::Red
+    } else {
+        Color::Yellow
+    };
+    let target = match super::icon(ctx, NAME, "docker⇢", "docker->")? {
+        Some(icon) => DecoratedString::new(target).with_icon(&icon),
+        None => DecoratedString::new(target),
+    };
+    Ok(target.colored(color))
+}
+
+/// `docker⇢ remote-builder` when the docker CLI talks to a daemon other than
+/// the local one, in red if its name has `prod` in it. Off unless
+/// `enabled = true`. Unrelated to the docker segment, which marks being
+/// inside of a container
+pub struct DockerContext;
+
+impl Segment for DockerContext {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_docker_context(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    const CONFIG: &str = r#"{
+	"auths": {
+		"ghcr.io": {}
+	},
+	"currentContext": "remote-builder"
+}"#;
+
+    fn shown(root: &TempDir, vars: &[(&str, &str)]) -> Result<String, SegmentError> {
+        let mut vars = vars.to_vec();
+        vars.push(("HOME", "/home/ada"));
+        let ctx = testing::context(&vars, Some("/")).with_root(root.path().to_path_buf());
+        get_docker_context(&ctx).map(|shown| shown.to_ansi())
+    }
+
+    #[test]
+    fn current_context_from_config() {
+        let root = TempDir::new();
+        root.write("home/ada/.docker/config.json", CONFIG);
+        assert_eq!(
+            shown(&root, &[]).unwrap(),
+            "\x1b[33mdocker⇢ remote-builder\x1b[39m"
+        );
+    }
+
+    #[test]
+    fn env_wins_over_config() {
+        let root = TempDir::new();
+        root.write("home/ada/.docker/config.json", CONFIG);
+        assert_eq!(
+            shown(&root, &[("DOCKER_CONTEXT", "prod-eu")]).unwrap(),
+            "\x1b[31mdocker⇢ prod-eu\x1b[39m"
+        );
+        let host = ("DOCKER_HOST", "ssh://deploy@ci");
+        let context = ("DOCKER_CONTEXT", "prod-eu");
+        assert_eq!(
+            shown(&root, &[host, context]).unwrap(),
+            "\x1b[33mdocker⇢ ssh://deploy@ci\x1b[39m"
+        );
+    }
+
+    #[test]
+    fn docker_config_dir() {
+        let root = TempDir::new();
+        root.write("etc/docker-cli/config.json", CONFIG);
+        let dir = ("DOCKER_CONFIG", "/etc/docker-cli");
+        assert!(shown(&root, &[dir]).unwrap().contains("remote-builder"));
+    }
+
+    #[test]
+    fn local_daemon() {
+        let root = TempDir::new();
+        assert!(shown(&root, &[]).is_err());
+
+        root.write("home/ada/.docker/config.json", "{\"auths\": {}}\n");
+        assert!(shown(&root, &[]).is_err());
+
+        root.write(
+            "home/ada/.docker/config.json",
+            "{\"currentContext\": \"default\"}\n",
+        );
+        assert!(shown(&root, &[]).is_err());
+        assert!(shown(&root, &[("DOCKER_CONTEXT", "default")]).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    config::{self, ConfigError, Table, Value},
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "env_watch";
+
+const DEFAULT_MAX_LEN: usize = 20;
+
+/// An entry of `vars`, either just the variable's name or a table with `name`
+/// and optionally `alias`, `max_len` and `mask`
+struct Watch {
+    name: String,
+    /// Shown in place of the name
+    alias: Option<String>,
+    max_len: usize,
+    /// Show only that the variable is set, for secrets
+    mask: bool,
+}
+
+fn parse_max_len(table: &Table) -> Result<Option<usize>, ConfigError> {
+    match config::get_int(table, "max_len")? {
+        Some(len) if len >= 0 => Ok(Some(len as usize)),
+        Some(_) => Err(ConfigError::invalid_field("max_len", "positive integer")),
+        None => Ok(None),
+    }
+}
+
+impl Watch {
+    fn from_value(value: &Value, default_max_len: usize) -> Result<Watch, ConfigError> {
+        let table = match value {
+            Value::String(name) => {
+                return Ok(Watch {
+                    name: name.clone(),
+                    alias: None,
+                    max_len: default_max_len,
+                    mask: false,
+                })
+            }
+            Value::Table(table) => table,
+            _ => return Err(ConfigError::invalid_field("vars", "string or table")),
+        };
+
+        Ok(Watch {
+            name: config::get_str(table, "name")?
+                .ok_or_else(|| ConfigError::invalid_field("name", "string"))?
+                .to_string(),
+            alias: config::get_str(table, "alias")?.map(str::to_string),
+            max_len: parse_max_len(table)?.unwrap_or(default_max_len),
+            mask: config::get_bool(table, "mask")?.unwrap_or(false),
+        })
+    }
+}
+
+fn watches(options: &Table) -> Result<Vec<Watch>, ConfigError> {
+    let default_max_len = parse_max_len(options)?.unwrap_or(DEFAULT_MAX_LEN);
+    match options.get("vars") {
+        None => Ok(vec![]),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| Watch::from_value(item, default_max_len))
+            .collect(),
+        Some(_) => Err(ConfigError::invalid_field("vars", "array")),
+    }
+}
+
+/// Shortens `value` to `max_len` chars by replacing its middle with `…`, so
+/// that both the start and the end stay recognizable
+fn ellipsize_middle(value: &str, max_len: usize) -> String {
+    let len = value.chars().count();
+    if len <= max_len {
+        return value.to_string();
+    }
+    if max_len == 0 {
+        return String::new();
+    }
+
+    let tail = (max_len - 1) / 2;
+    let head = max_len - 1 - tail;
+    let start: String = value.chars().take(head).collect();
+    let end: String = value.chars().skip(len - tail).collect();
+    format!("{start}…{end}")
+}
+
+fn get_env_watch(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let watches = watches(&ctx.config.segment(NAME).options)
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?;
+    if watches.is_empty() {
+        return Err(SegmentError::not_applicable(NAME, "no variables to watch"));
+    }
+
+    let shown: Vec<_> = watches
+        .iter()
+        .filter_map(|watch| {
+            let value = ctx.var(&watch.name)?;
+            let label = watch.alias.as_deref().unwrap_or(&watch.name);
+            let value = if watch.mask {
+                "set".to_string()
+            } else {
+                ellipsize_middle(value, watch.max_len)
+            };
+            Some(format!("{label}={value}"))
+        })
+        .collect();
+
+    if shown.is_empty() {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "none of the watched variables are set",
+        ));
+    }
+
+    Ok(DecoratedString::new(shown.join(" ")).colored(Color::Cyan))
+}
+
+/// The variables listed in `vars` that are set, with their values
+pub struct EnvWatch;
+
+impl Segment for EnvWatch {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_env_watch(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{args::Args, config::Config};
+
+    fn context(vars: &[(&str, &str)], config: &str) -> Context {
+        let config = Config::from_table(&config::parse(config).unwrap()).unwrap();
+        let env: HashMap<_, _> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Context::with_env(Args::default(), config, env, None)
+    }
+
+    #[test]
+    fn shows_only_set_variables() {
+        let ctx = context(
+            &[("KUBECONFIG", "dev.yaml")],
+            "[segments.env_watch]\nvars = [\"RUSTFLAGS\", \"KUBECONFIG\"]\n",
+        );
+        assert_eq!(
+            get_env_watch(&ctx).unwrap().to_plain(),
+            "KUBECONFIG=dev.yaml"
+        );
+
+        let ctx = context(&[], "[segments.env_watch]\nvars = [\"RUSTFLAGS\"]\n");
+        assert!(get_env_watch(&ctx).is_err());
+    }
+
+    #[test]
+    fn aliases_truncation_and_masks() {
+        let config = r#"
+[segments.env_watch]
+max_len = 9
+vars = [
+    { name = "RUSTFLAGS", alias = "rf" },
+    "KUBECONFIG",
+    { name = "AWS_VAULT", mask = true },
+    { name = "EDITOR", max_len = 1 },
+]
+"#;
+        let ctx = context(
+            &[
+                ("RUSTFLAGS", "-C target-cpu=native"),
+                ("KUBECONFIG", "dev.yaml"),
+                ("AWS_VAULT", "prod-admin"),
+                ("EDITOR", "vim"),
+            ],
+            config,
+        );
+        assert_eq!(
+            get_env_watch(&ctx).unwrap().to_plain(),
+            "rf=-C t…tive KUBECONFIG=dev.yaml AWS_VAULT=set EDITOR=…"
+        );
+    }
+
+    #[test]
+    fn ellipsizes_middle() {
+        assert_eq!(ellipsize_middle("abcdef", 6), "abcdef");
+        assert_eq!(ellipsize_middle("abcdefg", 6), "abc…fg");
+        assert_eq!(ellipsize_middle("abcdefg", 1), "…");
+        assert_eq!(ellipsize_middle("abcdefg", 0), "");
+    }
+
+    #[test]
+    fn rejects_entries_without_name() {
+        let ctx = context(&[], "[segments.env_watch]\nvars = [{ alias = \"x\" }]\n");
+        assert!(get_env_watch(&ctx).is_err());
+    }
+}
@@ -0,0 +1,770 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    cache, config, custom,
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "git";
+
+/// How long `git status` gets to count the changed files
+const STATUS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long `git status` gets to tell whether the work tree has changes for
+/// the prompt char, which holds up the whole prompt
+const DIRTY_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// How long `promptline prefetch` gets to count the changed files, so that it
+/// never outlives the directory change that started it by more than that
+const PREFETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long the counts of changed files are reused, so a burst of prompts,
+/// e.g. from holding down enter, runs git once
+const STATUS_TTL: Duration = Duration::from_secs(2);
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A line of a reflog, as in `.git/logs/HEAD`
+#[derive(Debug, PartialEq)]
+pub(crate) struct ReflogEntry<'a> {
+    pub(crate) old: &'a str,
+    pub(crate) new: &'a str,
+    /// Name and email, as `Jane Doe <jane@example.com>`
+    pub(crate) author: &'a str,
+    /// Seconds since the unix epoch
+    pub(crate) time: i64,
+    /// Offset of the author's timezone, as `+0100`
+    pub(crate) tz: &'a str,
+    pub(crate) message: &'a str,
+}
+
+/// Parses a reflog line, `<old> <new> <author> <time> <tz>\t<message>`.
+/// `None` if it isn't one
+pub(crate) fn parse_reflog_line(line: &str) -> Option<ReflogEntry<'_>> {
+    let (entry, message) = match line.split_once('\t') {
+        Some((entry, message)) => (entry, message),
+        None => (line, ""),
+    };
+    let (old, rest) = entry.split_once(' ')?;
+    let (new, rest) = rest.split_once(' ')?;
+    // The author can have spaces in it, the time and tz can't
+    let (rest, tz) = rest.rsplit_once(' ')?;
+    let (author, time) = rest.rsplit_once(' ')?;
+    Some(ReflogEntry {
+        old,
+        new,
+        author,
+        time: time.parse().ok()?,
+        tz,
+        message,
+    })
+}
+
+/// Whether the last entry of the reflog of `ref_path` is more than `days`
+/// old. A ref without a reflog isn't
+fn is_stale(git_dir: &Path, ref_path: &Path, days: u64, now: SystemTime) -> bool {
+    let log = match fs::read_to_string(git_dir.join("logs").join(ref_path)) {
+        Ok(log) => log,
+        Err(_) => return false,
+    };
+    let last = match log.lines().rev().find_map(parse_reflog_line) {
+        Some(last) => last,
+        None => return false,
+    };
+    let last = UNIX_EPOCH + Duration::from_secs(last.time.max(0) as u64);
+    now.duration_since(last)
+        .is_ok_and(|age| age > Duration::from_secs(days * SECS_PER_DAY))
+}
+
+/// Lines of a rebase or sequencer todo file that are steps, leaving out
+/// comments and blank lines
+fn todo_steps(todo: &str) -> impl Iterator<Item = &str> {
+    todo.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// The full name of the command a todo step starts with
+fn step_command(step: &str) -> &str {
+    let command = step.split_whitespace().next().unwrap_or(step);
+    match command {
+        "p" => "pick",
+        "r" => "reword",
+        "e" => "edit",
+        "s" => "squash",
+        "f" => "fixup",
+        "x" => "exec",
+        "b" => "break",
+        "d" => "drop",
+        "l" => "label",
+        "t" => "reset",
+        "m" => "merge",
+        command => command,
+    }
+}
+
+/// How far along an interactive rebase or a cherry-pick or revert of several
+/// commits is, as `pick 4/12` or `revert 3 left`. `None` if there is none or
+/// it can't be counted
+fn operation_progress(git_dir: &Path) -> Option<String> {
+    let rebase = git_dir.join("rebase-merge");
+    if let Ok(done) = fs::read_to_string(rebase.join("done")) {
+        let todo = fs::read_to_string(rebase.join("git-rebase-todo")).ok()?;
+        // The step being carried out has already moved to `done`
+        let current = todo_steps(&done).last()?;
+        let done = todo_steps(&done).count();
+        let total = done + todo_steps(&todo).count();
+        return Some(format!("{} {done}/{total}", step_command(current)));
+    }
+
+    // Only what's left is kept for a sequence, including the current commit
+    let todo = fs::read_to_string(git_dir.join("sequencer/todo")).ok()?;
+    let next = todo_steps(&todo).next()?;
+    let command = match step_command(next) {
+        "pick" => "cherry-pick",
+        command => command,
+    };
+    Some(format!("{command} {} left", todo_steps(&todo).count()))
+}
+
+/// Number of files in each state, as `git status` reports them
+#[derive(Debug, Default, PartialEq)]
+struct StatusCounts {
+    staged: usize,
+    modified: usize,
+    deleted: usize,
+    untracked: usize,
+}
+
+/// Counts of the lines of `git status --porcelain`. A file both staged and
+/// changed again since is counted in both
+fn parse_porcelain(output: &str) -> StatusCounts {
+    let mut counts = StatusCounts::default();
+    for line in output.lines() {
+        let mut codes = line.chars();
+        let (index, work_tree) = match (codes.next(), codes.next()) {
+            (Some(index), Some(work_tree)) => (index, work_tree),
+            _ => continue,
+        };
+        match (index, work_tree) {
+            ('?', '?') => counts.untracked += 1,
+            ('!', '!') => (),
+            (index, work_tree) => {
+                if index != ' ' {
+                    counts.staged += 1;
+                }
+                match work_tree {
+                    'D' => counts.deleted += 1,
+                    ' ' => (),
+                    _ => counts.modified += 1,
+                }
+            }
+        }
+    }
+    counts
+}
+
+/// `+3 ~2 -1 ?4` for staged, modified, deleted and untracked files, leaving
+/// out the states no file is in. `None` for a clean work tree
+fn format_counts(counts: &StatusCounts) -> Option<DecoratedString> {
+    let parts: Vec<_> = [
+        (counts.staged, '+', Color::Green),
+        (counts.modified, '~', Color::Yellow),
+        (counts.deleted, '-', Color::Red),
+        (counts.untracked, '?', Color::Cyan),
+    ]
+    .iter()
+    .filter(|(count, ..)| *count > 0)
+    .map(|&(count, sigil, color)| DecoratedString::new(format!("{sigil}{count}")).colored(color))
+    .collect();
+
+    (!parts.is_empty()).then(|| DecoratedString::join(parts, " "))
+}
+
+/// Counts of the changed files in the work tree at `repo`, `None` if `git`
+/// couldn't tell within `timeout`
+fn status_counts(repo: &Path, timeout: Duration) -> Option<StatusCounts> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(repo).args(["status", "--porcelain"]);
+    let output = custom::run_with_timeout(command, timeout).ok()?;
+    output
+        .status
+        .success()
+        .then(|| parse_porcelain(&output.stdout))
+}
+
+/// Whether `dir` is a git dir itself, like a `.git` or a bare repo
+fn is_git_dir(dir: &Path) -> bool {
+    dir.join("HEAD").is_file() && dir.join("refs").is_dir() && dir.join("objects").is_dir()
+}
+
+/// The git dir the cwd is inside of, e.g. in `.git/hooks`. Only the
+/// directories below the enclosing work tree are checked, if there is one
+fn git_dir_above_cwd(ctx: &Context) -> Option<PathBuf> {
+    let cwd = ctx.path(ctx.cwd()?);
+    cwd.ancestors()
+        .take_while(|dir| Some(*dir) != ctx.git_root())
+        .find(|dir| is_git_dir(dir))
+        .map(Path::to_path_buf)
+}
+
+/// The git dir of the repo the cwd is in, following the link in a `.git` file,
+/// and whether the cwd is inside of the git dir rather than the work tree.
+/// Errors are reported as coming from `segment`
+fn locate_git_dir(ctx: &Context, segment: &str) -> Result<(PathBuf, bool), SegmentError> {
+    ctx.cwd()
+        .ok_or_else(|| SegmentError::failed(segment, "failed to get cwd"))?;
+    if let Some(git_dir) = git_dir_above_cwd(ctx) {
+        return Ok((git_dir, true));
+    }
+
+    let repo = ctx
+        .git_root()
+        .ok_or_else(|| SegmentError::not_applicable(segment, "not a git repo"))?;
+
+    // if .git has gitdir:.... we have to follow the link
+
+    let mut git_dir = repo.join(".git");
+    if git_dir.is_file() {
+        let git_content = fs::read_to_string(git_dir).map_err(|e| {
+            SegmentError::failed(segment, "failed to read .git file").with_source(e)
+        })?;
+
+        const PREFIX: &str = "gitdir: ";
+
+        match git_content.strip_prefix(PREFIX) {
+            Some(v) => git_dir = v.trim().into(),
+            None => return Err(SegmentError::failed(segment, "unexpected git content")),
+        }
+    }
+
+    Ok((git_dir, false))
+}
+
+/// The git dir of the repo the cwd is in, whether the cwd is in its work tree
+/// or the git dir itself. Errors are reported as coming from `segment`
+#[cfg(any(feature = "unpushed", feature = "repo-size"))]
+pub(crate) fn git_dir(ctx: &Context, segment: &str) -> Result<PathBuf, SegmentError> {
+    locate_git_dir(ctx, segment).map(|(git_dir, _)| git_dir)
+}
+
+/// Where the counts of changed files in the work tree at `repo` are kept
+fn status_cache_path(ctx: &Context, repo: &Path) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    repo.hash(&mut hasher);
+    Some(
+        ctx.cache_dir()?
+            .join(format!("status-{:016x}", hasher.finish())),
+    )
+}
+
+/// The counts as a line of a cache file, `staged modified deleted untracked`
+fn format_cached(counts: &StatusCounts) -> String {
+    let StatusCounts {
+        staged,
+        modified,
+        deleted,
+        untracked,
+    } = counts;
+    format!("{staged} {modified} {deleted} {untracked}")
+}
+
+fn parse_cached(cached: &str) -> Option<StatusCounts> {
+    let mut counts = cached.split(' ').map(|count| count.parse().ok());
+    let parsed = StatusCounts {
+        staged: counts.next()??,
+        modified: counts.next()??,
+        deleted: counts.next()??,
+        untracked: counts.next()??,
+    };
+    counts.next().is_none().then_some(parsed)
+}
+
+/// Counts of the changed files in the work tree at `repo`, from the cache if
+/// it was written in the last `STATUS_TTL`, or else from `git status` given
+/// `timeout`
+fn cached_status_counts(ctx: &Context, repo: &Path, timeout: Duration) -> Option<StatusCounts> {
+    let cache_path = status_cache_path(ctx, repo);
+    let cached = cache_path
+        .as_ref()
+        .and_then(|path| cache::read(path, STATUS_TTL))
+        .and_then(|cached| parse_cached(&cached));
+    if cached.is_some() {
+        return cached;
+    }
+
+    let counts = status_counts(repo, timeout)?;
+    if let Some(path) = &cache_path {
+        let _ = cache::write(path, &format_cached(&counts));
+    }
+    Some(counts)
+}
+
+/// The work tree the cwd is in, `None` in the git dir or outside of a repo
+fn work_tree(ctx: &Context) -> Option<&Path> {
+    let (_, in_git_dir) = locate_git_dir(ctx, NAME).ok()?;
+    if in_git_dir {
+        return None;
+    }
+    ctx.git_root()
+}
+
+/// Counts the changed files of the work tree the cwd is in and caches them,
+/// so that the prompt after a directory change finds them there. Nothing is
+/// done outside of a work tree, or on a network filesystem where the prompt
+/// doesn't count them either. Whether the counts were cached
+pub fn prefetch(ctx: &Context) -> bool {
+    if ctx.network_fs {
+        return false;
+    }
+    let repo = match work_tree(ctx) {
+        Some(repo) => repo,
+        None => return false,
+    };
+    // Without a cache there is nowhere to keep the counts
+    let cache_path = match status_cache_path(ctx, repo) {
+        Some(path) => path,
+        None => return false,
+    };
+    match status_counts(repo, PREFETCH_TIMEOUT) {
+        Some(counts) => {
+            let _ = cache::write(&cache_path, &format_cached(&counts));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Whether the work tree the cwd is in has changes, whether or not the git
+/// segment is enabled. `None` outside of a work tree, on a network filesystem,
+/// or if git couldn't tell in time. The answer is reused for a couple of
+/// seconds
+pub(crate) fn is_dirty(ctx: &Context) -> Option<bool> {
+    if ctx.network_fs {
+        return None;
+    }
+    let repo = work_tree(ctx)?;
+    let counts = cached_status_counts(ctx, repo, DIRTY_TIMEOUT)?;
+    Some(counts != StatusCounts::default())
+}
+
+fn get_git_info(ctx: &Context, now: SystemTime) -> Result<DecoratedString, SegmentError> {
+    let stale_days = config::get_int(&ctx.config.segment(NAME).options, "stale_days")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?;
+    let (git_dir, in_git_dir) = locate_git_dir(ctx, NAME)?;
+
+    let head_content = fs::read_to_string(git_dir.join("HEAD"))
+        .map_err(|e| SegmentError::failed(NAME, "failed to read git HEAD").with_source(e))?;
+
+    const REF_PREFIX: &str = "ref: ";
+    let mut color = Color::Green;
+    let output = match head_content.strip_prefix(REF_PREFIX) {
+        Some(refs_path) => {
+            let refs_path = Path::new(refs_path.trim());
+
+            let commit_hash = fs::read_to_string(git_dir.join(refs_path))
+                .map_err(|e| SegmentError::failed(NAME, "failed to read ref").with_source(e))?;
+
+            let short_hash = &commit_hash[..14];
+            let ref_name = refs_path
+                .file_name()
+                .ok_or_else(|| SegmentError::failed(NAME, "failed to get ref name"))?
+                .to_string_lossy();
+
+            let stale = stale_days
+                .is_some_and(|days| is_stale(&git_dir, refs_path, days.max(0) as u64, now));
+            if stale {
+                color = Color::Yellow;
+            }
+
+            format!("{ref_name} {short_hash}")
+        }
+        None => head_content[..14].to_string(),
+    };
+
+    let branch = match super::icon(ctx, NAME, "\u{e0a0}", "git:")? {
+        Some(icon) => DecoratedString::new(output).with_icon(&icon),
+        None => DecoratedString::new(output),
+    };
+    let mut branch = branch.colored(color).bold();
+    if let Some(progress) = operation_progress(&git_dir) {
+        branch = branch.with_suffix(DecoratedString::new(progress).colored(Color::Yellow));
+    }
+    if in_git_dir {
+        return Ok(branch.with_suffix(DecoratedString::new("in .git".into()).dim()));
+    }
+
+    // Counting stats every file of the work tree, a round trip each on a
+    // network filesystem
+    let counts = match (ctx.var("PROMPTLINE_GIT_COUNTS"), ctx.git_root()) {
+        (Some("1"), Some(repo)) if !ctx.network_fs => {
+            cached_status_counts(ctx, repo, STATUS_TIMEOUT)
+        }
+        _ => None,
+    };
+    match counts.as_ref().and_then(format_counts) {
+        Some(counts) => Ok(branch.with_suffix(counts)),
+        None => Ok(branch),
+    }
+}
+
+/// Checked out branch and commit of the enclosing git repo, marked `in .git`
+/// when the cwd is inside of the git dir. With `$PROMPTLINE_GIT_COUNTS` set
+/// to 1 the changed files are counted too, as `+3 ~2 -1 ?4` for staged,
+/// modified, deleted and untracked. With `stale_days` set, a branch without a
+/// commit in that many days is shown in yellow. An unfinished interactive
+/// rebase or sequence of cherry-picks shows how far along it is
+pub struct Git;
+
+impl Segment for Git {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_git_info(ctx, SystemTime::now()).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{self, Config},
+        testing::{self, TempDir},
+    };
+
+    const HASH: &str = "0123456789abcdef0123456789abcdef01234567\n";
+
+    fn repo(git_dir: &str) -> TempDir {
+        let root = TempDir::new();
+        root.write(&format!("{git_dir}/HEAD"), "ref: refs/heads/main\n");
+        root.write(&format!("{git_dir}/refs/heads/main"), HASH);
+        root.write(&format!("{git_dir}/objects/info/packs"), "");
+        root
+    }
+
+    fn shown_with(root: &TempDir, cwd: &str, toml: &str) -> String {
+        let mut ctx = testing::context(&[], Some(cwd)).with_root(root.path().to_path_buf());
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        get_git_info(&ctx, SystemTime::now()).unwrap().to_plain()
+    }
+
+    fn shown(root: &TempDir, cwd: &str) -> String {
+        shown_with(root, cwd, "")
+    }
+
+    #[test]
+    fn work_tree() {
+        let root = repo("src/app/.git");
+        root.write("src/app/lib/mod.rs", "");
+        assert_eq!(shown(&root, "/src/app/lib"), "\u{e0a0} main 0123456789abcd");
+    }
+
+    #[test]
+    fn inside_git_dir() {
+        let root = repo("src/app/.git");
+        assert_eq!(
+            shown(&root, "/src/app/.git/refs"),
+            "\u{e0a0} main 0123456789abcd in .git"
+        );
+        assert_eq!(
+            shown(&root, "/src/app/.git"),
+            "\u{e0a0} main 0123456789abcd in .git"
+        );
+    }
+
+    #[test]
+    fn bare_repo() {
+        let root = repo("srv/app.git");
+        assert_eq!(
+            shown(&root, "/srv/app.git/refs/heads"),
+            "\u{e0a0} main 0123456789abcd in .git"
+        );
+    }
+
+    #[test]
+    fn labels() {
+        let root = repo("src/app/.git");
+        assert_eq!(
+            shown_with(&root, "/src/app", "icons = \"ascii\"\n"),
+            "git: main 0123456789abcd"
+        );
+        assert_eq!(
+            shown_with(
+                &root,
+                "/src/app",
+                "icons = \"ascii\"\n[segments.git]\nicon = \"\"\n"
+            ),
+            "main 0123456789abcd"
+        );
+        assert_eq!(
+            shown_with(&root, "/src/app", "[segments.git]\nicon = \"on\"\n"),
+            "on main 0123456789abcd"
+        );
+    }
+
+    #[test]
+    fn mid_rebase() {
+        let root = repo("src/app/.git");
+        root.write(
+            "src/app/.git/rebase-merge/done",
+            "pick 1111111 First\npick 2222222 Second\n# comment\n\nexec make test\n",
+        );
+        root.write(
+            "src/app/.git/rebase-merge/git-rebase-todo",
+            "pick 3333333 Third\ns 4444444 Fourth\n\n# Rebase 0123456..4444444 onto 0123456 (4 commands)\n#\n# Commands:\n# p, pick <commit> = use commit\n",
+        );
+        assert_eq!(
+            shown(&root, "/src/app"),
+            "\u{e0a0} main 0123456789abcd exec 3/5"
+        );
+
+        // Nothing left to count from
+        root.write("src/app/.git/rebase-merge/done", "# nothing yet\n");
+        assert_eq!(shown(&root, "/src/app"), "\u{e0a0} main 0123456789abcd");
+    }
+
+    #[test]
+    fn mid_cherry_pick() {
+        let root = repo("src/app/.git");
+        root.write(
+            "src/app/.git/sequencer/todo",
+            "pick 1111111 First\npick 2222222 Second\npick 3333333 Third\n",
+        );
+        assert_eq!(
+            shown(&root, "/src/app"),
+            "\u{e0a0} main 0123456789abcd cherry-pick 3 left"
+        );
+
+        root.write("src/app/.git/sequencer/todo", "revert 1111111 First\n");
+        assert_eq!(
+            shown(&root, "/src/app/.git"),
+            "\u{e0a0} main 0123456789abcd revert 1 left in .git"
+        );
+    }
+
+    const DAY: Duration = Duration::from_secs(SECS_PER_DAY);
+
+    fn reflog_line(time: SystemTime) -> String {
+        let time = time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        format!(
+            "{0} {0} Jane Doe <jane@example.com> {time} +0100\tcommit: Fix it\n",
+            &HASH[..40]
+        )
+    }
+
+    #[test]
+    fn stale_branch() {
+        let root = repo("src/app/.git");
+        let now = SystemTime::now();
+        let log = reflog_line(now - DAY * 40) + &reflog_line(now - DAY * 31);
+        root.write("src/app/.git/logs/refs/heads/main", &log);
+
+        let color = |toml: &str, now| {
+            let mut ctx =
+                testing::context(&[], Some("/src/app")).with_root(root.path().to_path_buf());
+            ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+            let shown = get_git_info(&ctx, now).unwrap().to_ansi();
+            if shown.contains("\x1b[33m") {
+                Color::Yellow
+            } else {
+                assert!(shown.contains("\x1b[32m"), "{}", shown);
+                Color::Green
+            }
+        };
+        let stale_days = "[segments.git]\nstale_days = 30\n";
+        assert_eq!(color(stale_days, now), Color::Yellow);
+        assert_eq!(color(stale_days, now - DAY * 2), Color::Green);
+        // Off unless asked for
+        assert_eq!(color("", now), Color::Green);
+
+        // Nothing to tell the age from
+        root.write("src/app/.git/logs/refs/heads/main", "");
+        assert_eq!(color(stale_days, now), Color::Green);
+    }
+
+    #[test]
+    fn parses_reflog_lines() {
+        let line = "0000000000000000000000000000000000000000 0123456789abcdef0123456789abcdef01234567 \
+                    Jane Doe <jane@example.com> 1700000000 -0500\tclone: from https://example.com/app";
+        assert_eq!(
+            parse_reflog_line(line),
+            Some(ReflogEntry {
+                old: "0000000000000000000000000000000000000000",
+                new: "0123456789abcdef0123456789abcdef01234567",
+                author: "Jane Doe <jane@example.com>",
+                time: 1_700_000_000,
+                tz: "-0500",
+                message: "clone: from https://example.com/app",
+            })
+        );
+
+        let no_message = parse_reflog_line("a b Jane <j@x> 12 +0000").unwrap();
+        assert_eq!((no_message.time, no_message.message), (12, ""));
+        assert_eq!(parse_reflog_line(""), None);
+        assert_eq!(
+            parse_reflog_line("a b Jane <j@x> yesterday +0000\tmsg"),
+            None
+        );
+    }
+
+    fn counts(staged: usize, modified: usize, deleted: usize, untracked: usize) -> StatusCounts {
+        StatusCounts {
+            staged,
+            modified,
+            deleted,
+            untracked,
+        }
+    }
+
+    #[test]
+    fn formats_counts() {
+        let shown = |counts| format_counts(&counts).map(|counts| counts.to_plain());
+        assert_eq!(shown(counts(3, 2, 1, 4)).as_deref(), Some("+3 ~2 -1 ?4"));
+        assert_eq!(shown(counts(0, 2, 0, 4)).as_deref(), Some("~2 ?4"));
+        assert_eq!(shown(counts(0, 0, 0, 0)), None);
+        assert_eq!(
+            format_counts(&counts(1, 0, 1, 0)).unwrap().to_ansi(),
+            "\x1b[32m+1\x1b[39m \x1b[31m-1\x1b[39m"
+        );
+    }
+
+    #[test]
+    fn parses_porcelain() {
+        let output = "M  staged.rs\nMM both.rs\n M changed.rs\n D gone.rs\nD  removed.rs\n\
+                      A  new.rs\n?? notes.txt\n?? tmp/\n!! target/\n";
+        assert_eq!(parse_porcelain(output), counts(4, 2, 1, 2));
+        assert_eq!(parse_porcelain(""), counts(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn dirty_from_cache() {
+        let root = repo("work/.git");
+        root.write("work/src/main.rs", "");
+        let vars = [("XDG_RUNTIME_DIR", "/run/user/1000")];
+        let ctx = testing::context(&vars, Some("/work/src")).with_root(root.path().to_path_buf());
+        let cache = status_cache_path(&ctx, ctx.git_root().unwrap()).unwrap();
+
+        cache::write(&cache, "0 1 0 0").unwrap();
+        assert_eq!(is_dirty(&ctx), Some(true));
+        cache::write(&cache, "0 0 0 0").unwrap();
+        assert_eq!(is_dirty(&ctx), Some(false));
+
+        // Not even asked on a network filesystem
+        let mut ctx =
+            testing::context(&vars, Some("/work/src")).with_root(root.path().to_path_buf());
+        ctx.network_fs = true;
+        assert_eq!(is_dirty(&ctx), None);
+
+        // Only the work tree has changes
+        let ctx = testing::context(&vars, Some("/work/.git")).with_root(root.path().to_path_buf());
+        assert_eq!(is_dirty(&ctx), None);
+        let ctx = testing::context(&vars, Some("/")).with_root(root.path().to_path_buf());
+        assert_eq!(is_dirty(&ctx), None);
+    }
+
+    #[test]
+    fn counts_in_any_path() {
+        let root = TempDir::new();
+        let repo = root.path().join("a \"b\" $c `d` \\e %f");
+        fs::create_dir_all(&repo).unwrap();
+        // Only where git is installed
+        let init = Command::new("git").args(["init", "-q"]).arg(&repo).status();
+        if !matches!(init, Ok(status) if status.success()) {
+            return;
+        }
+        fs::write(repo.join("notes.txt"), "").unwrap();
+        assert_eq!(
+            status_counts(&repo, Duration::from_secs(5)),
+            Some(counts(0, 0, 0, 1))
+        );
+    }
+
+    #[test]
+    fn cached_counts() {
+        let counts = counts(3, 2, 1, 4);
+        assert_eq!(format_cached(&counts), "3 2 1 4");
+        assert_eq!(parse_cached("3 2 1 4"), Some(counts));
+        assert_eq!(parse_cached("3 2 1"), None);
+        assert_eq!(parse_cached("3 2 1 4 5"), None);
+        assert_eq!(parse_cached("1"), None);
+    }
+
+    #[test]
+    fn counts_from_warm_cache() {
+        let root = repo("work/.git");
+        let vars = [
+            ("XDG_RUNTIME_DIR", "/run/user/1000"),
+            ("PROMPTLINE_GIT_COUNTS", "1"),
+        ];
+        let ctx = testing::context(&vars, Some("/work")).with_root(root.path().to_path_buf());
+        let cache = status_cache_path(&ctx, ctx.git_root().unwrap()).unwrap();
+        cache::write(&cache, "1 0 0 2").unwrap();
+        assert_eq!(
+            get_git_info(&ctx, SystemTime::now()).unwrap().to_plain(),
+            format!("\u{e0a0} main {} +1 ?2", &HASH[..14])
+        );
+    }
+
+    #[test]
+    fn no_counts_on_network_fs() {
+        let root = repo("work/.git");
+        let vars = [
+            ("XDG_RUNTIME_DIR", "/run/user/1000"),
+            ("PROMPTLINE_GIT_COUNTS", "1"),
+        ];
+        let mut ctx = testing::context(&vars, Some("/work")).with_root(root.path().to_path_buf());
+        ctx.network_fs = true;
+        // Left out altogether, rather than shown only while they are cached
+        let cache = status_cache_path(&ctx, ctx.git_root().unwrap()).unwrap();
+        cache::write(&cache, "1 0 0 2").unwrap();
+        assert_eq!(
+            get_git_info(&ctx, SystemTime::now()).unwrap().to_plain(),
+            format!("\u{e0a0} main {}", &HASH[..14])
+        );
+    }
+
+    #[test]
+    fn prefetch_only_in_a_work_tree() {
+        let root = repo("work/.git");
+        let vars = [("XDG_RUNTIME_DIR", "/run/user/1000")];
+        for cwd in ["/", "/work/.git"] {
+            let ctx = testing::context(&vars, Some(cwd)).with_root(root.path().to_path_buf());
+            assert!(!prefetch(&ctx));
+        }
+        // No counts were cached for the next prompt to find
+        let cached = fs::read_dir(root.path().join("run/user/1000/promptline"))
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .any(|entry| entry.file_name().to_string_lossy().starts_with("status-"));
+        assert!(!cached);
+
+        let ctx = testing::context(&[], Some("/work")).with_root(root.path().to_path_buf());
+        assert!(!prefetch(&ctx));
+    }
+
+    #[test]
+    fn no_prefetch_on_network_fs() {
+        let root = repo("work/.git");
+        let vars = [("XDG_RUNTIME_DIR", "/run/user/1000")];
+        let mut ctx = testing::context(&vars, Some("/work")).with_root(root.path().to_path_buf());
+        ctx.network_fs = true;
+        assert!(!prefetch(&ctx));
+        assert!(!root.path().join("run/user/1000/promptline").exists());
+    }
+}
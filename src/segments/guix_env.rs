@@ -0,0 +1,56 @@
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "guix_env";
+
+#[cfg(windows)]
+fn get_guix_env(_ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    Err(SegmentError::not_applicable(
+        NAME,
+        "guix doesn't run on windows",
+    ))
+}
+
+/// `guix shell` and the older `guix environment` both set
+/// `GUIX_ENVIRONMENT` to the profile of the environment
+#[cfg(not(windows))]
+fn get_guix_env(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    ctx.var("GUIX_ENVIRONMENT")
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "not in guix environment"))?;
+
+    Ok(DecoratedString::new("guix env".into()).bold())
+}
+
+/// Shown inside `guix shell` and `guix environment`
+pub struct GuixEnv;
+
+impl Segment for GuixEnv {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_guix_env(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn in_guix_environment() {
+        let ctx = testing::context(&[("GUIX_ENVIRONMENT", "/gnu/store/abc-profile")], None);
+        assert_eq!(get_guix_env(&ctx).unwrap().to_plain(), "guix env");
+    }
+
+    #[test]
+    fn not_in_guix_environment() {
+        let ctx = testing::context(&[], None);
+        assert!(get_guix_env(&ctx).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::{
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "hg";
+
+/// The two parents at the start of a dirstate
+const DIRSTATE_HEADER: usize = 40;
+/// State, mode, size, mtime and name length of a dirstate entry
+const DIRSTATE_ENTRY: usize = 17;
+/// Size recorded for files merged in from the other parent
+const FROM_OTHER_PARENT: i32 = -2;
+
+/// What the working copy looks like, for the color of the segment
+#[derive(Debug, PartialEq)]
+enum WorkingCopy {
+    Clean,
+    /// Files were added, removed or changed
+    Dirty,
+    /// A merge is in progress, with this many files still unresolved
+    Merging(usize),
+    /// The dirstate is in a format this doesn't read, like dirstate-v2
+    Unknown,
+}
+
+/// Whether a v1 dirstate shows pending changes: files added, removed or
+/// merged, or tracked files whose size changed or that are gone. Files that
+/// only have a new mtime may be unchanged and are left to `hg status`.
+/// `None` if the dirstate isn't v1
+fn dirstate_dirty(hg_root: &Path, dirstate: &[u8]) -> Option<bool> {
+    if dirstate.starts_with(b"dirstate-v2") {
+        return None;
+    }
+
+    let be_i32 = |bytes: &[u8]| i32::from_be_bytes(bytes.try_into().expect("4 bytes"));
+    let mut entries = dirstate.get(DIRSTATE_HEADER..)?;
+    while !entries.is_empty() {
+        let entry = entries.get(..DIRSTATE_ENTRY)?;
+        let size = be_i32(&entry[5..9]);
+        let len = usize::try_from(be_i32(&entry[13..17])).ok()?;
+        let name = entries.get(DIRSTATE_ENTRY..DIRSTATE_ENTRY + len)?;
+        entries = &entries[DIRSTATE_ENTRY + len..];
+
+        match entry[0] {
+            b'n' if size == FROM_OTHER_PARENT => return Some(true),
+            // A negative size asks hg to look at the file itself
+            b'n' if size < 0 => {}
+            b'n' => {
+                // Copies are recorded as `name\0source`
+                let name = name.split(|&b| b == 0).next().unwrap_or(name);
+                let path = hg_root.join(String::from_utf8_lossy(name).as_ref());
+                match fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.len() & 0x7fff_ffff == size as u64 => {}
+                    _ => return Some(true),
+                }
+            }
+            _ => return Some(true),
+        }
+    }
+    Some(false)
+}
+
+/// Files that `hg resolve` still lists as unresolved, from the `F` (file),
+/// `C` (change/delete) and `P` (path conflict) records of a merge state.
+/// Each record is the file followed by its state, `u` or `pu` when
+/// unresolved, separated by NULs
+fn count_unresolved<'a>(records: impl Iterator<Item = &'a [u8]>) -> usize {
+    records
+        .filter(|record| {
+            let mut fields = record.split(|&b| b == 0);
+            matches!(fields.nth(1), Some(b"u") | Some(b"pu"))
+        })
+        .count()
+}
+
+/// Records of a `.hg/merge/state2`, each a type byte, a big-endian length
+/// and that many bytes of data
+fn state2_records(state: &[u8]) -> Vec<&[u8]> {
+    let mut records = vec![];
+    let mut rest = state;
+    while rest.len() >= 5 {
+        let len = u32::from_be_bytes(rest[1..5].try_into().expect("4 bytes")) as usize;
+        let data = match rest.get(5..5 + len) {
+            Some(data) => data,
+            None => break,
+        };
+        if matches!(rest[0], b'F' | b'C' | b'P') {
+            records.push(data);
+        }
+        rest = &rest[5 + len..];
+    }
+    records
+}
+
+/// Unresolved files of the merge in progress, preferring the newer
+/// `state2` as hg does. A `.hg/merge/state` has the local node on its first
+/// line and a file record on each of the others
+fn unresolved_files(merge_dir: &Path) -> usize {
+    if let Ok(state) = fs::read(merge_dir.join("state2")) {
+        return count_unresolved(state2_records(&state).into_iter());
+    }
+
+    match fs::read(merge_dir.join("state")) {
+        Ok(state) => count_unresolved(state.split(|&b| b == b'\n').skip(1)),
+        Err(_) => 0,
+    }
+}
+
+/// State of the working copy at `hg_root`. `Unknown` on a network filesystem,
+/// where statting every tracked file would hold up the prompt
+fn working_copy(ctx: &Context, hg_root: &Path) -> WorkingCopy {
+    if ctx.network_fs {
+        return WorkingCopy::Unknown;
+    }
+
+    let merge_dir = hg_root.join(".hg/merge");
+    if merge_dir.is_dir() {
+        return WorkingCopy::Merging(unresolved_files(&merge_dir));
+    }
+
+    let dirstate = match fs::read(hg_root.join(".hg/dirstate")) {
+        Ok(dirstate) => dirstate,
+        Err(_) => return WorkingCopy::Unknown,
+    };
+    match dirstate_dirty(hg_root, &dirstate) {
+        Some(true) => WorkingCopy::Dirty,
+        Some(false) => WorkingCopy::Clean,
+        None => WorkingCopy::Unknown,
+    }
+}
+
+fn get_mercurial_info(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    ctx.cwd()
+        .ok_or_else(|| SegmentError::failed(NAME, "failed to get working directory"))?;
+    let hg_root = ctx
+        .hg_root()
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "working directory not in hg repo"))?;
+
+    let mut hg_components = vec![];
+
+    {
+        let mut maybe_push_file = |path| {
+            // Don't care if this fails, just don't include it in the results
+            let _ = File::open(hg_root.join(path))
+                .and_then(|mut f| {
+                    let mut output = String::new();
+                    f.read_to_string(&mut output)?;
+                    Ok(output)
+                })
+                .map(|s| hg_components.push(s.trim().to_string()));
+        };
+
+        maybe_push_file(hg_root.join(".hg/bookmarks.current"));
+        maybe_push_file(hg_root.join(".hg/branch"));
+    }
+
+    let s = File::open(hg_root.join(".hg/dirstate"))
+        .iter()
+        .flat_map(|f| io::BufReader::new(f).bytes())
+        .take(6)
+        .filter_map(|x| x.ok())
+        .map(|x| format!("{x:02x}"))
+        .collect::<String>();
+
+    hg_components.push(s);
+
+    let mut output = String::new();
+
+    for (i, component) in hg_components.iter().enumerate() {
+        if component.is_empty() {
+            continue;
+        }
+        if i != 0 {
+            output.push(' ')
+        }
+        output.push_str(component);
+    }
+
+    if output.is_empty() {
+        return Ok(DecoratedString::new(output));
+    }
+
+    let output = match super::icon(ctx, NAME, "☿", "hg:")? {
+        Some(icon) => DecoratedString::new(output).with_icon(&icon),
+        None => DecoratedString::new(output),
+    };
+    let output = match working_copy(ctx, hg_root) {
+        WorkingCopy::Clean | WorkingCopy::Unknown => output.colored(Color::Green),
+        WorkingCopy::Dirty => output.colored(Color::Yellow),
+        WorkingCopy::Merging(0) => output.colored(Color::Red),
+        WorkingCopy::Merging(unresolved) => output
+            .colored(Color::Red)
+            .with_suffix(DecoratedString::new(format!("✗{unresolved}")).colored(Color::Red)),
+    };
+    Ok(output.bold())
+}
+
+/// Bookmark, branch and working copy parent of the enclosing mercurial repo.
+/// Green when clean, yellow with pending changes and red during a merge,
+/// followed by the number of unresolved files
+pub struct Hg;
+
+impl Segment for Hg {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_mercurial_info(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    fn dirstate_entry(state: u8, size: i32, name: &str) -> Vec<u8> {
+        let mut entry = vec![state];
+        entry.extend(0o100644i32.to_be_bytes());
+        entry.extend(size.to_be_bytes());
+        entry.extend(0i32.to_be_bytes());
+        entry.extend((name.len() as i32).to_be_bytes());
+        entry.extend(name.as_bytes());
+        entry
+    }
+
+    /// A repo with `main.rs` tracked, and `entries` in its dirstate
+    fn repo(entries: &[Vec<u8>]) -> TempDir {
+        let root = TempDir::new();
+        root.write("repo/.hg/branch", "default\n");
+        root.write("repo/main.rs", "fn main() {}\n");
+        let mut dirstate = vec![0xab; DIRSTATE_HEADER];
+        for entry in entries {
+            dirstate.extend(entry);
+        }
+        fs::write(root.path().join("repo/.hg/dirstate"), dirstate).unwrap();
+        root
+    }
+
+    fn render(root: &TempDir) -> String {
+        let ctx = testing::context(&[], Some("/repo")).with_root(root.path().to_path_buf());
+        get_mercurial_info(&ctx).unwrap().to_ansi()
+    }
+
+    #[test]
+    fn clean_is_green() {
+        let root = repo(&[dirstate_entry(b'n', 13, "main.rs")]);
+        assert_eq!(
+            render(&root),
+            "\x1b[1m\x1b[32m☿ default abababababab\x1b[39m\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn pending_changes_are_yellow() {
+        let changes = [
+            dirstate_entry(b'n', 5, "main.rs"),
+            dirstate_entry(b'n', 1, "gone.rs"),
+            dirstate_entry(b'a', 0, "new.rs"),
+            dirstate_entry(b'n', FROM_OTHER_PARENT, "main.rs"),
+        ];
+        for change in changes.iter() {
+            let root = repo(&[dirstate_entry(b'n', 13, "main.rs"), change.clone()]);
+            let ctx = testing::context(&[], Some("/repo")).with_root(root.path().to_path_buf());
+            assert_eq!(
+                working_copy(&ctx, ctx.hg_root().unwrap()),
+                WorkingCopy::Dirty
+            );
+        }
+
+        let root = repo(&[dirstate_entry(b'r', 0, "main.rs")]);
+        assert!(render(&root).contains("\x1b[33m"));
+    }
+
+    #[test]
+    fn merge_is_red() {
+        let root = repo(&[dirstate_entry(b'n', 13, "main.rs")]);
+        root.write(
+            "repo/.hg/merge/state",
+            "0123abcd\nmain.rs\0u\0hash\nlib.rs\0r\0hash\nbin.rs\0pu\0hash\n",
+        );
+        assert_eq!(
+            render(&root),
+            "\x1b[1m\x1b[31m☿ default abababababab\x1b[39m \x1b[31m✗2\x1b[39m\x1b[22m"
+        );
+
+        fs::remove_file(root.path().join("repo/.hg/merge/state")).unwrap();
+        assert!(render(&root).starts_with("\x1b[1m\x1b[31m☿ default abababababab\x1b[39m\x1b[22m"));
+    }
+
+    #[test]
+    fn merge_state2_records() {
+        let record = |kind: u8, data: &[u8]| {
+            let mut record = vec![kind];
+            record.extend((data.len() as u32).to_be_bytes());
+            record.extend(data);
+            record
+        };
+        let mut state = record(b'L', b"0123abcd");
+        state.extend(record(b'F', b"main.rs\0u\0hash"));
+        state.extend(record(b'C', b"lib.rs\0r\0hash"));
+        state.extend(record(b'P', b"dir\0pu\0other"));
+        assert_eq!(count_unresolved(state2_records(&state).into_iter()), 2);
+        // A truncated record is ignored
+        assert_eq!(
+            count_unresolved(state2_records(&state[..state.len() - 1]).into_iter()),
+            1
+        );
+    }
+
+    #[test]
+    fn unknown_dirstate_keeps_green() {
+        let root = repo(&[]);
+        fs::write(root.path().join("repo/.hg/dirstate"), "dirstate-v2\n").unwrap();
+        assert!(render(&root).contains("\x1b[32m"));
+    }
+
+    #[test]
+    fn network_fs_keeps_green() {
+        let root = repo(&[dirstate_entry(b'a', 0, "new.rs")]);
+        root.write("repo/.hg/merge/state", "0123abcd\nmain.rs\0u\0hash\n");
+        let mut ctx = testing::context(&[], Some("/repo")).with_root(root.path().to_path_buf());
+        ctx.network_fs = true;
+        assert_eq!(
+            working_copy(&ctx, ctx.hg_root().unwrap()),
+            WorkingCopy::Unknown
+        );
+        assert!(get_mercurial_info(&ctx)
+            .unwrap()
+            .to_ansi()
+            .contains("\x1b[32m"));
+    }
+}
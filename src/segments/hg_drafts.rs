@@ -0,0 +1,118 @@
+use std::{collections::HashSet, fs::File, io::Read};
+
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "hg_drafts";
+
+/// `phaseroots` is one short line per root, a repo with more than this has
+/// bigger problems than its prompt
+const MAX_PHASEROOTS_BYTES: u64 = 64 * 1024;
+
+/// Phase number of drafts in `phaseroots`, secret changesets are `2`
+const DRAFT: &str = "1";
+
+/// Distinct draft roots in a `phaseroots` file, lines of a phase number and a
+/// node. Counting draft changesets needs the changelog, but every chain of
+/// unpushed drafts starts at a root, and none are left once all are public
+fn count_draft_roots(phaseroots: &str) -> usize {
+    phaseroots
+        .lines()
+        .filter_map(|line| match line.split_once(' ') {
+            Some((DRAFT, node)) if !node.is_empty() => Some(node),
+            _ => None,
+        })
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+fn get_hg_drafts(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let hg_root = ctx
+        .hg_root()
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "working directory not in hg repo"))?;
+
+    let mut phaseroots = String::new();
+    // Without the file there are no drafts, all changesets are public
+    if let Ok(file) = File::open(hg_root.join(".hg/store/phaseroots")) {
+        file.take(MAX_PHASEROOTS_BYTES)
+            .read_to_string(&mut phaseroots)
+            .map_err(|e| SegmentError::failed(NAME, "failed to read phaseroots").with_source(e))?;
+    }
+
+    match count_draft_roots(&phaseroots) {
+        0 => Err(SegmentError::not_applicable(NAME, "no draft changesets")),
+        drafts => Ok(DecoratedString::new(format!("↑{drafts}")).dim()),
+    }
+}
+
+/// `↑n` for the draft changesets of the enclosing mercurial repo, roughly
+/// the ones not pushed yet. Counts the roots of draft chains rather than
+/// every changeset. Off unless `enabled = true`
+pub struct HgDrafts;
+
+impl Segment for HgDrafts {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_hg_drafts(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    const NODE_A: &str = "3f786850e387550fdab836ed7e6dc881de23001b";
+    const NODE_B: &str = "89e6c98d92887913cadf06b2adb97f26cde4849b";
+
+    fn repo(phaseroots: Option<&str>) -> (TempDir, Context) {
+        let root = TempDir::new();
+        root.write("repo/.hg/requires", "store\n");
+        if let Some(phaseroots) = phaseroots {
+            root.write("repo/.hg/store/phaseroots", phaseroots);
+        }
+        let ctx = testing::context(&[], Some("/repo")).with_root(root.path().to_path_buf());
+        (root, ctx)
+    }
+
+    fn hidden(ctx: &Context) -> bool {
+        matches!(get_hg_drafts(ctx), Err(e) if e.kind == ErrorKind::NotApplicable)
+    }
+
+    #[test]
+    fn counts_draft_roots() {
+        let (_root, ctx) = repo(Some(&format!("1 {NODE_A}\n1 {NODE_B}\n2 {NODE_A}\n")));
+        assert_eq!(get_hg_drafts(&ctx).unwrap().to_ansi(), "\x1b[2m↑2\x1b[22m");
+    }
+
+    #[test]
+    fn nothing_without_drafts() {
+        let (_root, ctx) = repo(Some(&format!("2 {NODE_A}\n")));
+        assert!(hidden(&ctx));
+        let (_root, ctx) = repo(Some(""));
+        assert!(hidden(&ctx));
+        let (_root, ctx) = repo(None);
+        assert!(hidden(&ctx));
+    }
+
+    #[test]
+    fn ignores_garbage() {
+        assert_eq!(
+            count_draft_roots(&format!("1\n1 \nx {NODE_A}\n1 {NODE_A}\n1 {NODE_A}\n")),
+            1
+        );
+    }
+}
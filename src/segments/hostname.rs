@@ -0,0 +1,140 @@
+use std::ffi::OsStr;
+
+use crate::{
+    config,
+    decorated::{Color, DecoratedString},
+    platform,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "hostname";
+
+/// `name` without its domain, e.g. `laptop` for `laptop.local`
+fn strip_domain(name: &str) -> &str {
+    match name.split_once('.') {
+        Some((host, _)) if !host.is_empty() => host,
+        _ => name,
+    }
+}
+
+/// `host` is the machine's name as the system reports it, with invalid UTF-8
+/// replaced unless `strict_utf8 = true`. It is shown without
+/// its domain unless `strip_domain = false`, then replaced by its entry in
+/// `[host_aliases]` if there is one
+fn get_hostname(ctx: &Context, host: &OsStr) -> Result<DecoratedString, SegmentError> {
+    let strip = config::get_bool(&ctx.config.segment(NAME).options, "strip_domain")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .unwrap_or(true);
+
+    let host = super::name_to_str(ctx, NAME, host)?;
+    let res = if strip { strip_domain(&host) } else { &host };
+
+    let res = match ctx.config.host_aliases.get(&res.to_lowercase()) {
+        Some(alias) => alias,
+        None => res,
+    };
+
+    let res = DecoratedString::new(res.to_string())
+        .colored(Color::Green)
+        .bold();
+
+    Ok(res)
+}
+
+/// Name of the machine, without its domain unless `strip_domain = false`, or its
+/// alias from the config
+pub struct Hostname;
+
+impl Segment for Hostname {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        let host = platform::host_name(ctx)
+            .map_err(|e| SegmentError::failed(NAME, "failed to get host name").with_source(e))?;
+        get_hostname(ctx, &host).map(Into::into)
+    }
+
+    fn preview(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_hostname(ctx, OsStr::new("devbox.local")).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{self, Config},
+        testing,
+    };
+
+    fn with_config(toml: &str) -> Context {
+        let mut ctx = testing::context(&[], None);
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        ctx
+    }
+
+    fn shown(ctx: &Context, host: &str) -> String {
+        get_hostname(ctx, OsStr::new(host)).unwrap().to_plain()
+    }
+
+    #[test]
+    fn strips_mdns_suffix() {
+        let name = format!("{}.local", "a".repeat(64));
+        assert_eq!(name.len(), 70);
+        assert_eq!(strip_domain(&name), "a".repeat(64));
+    }
+
+    #[test]
+    fn strips_everything_after_first_dot() {
+        assert_eq!(strip_domain("build-01.ci.example.com"), "build-01");
+        assert_eq!(strip_domain("laptop"), "laptop");
+        assert_eq!(strip_domain(".hidden"), ".hidden");
+    }
+
+    #[test]
+    fn shows_first_label_by_default() {
+        let ctx = testing::context(&[], None);
+        assert_eq!(
+            shown(&ctx, "web-prod-03.internal.example.com"),
+            "web-prod-03"
+        );
+        assert_eq!(shown(&ctx, "laptop"), "laptop");
+        assert_eq!(
+            get_hostname(&ctx, OsStr::new("laptop")).unwrap().to_ansi(),
+            "\x1b[1m\x1b[32mlaptop\x1b[39m\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn keeps_fqdn_when_asked() {
+        let ctx = with_config("[segments.hostname]\nstrip_domain = false\n");
+        assert_eq!(
+            shown(&ctx, "web-prod-03.internal.example.com"),
+            "web-prod-03.internal.example.com"
+        );
+    }
+
+    #[test]
+    fn aliases_after_stripping_ignoring_case() {
+        let ctx = with_config("[host_aliases]\n\"Web-Prod-03\" = \"prod3\"\n");
+        assert_eq!(shown(&ctx, "web-prod-03.internal.example.com"), "prod3");
+        assert_eq!(shown(&ctx, "WEB-PROD-03"), "prod3");
+        assert_eq!(shown(&ctx, "web-prod-04"), "web-prod-04");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replaces_invalid_utf8() {
+        use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+        let host = OsString::from_vec(b"caf\xe9.example.com".to_vec());
+        let ctx = testing::context(&[], None);
+        assert_eq!(get_hostname(&ctx, &host).unwrap().to_plain(), "caf\u{fffd}");
+
+        let ctx = with_config("[segments.hostname]\nstrict_utf8 = true\n");
+        assert!(get_hostname(&ctx, &host).is_err());
+    }
+}
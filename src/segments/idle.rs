@@ -0,0 +1,221 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    cache, config,
+    decorated::DecoratedString,
+    platform,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "idle";
+
+/// Gap between prompts below which nothing is shown
+const DEFAULT_THRESHOLD: Duration = Duration::from_secs(15 * 60);
+
+/// State of terminals that haven't shown a prompt for this long, most likely
+/// because they were closed, is removed
+const STATE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const STATE_PREFIX: &str = "idle-";
+
+/// The file holding when `tty` last showed a prompt
+fn state_path(dir: &Path, tty: &str) -> PathBuf {
+    let tty = tty.trim_start_matches('/').replace('/', "-");
+    dir.join(format!("{STATE_PREFIX}{tty}"))
+}
+
+/// Time since the prompt recorded in the state file at `path`, if there is
+/// one, recording `now` for the next prompt
+fn idle_since(path: &Path, now: SystemTime) -> Option<Duration> {
+    let last = fs::read_to_string(path)
+        .ok()
+        .and_then(|last| last.trim().parse().ok());
+    let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let _ = cache::write(path, &secs.to_string());
+
+    now.duration_since(UNIX_EPOCH + Duration::from_secs(last?))
+        .ok()
+}
+
+/// Removes the state files in `dir` older than `STATE_TTL`, other than the
+/// one at `keep`
+fn remove_stale(dir: &Path, keep: &Path, now: SystemTime) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let is_state = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.starts_with(STATE_PREFIX));
+        let is_stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > STATE_TTL);
+        if is_state && is_stale && entry.path() != keep {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// `1h12m`, `40m` or `2d3h`
+fn format_idle(idle: Duration) -> String {
+    let minutes = idle.as_secs() / 60;
+    let hours = minutes / 60;
+    match (hours / 24, hours % 24, minutes % 60) {
+        (0, 0, minutes) => format!("{minutes}m"),
+        (0, hours, minutes) => format!("{hours}h{minutes}m"),
+        (days, hours, _) => format!("{days}d{hours}h"),
+    }
+}
+
+fn get_idle(
+    ctx: &Context,
+    tty: Option<String>,
+    now: SystemTime,
+) -> Result<DecoratedString, SegmentError> {
+    let threshold = config::get_int(&ctx.config.segment(NAME).options, "threshold_mins")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .map_or(DEFAULT_THRESHOLD, |mins| {
+            Duration::from_secs(mins.max(0) as u64 * 60)
+        });
+
+    let tty = tty.ok_or_else(|| SegmentError::not_applicable(NAME, "not on a terminal"))?;
+    let dir = ctx
+        .cache_dir()
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "nowhere to keep state"))?;
+    let path = state_path(&dir, &tty);
+
+    let idle = idle_since(&path, now);
+    // Cleaning up only after long gaps and on new terminals keeps the
+    // directory from being listed every prompt
+    if idle.is_none_or(|idle| idle >= threshold) {
+        remove_stale(&dir, &path, now);
+    }
+
+    match idle {
+        Some(idle) if idle >= threshold => {
+            Ok(DecoratedString::new(format!("idle {}", format_idle(idle))).dim())
+        }
+        _ => Err(SegmentError::not_applicable(NAME, "not idle for long")),
+    }
+}
+
+/// `idle 1h12m` when the last prompt on this terminal was shown more than
+/// `threshold_mins` ago, 15 by default. Off unless `enabled = true`, as it
+/// writes a file in the cache dir every prompt
+pub struct Idle;
+
+impl Segment for Idle {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_idle(ctx, platform::tty_name(), SystemTime::now()).map(Into::into)
+    }
+
+    /// Leaves the state of the terminal alone
+    fn preview(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        let idle = format_idle(Duration::from_secs(72 * 60));
+        Ok(DecoratedString::new(format!("idle {idle}")).dim().into())
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    const TTY: &str = "/dev/pts/3";
+
+    fn context(root: &TempDir) -> Context {
+        testing::context(&[("XDG_RUNTIME_DIR", "/run")], Some("/"))
+            .with_root(root.path().to_path_buf())
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let root = TempDir::new();
+        let ctx = context(&root);
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let idle = |after| get_idle(&ctx, Some(TTY.into()), start + after);
+
+        // Nothing to compare the first prompt to
+        assert!(idle(Duration::ZERO).is_err());
+        let res = idle(minutes(5));
+        assert!(matches!(res, Err(e) if e.kind == ErrorKind::NotApplicable));
+        assert_eq!(idle(minutes(77)).unwrap().to_plain(), "idle 1h12m");
+        assert_eq!(
+            idle(minutes(77 + 16)).unwrap().to_ansi(),
+            "\x1b[2midle 16m\x1b[22m"
+        );
+
+        let state = root.path().join("run/promptline/idle-dev-pts-3");
+        assert_eq!(fs::read_to_string(state).unwrap(), "1700005580");
+    }
+
+    #[test]
+    fn no_terminal() {
+        let root = TempDir::new();
+        let res = get_idle(&context(&root), None, SystemTime::now());
+        assert!(matches!(res, Err(e) if e.kind == ErrorKind::NotApplicable));
+        assert!(!root.path().join("run/promptline").exists());
+    }
+
+    #[test]
+    fn threshold() {
+        let root = TempDir::new();
+        let mut ctx = context(&root);
+        ctx.config = config::Config::from_table(
+            &config::parse("[segments.idle]\nthreshold_mins = 1\n").unwrap(),
+        )
+        .unwrap();
+        let start = SystemTime::now();
+        get_idle(&ctx, Some(TTY.into()), start).unwrap_err();
+        let idle = get_idle(&ctx, Some(TTY.into()), start + minutes(2)).unwrap();
+        assert_eq!(idle.to_plain(), "idle 2m");
+    }
+
+    #[test]
+    fn stale_state_is_removed() {
+        let root = TempDir::new();
+        let ctx = context(&root);
+        root.write("run/promptline/idle-dev-pts-9", "1");
+        root.write("run/promptline/sudo-dev-pts-9", "1");
+
+        let tomorrow = SystemTime::now() + STATE_TTL + minutes(1);
+        get_idle(&ctx, Some(TTY.into()), tomorrow).unwrap_err();
+        let dir = root.path().join("run/promptline");
+        assert!(!dir.join("idle-dev-pts-9").exists());
+        assert!(dir.join("sudo-dev-pts-9").exists());
+        assert!(dir.join("idle-dev-pts-3").exists());
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(format_idle(minutes(40)), "40m");
+        assert_eq!(format_idle(minutes(60)), "1h0m");
+        assert_eq!(format_idle(minutes(51 * 60 + 5)), "2d3h");
+    }
+}
@@ -0,0 +1,215 @@
+use std::{fs, path::Path};
+
+use super::project_root;
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "java";
+
+const MAVEN: &str = "pom.xml";
+const MARKERS: &[&str] = &[MAVEN, "build.gradle", "build.gradle.kts"];
+const GRADLE_SETTINGS: &[&str] = &["settings.gradle", "settings.gradle.kts"];
+
+/// Text between `<tag>` and `</tag>` on `line`
+fn xml_text<'a>(line: &'a str, tag: &str) -> Option<&'a str> {
+    let start = line.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = line[start..].find(&format!("</{tag}>"))? + start;
+    Some(line[start..end].trim())
+}
+
+/// The project's own `artifactId`. It comes before the dependencies and
+/// plugins, whose ids are skipped along with the one of the parent project
+fn maven_artifact(pom: &str) -> Option<&str> {
+    let mut in_parent = false;
+    for line in pom.lines() {
+        if line.contains("<parent>") {
+            in_parent = true;
+        }
+        if line.contains("</parent>") {
+            in_parent = false;
+            continue;
+        }
+        if [
+            "<dependencies>",
+            "<dependencyManagement>",
+            "<build>",
+            "<modules>",
+        ]
+        .iter()
+        .any(|tag| line.contains(tag))
+        {
+            return None;
+        }
+        if !in_parent {
+            if let Some(artifact) = xml_text(line, "artifactId") {
+                return Some(artifact).filter(|artifact| !artifact.is_empty());
+            }
+        }
+    }
+    None
+}
+
+/// `rootProject.name` from a Groovy or Kotlin settings script, if it is set to
+/// a string literal
+fn gradle_project(settings: &str) -> Option<&str> {
+    settings.lines().find_map(|line| {
+        let value = line
+            .trim()
+            .strip_prefix("rootProject.name")?
+            .trim_start()
+            .strip_prefix('=')?
+            .trim();
+        ['"', '\'']
+            .iter()
+            .find_map(|&quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .filter(|name| !name.is_empty())
+    })
+}
+
+/// Name of the project rooted at `root`, if it can be read off the build files
+fn project_name(root: &Path) -> Option<String> {
+    if let Ok(pom) = fs::read_to_string(root.join(MAVEN)) {
+        return maven_artifact(&pom).map(String::from);
+    }
+
+    GRADLE_SETTINGS.iter().find_map(|file| {
+        let settings = fs::read_to_string(root.join(file)).ok()?;
+        gradle_project(&settings).map(String::from)
+    })
+}
+
+/// Major version from `JAVA_VERSION` in the `release` file of a JDK, `8` for
+/// the old `1.8.0_392` style
+fn release_major(release: &str) -> Option<u32> {
+    let version = release.lines().find_map(|line| {
+        line.strip_prefix("JAVA_VERSION=")
+            .map(|version| version.trim_matches('"'))
+    })?;
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+fn get_java(ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+    let (root, depth) = project_root(ctx, NAME, MARKERS)?;
+
+    let major = ctx
+        .var("JAVA_HOME")
+        .and_then(|home| ctx.read_to_string(Path::new(home).join("release")).ok())
+        .and_then(|release| release_major(&release));
+
+    let glyph = super::with_icon(ctx, NAME, "☕", "java:", project_name(&root))?;
+    let java = match major {
+        Some(major) => glyph.with_suffix(DecoratedString::new(major.to_string()).dim()),
+        None => glyph,
+    };
+    Ok(RenderedSegment::from(java).with_project_depth(depth))
+}
+
+/// `☕` with the project name inside Maven and Gradle projects, followed by
+/// the major version of the JDK in `$JAVA_HOME`. Off unless `enabled = true`
+pub struct Java;
+
+impl Segment for Java {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_java(ctx)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    const POM: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0">
+  <modelVersion>4.0.0</modelVersion>
+  <parent>
+    <groupId>org.springframework.boot</groupId>
+    <artifactId>spring-boot-starter-parent</artifactId>
+  </parent>
+  <groupId>com.example</groupId>
+  <artifactId>billing</artifactId>
+  <dependencies>
+    <dependency>
+      <artifactId>spring-boot-starter-web</artifactId>
+    </dependency>
+  </dependencies>
+</project>
+"#;
+
+    fn in_dir(root: &TempDir, cwd: &str, vars: &[(&str, &str)]) -> Context {
+        testing::context(vars, Some(cwd)).with_root(root.path().to_path_buf())
+    }
+
+    #[test]
+    fn maven_layout() {
+        let root = TempDir::new();
+        root.write("src/billing/pom.xml", POM);
+        root.write("src/billing/src/main/java/App.java", "");
+        root.write(
+            "jdk/release",
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.9\"\n",
+        );
+
+        let ctx = in_dir(&root, "/src/billing/src/main", &[("JAVA_HOME", "/jdk")]);
+        let java = get_java(&ctx).unwrap();
+        assert_eq!(java.to_plain(), "☕ billing 17");
+        assert_eq!(java.to_ansi(), "☕ billing \x1b[2m17\x1b[22m");
+    }
+
+    #[test]
+    fn gradle_layout() {
+        let root = TempDir::new();
+        root.write(
+            "src/app/settings.gradle.kts",
+            "rootProject.name = \"app\"\ninclude(\"core\")\n",
+        );
+        root.write("src/app/build.gradle.kts", "plugins { java }\n");
+        root.write("jdk8/release", "JAVA_VERSION=\"1.8.0_392\"\n");
+
+        let ctx = in_dir(&root, "/src/app", &[("JAVA_HOME", "/jdk8")]);
+        assert_eq!(get_java(&ctx).unwrap().to_plain(), "☕ app 8");
+    }
+
+    #[test]
+    fn glyph_only() {
+        let root = TempDir::new();
+        root.write("src/lib/build.gradle", "apply plugin: 'java'\n");
+
+        let ctx = in_dir(&root, "/src/lib", &[]);
+        assert_eq!(get_java(&ctx).unwrap().to_plain(), "☕");
+    }
+
+    #[test]
+    fn not_a_jvm_project() {
+        let root = TempDir::new();
+        root.write("src/other/Cargo.toml", "");
+        assert!(get_java(&in_dir(&root, "/src/other", &[])).is_err());
+    }
+
+    #[test]
+    fn peeks() {
+        assert_eq!(maven_artifact(POM), Some("billing"));
+        assert_eq!(
+            maven_artifact("<project>\n<dependencies>\n<artifactId>x</artifactId>\n"),
+            None
+        );
+        assert_eq!(gradle_project("rootProject.name = 'demo'\n"), Some("demo"));
+        assert_eq!(gradle_project("rootProject.name = computeName()\n"), None);
+        assert_eq!(release_major("JAVA_VERSION=\"21\"\n"), Some(21));
+    }
+}
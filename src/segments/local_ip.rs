@@ -0,0 +1,155 @@
+use std::net::Ipv4Addr;
+
+use super::offline;
+use crate::{
+    config,
+    decorated::DecoratedString,
+    platform::{self, NetworkInterface, PlatformError},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "local_ip";
+
+/// The first of `addresses` that isn't link-local, falling back to a
+/// link-local one
+fn pick_address(addresses: &[Ipv4Addr]) -> Option<Ipv4Addr> {
+    addresses
+        .iter()
+        .find(|address| !address.is_link_local())
+        .or_else(|| addresses.first())
+        .copied()
+}
+
+/// `list_interfaces` is called only once the interface is known
+fn get_local_ip(
+    ctx: &Context,
+    list_interfaces: impl FnOnce() -> Result<Vec<NetworkInterface>, PlatformError>,
+) -> Result<DecoratedString, SegmentError> {
+    let options = &ctx.config.segment(NAME).options;
+    let pinned = config::get_str(options, "interface")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?;
+
+    let name = match pinned {
+        Some(name) => name.to_string(),
+        None => {
+            let route = ctx.read_to_string("/proc/net/route").map_err(|e| {
+                SegmentError::not_applicable(NAME, "no routing table").with_source(e)
+            })?;
+            offline::ipv4_default_interface(&route)
+                .ok_or_else(|| SegmentError::not_applicable(NAME, "no default route"))?
+                .to_string()
+        }
+    };
+
+    let interfaces = list_interfaces().map_err(|e| {
+        SegmentError::not_applicable(NAME, "failed to list interfaces").with_source(e)
+    })?;
+    let address = interfaces
+        .iter()
+        .find(|interface| interface.name == name)
+        .and_then(|interface| pick_address(&interface.ipv4))
+        .ok_or_else(|| SegmentError::not_applicable(NAME, format!("{name} has no IPv4 address")))?;
+
+    Ok(DecoratedString::new(address.to_string()).dim())
+}
+
+/// IPv4 address of the interface with the default route, or of the one set
+/// with `interface`. Off unless `enabled = true`
+pub struct LocalIp;
+
+impl Segment for LocalIp {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_local_ip(ctx, platform::network_interfaces).map(Into::into)
+    }
+
+    fn preview(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        let wlan0 = NetworkInterface {
+            name: "wlan0".into(),
+            up: true,
+            has_address: true,
+            ipv4: vec![[192, 168, 1, 42].into()],
+        };
+        get_local_ip(ctx, || Ok(vec![wlan0])).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{args::Args, config::Config, segment::ErrorKind, testing::TempDir};
+
+    const ROUTE: &str =
+        "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+        wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\n";
+
+    fn interfaces() -> Result<Vec<NetworkInterface>, PlatformError> {
+        let interface = |name: &str, ipv4: &[[u8; 4]]| NetworkInterface {
+            name: name.to_string(),
+            up: true,
+            has_address: !ipv4.is_empty(),
+            ipv4: ipv4.iter().map(|&octets| Ipv4Addr::from(octets)).collect(),
+        };
+        Ok(vec![
+            interface("lo", &[[127, 0, 0, 1]]),
+            interface("eth0", &[[10, 0, 0, 7]]),
+            interface("wlan0", &[[169, 254, 3, 4], [192, 168, 1, 42]]),
+        ])
+    }
+
+    fn with_route(route: Option<&str>, config: &str) -> (TempDir, Context) {
+        let root = TempDir::new();
+        if let Some(route) = route {
+            root.write("proc/net/route", route);
+        }
+        let config = Config::from_table(&config::parse(config).unwrap()).unwrap();
+        let ctx = Context::with_env(Args::default(), config, Default::default(), None)
+            .with_root(root.path().to_path_buf());
+        (root, ctx)
+    }
+
+    #[test]
+    fn address_of_default_route_interface() {
+        let (_root, ctx) = with_route(Some(ROUTE), "");
+        let ip = get_local_ip(&ctx, interfaces).unwrap();
+        assert_eq!(ip.to_ansi(), "\x1b[2m192.168.1.42\x1b[22m");
+    }
+
+    #[test]
+    fn pinned_interface() {
+        let (_root, ctx) = with_route(None, "[segments.local_ip]\ninterface = \"eth0\"\n");
+        assert_eq!(
+            get_local_ip(&ctx, interfaces).unwrap().to_plain(),
+            "10.0.0.7"
+        );
+    }
+
+    #[test]
+    fn hidden_without_default_route() {
+        let header = ROUTE.lines().next().unwrap();
+        for route in [None, Some(header)] {
+            let (_root, ctx) = with_route(route, "");
+            assert!(matches!(
+                get_local_ip(&ctx, interfaces),
+                Err(e) if e.kind == ErrorKind::NotApplicable
+            ));
+        }
+    }
+
+    #[test]
+    fn prefers_routable_addresses() {
+        let link_local = Ipv4Addr::new(169, 254, 0, 1);
+        let lan = Ipv4Addr::new(192, 168, 0, 2);
+        assert_eq!(pick_address(&[link_local, lan]), Some(lan));
+        assert_eq!(pick_address(&[link_local]), Some(link_local));
+        assert_eq!(pick_address(&[]), None);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "locale";
+
+/// Whether `locale` names a UTF-8 codeset, e.g. `en_US.UTF-8`, `de_DE.utf8` or
+/// `C.UTF-8@euro`
+fn is_utf8(locale: &str) -> bool {
+    let codeset = match locale.split_once('.') {
+        Some((_, rest)) => rest.split('@').next().unwrap_or(rest),
+        None => return false,
+    };
+    codeset.replace('-', "").eq_ignore_ascii_case("utf8")
+}
+
+/// The locale that decides the character set, by the same precedence as libc
+fn ctype_locale(ctx: &Context) -> Option<&str> {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| ctx.var(name))
+        .find(|value| !value.is_empty())
+}
+
+fn get_locale(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    match ctype_locale(ctx) {
+        Some(locale) if is_utf8(locale) => {
+            Err(SegmentError::not_applicable(NAME, "locale is UTF-8"))
+        }
+        _ => Ok(DecoratedString::new("locale!".into())
+            .colored(Color::Yellow)
+            .bold()),
+    }
+}
+
+/// Warns when the locale isn't UTF-8, so that tools mangling output don't
+/// come as a surprise. Off unless `enabled = true`
+pub struct Locale;
+
+impl Segment for Locale {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_locale(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn utf8_spellings() {
+        assert!(is_utf8("en_US.UTF-8"));
+        assert!(is_utf8("de_DE.utf8"));
+        assert!(is_utf8("C.UTF-8"));
+        assert!(is_utf8("fr_FR.UTF-8@euro"));
+        assert!(!is_utf8("C"));
+        assert!(!is_utf8("en_US.ISO-8859-1"));
+        assert!(!is_utf8("en_US"));
+    }
+
+    #[test]
+    fn lc_all_c_overrides_lang() {
+        let ctx = testing::context(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")], None);
+        assert_eq!(get_locale(&ctx).unwrap().to_plain(), "locale!");
+    }
+
+    #[test]
+    fn no_warning_for_utf8() {
+        for vars in [
+            &[("LANG", "en_US.UTF-8")][..],
+            &[("LC_ALL", "C.UTF-8")],
+            &[("LC_ALL", ""), ("LC_CTYPE", "en_GB.utf8"), ("LANG", "C")],
+        ] {
+            let ctx = testing::context(vars, None);
+            assert!(get_locale(&ctx).is_err(), "{:?}", vars);
+        }
+    }
+
+    #[test]
+    fn warns_without_any_locale() {
+        let ctx = testing::context(&[], None);
+        assert!(get_locale(&ctx).is_ok());
+    }
+}
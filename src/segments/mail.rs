@@ -0,0 +1,145 @@
+use std::{fs, io};
+
+use crate::{
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "mail";
+
+fn unreadable(e: io::Error) -> SegmentError {
+    SegmentError::not_applicable(NAME, "can't read $MAIL").with_source(e)
+}
+
+fn get_mail(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let mail = ctx.path(
+        ctx.var("MAIL")
+            .ok_or_else(|| SegmentError::not_applicable(NAME, "$MAIL not set"))?,
+    );
+    let metadata = fs::metadata(&mail).map_err(unreadable)?;
+
+    let icon = || super::with_icon(ctx, NAME, "✉", "mail", None);
+
+    // A Maildir delivers each new message as a file in `new/`
+    if metadata.is_dir() {
+        let new = fs::read_dir(mail.join("new"))
+            .map_err(unreadable)?
+            .flatten()
+            .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+            .count();
+        if new == 0 {
+            return Err(SegmentError::not_applicable(NAME, "no new mail"));
+        }
+        return Ok(icon()?
+            .with_suffix(DecoratedString::new(new.to_string()))
+            .colored(Color::Yellow));
+    }
+
+    // Mail readers access the mbox when reading it, delivery only modifies it
+    let modified = metadata.modified().map_err(unreadable)?;
+    let accessed = metadata.accessed().map_err(unreadable)?;
+    if metadata.len() == 0 || accessed >= modified {
+        return Err(SegmentError::not_applicable(NAME, "no new mail"));
+    }
+
+    Ok(icon()?.colored(Color::Yellow))
+}
+
+/// `✉` when the mbox or Maildir at `$MAIL` has new mail. Off unless
+/// `enabled = true`
+pub struct Mail;
+
+impl Segment for Mail {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_mail(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        time::{Duration, SystemTime},
+    };
+
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    fn is_not_applicable(res: Result<DecoratedString, SegmentError>) -> bool {
+        matches!(res, Err(e) if e.kind == ErrorKind::NotApplicable)
+    }
+
+    /// An mbox containing `contents`, modified after it was last read if `unread`
+    fn mbox(dir: &TempDir, contents: &str, unread: bool) -> Context {
+        dir.write("mbox", contents);
+        let path = dir.path().join("mbox");
+        let modified = SystemTime::now() - Duration::from_secs(60);
+        let accessed = if unread {
+            modified - Duration::from_secs(60)
+        } else {
+            modified + Duration::from_secs(30)
+        };
+        let times = fs::FileTimes::new()
+            .set_accessed(accessed)
+            .set_modified(modified);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_times(times)
+            .unwrap();
+        testing::context(&[("MAIL", path.to_str().unwrap())], None)
+    }
+
+    #[test]
+    fn new_mail_in_mbox() {
+        let dir = TempDir::new();
+        let ctx = mbox(&dir, "From someone\n", true);
+        assert_eq!(get_mail(&ctx).unwrap().to_ansi(), "\x1b[33m✉\x1b[39m");
+    }
+
+    #[test]
+    fn read_or_empty_mbox() {
+        let dir = TempDir::new();
+        assert!(is_not_applicable(get_mail(&mbox(
+            &dir,
+            "From someone\n",
+            false
+        ))));
+        assert!(is_not_applicable(get_mail(&mbox(&dir, "", true))));
+    }
+
+    #[test]
+    fn counts_maildir_new() {
+        let dir = TempDir::new();
+        dir.write("Maildir/new/1", "a");
+        dir.write("Maildir/new/2", "b");
+        dir.write("Maildir/cur/3", "c");
+        let maildir = dir.path().join("Maildir");
+        let ctx = testing::context(&[("MAIL", maildir.to_str().unwrap())], None);
+        assert_eq!(get_mail(&ctx).unwrap().to_plain(), "✉ 2");
+    }
+
+    #[test]
+    fn hidden_without_mail() {
+        let dir = TempDir::new();
+        let missing = dir.path().join("missing");
+        assert!(is_not_applicable(get_mail(&testing::context(&[], None))));
+        assert!(is_not_applicable(get_mail(&testing::context(
+            &[("MAIL", missing.to_str().unwrap())],
+            None
+        ))));
+    }
+}
@@ -0,0 +1,493 @@
+//! The segments built into promptline. Each one is behind a cargo feature of
+//! the same name, with `_` replaced by `-`
+
+#[cfg(feature = "bazel")]
+pub mod bazel;
+#[cfg(feature = "cargo")]
+pub mod cargo;
+#[cfg(feature = "cmake")]
+pub mod cmake;
+#[cfg(feature = "conda")]
+pub mod conda;
+#[cfg(feature = "cpu-temp")]
+pub mod cpu_temp;
+#[cfg(feature = "cwd")]
+pub mod cwd;
+#[cfg(feature = "docker")]
+pub mod docker;
+#[cfg(feature = "docker-context")]
+pub mod docker_context;
+#[cfg(feature = "env-watch")]
+pub mod env_watch;
+#[cfg(feature = "git")]
+pub mod git;
+#[cfg(feature = "guix-env")]
+pub mod guix_env;
+#[cfg(feature = "hg")]
+pub mod hg;
+#[cfg(feature = "hg-drafts")]
+pub mod hg_drafts;
+#[cfg(feature = "hostname")]
+pub mod hostname;
+#[cfg(feature = "idle")]
+pub mod idle;
+#[cfg(feature = "java")]
+pub mod java;
+#[cfg(feature = "local-ip")]
+pub mod local_ip;
+#[cfg(feature = "locale")]
+pub mod locale;
+#[cfg(feature = "mail")]
+pub mod mail;
+#[cfg(feature = "nix-path")]
+pub mod nix_path;
+#[cfg(feature = "nix-shell")]
+pub mod nix_shell;
+#[cfg(feature = "node")]
+pub mod node;
+#[cfg(feature = "offline")]
+pub mod offline;
+#[cfg(feature = "php")]
+pub mod php;
+#[cfg(feature = "repo-size")]
+pub mod repo_size;
+#[cfg(feature = "ruby")]
+pub mod ruby;
+#[cfg(feature = "rust-toolchain")]
+pub mod rust_toolchain;
+#[cfg(feature = "sandbox")]
+pub mod sandbox;
+#[cfg(feature = "shell")]
+pub mod shell;
+#[cfg(feature = "shlvl")]
+pub mod shlvl;
+#[cfg(feature = "status")]
+pub mod status;
+#[cfg(feature = "sudo")]
+pub mod sudo;
+#[cfg(feature = "systemd")]
+pub mod systemd;
+#[cfg(feature = "time")]
+pub mod time;
+#[cfg(feature = "umask")]
+pub mod umask;
+#[cfg(feature = "unpushed")]
+pub mod unpushed;
+#[cfg(feature = "updates")]
+pub mod updates;
+#[cfg(feature = "uptime")]
+pub mod uptime;
+#[cfg(feature = "user")]
+pub mod user;
+#[cfg(feature = "users")]
+pub mod users;
+#[cfg(feature = "venv")]
+pub mod venv;
+#[cfg(feature = "vi-mode")]
+pub mod vi_mode;
+#[cfg(feature = "vm")]
+pub mod vm;
+#[cfg(feature = "vpn")]
+pub mod vpn;
+
+#[cfg(any(
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "bazel",
+    feature = "cargo"
+))]
+use std::path::PathBuf;
+use std::sync::Arc;
+#[cfg(any(feature = "hostname", feature = "user"))]
+use std::{borrow::Cow, ffi::OsStr};
+
+#[cfg(any(
+    feature = "hostname",
+    feature = "user",
+    feature = "git",
+    feature = "hg",
+    feature = "repo-size",
+    feature = "cargo",
+    feature = "docker",
+    feature = "docker-context",
+    feature = "conda",
+    feature = "venv",
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "rust-toolchain",
+    feature = "users",
+    feature = "vpn",
+    feature = "mail",
+    feature = "cpu-temp"
+))]
+use crate::config;
+#[cfg(any(
+    feature = "git",
+    feature = "hg",
+    feature = "repo-size",
+    feature = "cargo",
+    feature = "docker",
+    feature = "docker-context",
+    feature = "conda",
+    feature = "venv",
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "rust-toolchain",
+    feature = "users",
+    feature = "vpn",
+    feature = "mail",
+    feature = "cpu-temp"
+))]
+use crate::config::IconMode;
+#[cfg(any(
+    feature = "docker",
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "mail"
+))]
+use crate::decorated::DecoratedString;
+use crate::segment::Segment;
+#[cfg(any(
+    feature = "hostname",
+    feature = "user",
+    feature = "git",
+    feature = "hg",
+    feature = "repo-size",
+    feature = "cargo",
+    feature = "docker",
+    feature = "docker-context",
+    feature = "conda",
+    feature = "venv",
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "rust-toolchain",
+    feature = "users",
+    feature = "vpn",
+    feature = "mail",
+    feature = "cpu-temp",
+    feature = "bazel"
+))]
+use crate::{segment::SegmentError, Context};
+
+/// A name from the system as shown by `segment`, with invalid UTF-8 replaced.
+/// With `strict_utf8 = true` in the segment's config it fails instead
+#[cfg(any(feature = "hostname", feature = "user"))]
+pub(crate) fn name_to_str<'a>(
+    ctx: &Context,
+    segment: &str,
+    name: &'a OsStr,
+) -> Result<Cow<'a, str>, SegmentError> {
+    let strict = config::get_bool(&ctx.config.segment(segment).options, "strict_utf8")
+        .map_err(|e| SegmentError::failed(segment, "invalid config").with_source(e))?
+        .unwrap_or(false);
+
+    match name.to_str() {
+        Some(name) => Ok(Cow::Borrowed(name)),
+        None if strict => Err(SegmentError::failed(segment, "name is not valid UTF-8")),
+        None => Ok(name.to_string_lossy()),
+    }
+}
+
+/// The mark `segment` puts in front of its text: `icon` from its config if
+/// set, where an empty one leaves the text unmarked, or else `unicode` or
+/// `ascii` depending on the `icons` mode
+#[cfg(any(
+    feature = "git",
+    feature = "hg",
+    feature = "repo-size",
+    feature = "cargo",
+    feature = "docker",
+    feature = "docker-context",
+    feature = "conda",
+    feature = "venv",
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "rust-toolchain",
+    feature = "users",
+    feature = "vpn",
+    feature = "mail",
+    feature = "cpu-temp"
+))]
+pub(crate) fn icon(
+    ctx: &Context,
+    segment: &str,
+    unicode: &str,
+    ascii: &str,
+) -> Result<Option<String>, SegmentError> {
+    let options = &ctx.config.segment(segment).options;
+    let configured = config::get_str(options, "icon")
+        .map_err(|e| SegmentError::failed(segment, "invalid config").with_source(e))?;
+    let icon = configured.unwrap_or(match ctx.config.icons {
+        IconMode::Unicode => unicode,
+        IconMode::Ascii => ascii,
+    });
+    Ok(Some(icon.to_string()).filter(|icon| !icon.is_empty()))
+}
+
+/// `text` behind the icon `segment` gets from `icon`, or the icon on its own
+/// when there's no text
+#[cfg(any(
+    feature = "docker",
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "mail"
+))]
+pub(crate) fn with_icon(
+    ctx: &Context,
+    segment: &str,
+    unicode: &str,
+    ascii: &str,
+    text: Option<String>,
+) -> Result<DecoratedString, SegmentError> {
+    Ok(match (icon(ctx, segment, unicode, ascii)?, text) {
+        (Some(icon), Some(text)) => DecoratedString::new(text).with_icon(&icon),
+        (None, Some(text)) => DecoratedString::new(text),
+        (icon, None) => DecoratedString::new(icon.unwrap_or_default()),
+    })
+}
+
+/// Root of the project marked by any of `markers` that the cwd is in, and how
+/// many directories up from the cwd it is
+#[cfg(any(
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "bazel",
+    feature = "cargo"
+))]
+pub(crate) fn project_root(
+    ctx: &Context,
+    segment: &str,
+    markers: &[&str],
+) -> Result<(PathBuf, usize), SegmentError> {
+    let root = ctx
+        .find_root(markers)
+        .ok_or_else(|| SegmentError::not_applicable(segment, "not in a project"))?;
+    let cwd_depth = ctx
+        .cwd()
+        .map_or(0, |cwd| ctx.path(cwd).components().count());
+    let depth = cwd_depth.saturating_sub(root.components().count());
+    Ok((root, depth))
+}
+
+/// `value` with the quotes around it removed, if it's a JSON string
+#[cfg(any(feature = "node", feature = "docker-context"))]
+pub(crate) fn json_string(value: &str) -> Option<&str> {
+    let value = value.trim_start().strip_prefix('"')?;
+    value.split('"').next()
+}
+
+/// The JSON value of `key` in `json`, the first time it shows up as a key.
+/// Enough to pick a field out of a small file without parsing all of it
+#[cfg(any(feature = "node", feature = "docker-context"))]
+pub(crate) fn json_value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let quoted = format!("\"{key}\"");
+    json.match_indices(&quoted)
+        .find_map(|(start, _)| json[start + quoted.len()..].trim_start().strip_prefix(':'))
+}
+
+/// All built in segments that were compiled in, in the order they are printed
+pub fn builtin() -> Vec<Arc<dyn Segment>> {
+    vec![
+        #[cfg(feature = "time")]
+        Arc::new(time::Time),
+        #[cfg(feature = "idle")]
+        Arc::new(idle::Idle),
+        #[cfg(feature = "docker")]
+        Arc::new(docker::Docker),
+        #[cfg(feature = "docker-context")]
+        Arc::new(docker_context::DockerContext),
+        #[cfg(feature = "sandbox")]
+        Arc::new(sandbox::Sandbox),
+        #[cfg(feature = "vm")]
+        Arc::new(vm::Vm),
+        #[cfg(feature = "user")]
+        Arc::new(user::User),
+        #[cfg(feature = "users")]
+        Arc::new(users::Users),
+        #[cfg(feature = "sudo")]
+        Arc::new(sudo::Sudo),
+        #[cfg(feature = "hostname")]
+        Arc::new(hostname::Hostname),
+        #[cfg(feature = "uptime")]
+        Arc::new(uptime::Uptime),
+        #[cfg(feature = "systemd")]
+        Arc::new(systemd::Systemd),
+        #[cfg(feature = "updates")]
+        Arc::new(updates::Updates),
+        #[cfg(feature = "cwd")]
+        Arc::new(cwd::Cwd),
+        #[cfg(feature = "shell")]
+        Arc::new(shell::Shell),
+        #[cfg(feature = "shlvl")]
+        Arc::new(shlvl::Shlvl),
+        #[cfg(feature = "status")]
+        Arc::new(status::Status),
+        #[cfg(feature = "umask")]
+        Arc::new(umask::Umask),
+        #[cfg(feature = "hg")]
+        Arc::new(hg::Hg),
+        #[cfg(feature = "hg-drafts")]
+        Arc::new(hg_drafts::HgDrafts),
+        #[cfg(feature = "git")]
+        Arc::new(git::Git),
+        #[cfg(feature = "unpushed")]
+        Arc::new(unpushed::Unpushed),
+        #[cfg(feature = "repo-size")]
+        Arc::new(repo_size::RepoSize),
+        #[cfg(feature = "vpn")]
+        Arc::new(vpn::Vpn),
+        #[cfg(feature = "offline")]
+        Arc::new(offline::Offline),
+        #[cfg(feature = "local-ip")]
+        Arc::new(local_ip::LocalIp),
+        #[cfg(feature = "conda")]
+        Arc::new(conda::Conda),
+        #[cfg(feature = "venv")]
+        Arc::new(venv::Venv),
+        #[cfg(feature = "nix-shell")]
+        Arc::new(nix_shell::NixShell),
+        #[cfg(feature = "nix-path")]
+        Arc::new(nix_path::NixPath),
+        #[cfg(feature = "guix-env")]
+        Arc::new(guix_env::GuixEnv),
+        #[cfg(feature = "rust-toolchain")]
+        Arc::new(rust_toolchain::RustToolchain),
+        #[cfg(feature = "cargo")]
+        Arc::new(cargo::Cargo),
+        #[cfg(feature = "java")]
+        Arc::new(java::Java),
+        #[cfg(feature = "php")]
+        Arc::new(php::Php),
+        #[cfg(feature = "ruby")]
+        Arc::new(ruby::Ruby),
+        #[cfg(feature = "node")]
+        Arc::new(node::Node),
+        #[cfg(feature = "bazel")]
+        Arc::new(bazel::Bazel),
+        #[cfg(feature = "cmake")]
+        Arc::new(cmake::Cmake),
+        #[cfg(feature = "env-watch")]
+        Arc::new(env_watch::EnvWatch),
+        #[cfg(feature = "locale")]
+        Arc::new(locale::Locale),
+        #[cfg(feature = "mail")]
+        Arc::new(mail::Mail),
+        #[cfg(feature = "cpu-temp")]
+        Arc::new(cpu_temp::CpuTemp),
+        #[cfg(feature = "vi-mode")]
+        Arc::new(vi_mode::ViMode),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_matches_features() {
+        let names: Vec<_> = builtin().iter().map(|s| s.name().to_string()).collect();
+        let compiled = [
+            ("time", cfg!(feature = "time")),
+            ("idle", cfg!(feature = "idle")),
+            ("docker", cfg!(feature = "docker")),
+            ("docker_context", cfg!(feature = "docker-context")),
+            ("sandbox", cfg!(feature = "sandbox")),
+            ("vm", cfg!(feature = "vm")),
+            ("user", cfg!(feature = "user")),
+            ("users", cfg!(feature = "users")),
+            ("sudo", cfg!(feature = "sudo")),
+            ("hostname", cfg!(feature = "hostname")),
+            ("uptime", cfg!(feature = "uptime")),
+            ("systemd", cfg!(feature = "systemd")),
+            ("updates", cfg!(feature = "updates")),
+            ("cwd", cfg!(feature = "cwd")),
+            ("shell", cfg!(feature = "shell")),
+            ("shlvl", cfg!(feature = "shlvl")),
+            ("status", cfg!(feature = "status")),
+            ("umask", cfg!(feature = "umask")),
+            ("hg", cfg!(feature = "hg")),
+            ("hg_drafts", cfg!(feature = "hg-drafts")),
+            ("git", cfg!(feature = "git")),
+            ("unpushed", cfg!(feature = "unpushed")),
+            ("repo_size", cfg!(feature = "repo-size")),
+            ("vpn", cfg!(feature = "vpn")),
+            ("offline", cfg!(feature = "offline")),
+            ("local_ip", cfg!(feature = "local-ip")),
+            ("conda", cfg!(feature = "conda")),
+            ("venv", cfg!(feature = "venv")),
+            ("nix_shell", cfg!(feature = "nix-shell")),
+            ("nix_path", cfg!(feature = "nix-path")),
+            ("guix_env", cfg!(feature = "guix-env")),
+            ("rust_toolchain", cfg!(feature = "rust-toolchain")),
+            ("cargo", cfg!(feature = "cargo")),
+            ("java", cfg!(feature = "java")),
+            ("php", cfg!(feature = "php")),
+            ("ruby", cfg!(feature = "ruby")),
+            ("node", cfg!(feature = "node")),
+            ("bazel", cfg!(feature = "bazel")),
+            ("cmake", cfg!(feature = "cmake")),
+            ("env_watch", cfg!(feature = "env-watch")),
+            ("locale", cfg!(feature = "locale")),
+            ("mail", cfg!(feature = "mail")),
+            ("cpu_temp", cfg!(feature = "cpu-temp")),
+            ("vi_mode", cfg!(feature = "vi-mode")),
+        ];
+
+        for (name, enabled) in compiled {
+            assert_eq!(names.iter().any(|n| n == name), enabled, "{}", name);
+        }
+    }
+
+    #[cfg(all(unix, feature = "java", feature = "php", feature = "ruby"))]
+    #[test]
+    fn nearest_project_segments() {
+        use crate::{
+            runner,
+            testing::{self, TempDir},
+        };
+
+        let root = TempDir::new();
+        root.write("mono/composer.json", "{}\n");
+        root.write("mono/tools/Gemfile", "ruby \"3.2.2\"\n");
+        root.write("mono/tools/gen/build.gradle", "\n");
+
+        let segments: Vec<Arc<dyn Segment>> = vec![
+            Arc::new(java::Java),
+            Arc::new(php::Php),
+            Arc::new(ruby::Ruby),
+        ];
+        let shown = |cwd: &str, vars: &[(&str, &str)]| -> Vec<String> {
+            let ctx = testing::context(vars, Some(cwd)).with_root(root.path().to_path_buf());
+            runner::render_all(&segments, &Arc::new(ctx))
+                .into_iter()
+                .filter_map(|(result, _)| result.ok())
+                .map(|rendered| rendered.to_plain())
+                .collect()
+        };
+
+        assert_eq!(shown("/mono", &[]), ["🐘"]);
+        assert_eq!(shown("/mono/tools/bin", &[]), ["💎 3.2.2"]);
+        assert_eq!(shown("/mono/tools/gen", &[]), ["☕"]);
+        assert_eq!(
+            shown("/mono/tools/gen", &[("PROMPTLINE_PROJECT_SEGMENTS", "all")]),
+            ["☕", "🐘", "💎 3.2.2"]
+        );
+        assert!(shown("/mono/tools", &[("PROMPTLINE_PROJECT_SEGMENTS", "none")]).is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "nix_path";
+
+#[cfg(windows)]
+fn get_nix_path(_ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    Err(SegmentError::not_applicable(
+        NAME,
+        "nix doesn't run on windows",
+    ))
+}
+
+/// `nix shell` and `nix run` set no variable, but put the store paths of
+/// their packages in front of `PATH`. Store paths further back are left out,
+/// as a profile or the system itself may add them
+#[cfg(not(windows))]
+fn get_nix_path(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    if ctx.var("IN_NIX_SHELL").is_some() {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "nix_shell segment shows the shell",
+        ));
+    }
+
+    let path = ctx.var("PATH").unwrap_or("");
+    let first = std::env::split_paths(path).next().unwrap_or_default();
+    if !first.starts_with("/nix/store") {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "PATH doesn't start in the nix store",
+        ));
+    }
+
+    Ok(DecoratedString::new("nix*".into()).dim())
+}
+
+/// Dim `nix*` inside `nix shell` and `nix run`, guessed from `PATH`. Can be
+/// turned off on its own, as it is a guess unlike the `nix_shell` segment
+pub struct NixPath;
+
+impl Segment for NixPath {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_nix_path(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const STORE_BIN: &str = "/nix/store/8ifd4pyqdq0zp5wqc2ph3fqsyw7ms2l7-hello-2.12.1/bin";
+
+    fn nix_path(vars: &[(&str, &str)]) -> Result<DecoratedString, SegmentError> {
+        get_nix_path(&testing::context(vars, None))
+    }
+
+    #[test]
+    fn store_path_first() {
+        let path = format!("{STORE_BIN}:/usr/bin");
+        let nix = nix_path(&[("PATH", &path)]).unwrap();
+        assert_eq!(nix.to_ansi(), "\x1b[2mnix*\x1b[22m");
+    }
+
+    #[test]
+    fn store_path_later() {
+        let path = format!("/usr/bin:{STORE_BIN}");
+        assert!(nix_path(&[("PATH", &path)]).is_err());
+        assert!(nix_path(&[("PATH", "/run/current-system/sw/bin")]).is_err());
+        assert!(nix_path(&[]).is_err());
+    }
+
+    #[test]
+    fn left_to_nix_shell() {
+        let path = format!("{STORE_BIN}:/usr/bin");
+        assert!(nix_path(&[("PATH", &path), ("IN_NIX_SHELL", "impure")]).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "nix_shell";
+
+#[cfg(windows)]
+fn show_nix_shell(_ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    Err(SegmentError::not_applicable(
+        NAME,
+        "nix doesn't run on windows",
+    ))
+}
+
+#[cfg(not(windows))]
+fn show_nix_shell(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    ctx.var("IN_NIX_SHELL")
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "not in nix shell"))?;
+
+    let shell_name = ctx.var("name").unwrap_or("nix-shell").to_string();
+
+    Ok(DecoratedString::new(shell_name).with_icon("nix:").bold())
+}
+
+/// Name of the nix-shell the user is in
+pub struct NixShell;
+
+impl Segment for NixShell {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        show_nix_shell(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn shows_name() {
+        let ctx = testing::context(&[("IN_NIX_SHELL", "impure"), ("name", "dev")], None);
+        assert_eq!(show_nix_shell(&ctx).unwrap().to_plain(), "nix: dev");
+    }
+
+    #[test]
+    fn default_name() {
+        let ctx = testing::context(&[("IN_NIX_SHELL", "pure")], None);
+        assert_eq!(show_nix_shell(&ctx).unwrap().to_plain(), "nix: nix-shell");
+    }
+
+    #[test]
+    fn not_in_nix_shell() {
+        let ctx = testing::context(&[("name", "dev")], None);
+        assert!(show_nix_shell(&ctx).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    fs,
+    path::{Component, Path},
+};
+
+use super::{json_string, json_value, project_root};
+use crate::{
+    decorated::Color,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "node";
+
+const PACKAGE_JSON: &str = "package.json";
+const NVMRC: &str = ".nvmrc";
+
+/// The version in a dir of `path` named like `v20.11.1`, as nvm and fnm name
+/// the dirs they install node into
+fn version_in_path(path: &str) -> Option<&str> {
+    Path::new(path).components().find_map(|component| {
+        let dir = match component {
+            Component::Normal(dir) => dir.to_str()?,
+            _ => return None,
+        };
+        let version = dir.strip_prefix('v')?;
+        let is_version = version.starts_with(|c: char| c.is_ascii_digit())
+            && version.chars().all(|c| c.is_ascii_digit() || c == '.');
+        is_version.then_some(version)
+    })
+}
+
+/// Node version pinned in the `volta` object of a package.json, as in
+/// `"volta": { "node": "20.11.1" }`. The object is flat, so it is read as far
+/// as the first `}` rather than parsing the JSON
+fn volta_pin(package_json: &str) -> Option<&str> {
+    let volta = json_value(package_json, "volta")?
+        .trim_start()
+        .strip_prefix('{')?;
+    let volta = volta.split('}').next()?;
+    json_string(json_value(volta, "node")?).filter(|version| !version.is_empty())
+}
+
+/// The version of node the version manager in use has put first in `$PATH`
+fn active_version(ctx: &Context, package_json: &str) -> Option<String> {
+    if ctx.var("VOLTA_HOME").is_some() {
+        if let Some(pin) = volta_pin(package_json) {
+            return Some(pin.to_string());
+        }
+    }
+    if let Some(version) = ctx.var("NVM_BIN").and_then(version_in_path) {
+        return Some(version.to_string());
+    }
+    // fnm links a dir per shell to the installation it is using
+    let multishell = ctx.var("FNM_MULTISHELL_PATH")?;
+    let target = fs::read_link(ctx.path(multishell)).ok()?;
+    version_in_path(target.to_str()?).map(str::to_string)
+}
+
+/// Version asked for in a `.nvmrc`, without the `v` it can have
+fn nvmrc_version(contents: &str) -> Option<&str> {
+    let version = contents.lines().next()?.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    Some(version).filter(|version| !version.is_empty())
+}
+
+/// Whether `active` is the version `.nvmrc` asks for, where `20` takes any
+/// 20.x. Aliases like `lts/*` can't be checked without the manager, so they
+/// are taken to match
+fn satisfies(active: &str, wanted: &str) -> bool {
+    if !wanted.starts_with(|c: char| c.is_ascii_digit()) {
+        return true;
+    }
+    let mut active = active.split('.');
+    wanted.split('.').all(|part| active.next() == Some(part))
+}
+
+fn get_node(ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+    let (root, depth) = project_root(ctx, NAME, &[PACKAGE_JSON, NVMRC])?;
+
+    let nvmrc = fs::read_to_string(root.join(NVMRC)).unwrap_or_default();
+    let package_json = fs::read_to_string(root.join(PACKAGE_JSON)).unwrap_or_default();
+    let wanted = nvmrc_version(&nvmrc);
+    let icon = |version| super::with_icon(ctx, NAME, "⬢", "node:", version);
+    let node = match (active_version(ctx, &package_json), wanted) {
+        (Some(active), Some(wanted)) if !satisfies(&active, wanted) => {
+            icon(Some(active))?.colored(Color::Red)
+        }
+        (Some(active), _) => icon(Some(active))?.dim(),
+        (None, wanted) => icon(wanted.map(str::to_string))?.dim(),
+    };
+    Ok(RenderedSegment::from(node).with_project_depth(depth))
+}
+
+/// `⬢` with the node version inside node projects. The version is the one
+/// nvm, fnm or volta has made active, in red if it isn't the one the
+/// project's `.nvmrc` asks for, or else the one in `.nvmrc`
+pub struct Node;
+
+impl Segment for Node {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_node(ctx)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    const PACKAGE_JSON: &str = r#"{
+  "name": "shop",
+  "engines": { "node": ">=18" },
+  "volta": {
+    "node": "20.11.1",
+    "npm": "10.2.4"
+  }
+}
+"#;
+
+    fn shown(root: &TempDir, vars: &[(&str, &str)]) -> String {
+        let ctx = testing::context(vars, Some("/src/shop")).with_root(root.path().to_path_buf());
+        get_node(&ctx).unwrap().to_ansi()
+    }
+
+    #[test]
+    fn nvm() {
+        assert_eq!(
+            version_in_path("/home/ada/.nvm/versions/node/v20.11.1/bin"),
+            Some("20.11.1")
+        );
+        assert_eq!(version_in_path("/usr/local/bin"), None);
+        assert_eq!(
+            version_in_path("/home/ada/.nvm/versions/node/vendor/bin"),
+            None
+        );
+
+        let root = TempDir::new();
+        root.write("src/shop/.nvmrc", "v20\n");
+        let nvm_bin = ("NVM_BIN", "/home/ada/.nvm/versions/node/v20.11.1/bin");
+        assert_eq!(shown(&root, &[nvm_bin]), "\x1b[2m⬢ 20.11.1\x1b[22m");
+
+        // The classic forgetting to `nvm use`
+        let nvm_bin = ("NVM_BIN", "/home/ada/.nvm/versions/node/v18.19.0/bin");
+        assert_eq!(shown(&root, &[nvm_bin]), "\x1b[31m⬢ 18.19.0\x1b[39m");
+    }
+
+    #[test]
+    fn fnm() {
+        let root = TempDir::new();
+        root.write("src/shop/.nvmrc", "20.11\n");
+        root.write(
+            "home/ada/.local/share/fnm/node-versions/v20.11.1/installation/bin/node",
+            "",
+        );
+        root.write("run/user/1000/fnm_multishells/.keep", "");
+        symlink(
+            root.path()
+                .join("home/ada/.local/share/fnm/node-versions/v20.11.1/installation"),
+            root.path()
+                .join("run/user/1000/fnm_multishells/4242_1700000000"),
+        )
+        .unwrap();
+
+        let multishell = (
+            "FNM_MULTISHELL_PATH",
+            "/run/user/1000/fnm_multishells/4242_1700000000",
+        );
+        assert_eq!(shown(&root, &[multishell]), "\x1b[2m⬢ 20.11.1\x1b[22m");
+    }
+
+    #[test]
+    fn volta() {
+        assert_eq!(volta_pin(PACKAGE_JSON), Some("20.11.1"));
+        assert_eq!(volta_pin(r#"{"volta":{"npm":"10.2.4"},"node":"x"}"#), None);
+        assert_eq!(volta_pin(r#"{"name": "volta"}"#), None);
+
+        let root = TempDir::new();
+        root.write("src/shop/package.json", PACKAGE_JSON);
+        root.write("src/shop/.nvmrc", "lts/iron\n");
+        let volta_home = ("VOLTA_HOME", "/home/ada/.volta");
+        assert_eq!(shown(&root, &[volta_home]), "\x1b[2m⬢ 20.11.1\x1b[22m");
+        // Without volta the pin means nothing
+        assert_eq!(shown(&root, &[]), "\x1b[2m⬢ lts/iron\x1b[22m");
+    }
+
+    #[test]
+    fn versions_match() {
+        assert!(satisfies("20.11.1", "20"));
+        assert!(satisfies("20.11.1", "20.11.1"));
+        assert!(!satisfies("20.11.1", "20.1"));
+        assert!(!satisfies("2.0.0", "20"));
+        assert!(satisfies("18.19.0", "lts/*"));
+        assert_eq!(nvmrc_version("v20.11.1\n"), Some("20.11.1"));
+        assert_eq!(nvmrc_version("\n"), None);
+    }
+
+    #[test]
+    fn no_version() {
+        let root = TempDir::new();
+        root.write("src/shop/package.json", "{}\n");
+        assert_eq!(shown(&root, &[]), "\x1b[2m⬢\x1b[22m");
+    }
+}
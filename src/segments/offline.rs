@@ -0,0 +1,178 @@
+use crate::{
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "offline";
+
+/// `RTF_UP` from the kernel's route flags
+const RTF_UP: u32 = 0x0001;
+/// `RTF_REJECT`, set on the unreachable default route IPv6 keeps on `lo`
+const RTF_REJECT: u32 = 0x0200;
+
+fn is_usable(flags: u32) -> bool {
+    flags & RTF_UP != 0 && flags & RTF_REJECT == 0
+}
+
+fn parse_hex(field: &str) -> Option<u32> {
+    u32::from_str_radix(field, 16).ok()
+}
+
+/// The interface of the first up default route in `/proc/net/route`, the one
+/// with destination and mask `00000000`. The first line is a header
+pub(crate) fn ipv4_default_interface(route: &str) -> Option<&str> {
+    route.lines().skip(1).find_map(|line| {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        match (
+            fields.get(1).copied().and_then(parse_hex),
+            fields.get(3).copied().and_then(parse_hex),
+            fields.get(7).copied().and_then(parse_hex),
+        ) {
+            (Some(0), Some(flags), Some(0)) if is_usable(flags) => Some(fields[0]),
+            _ => None,
+        }
+    })
+}
+
+fn has_ipv4_default(route: &str) -> bool {
+    ipv4_default_interface(route).is_some()
+}
+
+/// Whether `/proc/net/ipv6_route` has an up default route, `::/0`. It has no
+/// header, the destination and its prefix length come first and the flags are
+/// the ninth field
+fn has_ipv6_default(route: &str) -> bool {
+    route.lines().any(|line| {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let default = fields
+            .first()
+            .is_some_and(|dest| dest.len() == 32 && dest.chars().all(|c| c == '0'));
+        match (fields.get(1).copied().and_then(parse_hex), fields.get(8)) {
+            (Some(0), Some(flags)) if default => parse_hex(flags).is_some_and(is_usable),
+            _ => false,
+        }
+    })
+}
+
+fn get_offline(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let ipv4 = ctx.read_to_string("/proc/net/route");
+    let ipv6 = ctx.read_to_string("/proc/net/ipv6_route");
+    if ipv4.is_err() && ipv6.is_err() {
+        return Err(SegmentError::not_applicable(NAME, "no routing table"));
+    }
+
+    let online = ipv4.is_ok_and(|route| has_ipv4_default(&route))
+        || ipv6.is_ok_and(|route| has_ipv6_default(&route));
+    if online {
+        return Err(SegmentError::not_applicable(NAME, "default route is up"));
+    }
+
+    Ok(DecoratedString::new("offline".into())
+        .colored(Color::Red)
+        .bold())
+}
+
+/// Warns when there is no default route. Off unless `enabled = true`, as
+/// containers often run without one on purpose
+pub struct Offline;
+
+impl Segment for Offline {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_offline(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    const IPV4_HEADER: &str =
+        "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n";
+    const IPV4_DEFAULT: &str = "eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n";
+    const IPV4_LAN: &str = "eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n";
+
+    const IPV6_DEFAULT: &str = "00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003 wlan0\n";
+    const IPV6_LO_UNREACHABLE: &str = "00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200 lo\n";
+    const IPV6_LINK_LOCAL: &str = "fe800000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001 wlan0\n";
+
+    #[test]
+    fn ipv4_default_route() {
+        assert!(has_ipv4_default(&format!(
+            "{IPV4_HEADER}{IPV4_LAN}{IPV4_DEFAULT}"
+        )));
+        assert!(!has_ipv4_default(&format!("{IPV4_HEADER}{IPV4_LAN}")));
+        assert!(!has_ipv4_default(IPV4_HEADER));
+        assert_eq!(
+            ipv4_default_interface(&format!("{IPV4_HEADER}{IPV4_LAN}{IPV4_DEFAULT}")),
+            Some("eth0")
+        );
+        // Down routes don't count
+        assert!(!has_ipv4_default(&format!(
+            "{IPV4_HEADER}eth0\t00000000\t0101A8C0\t0002\t0\t0\t100\t00000000\t0\t0\t0\n"
+        )));
+    }
+
+    #[test]
+    fn ipv6_default_route() {
+        assert!(has_ipv6_default(&format!(
+            "{IPV6_LINK_LOCAL}{IPV6_DEFAULT}"
+        )));
+        assert!(!has_ipv6_default(&format!(
+            "{IPV6_LINK_LOCAL}{IPV6_LO_UNREACHABLE}"
+        )));
+        assert!(!has_ipv6_default("garbage\n"));
+    }
+
+    fn with_routes(ipv4: Option<&str>, ipv6: Option<&str>) -> (TempDir, Context) {
+        let root = TempDir::new();
+        if let Some(ipv4) = ipv4 {
+            root.write("proc/net/route", ipv4);
+        }
+        if let Some(ipv6) = ipv6 {
+            root.write("proc/net/ipv6_route", ipv6);
+        }
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        (root, ctx)
+    }
+
+    fn hidden(ctx: &Context) -> bool {
+        matches!(get_offline(ctx), Err(e) if e.kind == ErrorKind::NotApplicable)
+    }
+
+    #[test]
+    fn offline_without_any_default() {
+        let (_root, ctx) = with_routes(Some(IPV4_HEADER), Some(IPV6_LO_UNREACHABLE));
+        assert_eq!(get_offline(&ctx).unwrap().to_plain(), "offline");
+    }
+
+    #[test]
+    fn either_family_is_enough() {
+        let (_root, ctx) = with_routes(
+            Some(&format!("{IPV4_HEADER}{IPV4_DEFAULT}")),
+            Some(IPV6_LO_UNREACHABLE),
+        );
+        assert!(hidden(&ctx));
+
+        let (_root, ctx) = with_routes(None, Some(IPV6_DEFAULT));
+        assert!(hidden(&ctx));
+    }
+
+    #[test]
+    fn hidden_without_proc() {
+        let (_root, ctx) = with_routes(None, None);
+        assert!(hidden(&ctx));
+    }
+}
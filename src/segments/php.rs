@@ -0,0 +1,98 @@
+use std::fs;
+
+use super::project_root;
+use crate::{
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "php";
+
+const COMPOSER: &str = "composer.json";
+
+/// The package `name` in a `composer.json`, from the first `"name": "..."`
+/// line. Composer puts it at the top, above any authors with names of their own
+fn package_name(composer: &str) -> Option<&str> {
+    composer.lines().find_map(|line| {
+        let value = line
+            .trim()
+            .strip_prefix("\"name\"")?
+            .trim_start()
+            .strip_prefix(':')?
+            .trim()
+            .trim_end_matches(',');
+        value
+            .strip_prefix('"')?
+            .strip_suffix('"')
+            .filter(|name| !name.is_empty())
+    })
+}
+
+fn get_php(ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+    let (root, depth) = project_root(ctx, NAME, &[COMPOSER])?;
+
+    let composer = fs::read_to_string(root.join(COMPOSER)).unwrap_or_default();
+    let name = package_name(&composer).map(str::to_string);
+    let php = super::with_icon(ctx, NAME, "🐘", "php:", name)?;
+    Ok(RenderedSegment::from(php.dim()).with_project_depth(depth))
+}
+
+/// `🐘` with the package name inside Composer projects
+pub struct Php;
+
+impl Segment for Php {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_php(ctx)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    const COMPOSER: &str = r#"{
+    "name": "acme/shop",
+    "type": "project",
+    "authors": [
+        { "name": "Jane Doe" }
+    ]
+}
+"#;
+
+    #[test]
+    fn composer_project() {
+        let root = TempDir::new();
+        root.write("src/shop/composer.json", COMPOSER);
+        root.write("src/shop/public/index.php", "");
+
+        let ctx =
+            testing::context(&[], Some("/src/shop/public")).with_root(root.path().to_path_buf());
+        assert_eq!(
+            get_php(&ctx).unwrap().to_ansi(),
+            "\x1b[2m🐘 acme/shop\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn malformed_composer_json() {
+        let root = TempDir::new();
+        root.write("src/shop/composer.json", "{ \"name\": ");
+
+        let ctx = testing::context(&[], Some("/src/shop")).with_root(root.path().to_path_buf());
+        assert_eq!(get_php(&ctx).unwrap().to_plain(), "🐘");
+    }
+
+    #[test]
+    fn not_a_composer_project() {
+        let root = TempDir::new();
+        root.write("src/shop/index.php", "");
+
+        let ctx = testing::context(&[], Some("/src/shop")).with_root(root.path().to_path_buf());
+        assert!(get_php(&ctx).is_err());
+    }
+}
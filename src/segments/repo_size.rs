@@ -0,0 +1,142 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    config,
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    segments::git,
+    size::format_size,
+    Context,
+};
+
+const NAME: &str = "repo_size";
+
+/// Packs above this many MiB make a repo worth warning about, unless the config
+/// says otherwise. High enough for big but healthy repos to stay quiet
+const DEFAULT_THRESHOLD_MIB: u64 = 8 * 1024;
+
+/// Entries of `objects/pack` looked at. `git gc` keeps a handful, a repo with
+/// far more than this is already slow for reasons the prompt can't fix
+const MAX_PACK_ENTRIES: usize = 1024;
+
+/// Total size of the files in `objects/pack`. Loose objects are left out, as
+/// counting them means reading 256 directories every prompt
+fn pack_size(git_dir: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(git_dir.join("objects/pack"))?.take(MAX_PACK_ENTRIES) {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            total += metadata.len();
+        }
+    }
+    Ok(total)
+}
+
+fn get_repo_size(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let threshold_mib = config::get_int(&ctx.config.segment(NAME).options, "threshold_mib")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .map_or(DEFAULT_THRESHOLD_MIB, |mib| mib.max(0) as u64);
+
+    let git_dir = git::git_dir(ctx, NAME)?;
+    let size = pack_size(&git_dir)
+        .map_err(|e| SegmentError::not_applicable(NAME, "failed to read packs").with_source(e))?;
+
+    if size <= threshold_mib.saturating_mul(1024 * 1024) {
+        return Err(SegmentError::not_applicable(NAME, "packs below threshold"));
+    }
+
+    let text = DecoratedString::new(format_size(size));
+    let text = match super::icon(ctx, NAME, "⚠", "size:")? {
+        Some(icon) => text.with_icon(&icon),
+        None => text,
+    };
+    Ok(text.dim())
+}
+
+/// `⚠ 4.2G` when the packs of the enclosing git repo add up to more than
+/// `threshold_mib`, e.g. after build artifacts were committed. Off unless
+/// `enabled = true`
+pub struct RepoSize;
+
+impl Segment for RepoSize {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_repo_size(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    /// A repo with packs of `sizes`, as sparse files so they take no room
+    fn repo(sizes: &[u64]) -> TempDir {
+        let root = TempDir::new();
+        root.write("repo/.git/HEAD", "ref: refs/heads/main\n");
+        root.write("repo/.git/refs/heads/main", "");
+        for (i, size) in sizes.iter().enumerate() {
+            let relative = format!("repo/.git/objects/pack/pack-{i}.pack");
+            root.write(&relative, "");
+            let file = fs::OpenOptions::new()
+                .write(true)
+                .open(root.path().join(relative))
+                .unwrap();
+            file.set_len(*size).unwrap();
+        }
+        root
+    }
+
+    fn render(root: &TempDir, toml: &str) -> Result<DecoratedString, SegmentError> {
+        let mut ctx = testing::context(&[], Some("/repo")).with_root(root.path().to_path_buf());
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        get_repo_size(&ctx)
+    }
+
+    #[test]
+    fn large_packs() {
+        let root = repo(&[GIB * 4, GIB * 5, GIB / 5]);
+        assert_eq!(
+            render(&root, "").unwrap().to_ansi(),
+            "\x1b[2m⚠ 9.2G\x1b[22m"
+        );
+        assert_eq!(
+            render(&root, "icons = \"ascii\"\n").unwrap().to_plain(),
+            "size: 9.2G"
+        );
+    }
+
+    #[test]
+    fn below_threshold() {
+        let root = repo(&[GIB * 4]);
+        let res = render(&root, "");
+        assert!(matches!(res, Err(e) if e.kind == ErrorKind::NotApplicable));
+
+        let res = render(&root, "[segments.repo_size]\nthreshold_mib = 1024\n");
+        assert_eq!(res.unwrap().to_plain(), "⚠ 4.0G");
+    }
+
+    #[test]
+    fn no_packs() {
+        let root = repo(&[]);
+        let res = render(&root, "[segments.repo_size]\nthreshold_mib = 0\n");
+        assert!(matches!(res, Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+}
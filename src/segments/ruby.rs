@@ -0,0 +1,99 @@
+use std::fs;
+
+use super::project_root;
+use crate::{
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "ruby";
+
+const GEMFILE: &str = "Gemfile";
+
+/// Version in a `.ruby-version` file, which version managers accept with or
+/// without a `ruby-` prefix
+fn version_file(contents: &str) -> Option<&str> {
+    let version = contents.lines().next()?.trim();
+    let version = version.strip_prefix("ruby-").unwrap_or(version);
+    Some(version).filter(|version| !version.is_empty())
+}
+
+/// Version from a `ruby "3.2.2"` line in a Gemfile
+fn gemfile_version(gemfile: &str) -> Option<&str> {
+    gemfile.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("ruby ")?.trim();
+        ['"', '\'']
+            .iter()
+            .find_map(|&quote| value.strip_prefix(quote)?.split(quote).next())
+            .filter(|version| !version.is_empty())
+    })
+}
+
+fn get_ruby(ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+    let (root, depth) = project_root(ctx, NAME, &[GEMFILE])?;
+
+    let pinned = fs::read_to_string(root.join(".ruby-version")).unwrap_or_default();
+    let gemfile = fs::read_to_string(root.join(GEMFILE)).unwrap_or_default();
+    let version = version_file(&pinned)
+        .or_else(|| gemfile_version(&gemfile))
+        .map(str::to_string);
+    let ruby = super::with_icon(ctx, NAME, "💎", "ruby:", version)?;
+    Ok(RenderedSegment::from(ruby.dim()).with_project_depth(depth))
+}
+
+/// `💎` with the pinned Ruby version inside Bundler projects
+pub struct Ruby;
+
+impl Segment for Ruby {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_ruby(ctx)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    const GEMFILE: &str =
+        "source \"https://rubygems.org\"\nruby \"3.2.2\"\ngem \"rails\", \"~> 7.1\"\n";
+
+    fn in_dir(root: &TempDir, cwd: &str) -> Context {
+        testing::context(&[], Some(cwd)).with_root(root.path().to_path_buf())
+    }
+
+    #[test]
+    fn gemfile_ruby_line() {
+        let root = TempDir::new();
+        root.write("src/blog/Gemfile", GEMFILE);
+        assert_eq!(
+            get_ruby(&in_dir(&root, "/src/blog")).unwrap().to_ansi(),
+            "\x1b[2m💎 3.2.2\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn ruby_version_file_wins() {
+        let root = TempDir::new();
+        root.write("src/blog/Gemfile", GEMFILE);
+        root.write("src/blog/.ruby-version", "ruby-3.3.0\n");
+        assert_eq!(
+            get_ruby(&in_dir(&root, "/src/blog")).unwrap().to_plain(),
+            "💎 3.3.0"
+        );
+    }
+
+    #[test]
+    fn unpinned_gemfile() {
+        let root = TempDir::new();
+        root.write("src/blog/Gemfile", "gem 'sinatra'\nruby(\n");
+        assert_eq!(
+            get_ruby(&in_dir(&root, "/src/blog")).unwrap().to_plain(),
+            "💎"
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::{self, Table, Value},
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "rust_toolchain";
+
+/// Files pinning the toolchain of the directory they are in, in the order
+/// rustup looks for them
+const TOOLCHAIN_FILES: &[&str] = &["rust-toolchain", "rust-toolchain.toml"];
+
+fn rustup_home(ctx: &Context) -> Option<PathBuf> {
+    match ctx.var("RUSTUP_HOME") {
+        Some(home) => Some(PathBuf::from(home)),
+        None => Some(Path::new(ctx.var("HOME")?).join(".rustup")),
+    }
+}
+
+/// `channel` from a `rust-toolchain.toml`, or the whole of a legacy
+/// `rust-toolchain` file that only names the toolchain
+fn file_channel(contents: &str) -> Option<String> {
+    if let Ok(table) = config::parse(contents) {
+        let toolchain = config::get_table(&table, "toolchain").ok()??;
+        return config::get_str(toolchain, "channel")
+            .ok()?
+            .map(String::from);
+    }
+
+    let channel = contents.trim();
+    Some(channel.to_string()).filter(|channel| !channel.is_empty() && !channel.contains('\n'))
+}
+
+/// `nightly` out of `nightly-x86_64-unknown-linux-gnu`, keeping a date if the
+/// channel has one. Custom toolchains are left as they are
+fn short_name(toolchain: &str) -> String {
+    let parts: Vec<_> = toolchain.split('-').collect();
+    let is_channel = ["stable", "beta", "nightly"].contains(&parts[0])
+        || parts[0].starts_with(|c: char| c.is_ascii_digit());
+    if !is_channel {
+        return toolchain.to_string();
+    }
+
+    let is_digits = |part: &str, len| part.len() == len && part.chars().all(|c| c.is_ascii_digit());
+    let has_date = parts.len() >= 4
+        && is_digits(parts[1], 4)
+        && is_digits(parts[2], 2)
+        && is_digits(parts[3], 2);
+    parts[..if has_date { 4 } else { 1 }].join("-")
+}
+
+/// The toolchain that applies to the cwd over the default, following rustup:
+/// `$RUSTUP_TOOLCHAIN` first, then the closest directory with either an
+/// override from `rustup override set` or a toolchain file, the override
+/// winning when one directory has both
+fn override_toolchain(ctx: &Context, overrides: Option<&Table>) -> Option<String> {
+    if let Some(toolchain) = ctx.var("RUSTUP_TOOLCHAIN") {
+        return Some(toolchain.to_string());
+    }
+
+    ctx.cwd()?.ancestors().find_map(|dir| {
+        let set = dir
+            .to_str()
+            .and_then(|dir| overrides?.get(dir))
+            .and_then(|toolchain| match toolchain {
+                Value::String(toolchain) => Some(toolchain.clone()),
+                _ => None,
+            });
+        set.or_else(|| {
+            TOOLCHAIN_FILES.iter().find_map(|file| {
+                let contents = ctx.read_to_string(dir.join(file)).ok()?;
+                file_channel(&contents)
+            })
+        })
+    })
+}
+
+fn get_rust_toolchain(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let settings = match rustup_home(ctx).map(|home| ctx.read_to_string(home.join("settings.toml")))
+    {
+        Some(Ok(settings)) => config::parse(&settings)
+            .map_err(|e| SegmentError::failed(NAME, "invalid rustup settings").with_source(e))?,
+        _ => Table::new(),
+    };
+    let invalid = |e| SegmentError::failed(NAME, "invalid rustup settings").with_source(e);
+    let overrides = config::get_table(&settings, "overrides").map_err(invalid)?;
+    let default = config::get_str(&settings, "default_toolchain").map_err(invalid)?;
+
+    let toolchain = override_toolchain(ctx, overrides)
+        .map(|toolchain| short_name(&toolchain))
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "no toolchain override"))?;
+    if default.map(short_name).as_ref() == Some(&toolchain) {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "override is the default toolchain",
+        ));
+    }
+
+    let toolchain = match super::icon(ctx, NAME, "⚙", "rust:")? {
+        Some(icon) => DecoratedString::new(toolchain).with_icon(&icon),
+        None => DecoratedString::new(toolchain),
+    };
+    Ok(toolchain.colored(Color::Yellow))
+}
+
+/// Rust toolchain picked by a rustup override, `$RUSTUP_TOOLCHAIN` or a
+/// `rust-toolchain(.toml)` file, when it differs from the default one
+pub struct RustToolchain;
+
+impl Segment for RustToolchain {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_rust_toolchain(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    const SETTINGS: &str = "version = \"12\"\n\
+        default_toolchain = \"stable-x86_64-unknown-linux-gnu\"\n\
+        profile = \"default\"\n\n\
+        [overrides]\n\
+        \"/home/me/fuzz\" = \"nightly-x86_64-unknown-linux-gnu\"\n\
+        \"/home/me/fuzz/old\" = \"nightly-2023-06-01-x86_64-unknown-linux-gnu\"\n\
+        \"/home/me/pinned\" = \"stable-x86_64-unknown-linux-gnu\"\n";
+
+    fn in_dir(root: &TempDir, cwd: &str, vars: &[(&str, &str)]) -> Context {
+        let mut vars = vars.to_vec();
+        vars.push(("HOME", "/home/me"));
+        testing::context(&vars, Some(cwd)).with_root(root.path().to_path_buf())
+    }
+
+    fn rustup() -> TempDir {
+        let root = TempDir::new();
+        root.write("home/me/.rustup/settings.toml", SETTINGS);
+        root
+    }
+
+    fn hidden(ctx: &Context) -> bool {
+        matches!(get_rust_toolchain(ctx), Err(e) if e.kind == ErrorKind::NotApplicable)
+    }
+
+    #[test]
+    fn directory_override() {
+        let root = rustup();
+        let ctx = in_dir(&root, "/home/me/fuzz/src", &[]);
+        assert_eq!(
+            get_rust_toolchain(&ctx).unwrap().to_ansi(),
+            "\x1b[33m⚙ nightly\x1b[39m"
+        );
+
+        let ctx = in_dir(&root, "/home/me/fuzz/old", &[]);
+        assert_eq!(
+            get_rust_toolchain(&ctx).unwrap().to_plain(),
+            "⚙ nightly-2023-06-01"
+        );
+    }
+
+    #[test]
+    fn matches_whole_components() {
+        let root = rustup();
+        assert!(hidden(&in_dir(&root, "/home/me/fuzzy", &[])));
+    }
+
+    #[test]
+    fn hidden_for_default() {
+        let root = rustup();
+        assert!(hidden(&in_dir(&root, "/home/me/pinned", &[])));
+        assert!(hidden(&in_dir(&root, "/home/me", &[])));
+    }
+
+    #[test]
+    fn toolchain_files() {
+        let root = rustup();
+        root.write(
+            "home/me/proj/rust-toolchain.toml",
+            "[toolchain]\nchannel = \"1.70.0\"\ncomponents = [\"clippy\"]\n",
+        );
+        root.write("home/me/legacy/rust-toolchain", "beta\n");
+        assert_eq!(
+            get_rust_toolchain(&in_dir(&root, "/home/me/proj/src", &[]))
+                .unwrap()
+                .to_plain(),
+            "⚙ 1.70.0"
+        );
+        assert_eq!(
+            get_rust_toolchain(&in_dir(&root, "/home/me/legacy", &[]))
+                .unwrap()
+                .to_plain(),
+            "⚙ beta"
+        );
+    }
+
+    #[test]
+    fn precedence() {
+        let root = rustup();
+        // The override for /home/me/fuzz wins over a file further up
+        root.write("home/rust-toolchain", "beta\n");
+        // and loses to a file closer to the cwd
+        root.write("home/me/fuzz/pinned/rust-toolchain", "1.70.0\n");
+
+        let ctx = in_dir(&root, "/home/me/fuzz", &[]);
+        assert_eq!(get_rust_toolchain(&ctx).unwrap().to_plain(), "⚙ nightly");
+        let ctx = in_dir(&root, "/home/me/fuzz/pinned", &[]);
+        assert_eq!(get_rust_toolchain(&ctx).unwrap().to_plain(), "⚙ 1.70.0");
+
+        let ctx = in_dir(&root, "/home/me/fuzz", &[("RUSTUP_TOOLCHAIN", "my-build")]);
+        assert_eq!(get_rust_toolchain(&ctx).unwrap().to_plain(), "⚙ my-build");
+    }
+
+    #[test]
+    fn short_names() {
+        assert_eq!(short_name("nightly-aarch64-apple-darwin"), "nightly");
+        assert_eq!(short_name("1.75.0-x86_64-pc-windows-msvc"), "1.75.0");
+        assert_eq!(short_name("beta-2024-01-02"), "beta-2024-01-02");
+        assert_eq!(short_name("my-build"), "my-build");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "sandbox";
+
+#[cfg(windows)]
+fn get_sandbox(_ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    Err(SegmentError::not_applicable(
+        NAME,
+        "sandboxes aren't detected on windows",
+    ))
+}
+
+/// `name` from the `[Application]` group of a `.flatpak-info` file
+#[cfg(not(windows))]
+fn flatpak_app(info: &str) -> Option<&str> {
+    let mut in_application = false;
+    for line in info.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_application = line == "[Application]";
+        } else if in_application {
+            if let Some(name) = line.strip_prefix("name=") {
+                return Some(name.trim()).filter(|name| !name.is_empty());
+            }
+        }
+    }
+    None
+}
+
+/// Docker first, as its own segment already shows it, then Flatpak, then
+/// Snap. Only the first one that applies is shown
+#[cfg(not(windows))]
+fn get_sandbox(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    if super::docker::in_container(ctx) {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "docker segment shows the container",
+        ));
+    }
+
+    if let Ok(info) = ctx.read_to_string("/.flatpak-info") {
+        let app = flatpak_app(&info).map(str::to_string);
+        return Ok(super::with_icon(ctx, NAME, "📦", "flatpak:", app)?.dim());
+    }
+
+    if ctx.var("SNAP").is_some() {
+        let marker = match ctx.var("SNAP_NAME") {
+            Some(name) => format!("snap: {name}"),
+            None => "snap".to_string(),
+        };
+        return Ok(DecoratedString::new(marker).dim());
+    }
+
+    Err(SegmentError::not_applicable(NAME, "not sandboxed"))
+}
+
+/// Marker shown inside Flatpak and Snap sandboxes, where paths and tooling
+/// differ from the host
+pub struct Sandbox;
+
+impl Segment for Sandbox {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_sandbox(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    const FLATPAK_INFO: &str = "[Application]\nname=org.gnome.Builder\nruntime=runtime/org.gnome.Sdk/x86_64/45\n\n[Instance]\ninstance-id=1234\n";
+
+    fn context(root: &TempDir, vars: &[(&str, &str)]) -> Context {
+        testing::context(vars, None).with_root(root.path().to_path_buf())
+    }
+
+    fn hidden(ctx: &Context) -> bool {
+        matches!(get_sandbox(ctx), Err(e) if e.kind == ErrorKind::NotApplicable)
+    }
+
+    #[test]
+    fn flatpak_app_name() {
+        let root = TempDir::new();
+        root.write(".flatpak-info", FLATPAK_INFO);
+        let ctx = context(&root, &[]);
+        assert_eq!(
+            get_sandbox(&ctx).unwrap().to_ansi(),
+            "\x1b[2m📦 org.gnome.Builder\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn flatpak_info_parsing() {
+        assert_eq!(flatpak_app(FLATPAK_INFO), Some("org.gnome.Builder"));
+        assert_eq!(flatpak_app("[Instance]\nname=nope\n"), None);
+        assert_eq!(flatpak_app(""), None);
+    }
+
+    #[test]
+    fn snap_name() {
+        let root = TempDir::new();
+        let ctx = context(&root, &[("SNAP", "/snap/foo/12"), ("SNAP_NAME", "foo")]);
+        assert_eq!(get_sandbox(&ctx).unwrap().to_plain(), "snap: foo");
+    }
+
+    #[test]
+    fn precedence() {
+        let root = TempDir::new();
+        root.write(".flatpak-info", FLATPAK_INFO);
+        let ctx = context(&root, &[("SNAP", "/snap/foo/12"), ("SNAP_NAME", "foo")]);
+        assert_eq!(
+            get_sandbox(&ctx).unwrap().to_plain(),
+            "📦 org.gnome.Builder"
+        );
+
+        root.write(".dockerenv", "");
+        assert!(hidden(&ctx));
+    }
+
+    #[test]
+    fn not_sandboxed() {
+        let root = TempDir::new();
+        assert!(hidden(&context(&root, &[])));
+    }
+}
@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use crate::{
+    decorated::DecoratedString,
+    platform,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "shell";
+
+/// Linux truncates process names to this many bytes in `/proc/<pid>/comm`
+const COMM_MAX: usize = 15;
+
+/// Name of the process `pid` is running, `None` without a `/proc` to ask
+fn process_name(ctx: &Context, pid: u32) -> Option<String> {
+    let comm = ctx.read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    Some(comm.trim_end().to_string())
+}
+
+fn truncate_comm(name: &str) -> &str {
+    let mut end = name.len().min(COMM_MAX);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
+/// Shows the shell promptline is running in when it isn't the login shell from
+/// `$SHELL`, or always if `$PROMPTLINE_SHOW_SHELL` is set. `parent` is the
+/// process that started promptline: the shell itself, or a subshell forked off
+/// it for `$(...)`, which has the same name
+fn get_shell(ctx: &Context, parent: Option<u32>) -> Result<DecoratedString, SegmentError> {
+    let shell = Path::new(
+        ctx.var("SHELL")
+            .ok_or_else(|| SegmentError::not_applicable(NAME, "shell env not set"))?,
+    );
+
+    let name = shell
+        .file_name()
+        .ok_or_else(|| SegmentError::failed(NAME, "failed to get shell name"))?
+        .to_string_lossy();
+
+    let running = match parent.and_then(|pid| process_name(ctx, pid)) {
+        Some(running) if ctx.var("PROMPTLINE_SHOW_SHELL").is_none() => running,
+        // Without a way to tell which shell is running, show the login shell
+        // as before
+        _ => return Ok(DecoratedString::new(name.to_string()).bold()),
+    };
+
+    if running == truncate_comm(&name) {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "running the login shell",
+        ));
+    }
+
+    Ok(DecoratedString::new(running).bold())
+}
+
+/// The shell name followed by the nesting depth, when the shlvl segment shows it
+/// here
+#[cfg(feature = "shlvl")]
+fn with_depth(ctx: &Context, shell: DecoratedString) -> DecoratedString {
+    match super::shlvl::shell_suffix(ctx) {
+        Some(depth) => shell.with_suffix(depth),
+        None => shell,
+    }
+}
+
+#[cfg(not(feature = "shlvl"))]
+fn with_depth(_ctx: &Context, shell: DecoratedString) -> DecoratedString {
+    shell
+}
+
+/// Name of the shell, when it isn't the user's login shell
+pub struct Shell;
+
+impl Segment for Shell {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_shell(ctx, platform::parent_pid())
+            .map(|shell| with_depth(ctx, shell))
+            .map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    /// A context with `/proc/42/comm` reading `comm`
+    fn with_parent(root: &TempDir, vars: &[(&str, &str)], comm: &str) -> Context {
+        root.write("proc/42/comm", &format!("{comm}\n"));
+        testing::context(vars, None).with_root(root.path().to_path_buf())
+    }
+
+    #[test]
+    fn shows_file_name() {
+        let ctx = testing::context(&[("SHELL", "/usr/bin/zsh")], None);
+        assert_eq!(get_shell(&ctx, None).unwrap().to_plain(), "zsh");
+    }
+
+    #[test]
+    fn no_shell_var() {
+        let ctx = testing::context(&[], None);
+        assert!(matches!(get_shell(&ctx, None), Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+
+    #[test]
+    fn no_file_name() {
+        let ctx = testing::context(&[("SHELL", "/")], None);
+        assert!(matches!(get_shell(&ctx, None), Err(e) if e.kind == ErrorKind::Failed));
+    }
+
+    #[test]
+    fn hidden_in_login_shell() {
+        let root = TempDir::new();
+        let ctx = with_parent(&root, &[("SHELL", "/usr/bin/zsh")], "zsh");
+        assert!(matches!(get_shell(&ctx, Some(42)), Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+
+    #[test]
+    fn shows_other_shell() {
+        let root = TempDir::new();
+        let ctx = with_parent(&root, &[("SHELL", "/usr/bin/zsh")], "bash");
+        assert_eq!(get_shell(&ctx, Some(42)).unwrap().to_plain(), "bash");
+    }
+
+    #[test]
+    fn compares_truncated_name() {
+        let root = TempDir::new();
+        let ctx = with_parent(
+            &root,
+            &[("SHELL", "/opt/bin/my-extremely-long-shell")],
+            "my-extremely-lo",
+        );
+        assert!(get_shell(&ctx, Some(42)).is_err());
+    }
+
+    #[test]
+    fn forced_on() {
+        let root = TempDir::new();
+        let ctx = with_parent(
+            &root,
+            &[("SHELL", "/usr/bin/zsh"), ("PROMPTLINE_SHOW_SHELL", "1")],
+            "zsh",
+        );
+        assert_eq!(get_shell(&ctx, Some(42)).unwrap().to_plain(), "zsh");
+    }
+
+    #[cfg(feature = "shlvl")]
+    #[test]
+    fn shows_depth_when_shlvl_enabled() {
+        use crate::config::{self, Config};
+
+        let mut ctx = testing::context(&[("SHLVL", "3")], None);
+        let shell = || DecoratedString::new("bash".into());
+        assert_eq!(with_depth(&ctx, shell()).to_plain(), "bash");
+
+        let table = config::parse("[segments.shlvl]\nenabled = true\n").unwrap();
+        ctx.config = Config::from_table(&table).unwrap();
+        assert_eq!(with_depth(&ctx, shell()).to_plain(), "bash ⇲3");
+    }
+
+    #[test]
+    fn falls_back_without_proc() {
+        let root = TempDir::new();
+        let ctx = testing::context(&[("SHELL", "/usr/bin/zsh")], None)
+            .with_root(root.path().to_path_buf());
+        assert_eq!(get_shell(&ctx, Some(42)).unwrap().to_plain(), "zsh");
+    }
+}
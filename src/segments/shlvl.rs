@@ -0,0 +1,122 @@
+use crate::{
+    config,
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "shlvl";
+
+/// Nesting depth shown from the first shell started inside another one
+const DEFAULT_THRESHOLD: i64 = 2;
+
+/// Depth from `$SHLVL`, `None` if it is missing, not a number or not over the
+/// configured threshold
+fn depth(ctx: &Context) -> Result<Option<i64>, SegmentError> {
+    let threshold = config::get_int(&ctx.config.segment(NAME).options, "threshold")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .unwrap_or(DEFAULT_THRESHOLD);
+
+    let depth = ctx
+        .var("SHLVL")
+        .and_then(|level| level.trim().parse::<i64>().ok());
+
+    Ok(depth.filter(|depth| *depth > threshold))
+}
+
+fn decorate(depth: i64) -> DecoratedString {
+    DecoratedString::new(format!("⇲{depth}")).dim()
+}
+
+/// The depth to show after the shell name, if the shlvl segment is enabled and
+/// the depth is over its threshold
+#[cfg(feature = "shell")]
+pub(crate) fn shell_suffix(ctx: &Context) -> Option<DecoratedString> {
+    if ctx.config.segment(NAME).enabled != Some(true) {
+        return None;
+    }
+
+    depth(ctx).ok().flatten().map(decorate)
+}
+
+/// `shell_shown` is whether the shell segment is shown, and so already shows
+/// the depth
+fn get_shlvl(ctx: &Context, shell_shown: bool) -> Result<DecoratedString, SegmentError> {
+    let depth =
+        depth(ctx)?.ok_or_else(|| SegmentError::not_applicable(NAME, "not nested deep enough"))?;
+
+    if shell_shown {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "shown with the shell segment",
+        ));
+    }
+
+    Ok(decorate(depth))
+}
+
+#[cfg(feature = "shell")]
+fn shell_shown(ctx: &Context) -> bool {
+    use crate::{runner, segments::shell::Shell};
+
+    ctx.config.segment(Shell.name()).enabled != Some(false) && runner::render(&Shell, ctx).is_ok()
+}
+
+#[cfg(not(feature = "shell"))]
+fn shell_shown(_ctx: &Context) -> bool {
+    false
+}
+
+/// How deeply shells are nested, from `$SHLVL`. Off unless `enabled = true`,
+/// and then shown after the shell name when that segment is shown too
+pub struct Shlvl;
+
+impl Segment for Shlvl {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_shlvl(ctx, shell_shown(ctx)).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{segment::ErrorKind, testing};
+
+    #[test]
+    fn shown_over_threshold() {
+        let ctx = testing::context(&[("SHLVL", "3")], None);
+        assert_eq!(get_shlvl(&ctx, false).unwrap().to_plain(), "⇲3");
+        assert_eq!(
+            get_shlvl(&ctx, false).unwrap().to_ansi(),
+            "\x1b[2m⇲3\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn hidden_at_threshold() {
+        let ctx = testing::context(&[("SHLVL", "2")], None);
+        assert!(matches!(get_shlvl(&ctx, false), Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+
+    #[test]
+    fn hidden_when_not_a_number() {
+        for vars in [&[("SHLVL", "deep")][..], &[]] {
+            let ctx = testing::context(vars, None);
+            assert!(matches!(get_shlvl(&ctx, false), Err(e) if e.kind == ErrorKind::NotApplicable));
+        }
+    }
+
+    #[test]
+    fn hidden_when_shell_shows_it() {
+        let ctx = testing::context(&[("SHLVL", "5")], None);
+        assert!(matches!(get_shlvl(&ctx, true), Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+}
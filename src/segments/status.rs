@@ -0,0 +1,301 @@
+use std::convert::TryFrom;
+
+use crate::{
+    config::{self, ConfigError, Table, Value},
+    decorated::{Color, DecoratedString},
+    prompt,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "status";
+
+/// Statuses shown alike, from a `[[segments.status.classes]]` table
+#[derive(Debug, PartialEq)]
+struct StatusClass {
+    /// Inclusive ranges of the statuses in the class
+    codes: Vec<(u32, u32)>,
+    color: Option<Color>,
+    bold: bool,
+    /// Shown after the status
+    label: Option<String>,
+}
+
+/// An entry of `codes`, either a status or a range of them like `"129-159"`
+fn parse_codes(value: &Value) -> Option<(u32, u32)> {
+    match value {
+        Value::Integer(code) => {
+            let code = u32::try_from(*code).ok()?;
+            Some((code, code))
+        }
+        Value::String(range) => {
+            let (start, end) = range.split_once('-')?;
+            let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+            (start <= end).then_some((start, end))
+        }
+        _ => None,
+    }
+}
+
+impl StatusClass {
+    fn from_table(table: &Table) -> Result<StatusClass, ConfigError> {
+        let invalid_codes =
+            || ConfigError::invalid_field("classes.codes", "array of statuses or \"<from>-<to>\"");
+        let codes = match table.get("codes") {
+            Some(Value::Array(codes)) => codes
+                .iter()
+                .map(|code| parse_codes(code).ok_or_else(invalid_codes))
+                .collect::<Result<_, _>>()?,
+            _ => return Err(invalid_codes()),
+        };
+
+        let color = config::get_str(table, "color")?
+            .map(|name| {
+                Color::from_name(name)
+                    .ok_or_else(|| ConfigError::invalid_field("classes.color", "color name"))
+            })
+            .transpose()?;
+
+        Ok(StatusClass {
+            codes,
+            color,
+            bold: config::get_bool(table, "bold")?.unwrap_or(false),
+            label: config::get_str(table, "label")?.map(str::to_string),
+        })
+    }
+
+    fn contains(&self, status: u32) -> bool {
+        self.codes
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&status))
+    }
+}
+
+/// The configured classes, in the order they are listed
+fn classes(ctx: &Context) -> Result<Vec<StatusClass>, ConfigError> {
+    config::get_tables(&ctx.config.segment(NAME).options, "classes")?
+        .into_iter()
+        .map(StatusClass::from_table)
+        .collect()
+}
+
+/// A status of a pipeline styled by the first of `classes` it is in, or else
+/// in bold green for success and bold red for anything else
+fn styled(status: &str, classes: &[StatusClass]) -> DecoratedString {
+    let class = status
+        .parse()
+        .ok()
+        .and_then(|status| classes.iter().find(|class| class.contains(status)));
+    let class = match class {
+        Some(class) => class,
+        None => {
+            return DecoratedString::new(status.to_string())
+                .colored(prompt::status_color(status))
+                .bold()
+        }
+    };
+
+    let mut styled = DecoratedString::new(status.to_string());
+    if let Some(label) = &class.label {
+        styled = styled.with_suffix(DecoratedString::new(label.clone()));
+    }
+    if let Some(color) = class.color {
+        styled = styled.colored(color);
+    }
+    if class.bold {
+        styled = styled.bold();
+    }
+    styled
+}
+
+fn get_status(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let status = prompt::exit_status(ctx)
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "no exit status"))?;
+    let classes =
+        classes(ctx).map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?;
+
+    let statuses = match prompt::pipe_statuses(status) {
+        Some(statuses) => statuses,
+        None => return Ok(DecoratedString::new(status.to_string())),
+    };
+
+    match statuses.as_slice() {
+        [status] => Ok(styled(status, &classes)),
+        statuses => Ok(DecoratedString::join(
+            statuses
+                .iter()
+                .map(|status| styled(status, &classes))
+                .collect(),
+            "|",
+        )),
+    }
+}
+
+/// Exit status of the previous command, as passed in by the shell or in
+/// `$PROMPTLINE_STATUS`. For a pipeline every command's status is shown, as
+/// `0|1`. Each `[[segments.status.classes]]` styles the statuses in its
+/// `codes`, which can hold ranges like `"129-159"`, with its `color`, `bold`
+/// and `label`. The first class listed that has a status wins, and statuses
+/// in none of them are green for success and red for anything else
+pub struct Status;
+
+impl Segment for Status {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_status(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, segment::ErrorKind, testing};
+
+    const CLASSES: &str = r#"
+[[segments.status.classes]]
+codes = [130]
+color = "yellow"
+
+[[segments.status.classes]]
+codes = ["134-139"]
+color = "red"
+bold = true
+label = "crash"
+
+[[segments.status.classes]]
+codes = [130, 139]
+color = "magenta"
+"#;
+
+    fn with_classes(status: &str, toml: &str) -> Result<DecoratedString, SegmentError> {
+        let mut ctx = testing::context(&[], None);
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        ctx.args.status = Some(status.to_string());
+        get_status(&ctx)
+    }
+
+    fn shown(arg: Option<&str>, env: Option<&str>) -> Result<String, SegmentError> {
+        let vars: Vec<_> = env
+            .map(|env| ("PROMPTLINE_STATUS", env))
+            .into_iter()
+            .collect();
+        let mut ctx = testing::context(&vars, None);
+        ctx.args.status = arg.map(str::to_string);
+        get_status(&ctx).map(|status| status.to_plain())
+    }
+
+    fn status(status: &str) -> DecoratedString {
+        let mut ctx = testing::context(&[], None);
+        ctx.args.status = Some(status.to_string());
+        get_status(&ctx).unwrap()
+    }
+
+    #[test]
+    fn from_argv_or_env() {
+        assert_eq!(shown(Some("1"), None).unwrap(), "1");
+        assert_eq!(shown(None, Some("0 2")).unwrap(), "0|2");
+        assert_eq!(shown(Some("1"), Some("0")).unwrap(), "1");
+        assert_eq!(
+            shown(None, None).unwrap_err().kind,
+            ErrorKind::NotApplicable
+        );
+    }
+
+    #[test]
+    fn empty_status_is_none() {
+        assert_eq!(
+            shown(Some(""), None).unwrap_err().kind,
+            ErrorKind::NotApplicable
+        );
+        assert_eq!(
+            shown(Some(" "), Some("")).unwrap_err().kind,
+            ErrorKind::NotApplicable
+        );
+        assert_eq!(shown(Some(""), Some("130")).unwrap(), "130");
+    }
+
+    #[test]
+    fn single_status() {
+        assert_eq!(status("0").to_ansi(), "\x1b[1m\x1b[32m0\x1b[39m\x1b[22m");
+        assert_eq!(
+            status(" 130 ").to_ansi(),
+            "\x1b[1m\x1b[31m130\x1b[39m\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn pipeline_colors_each_status() {
+        assert_eq!(
+            status("0 141").to_ansi(),
+            "\x1b[1m\x1b[32m0\x1b[39m\x1b[22m|\x1b[1m\x1b[31m141\x1b[39m\x1b[22m"
+        );
+        assert_eq!(status("1:0:0").to_plain(), "1|0|0");
+    }
+
+    #[test]
+    fn junk_is_printed_raw() {
+        assert_eq!(status("0 oops").to_ansi(), "0 oops");
+    }
+
+    #[test]
+    fn parses_classes() {
+        let table = config::parse(CLASSES).unwrap();
+        let config = Config::from_table(&table).unwrap();
+        let mut ctx = testing::context(&[], None);
+        ctx.config = config;
+        let classes = classes(&ctx).unwrap();
+        assert_eq!(
+            classes[1],
+            StatusClass {
+                codes: vec![(134, 139)],
+                color: Some(Color::Red),
+                bold: true,
+                label: Some("crash".to_string()),
+            }
+        );
+        assert_eq!(classes[2].codes, [(130, 130), (139, 139)]);
+
+        assert_eq!(
+            parse_codes(&Value::String("129 - 159".into())),
+            Some((129, 159))
+        );
+        assert_eq!(parse_codes(&Value::String("159-129".into())), None);
+        assert_eq!(parse_codes(&Value::Integer(-1)), None);
+
+        for toml in [
+            "[[segments.status.classes]]\ncolor = \"red\"\n",
+            "[[segments.status.classes]]\ncodes = [\"x\"]\n",
+            "[[segments.status.classes]]\ncodes = [1]\ncolor = \"orange\"\n",
+        ] {
+            assert!(with_classes("1", toml).is_err());
+        }
+    }
+
+    #[test]
+    fn first_class_wins() {
+        assert_eq!(
+            with_classes("130", CLASSES).unwrap().to_ansi(),
+            "\x1b[33m130\x1b[39m"
+        );
+        assert_eq!(
+            with_classes("139", CLASSES).unwrap().to_ansi(),
+            "\x1b[1m\x1b[31m139 crash\x1b[39m\x1b[22m"
+        );
+        assert_eq!(with_classes("139", CLASSES).unwrap().value(), "139");
+    }
+
+    #[test]
+    fn default_outside_of_classes() {
+        assert_eq!(
+            with_classes("1", CLASSES).unwrap().to_ansi(),
+            "\x1b[1m\x1b[31m1\x1b[39m\x1b[22m"
+        );
+        assert_eq!(
+            with_classes("0 130", CLASSES).unwrap().to_ansi(),
+            "\x1b[1m\x1b[32m0\x1b[39m\x1b[22m|\x1b[33m130\x1b[39m"
+        );
+    }
+}
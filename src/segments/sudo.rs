@@ -0,0 +1,177 @@
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "sudo";
+
+#[cfg(windows)]
+fn get_sudo(_ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    Err(SegmentError::not_applicable(
+        NAME,
+        "sudo isn't checked on windows",
+    ))
+}
+
+#[cfg(not(windows))]
+fn get_sudo(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    imp::get_sudo(ctx, imp::check_sudo)
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        cache, config,
+        custom::{self, CustomError},
+        decorated::Color,
+        platform,
+    };
+
+    const DEFAULT_CACHE: Duration = Duration::from_secs(30);
+    const DEFAULT_TIMEOUT: Duration = Duration::from_millis(80);
+
+    /// Exit status of `sh` when it can't find the command
+    const NOT_FOUND: i32 = 127;
+
+    /// Whether `sudo` would run without asking for a password, `None` if it
+    /// isn't installed
+    pub fn check_sudo(timeout: Duration) -> Result<Option<bool>, SegmentError> {
+        let output = custom::run_command("sudo -n true 2>/dev/null", timeout).map_err(|e| {
+            let message = match e {
+                CustomError::Timeout(_) => "sudo took too long",
+                _ => "failed to run sudo",
+            };
+            SegmentError::failed(NAME, message).with_source(e)
+        })?;
+
+        match output.status.code() {
+            Some(NOT_FOUND) => Ok(None),
+            _ => Ok(Some(output.status.success())),
+        }
+    }
+
+    /// Where the last result is kept. sudo remembers credentials per
+    /// terminal, so each one gets its own file
+    fn cache_path(ctx: &Context) -> Option<PathBuf> {
+        let tty = platform::tty_name().unwrap_or_else(|| "notty".to_string());
+        let file = format!("sudo-{}", tty.trim_start_matches('/').replace('/', "-"));
+        Some(ctx.cache_dir()?.join(file))
+    }
+
+    /// The cached result, if it is younger than `max_age`
+    fn read_cache(path: &Path, max_age: Duration) -> Option<bool> {
+        match cache::read(path, max_age)?.trim() {
+            "1" => Some(true),
+            "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn get_sudo(
+        ctx: &Context,
+        check: impl FnOnce(Duration) -> Result<Option<bool>, SegmentError>,
+    ) -> Result<DecoratedString, SegmentError> {
+        let options = &ctx.config.segment(NAME).options;
+        let invalid = |e| SegmentError::failed(NAME, "invalid config").with_source(e);
+        let max_age = match config::get_int(options, "cache_secs").map_err(invalid)? {
+            Some(secs) => Duration::from_secs(secs.max(0) as u64),
+            None => DEFAULT_CACHE,
+        };
+        let timeout = config::get_duration_ms(options, "timeout_ms")
+            .map_err(invalid)?
+            .unwrap_or(DEFAULT_TIMEOUT);
+
+        let path = cache_path(ctx);
+        let cached = match path.as_ref().and_then(|path| read_cache(path, max_age)) {
+            Some(cached) => cached,
+            None => {
+                let cached = check(timeout)?
+                    .ok_or_else(|| SegmentError::not_applicable(NAME, "sudo not installed"))?;
+                if let Some(path) = &path {
+                    let _ = cache::write(path, if cached { "1" } else { "0" });
+                }
+                cached
+            }
+        };
+
+        if !cached {
+            return Err(SegmentError::not_applicable(
+                NAME,
+                "sudo would ask for a password",
+            ));
+        }
+
+        Ok(DecoratedString::new("#".into()).colored(Color::Red).bold())
+    }
+}
+
+/// Red `#` while sudo has cached credentials and won't ask for a password.
+/// Off unless `enabled = true`, as it spawns sudo every `cache_secs`
+pub struct Sudo;
+
+impl Segment for Sudo {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_sudo(ctx).map(Into::into)
+    }
+
+    #[cfg(not(windows))]
+    fn preview(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        imp::get_sudo(ctx, |_| Ok(Some(true))).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::imp::get_sudo;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    #[test]
+    fn shown_when_cached() {
+        let ctx = testing::context(&[], None);
+        let sudo = get_sudo(&ctx, |_| Ok(Some(true))).unwrap();
+        assert_eq!(sudo.to_ansi(), "\x1b[1m\x1b[31m#\x1b[39m\x1b[22m");
+    }
+
+    #[test]
+    fn hidden_when_not_cached_or_missing() {
+        let ctx = testing::context(&[], None);
+        for result in [Some(false), None] {
+            let sudo = get_sudo(&ctx, |_| Ok(result));
+            assert!(matches!(sudo, Err(e) if e.kind == ErrorKind::NotApplicable));
+        }
+    }
+
+    #[test]
+    fn reuses_cached_result() {
+        let runtime = TempDir::new();
+        let ctx = testing::context(
+            &[("XDG_RUNTIME_DIR", runtime.path().to_str().unwrap())],
+            None,
+        );
+
+        assert!(get_sudo(&ctx, |_| Ok(Some(true))).is_ok());
+        assert!(get_sudo(&ctx, |_| panic!("sudo run again")).is_ok());
+    }
+}
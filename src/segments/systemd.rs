@@ -0,0 +1,160 @@
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "systemd";
+
+#[cfg(windows)]
+fn get_systemd(_ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    Err(SegmentError::not_applicable(NAME, "no systemd on windows"))
+}
+
+#[cfg(not(windows))]
+fn get_systemd(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    imp::get_systemd(ctx, imp::system_state)
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{cache, config, custom, decorated::Color};
+
+    const DEFAULT_CACHE: Duration = Duration::from_secs(60);
+    const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+    /// States worth a marker, the rest are either fine or passing
+    const BAD_STATES: &[&str] = &["degraded", "maintenance"];
+
+    /// What `systemctl is-system-running` prints, `None` if it didn't answer in
+    /// time or isn't installed. `--quiet` would hide the state, which is
+    /// needed to tell `degraded` from `starting`
+    pub fn system_state(timeout: Duration) -> Option<String> {
+        let output =
+            custom::run_command("systemctl is-system-running 2>/dev/null", timeout).ok()?;
+        Some(output.stdout.trim().to_string()).filter(|state| !state.is_empty())
+    }
+
+    pub fn get_systemd(
+        ctx: &Context,
+        check: impl FnOnce(Duration) -> Option<String>,
+    ) -> Result<DecoratedString, SegmentError> {
+        let options = &ctx.config.segment(NAME).options;
+        let invalid = |e| SegmentError::failed(NAME, "invalid config").with_source(e);
+        let max_age = match config::get_int(options, "cache_secs").map_err(invalid)? {
+            Some(secs) => Duration::from_secs(secs.max(0) as u64),
+            None => DEFAULT_CACHE,
+        };
+        let timeout = config::get_duration_ms(options, "timeout_ms")
+            .map_err(invalid)?
+            .unwrap_or(DEFAULT_TIMEOUT);
+
+        // An empty file stands for no answer, so a missing systemctl isn't
+        // asked again every prompt either
+        let path = ctx.cache_dir().map(|dir| dir.join("systemd-state"));
+        let state = match path.as_ref().and_then(|path| cache::read(path, max_age)) {
+            Some(state) => state,
+            None => {
+                let state = check(timeout).unwrap_or_default();
+                if let Some(path) = &path {
+                    let _ = cache::write(path, &state);
+                }
+                state
+            }
+        };
+
+        let state = state.trim();
+        if !BAD_STATES.contains(&state) {
+            return Err(SegmentError::not_applicable(NAME, "system isn't degraded"));
+        }
+
+        Ok(DecoratedString::new(format!("✗ {state}"))
+            .colored(Color::Red)
+            .bold())
+    }
+}
+
+/// Red `✗ degraded` while `systemctl is-system-running` reports a degraded
+/// system or maintenance mode. Off unless `enabled = true`, as it spawns
+/// systemctl every `cache_secs`
+pub struct Systemd;
+
+impl Segment for Systemd {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_systemd(ctx).map(Into::into)
+    }
+
+    #[cfg(not(windows))]
+    fn preview(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        imp::get_systemd(ctx, |_| Some("degraded".into())).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::imp::get_systemd;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    #[test]
+    fn marks_degraded_and_maintenance() {
+        let ctx = testing::context(&[], None);
+        let degraded = get_systemd(&ctx, |_| Some("degraded".into())).unwrap();
+        assert_eq!(
+            degraded.to_ansi(),
+            "\x1b[1m\x1b[31m✗ degraded\x1b[39m\x1b[22m"
+        );
+        let maintenance = get_systemd(&ctx, |_| Some("maintenance".into())).unwrap();
+        assert_eq!(maintenance.to_plain(), "✗ maintenance");
+    }
+
+    #[test]
+    fn hidden_otherwise() {
+        let ctx = testing::context(&[], None);
+        for state in [Some("running"), Some("starting"), None] {
+            let systemd = get_systemd(&ctx, |_| state.map(String::from));
+            assert!(matches!(systemd, Err(e) if e.kind == ErrorKind::NotApplicable));
+        }
+    }
+
+    #[test]
+    fn reuses_cached_state() {
+        let runtime = TempDir::new();
+        let ctx = testing::context(
+            &[("XDG_RUNTIME_DIR", runtime.path().to_str().unwrap())],
+            None,
+        );
+
+        assert!(get_systemd(&ctx, |_| Some("degraded".into())).is_ok());
+        assert!(get_systemd(&ctx, |_| panic!("systemctl run again")).is_ok());
+    }
+
+    #[test]
+    fn caches_missing_systemctl() {
+        let runtime = TempDir::new();
+        let ctx = testing::context(
+            &[("XDG_RUNTIME_DIR", runtime.path().to_str().unwrap())],
+            None,
+        );
+
+        assert!(get_systemd(&ctx, |_| None).is_err());
+        assert!(get_systemd(&ctx, |_| panic!("systemctl run again")).is_err());
+    }
+}
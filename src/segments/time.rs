@@ -0,0 +1,120 @@
+use std::time::SystemTime;
+
+#[cfg(not(feature = "chrono"))]
+use crate::localtime::LocalTime;
+use crate::{
+    config,
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "time";
+const FORMAT: &str = "%H:%M";
+
+/// Years before this are taken for a clock that was never set, like that of a
+/// board without a battery backed clock starting at the epoch
+const DEFAULT_MIN_YEAR: i64 = 2020;
+
+/// `now` in the local zone formatted with `format`, along with its year
+#[cfg(feature = "chrono")]
+fn format_at(now: SystemTime, format: &str) -> Option<(i64, String)> {
+    use std::panic;
+
+    use chrono::{DateTime, Datelike, Local, Utc};
+
+    let utc = DateTime::<Utc>::from(now);
+    // chrono can panic on a badly set up $TZ, UTC beats losing the prompt
+    match panic::catch_unwind(|| utc.with_timezone(&Local)) {
+        Ok(local) => Some((local.year().into(), local.format(format).to_string())),
+        Err(_) => Some((utc.year().into(), utc.format(format).to_string())),
+    }
+}
+
+/// `now` in the local zone formatted with `format`, along with its year
+#[cfg(not(feature = "chrono"))]
+fn format_at(now: SystemTime, format: &str) -> Option<(i64, String)> {
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs() as i64;
+    let time = LocalTime::from_timestamp(timestamp)
+        .unwrap_or_else(|| LocalTime::utc_from_timestamp(timestamp));
+    Some((time.year.into(), time.format(format)))
+}
+
+fn get_time(ctx: &Context, now: SystemTime) -> Result<DecoratedString, SegmentError> {
+    // Lets tests snapshot the prompt without depending on the clock
+    if let Some(fake) = ctx.var("PROMPTLINE_FAKE_TIME") {
+        return Ok(DecoratedString::new(fake.to_string())
+            .bold()
+            .colored(Color::Cyan));
+    }
+
+    let min_year = config::get_int(&ctx.config.segment(NAME).options, "min_year")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .unwrap_or(DEFAULT_MIN_YEAR);
+    let (year, formatted) = format_at(now, FORMAT)
+        .ok_or_else(|| SegmentError::failed(NAME, "failed to get local time"))?;
+
+    let time = DecoratedString::new(formatted);
+    if year < min_year {
+        Ok(time.with_icon("⏰?").bold().colored(Color::Red))
+    } else {
+        Ok(time.bold().colored(Color::Cyan))
+    }
+}
+
+/// Local time as `HH:MM`, or `$PROMPTLINE_FAKE_TIME` if set. A clock set
+/// before `min_year`, 2020 by default, is shown in red as `⏰? HH:MM`
+pub struct Time;
+
+impl Segment for Time {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_time(ctx, SystemTime::now()).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use crate::{
+        config::{self, Config},
+        testing,
+    };
+
+    fn shown(toml: &str, now: SystemTime) -> DecoratedString {
+        let mut ctx = testing::context(&[], None);
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        get_time(&ctx, now).unwrap()
+    }
+
+    #[test]
+    fn unset_clock() {
+        let boot = UNIX_EPOCH + Duration::from_secs(5 * 60);
+        let time = shown("", boot);
+        assert!(time.to_plain().starts_with("⏰? "), "{}", time.to_plain());
+        assert!(time.to_ansi().contains("\x1b[31m"));
+    }
+
+    #[test]
+    fn set_clock() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_982_245);
+        let time = shown("", now);
+        assert!(!time.to_plain().contains('⏰'));
+        assert!(time.to_ansi().contains("\x1b[36m"));
+
+        let time = shown("[segments.time]\nmin_year = 2025\n", now);
+        assert!(time.to_plain().starts_with("⏰? "));
+    }
+
+    #[test]
+    fn fake_time_is_never_flagged() {
+        let ctx = testing::context(&[("PROMPTLINE_FAKE_TIME", "00:05")], None);
+        let time = get_time(&ctx, UNIX_EPOCH).unwrap();
+        assert_eq!(time.to_plain(), "00:05");
+    }
+}
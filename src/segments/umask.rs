@@ -0,0 +1,139 @@
+use crate::{
+    config,
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "umask";
+
+/// Permissions that should stay masked, group and other write
+const DEFAULT_BASELINE: u32 = 0o022;
+
+/// Parses an octal umask as printed by `umask`, with or without leading zeros
+fn parse_umask(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if s.is_empty() || s.len() > 4 {
+        return None;
+    }
+
+    u32::from_str_radix(s, 8).ok().filter(|mask| *mask <= 0o777)
+}
+
+/// The umask from `--umask`, or the `Umask:` line in `/proc/self/status`,
+/// which needs Linux 4.7 or later
+fn current_umask(ctx: &Context) -> Option<u32> {
+    if let Some(umask) = &ctx.args.umask {
+        return parse_umask(umask);
+    }
+
+    let status = ctx.read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Umask:"))
+        .and_then(parse_umask)
+}
+
+fn get_umask(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let baseline = config::get_str(&ctx.config.segment(NAME).options, "baseline")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .map(|baseline| {
+            parse_umask(baseline)
+                .ok_or_else(|| SegmentError::failed(NAME, format!("invalid baseline {baseline}")))
+        })
+        .transpose()?
+        .unwrap_or(DEFAULT_BASELINE);
+
+    let umask =
+        current_umask(ctx).ok_or_else(|| SegmentError::not_applicable(NAME, "umask not known"))?;
+
+    // Bits the baseline masks that the current umask lets through
+    if baseline & !umask == 0 {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "umask is at least as strict as the baseline",
+        ));
+    }
+
+    Ok(DecoratedString::new(format!("umask {umask:03o}"))
+        .colored(Color::Red)
+        .bold())
+}
+
+/// Warns when the umask lets through permissions that `baseline` masks
+pub struct Umask;
+
+impl Segment for Umask {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_umask(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    fn with_umask(umask: &str) -> Context {
+        let mut ctx = testing::context(&[], None);
+        ctx.args.umask = Some(umask.to_string());
+        ctx
+    }
+
+    #[test]
+    fn parses_octal_forms() {
+        assert_eq!(parse_umask("0022"), Some(0o022));
+        assert_eq!(parse_umask("022"), Some(0o022));
+        assert_eq!(parse_umask("22"), Some(0o022));
+        assert_eq!(parse_umask("0"), Some(0));
+        assert_eq!(parse_umask("u=rwx"), None);
+        assert_eq!(parse_umask("0800"), None);
+        assert_eq!(parse_umask(""), None);
+    }
+
+    #[test]
+    fn warns_when_permissive() {
+        assert_eq!(
+            get_umask(&with_umask("002")).unwrap().to_plain(),
+            "umask 002"
+        );
+        assert_eq!(get_umask(&with_umask("0")).unwrap().to_plain(), "umask 000");
+    }
+
+    #[test]
+    fn hidden_when_strict() {
+        for umask in ["022", "0077", "027"] {
+            let res = get_umask(&with_umask(umask));
+            assert!(
+                matches!(res, Err(e) if e.kind == ErrorKind::NotApplicable),
+                "{}",
+                umask
+            );
+        }
+    }
+
+    #[test]
+    fn reads_proc_status() {
+        let root = TempDir::new();
+        root.write(
+            "proc/self/status",
+            "Name:\tpromptline\nUmask:\t0002\nState:\tR\n",
+        );
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        assert_eq!(get_umask(&ctx).unwrap().to_plain(), "umask 002");
+    }
+
+    #[test]
+    fn hidden_without_data() {
+        let root = TempDir::new();
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        assert!(matches!(get_umask(&ctx), Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    segments::git,
+    Context,
+};
+
+const NAME: &str = "unpushed";
+
+/// Calls `f` with every file under `dir`, and `on_dir` with `dir` and every
+/// directory below it
+fn walk(dir: &Path, on_dir: &mut impl FnMut(&Path), f: &mut impl FnMut(&Path)) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    on_dir(dir);
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => walk(&path, on_dir, f),
+            Ok(_) => f(&path),
+            Err(_) => (),
+        }
+    }
+}
+
+fn is_hash(s: &str) -> bool {
+    (s.len() == 40 || s.len() == 64) && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Commits the refs under `prefix` point to, both loose and packed. Symbolic
+/// refs like `refs/remotes/origin/HEAD` are skipped, their target is listed
+/// anyway
+fn ref_hashes(git_dir: &Path, prefix: &str) -> HashSet<String> {
+    let mut hashes = HashSet::new();
+
+    walk(&git_dir.join(prefix), &mut |_| (), &mut |path| {
+        if let Ok(content) = fs::read_to_string(path) {
+            let hash = content.trim();
+            if is_hash(hash) {
+                hashes.insert(hash.to_string());
+            }
+        }
+    });
+
+    if let Ok(packed) = fs::read_to_string(git_dir.join("packed-refs")) {
+        for line in packed.lines() {
+            match line.split_once(' ') {
+                Some((hash, name)) if name.starts_with(prefix) && is_hash(hash) => {
+                    hashes.insert(hash.to_string());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    hashes
+}
+
+/// Every commit the remote tracking refs ever pointed to, from their reflogs.
+/// A branch that is behind its upstream still points at one of these
+fn reflog_hashes(git_dir: &Path) -> HashSet<String> {
+    let mut hashes = HashSet::new();
+
+    walk(
+        &git_dir.join("logs/refs/remotes"),
+        &mut |_| (),
+        &mut |path| {
+            let log = match fs::read_to_string(path) {
+                Ok(log) => log,
+                Err(_) => return,
+            };
+            for line in log.lines() {
+                let mut fields = line.split(' ');
+                for hash in fields.by_ref().take(2) {
+                    if is_hash(hash) && hash.bytes().any(|b| b != b'0') {
+                        hashes.insert(hash.to_string());
+                    }
+                }
+            }
+        },
+    );
+
+    hashes
+}
+
+/// Whether a local branch points at a commit no remote tracking ref has been
+/// seen at. Only compares tips, so a branch that was pushed and then reset
+/// onto an unpushed ancestor isn't noticed. `None` without any remote refs
+fn has_unpushed(git_dir: &Path) -> Option<bool> {
+    let mut remote = ref_hashes(git_dir, "refs/remotes/");
+    if remote.is_empty() {
+        return None;
+    }
+    remote.extend(reflog_hashes(git_dir));
+
+    let local = ref_hashes(git_dir, "refs/heads/");
+    Some(local.iter().any(|hash| !remote.contains(hash)))
+}
+
+/// Changes whenever a ref is created, moved or deleted. git updates refs by
+/// renaming a lock file over them, which touches the directory they are in
+fn refs_stamp(git_dir: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let mut hash_mtime = |path: &Path| {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+        if let Ok(modified) = modified {
+            path.hash(&mut hasher);
+            modified.hash(&mut hasher);
+        }
+    };
+
+    hash_mtime(&git_dir.join("packed-refs"));
+    for dir in ["refs/heads", "refs/remotes"] {
+        walk(&git_dir.join(dir), &mut hash_mtime, &mut |_| ());
+    }
+
+    hasher.finish()
+}
+
+/// `has_unpushed`, reusing the last answer for the repo until its refs change
+fn cached_has_unpushed(ctx: &Context, git_dir: &Path) -> Option<bool> {
+    let cache_path = ctx.cache_dir().map(|dir| {
+        let mut hasher = DefaultHasher::new();
+        git_dir.hash(&mut hasher);
+        dir.join(format!("unpushed-{:016x}", hasher.finish()))
+    });
+
+    let stamp = refs_stamp(git_dir).to_string();
+
+    if let Some(cached) = cache_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+    {
+        match cached.split_once(' ') {
+            Some((cached_stamp, "1")) if cached_stamp == stamp => return Some(true),
+            Some((cached_stamp, "0")) if cached_stamp == stamp => return Some(false),
+            _ => (),
+        }
+    }
+
+    let unpushed = has_unpushed(git_dir)?;
+
+    // Failing to cache only costs the next prompt another walk
+    if let Some(path) = &cache_path {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(path, format!("{stamp} {}", unpushed as u8));
+    }
+
+    Some(unpushed)
+}
+
+fn get_unpushed(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let git_dir = git::git_dir(ctx, NAME)?;
+
+    match cached_has_unpushed(ctx, &git_dir) {
+        Some(true) => Ok(DecoratedString::new("⇪".into()).dim()),
+        Some(false) => Err(SegmentError::not_applicable(NAME, "everything is pushed")),
+        None => Err(SegmentError::not_applicable(
+            NAME,
+            "no remote tracking refs",
+        )),
+    }
+}
+
+/// Marker for local branches with commits that aren't on any remote. Off
+/// unless `enabled = true`, as it reads every ref in the repo
+pub struct Unpushed;
+
+impl Segment for Unpushed {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn is_expensive(&self) -> bool {
+        true
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_unpushed(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    const PUSHED: &str = "1111111111111111111111111111111111111111";
+    const OLD: &str = "2222222222222222222222222222222222222222";
+    const LOCAL: &str = "3333333333333333333333333333333333333333";
+
+    fn repo() -> TempDir {
+        let repo = TempDir::new();
+        repo.write(".git/HEAD", "ref: refs/heads/main\n");
+        repo.write(".git/refs/heads/main", &format!("{PUSHED}\n"));
+        repo.write(".git/refs/remotes/origin/main", &format!("{PUSHED}\n"));
+        repo.write(
+            ".git/refs/remotes/origin/HEAD",
+            "ref: refs/remotes/origin/main\n",
+        );
+        repo
+    }
+
+    fn context(repo: &TempDir, vars: &[(&str, &str)]) -> Context {
+        testing::context(vars, Some(repo.path().to_str().unwrap()))
+    }
+
+    #[test]
+    fn nothing_unpushed() {
+        let repo = repo();
+        let res = get_unpushed(&context(&repo, &[]));
+        assert!(matches!(res, Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+
+    #[test]
+    fn local_only_commit() {
+        let repo = repo();
+        repo.write(
+            ".git/packed-refs",
+            &format!("# pack-refs with: peeled\n{LOCAL} refs/heads/feature\n"),
+        );
+        let res = get_unpushed(&context(&repo, &[])).unwrap();
+        assert_eq!(res.to_ansi(), "\x1b[2m⇪\x1b[22m");
+    }
+
+    #[test]
+    fn branch_behind_upstream_is_pushed() {
+        let repo = repo();
+        repo.write(".git/refs/heads/stale", &format!("{OLD}\n"));
+        repo.write(
+            ".git/logs/refs/remotes/origin/main",
+            &format!("{OLD} {PUSHED} A U Thor <a@example.com> 0 +0000\tfetch\n"),
+        );
+        assert!(get_unpushed(&context(&repo, &[])).is_err());
+    }
+
+    #[test]
+    fn no_remotes() {
+        let repo = TempDir::new();
+        repo.write(".git/refs/heads/main", &format!("{LOCAL}\n"));
+        assert!(has_unpushed(&repo.path().join(".git")).is_none());
+    }
+
+    #[test]
+    fn cache_invalidated_by_new_ref() {
+        let repo = repo();
+        let runtime = TempDir::new();
+        let ctx = context(
+            &repo,
+            &[("XDG_RUNTIME_DIR", runtime.path().to_str().unwrap())],
+        );
+        assert!(get_unpushed(&ctx).is_err());
+
+        repo.write(".git/refs/heads/feature", &format!("{LOCAL}\n"));
+        assert!(get_unpushed(&ctx).is_ok());
+    }
+}
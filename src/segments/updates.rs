@@ -0,0 +1,140 @@
+use crate::{
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "updates";
+
+/// Created by Debian and Ubuntu package scripts when an upgrade needs a reboot
+/// to take effect
+const REBOOT_REQUIRED: &str = "/var/run/reboot-required";
+
+/// Written by Ubuntu's update-notifier, the message shown at login
+const UPDATES_AVAILABLE: &str = "/var/lib/update-notifier/updates-available";
+
+/// The first number in `text`, as in `12 updates can be applied immediately.`
+fn first_number(text: &str) -> Option<u32> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .find(|word| !word.is_empty())?
+        .parse()
+        .ok()
+}
+
+fn get_updates(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let mut parts = vec![];
+    if ctx.exists(REBOOT_REQUIRED) {
+        parts.push(DecoratedString::new("↻ reboot".into()).colored(Color::Red));
+    }
+    // A message without a count, or a count of none, is nothing to show
+    let count = ctx
+        .read_to_string(UPDATES_AVAILABLE)
+        .ok()
+        .and_then(|text| first_number(&text))
+        .filter(|&count| count > 0);
+    if let Some(count) = count {
+        parts.push(DecoratedString::new(format!("⬆{count}")).dim());
+    }
+
+    if parts.is_empty() {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "no reboot or updates pending",
+        ));
+    }
+    Ok(DecoratedString::join(parts, " "))
+}
+
+/// A red `↻ reboot` when Debian or Ubuntu wants a reboot after an upgrade, and
+/// a dim `⬆12` with the count of updates update-notifier found. Off unless
+/// `enabled = true`
+pub struct Updates;
+
+impl Segment for Updates {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_updates(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    /// As written by update-notifier on Ubuntu 22.04
+    const UPDATES: &str = "
+12 updates can be applied immediately.
+5 of these updates are standard security updates.
+To see these additional updates run: apt list --upgradable
+
+";
+
+    /// As written by update-notifier on Ubuntu 18.04
+    const OLD_UPDATES: &str = "
+3 packages can be updated.
+0 updates are security updates.
+
+";
+
+    fn shown(root: &TempDir) -> Result<String, SegmentError> {
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        get_updates(&ctx).map(|updates| updates.to_ansi())
+    }
+
+    #[test]
+    fn reboot_and_updates() {
+        let root = TempDir::new();
+        root.write(
+            "var/run/reboot-required",
+            "*** System restart required ***\n",
+        );
+        root.write("var/lib/update-notifier/updates-available", UPDATES);
+        assert_eq!(
+            shown(&root).unwrap(),
+            "\x1b[31m↻ reboot\x1b[39m \x1b[2m⬆12\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn only_reboot() {
+        let root = TempDir::new();
+        root.write("var/run/reboot-required", "");
+        assert_eq!(shown(&root).unwrap(), "\x1b[31m↻ reboot\x1b[39m");
+    }
+
+    #[test]
+    fn updates_counts() {
+        assert_eq!(first_number(UPDATES), Some(12));
+        assert_eq!(first_number(OLD_UPDATES), Some(3));
+        assert_eq!(first_number("\n\n"), None);
+
+        let root = TempDir::new();
+        root.write("var/lib/update-notifier/updates-available", OLD_UPDATES);
+        assert_eq!(shown(&root).unwrap(), "\x1b[2m⬆3\x1b[22m");
+    }
+
+    #[test]
+    fn nothing_pending() {
+        let root = TempDir::new();
+        assert_eq!(shown(&root).unwrap_err().kind, ErrorKind::NotApplicable);
+
+        root.write(
+            "var/lib/update-notifier/updates-available",
+            "\n0 updates can be applied immediately.\n\n",
+        );
+        assert_eq!(shown(&root).unwrap_err().kind, ErrorKind::NotApplicable);
+        root.write("var/lib/update-notifier/updates-available", "");
+        assert_eq!(shown(&root).unwrap_err().kind, ErrorKind::NotApplicable);
+    }
+}
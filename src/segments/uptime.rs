@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use crate::{
+    config,
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "uptime";
+
+/// Uptime below which the box counts as freshly rebooted
+const DEFAULT_RECENT: Duration = Duration::from_secs(60 * 60);
+
+/// The first field of `/proc/uptime`, seconds since boot with a fraction
+fn parse_uptime(s: &str) -> Option<Duration> {
+    let secs: f64 = s.split_whitespace().next()?.parse().ok()?;
+    (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs(secs as u64))
+}
+
+/// `3d4h`, `5h` or `42m`. Minutes are only shown under an hour, hours are left
+/// out on the hour after the first day
+fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    let hours = minutes / 60;
+    let days = hours / 24;
+
+    match (days, hours % 24) {
+        (0, 0) => format!("{minutes}m"),
+        (0, hours) => format!("{hours}h"),
+        (days, 0) => format!("{days}d"),
+        (days, hours) => format!("{days}d{hours}h"),
+    }
+}
+
+fn get_uptime(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let recent = config::get_int(&ctx.config.segment(NAME).options, "recent_mins")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .map(|mins| Duration::from_secs(mins.max(0) as u64 * 60))
+        .unwrap_or(DEFAULT_RECENT);
+
+    let uptime = ctx
+        .read_to_string("/proc/uptime")
+        .map_err(|e| SegmentError::not_applicable(NAME, "no /proc/uptime").with_source(e))?;
+    let uptime = parse_uptime(&uptime)
+        .ok_or_else(|| SegmentError::failed(NAME, "failed to parse /proc/uptime"))?;
+
+    let text = DecoratedString::new(format!("up {}", format_uptime(uptime)));
+    if uptime < recent {
+        Ok(text.colored(Color::Red).bold())
+    } else {
+        Ok(text.dim())
+    }
+}
+
+/// Time since boot, red for `recent_mins` after a reboot. Off unless
+/// `enabled = true`
+pub struct Uptime;
+
+impl Segment for Uptime {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_uptime(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    fn formatted(secs: u64) -> String {
+        format_uptime(Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn minutes_under_an_hour() {
+        assert_eq!(formatted(0), "0m");
+        assert_eq!(formatted(59), "0m");
+        assert_eq!(formatted(60), "1m");
+        assert_eq!(formatted(3599), "59m");
+    }
+
+    #[test]
+    fn hours_under_a_day() {
+        assert_eq!(formatted(3600), "1h");
+        assert_eq!(formatted(3600 + 59 * 60), "1h");
+        assert_eq!(formatted(86399), "23h");
+    }
+
+    #[test]
+    fn days_and_hours() {
+        assert_eq!(formatted(86400), "1d");
+        assert_eq!(formatted(86400 + 3599), "1d");
+        assert_eq!(formatted(3 * 86400 + 4 * 3600), "3d4h");
+        assert_eq!(formatted(400 * 86400 + 23 * 3600), "400d23h");
+    }
+
+    #[test]
+    fn parses_proc_uptime() {
+        assert_eq!(
+            parse_uptime("350735.47 234388.90\n"),
+            Some(Duration::from_secs(350735))
+        );
+        assert_eq!(parse_uptime(""), None);
+        assert_eq!(parse_uptime("-1 0"), None);
+    }
+
+    fn with_uptime(uptime: &str) -> (TempDir, Context) {
+        let root = TempDir::new();
+        root.write("proc/uptime", uptime);
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        (root, ctx)
+    }
+
+    #[test]
+    fn red_after_reboot() {
+        let (_root, ctx) = with_uptime("1200.5 100.0\n");
+        let uptime = get_uptime(&ctx).unwrap();
+        assert_eq!(uptime.to_ansi(), "\x1b[1m\x1b[31mup 20m\x1b[39m\x1b[22m");
+
+        let (_root, ctx) = with_uptime("273600.0 100.0\n");
+        assert_eq!(
+            get_uptime(&ctx).unwrap().to_ansi(),
+            "\x1b[2mup 3d4h\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn hidden_without_proc() {
+        let root = TempDir::new();
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        assert!(matches!(get_uptime(&ctx), Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+}
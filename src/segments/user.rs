@@ -0,0 +1,234 @@
+use std::ffi::OsStr;
+
+use crate::{
+    conditions, config,
+    decorated::{Color, DecoratedString},
+    platform,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "user";
+
+/// The user that isn't worth showing, from `default_user` in the config or
+/// `$PROMPTLINE_DEFAULT_USER`
+fn default_user(ctx: &Context) -> Result<Option<String>, SegmentError> {
+    let configured = config::get_str(&ctx.config.segment(NAME).options, "default_user")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .map(str::to_string);
+    Ok(configured.or_else(|| ctx.var("PROMPTLINE_DEFAULT_USER").map(str::to_string)))
+}
+
+/// `user` is the name from the passwd entry, with invalid UTF-8 replaced unless
+/// `strict_utf8 = true`. `is_root` is whether promptline runs with root's
+/// privileges. Under sudo, `$SUDO_USER` is shown too, as `alice→deploy`. The
+/// default user is hidden, unless privileged or logged in over SSH
+fn get_user(ctx: &Context, user: &OsStr, is_root: bool) -> Result<DecoratedString, SegmentError> {
+    let user = super::name_to_str(ctx, NAME, user)?.into_owned();
+
+    let sudo_user = ctx
+        .var("SUDO_USER")
+        .filter(|sudo_user| !sudo_user.is_empty() && *sudo_user != user);
+
+    let plain_session = !is_root && sudo_user.is_none() && !conditions::is_ssh(ctx);
+    if plain_session && default_user(ctx)?.as_deref() == Some(&user) {
+        return Err(SegmentError::not_applicable(NAME, "default user"));
+    }
+
+    let (user, escalated) = match sudo_user {
+        Some(sudo_user) => (format!("{sudo_user}→{user}"), true),
+        None => (user, is_root),
+    };
+
+    let color = if escalated {
+        Color::Red
+    } else {
+        Color::Magenta
+    };
+    Ok(DecoratedString::new(user).colored(color).bold())
+}
+
+/// `uid=1000`, for users without a passwd entry as is common in containers.
+/// With `show_gid = true` the gid follows, as `uid=1000 gid=1000`
+fn get_user_ids(ctx: &Context, (uid, gid): (u32, u32)) -> Result<DecoratedString, SegmentError> {
+    let show_gid = config::get_bool(&ctx.config.segment(NAME).options, "show_gid")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .unwrap_or(false);
+
+    let ids = if show_gid {
+        format!("uid={uid} gid={gid}")
+    } else {
+        format!("uid={uid}")
+    };
+    let color = if uid == 0 { Color::Red } else { Color::Magenta };
+    Ok(DecoratedString::new(ids).colored(color).bold())
+}
+
+/// Name of the current user, red when running as root or through sudo
+pub struct User;
+
+impl Segment for User {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        let user = platform::user_name(ctx)
+            .map_err(|e| SegmentError::failed(NAME, "failed to get user").with_source(e))?;
+        match (user, platform::user_ids()) {
+            (Some(user), _) => get_user(ctx, &user, platform::is_root()),
+            (None, Some(ids)) => get_user_ids(ctx, ids),
+            (None, None) => Err(SegmentError::failed(NAME, "no active user")),
+        }
+        .map(Into::into)
+    }
+
+    fn preview(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_user(ctx, OsStr::new("ada"), false).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{segment::ErrorKind, testing};
+
+    // getpwuid_r fills in different struct layouts on Linux and macOS, this is
+    // the check that the lookup works wherever the tests are run
+    #[cfg(unix)]
+    #[test]
+    fn resolves_current_user() {
+        let ctx = testing::context(&[], None);
+        let user = platform::user_name(&ctx).unwrap().unwrap();
+        assert!(!user.is_empty());
+        assert!(!get_user(&ctx, &user, false).unwrap().value().is_empty());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn user_from_env() {
+        let ctx = testing::context(&[("USERNAME", "me")], None);
+        assert_eq!(platform::user_name(&ctx).unwrap().unwrap(), "me");
+    }
+
+    #[test]
+    fn plain_session_is_magenta() {
+        let ctx = testing::context(&[], None);
+        let user = get_user(&ctx, OsStr::new("me"), false).unwrap();
+        assert_eq!(user.to_ansi(), "\x1b[1m\x1b[35mme\x1b[39m\x1b[22m");
+    }
+
+    #[test]
+    fn root_privileges_are_red_under_any_name() {
+        let ctx = testing::context(&[], None);
+        let user = get_user(&ctx, OsStr::new("toor"), true).unwrap();
+        assert_eq!(user.to_ansi(), "\x1b[1m\x1b[31mtoor\x1b[39m\x1b[22m");
+    }
+
+    #[test]
+    fn sudo_shows_both_users() {
+        let ctx = testing::context(&[("SUDO_USER", "alice")], None);
+        let user = get_user(&ctx, OsStr::new("deploy"), false).unwrap();
+        assert_eq!(user.to_plain(), "alice→deploy");
+        assert!(user.to_ansi().contains("\x1b[31m"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replaces_invalid_utf8() {
+        use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+        let user = OsString::from_vec(b"j\xfcrgen".to_vec());
+        let ctx = testing::context(&[], None);
+        assert_eq!(
+            get_user(&ctx, &user, false).unwrap().to_plain(),
+            "j\u{fffd}rgen"
+        );
+    }
+
+    fn default_alice(vars: &[(&str, &str)]) -> Context {
+        let mut vars = vars.to_vec();
+        vars.push(("PROMPTLINE_DEFAULT_USER", "alice"));
+        testing::context(&vars, None)
+    }
+
+    #[test]
+    fn default_user_hidden_locally() {
+        let ctx = default_alice(&[]);
+        assert!(matches!(
+            get_user(&ctx, OsStr::new("alice"), false),
+            Err(e) if e.kind == ErrorKind::NotApplicable
+        ));
+    }
+
+    #[test]
+    fn default_user_shown_over_ssh() {
+        let ctx = default_alice(&[("SSH_CONNECTION", "10.0.0.1 50000 10.0.0.2 22")]);
+        assert_eq!(
+            get_user(&ctx, OsStr::new("alice"), false)
+                .unwrap()
+                .to_plain(),
+            "alice"
+        );
+    }
+
+    #[test]
+    fn other_users_shown() {
+        for vars in [&[][..], &[("SSH_TTY", "/dev/pts/1")]] {
+            let ctx = default_alice(vars);
+            assert_eq!(
+                get_user(&ctx, OsStr::new("bob"), false).unwrap().to_plain(),
+                "bob"
+            );
+        }
+    }
+
+    #[test]
+    fn default_user_shown_when_privileged() {
+        let ctx = default_alice(&[]);
+        assert!(get_user(&ctx, OsStr::new("alice"), true).is_ok());
+
+        let ctx = default_alice(&[("SUDO_USER", "bob")]);
+        assert_eq!(
+            get_user(&ctx, OsStr::new("alice"), false)
+                .unwrap()
+                .to_plain(),
+            "bob→alice"
+        );
+    }
+
+    #[test]
+    fn config_overrides_env() {
+        use crate::{args::Args, config::Config};
+
+        let config = "[segments.user]\ndefault_user = \"bob\"\n";
+        let config = Config::from_table(&config::parse(config).unwrap()).unwrap();
+        let env = vec![("PROMPTLINE_DEFAULT_USER".to_string(), "alice".to_string())];
+        let ctx = Context::with_env(Args::default(), config, env.into_iter().collect(), None);
+        assert!(get_user(&ctx, OsStr::new("bob"), false).is_err());
+        assert!(get_user(&ctx, OsStr::new("alice"), false).is_ok());
+    }
+
+    #[test]
+    fn ids_without_passwd_entry() {
+        let ctx = testing::context(&[], None);
+        let ids = get_user_ids(&ctx, (1000, 100)).unwrap();
+        assert_eq!(ids.to_ansi(), "\x1b[1m\x1b[35muid=1000\x1b[39m\x1b[22m");
+
+        let root = get_user_ids(&ctx, (0, 0)).unwrap();
+        assert_eq!(root.to_ansi(), "\x1b[1m\x1b[31muid=0\x1b[39m\x1b[22m");
+    }
+
+    #[test]
+    fn ids_with_gid() {
+        use crate::{args::Args, config::Config};
+
+        let config = "[segments.user]\nshow_gid = true\n";
+        let config = Config::from_table(&config::parse(config).unwrap()).unwrap();
+        let ctx = Context::with_env(Args::default(), config, Default::default(), None);
+        assert_eq!(
+            get_user_ids(&ctx, (1000, 100)).unwrap().to_plain(),
+            "uid=1000 gid=100"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::{collections::BTreeSet, fs};
+
+use crate::{
+    decorated::DecoratedString,
+    platform,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "users";
+
+/// Where glibc keeps the sessions currently logged in
+const UTMP: &str = "/var/run/utmp";
+
+/// Size of a glibc `struct utmp`. x86_64 keeps the 32-bit layout for
+/// compatibility, other 64-bit targets like aarch64 have a wider session and
+/// time. Other libcs aren't supported, and just show nothing unless their
+/// records happen to be the same size
+#[cfg(any(target_arch = "x86_64", target_pointer_width = "32"))]
+const RECORD_SIZE: usize = 384;
+#[cfg(not(any(target_arch = "x86_64", target_pointer_width = "32")))]
+const RECORD_SIZE: usize = 400;
+
+/// `ut_type` of a logged in user's session
+const USER_PROCESS: i16 = 7;
+
+/// Offset and length of `ut_user`, the same in every layout
+const USER_OFFSET: usize = 44;
+const USER_LEN: usize = 32;
+
+/// Users with a session in the records of `utmp`, each once. Records are
+/// `RECORD_SIZE` bytes, a partial one at the end is left out
+fn logged_in(utmp: &[u8]) -> BTreeSet<&[u8]> {
+    utmp.chunks_exact(RECORD_SIZE)
+        .filter(|record| i16::from_ne_bytes([record[0], record[1]]) == USER_PROCESS)
+        .map(|record| {
+            let user = &record[USER_OFFSET..USER_OFFSET + USER_LEN];
+            // NUL padded, unless the name takes up the whole field
+            let len = user.iter().position(|&b| b == 0).unwrap_or(USER_LEN);
+            &user[..len]
+        })
+        .filter(|user| !user.is_empty())
+        .collect()
+}
+
+fn get_users(ctx: &Context, me: Option<&[u8]>) -> Result<DecoratedString, SegmentError> {
+    let utmp = fs::read(ctx.path(UTMP))
+        .map_err(|_| SegmentError::not_applicable(NAME, "failed to read utmp"))?;
+
+    let others = logged_in(&utmp)
+        .into_iter()
+        .filter(|user| Some(*user) != me)
+        .count();
+    if others == 0 {
+        return Err(SegmentError::not_applicable(NAME, "nobody else logged in"));
+    }
+    let icon = super::icon(ctx, NAME, "👥", "users:")?.unwrap_or_default();
+    Ok(DecoratedString::new(format!("{icon}{others}")).dim())
+}
+
+/// A dim `👥3` with how many other users have a session on the machine, from
+/// utmp. Off unless `enabled = true`
+pub struct Users;
+
+impl Segment for Users {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        let me = platform::user_name(ctx)
+            .map_err(|e| SegmentError::failed(NAME, "failed to get user name").with_source(e))?;
+        let me = me.as_ref().and_then(|me| me.to_str());
+        get_users(ctx, me.map(str::as_bytes)).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    const BOOT_TIME: i16 = 2;
+    const LOGIN_PROCESS: i16 = 6;
+    const DEAD_PROCESS: i16 = 8;
+
+    /// A glibc utmp record, with the fields besides the user and line zeroed
+    fn record(kind: i16, user: &str, line: &str) -> Vec<u8> {
+        let mut record = vec![0; RECORD_SIZE];
+        record[..2].copy_from_slice(&kind.to_ne_bytes());
+        record[8..8 + line.len()].copy_from_slice(line.as_bytes());
+        record[USER_OFFSET..USER_OFFSET + user.len()].copy_from_slice(user.as_bytes());
+        record
+    }
+
+    /// Laid out like the utmp of a shared server: a boot record, a getty
+    /// waiting on a console, ada on two terminals, bob in tmux, carol logged
+    /// out and the user promptline runs as
+    fn utmp() -> Vec<u8> {
+        [
+            record(BOOT_TIME, "reboot", "~"),
+            record(LOGIN_PROCESS, "LOGIN", "tty1"),
+            record(USER_PROCESS, "ada", "pts/0"),
+            record(USER_PROCESS, "bob", "pts/1"),
+            record(USER_PROCESS, "ada", "pts/2"),
+            record(DEAD_PROCESS, "carol", "pts/3"),
+            record(USER_PROCESS, "me", "pts/4"),
+        ]
+        .concat()
+    }
+
+    fn write_utmp(root: &TempDir, utmp: &[u8]) {
+        root.write("var/run/.keep", "");
+        fs::write(root.path().join("var/run/utmp"), utmp).unwrap();
+    }
+
+    fn shown(root: &TempDir) -> Result<String, SegmentError> {
+        let ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        get_users(&ctx, Some(b"me")).map(|users| users.to_plain())
+    }
+
+    #[test]
+    fn parses_records() {
+        let utmp = utmp();
+        let users: Vec<_> = logged_in(&utmp).into_iter().collect();
+        assert_eq!(users, [&b"ada"[..], b"bob", b"me"]);
+
+        // A name filling the whole field has no NUL
+        let long = "a".repeat(USER_LEN);
+        let utmp = record(USER_PROCESS, &long, "pts/0");
+        let users: Vec<_> = logged_in(&utmp).into_iter().collect();
+        assert_eq!(users, [long.as_bytes()]);
+    }
+
+    #[test]
+    fn partial_record_is_left_out() {
+        let mut utmp = utmp();
+        utmp.extend(&record(USER_PROCESS, "dave", "pts/5")[..100]);
+        assert_eq!(logged_in(&utmp).len(), 3);
+    }
+
+    #[test]
+    fn counts_others() {
+        let root = TempDir::new();
+        write_utmp(&root, &utmp());
+        assert_eq!(shown(&root).unwrap(), "👥2");
+    }
+
+    #[test]
+    fn alone_or_unreadable() {
+        let root = TempDir::new();
+        assert_eq!(shown(&root).unwrap_err().kind, ErrorKind::NotApplicable);
+
+        write_utmp(&root, &record(USER_PROCESS, "me", "pts/0"));
+        assert_eq!(shown(&root).unwrap_err().kind, ErrorKind::NotApplicable);
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::{Component, Path};
+
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "venv";
+
+/// Length of the hash Poetry puts in the names of its envs
+const POETRY_HASH_LEN: usize = 8;
+
+/// A python virtual env, named the way it should be shown
+#[derive(Debug, PartialEq)]
+struct VenvName<'a> {
+    name: &'a str,
+    /// Python version, when the path has it
+    python: Option<&'a str>,
+}
+
+/// `dir` split into the project name and python version, if it is named the
+/// way Poetry names its envs, `<name>-<hash>-py<version>`
+fn parse_poetry_name(dir: &str) -> Option<VenvName<'_>> {
+    let (rest, python) = dir.rsplit_once("-py")?;
+    if python.is_empty() || !python.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    // The hash is base64 and can have a `-` in it, so it's found by length
+    let name_len = rest.len().checked_sub(POETRY_HASH_LEN + 1)?;
+    let (name, hash) = rest.split_at(name_len);
+    let hash = hash.strip_prefix('-')?;
+    let is_base64 = hash
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if name.is_empty() || !is_base64 {
+        return None;
+    }
+    Some(VenvName {
+        name,
+        python: Some(python),
+    })
+}
+
+/// How the env at `path` is shown. Envs in Poetry's cache are named after
+/// their project without the hash, in-project `.venv` dirs as PDM makes them
+/// after the dir they are in, and anything else after its own dir
+fn parse_venv_path(path: &str) -> Option<VenvName<'_>> {
+    let mut dirs = Path::new(path)
+        .components()
+        .rev()
+        .filter_map(|component| match component {
+            Component::Normal(dir) => dir.to_str(),
+            _ => None,
+        });
+    let dir = dirs.next()?;
+    let parent = dirs.next();
+
+    if dir == ".venv" {
+        if let Some(project) = parent {
+            return Some(VenvName {
+                name: project,
+                python: None,
+            });
+        }
+    }
+
+    let in_poetry_cache =
+        parent == Some("virtualenvs") && dirs.any(|dir| dir.eq_ignore_ascii_case("pypoetry"));
+    if in_poetry_cache {
+        if let Some(venv) = parse_poetry_name(dir) {
+            return Some(venv);
+        }
+    }
+
+    Some(VenvName {
+        name: dir,
+        python: None,
+    })
+}
+
+fn get_venv(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let path = ctx
+        .var("VIRTUAL_ENV")
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "no virtual env var"))?;
+    let venv = parse_venv_path(path)
+        .ok_or_else(|| SegmentError::failed(NAME, "failed to get virtual env name"))?;
+
+    let shown = match venv.python {
+        Some(python) => format!("{} ({python})", venv.name),
+        None => venv.name.to_string(),
+    };
+    let venv = match super::icon(ctx, NAME, "🐍", "venv:")? {
+        Some(icon) => DecoratedString::new(shown).with_icon(&icon),
+        None => DecoratedString::new(shown),
+    };
+    Ok(venv.bold())
+}
+
+/// Active python virtual env from `$VIRTUAL_ENV`. Poetry envs are shown as
+/// `🐍 myproj (3.11)` rather than by their hashed dir name, and in-project
+/// `.venv` dirs by the name of their project
+pub struct Venv;
+
+impl Segment for Venv {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_venv(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn shown(path: &str) -> (String, Option<String>) {
+        let venv = parse_venv_path(path).unwrap();
+        (venv.name.to_string(), venv.python.map(str::to_string))
+    }
+
+    fn named(name: &str, python: Option<&str>) -> (String, Option<String>) {
+        (name.to_string(), python.map(str::to_string))
+    }
+
+    #[test]
+    fn poetry_envs() {
+        assert_eq!(
+            shown("/home/ada/.cache/pypoetry/virtualenvs/myproj-aBc1_2-x-py3.11"),
+            named("myproj", Some("3.11"))
+        );
+        assert_eq!(
+            shown("/Users/ada/Library/Caches/pypoetry/virtualenvs/my-web-app-Xy9zQ8wE-py3.12"),
+            named("my-web-app", Some("3.12"))
+        );
+        assert_eq!(
+            shown("/c/Users/ada/AppData/Local/pypoetry/Cache/virtualenvs/shop-0a1B2c3D-py3.9"),
+            named("shop", Some("3.9"))
+        );
+    }
+
+    #[test]
+    fn poetry_lookalikes_keep_their_name() {
+        // Not in Poetry's cache
+        assert_eq!(
+            shown("/home/ada/envs/myproj-aBc12xYz-py3.11"),
+            named("myproj-aBc12xYz-py3.11", None)
+        );
+        // In the cache, but without a hash
+        assert_eq!(
+            shown("/home/ada/.cache/pypoetry/virtualenvs/tool-py3"),
+            named("tool-py3", None)
+        );
+        assert_eq!(
+            shown("/home/ada/.cache/pypoetry/virtualenvs/myproj-aBc12xYz-pypy"),
+            named("myproj-aBc12xYz-pypy", None)
+        );
+    }
+
+    #[test]
+    fn in_project_envs() {
+        assert_eq!(shown("/home/ada/src/shop/.venv"), named("shop", None));
+        assert_eq!(shown("/home/ada/src/shop/.venv/"), named("shop", None));
+        assert_eq!(shown("/.venv"), named(".venv", None));
+    }
+
+    #[test]
+    fn plain_envs() {
+        assert_eq!(
+            shown("/home/ada/.virtualenvs/science"),
+            named("science", None)
+        );
+        assert_eq!(shown("venv"), named("venv", None));
+        assert!(parse_venv_path("/").is_none());
+    }
+
+    #[test]
+    fn renders() {
+        let ctx = testing::context(
+            &[(
+                "VIRTUAL_ENV",
+                "/home/ada/.cache/pypoetry/virtualenvs/myproj-aBc12xYz-py3.11",
+            )],
+            None,
+        );
+        assert_eq!(get_venv(&ctx).unwrap().to_plain(), "🐍 myproj (3.11)");
+        assert!(get_venv(&testing::context(&[], None)).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    config,
+    decorated::{Color, DecoratedString},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "vi_mode";
+
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Insert,
+    Normal,
+}
+
+impl Mode {
+    /// Parses zsh's `$KEYMAP` or fish's `$fish_bind_mode`
+    fn from_shell(value: &str) -> Option<Mode> {
+        match value {
+            "main" | "viins" | "insert" => Some(Mode::Insert),
+            "vicmd" | "default" | "normal" => Some(Mode::Normal),
+            _ => None,
+        }
+    }
+}
+
+/// The mode from `--vi-mode`, or `$PROMPTLINE_VI_MODE` for shells that
+/// would rather export it
+fn get_vi_mode(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let value = ctx
+        .args
+        .vi_mode
+        .as_deref()
+        .or_else(|| ctx.var("PROMPTLINE_VI_MODE"))
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "vi mode not passed in"))?;
+
+    let mode = Mode::from_shell(value)
+        .ok_or_else(|| SegmentError::not_applicable(NAME, format!("unknown vi mode {value}")))?;
+
+    match mode {
+        Mode::Normal => Ok(DecoratedString::new("N".into())
+            .colored(Color::Yellow)
+            .bold()),
+        Mode::Insert => {
+            let show = config::get_bool(&ctx.config.segment(NAME).options, "show_insert")
+                .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+                .unwrap_or(true);
+            if !show {
+                return Err(SegmentError::not_applicable(NAME, "in insert mode"));
+            }
+            Ok(DecoratedString::new("I".into()).dim())
+        }
+    }
+}
+
+/// `N` or `I` for the line editor's vi mode, as passed in by the shell. The
+/// insert mode indicator is hidden with `show_insert = false`
+pub struct ViMode;
+
+impl Segment for ViMode {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_vi_mode(ctx).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{segment::ErrorKind, testing};
+
+    #[test]
+    fn parses_zsh_and_fish_modes() {
+        assert_eq!(Mode::from_shell("vicmd"), Some(Mode::Normal));
+        assert_eq!(Mode::from_shell("default"), Some(Mode::Normal));
+        assert_eq!(Mode::from_shell("main"), Some(Mode::Insert));
+        assert_eq!(Mode::from_shell("insert"), Some(Mode::Insert));
+        assert_eq!(Mode::from_shell("visual"), None);
+    }
+
+    #[test]
+    fn normal_mode_from_flag() {
+        let mut ctx = testing::context(&[("PROMPTLINE_VI_MODE", "main")], None);
+        ctx.args.vi_mode = Some("vicmd".into());
+        assert_eq!(
+            get_vi_mode(&ctx).unwrap().to_ansi(),
+            "\x1b[1m\x1b[33mN\x1b[39m\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn insert_mode_from_env() {
+        let ctx = testing::context(&[("PROMPTLINE_VI_MODE", "viins")], None);
+        assert_eq!(get_vi_mode(&ctx).unwrap().to_plain(), "I");
+    }
+
+    #[test]
+    fn unknown_mode_shows_nothing() {
+        let ctx = testing::context(&[("PROMPTLINE_VI_MODE", "replace_one")], None);
+        assert!(matches!(get_vi_mode(&ctx), Err(e) if e.kind == ErrorKind::NotApplicable));
+    }
+}
@@ -0,0 +1,186 @@
+#[cfg(not(windows))]
+use crate::config;
+use crate::{
+    decorated::DecoratedString,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "vm";
+
+#[cfg(windows)]
+fn get_vm(_ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    Err(SegmentError::not_applicable(
+        NAME,
+        "virtual machines aren't detected on windows",
+    ))
+}
+
+/// Hypervisors as named in the DMI vendor or product of their guests, and how
+/// they are shown
+#[cfg(not(windows))]
+const HYPERVISORS: &[(&str, &str)] = &[
+    ("QEMU", "qemu"),
+    ("KVM", "kvm"),
+    ("VirtualBox", "virtualbox"),
+    ("innotek", "virtualbox"),
+    ("VMware", "vmware"),
+    ("Xen", "xen"),
+    ("Parallels", "parallels"),
+    ("Bochs", "bochs"),
+];
+
+/// The hypervisor the DMI `sys_vendor` and `product_name` belong to
+#[cfg(not(windows))]
+fn hypervisor(vendor: &str, product: &str) -> Option<&'static str> {
+    // Physical Surface machines are made by Microsoft too
+    if vendor.trim() == "Microsoft Corporation" && product.trim() == "Virtual Machine" {
+        return Some("hyper-v");
+    }
+    // KVM guests have a QEMU vendor but a KVM product, the product says more
+    [product, vendor].iter().find_map(|field| {
+        HYPERVISORS
+            .iter()
+            .find(|(known, _)| field.contains(known))
+            .map(|&(_, name)| name)
+    })
+}
+
+#[cfg(not(windows))]
+fn get_vm(ctx: &Context) -> Result<DecoratedString, SegmentError> {
+    let in_container = config::get_bool(&ctx.config.segment(NAME).options, "in_container")
+        .map_err(|e| SegmentError::failed(NAME, "invalid config").with_source(e))?
+        .unwrap_or(false);
+    if super::docker::in_container(ctx) && !in_container {
+        return Err(SegmentError::not_applicable(
+            NAME,
+            "docker segment shows the container",
+        ));
+    }
+
+    // Unreadable DMI names no hypervisor, as if it were bare metal
+    let vendor = ctx
+        .read_to_string("/sys/class/dmi/id/sys_vendor")
+        .unwrap_or_default();
+    let product = ctx
+        .read_to_string("/sys/class/dmi/id/product_name")
+        .unwrap_or_default();
+    let name = hypervisor(&vendor, &product);
+    // Minimal guests can have DMI that names no hypervisor, but virtio
+    // devices give them away
+    let name = match name {
+        Some(name) => name,
+        None if ctx.exists("/dev/virtio-ports") => "vm",
+        None => return Err(SegmentError::not_applicable(NAME, "not a virtual machine")),
+    };
+    let vm = match super::icon(ctx, NAME, "⬒", "vm:")? {
+        Some(icon) => DecoratedString::new(name.to_string()).with_icon(&icon),
+        None => DecoratedString::new(name.to_string()),
+    };
+    Ok(vm.dim())
+}
+
+/// `⬒ qemu` with the hypervisor inside virtual machines, or `⬒ vm` if it can't
+/// be told. Off unless `enabled = true`. Inside a docker container only the
+/// docker segment is shown, unless `in_container = true`
+pub struct Vm;
+
+impl Segment for Vm {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_vm(ctx).map(Into::into)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        segment::ErrorKind,
+        testing::{self, TempDir},
+    };
+
+    fn guest(vendor: &str, product: &str) -> TempDir {
+        let root = TempDir::new();
+        root.write("sys/class/dmi/id/sys_vendor", &format!("{vendor}\n"));
+        root.write("sys/class/dmi/id/product_name", &format!("{product}\n"));
+        root
+    }
+
+    fn shown_with(root: &TempDir, toml: &str) -> Result<String, SegmentError> {
+        let mut ctx = testing::context(&[], None).with_root(root.path().to_path_buf());
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        get_vm(&ctx).map(|vm| vm.to_plain())
+    }
+
+    fn shown(root: &TempDir) -> Result<String, SegmentError> {
+        shown_with(root, "")
+    }
+
+    fn hidden(shown: Result<String, SegmentError>) -> bool {
+        matches!(shown, Err(e) if e.kind == ErrorKind::NotApplicable)
+    }
+
+    #[test]
+    fn hypervisors() {
+        assert_eq!(
+            hypervisor("QEMU", "Standard PC (Q35 + ICH9, 2009)"),
+            Some("qemu")
+        );
+        assert_eq!(hypervisor("QEMU", "KVM"), Some("kvm"));
+        assert_eq!(hypervisor("innotek GmbH", "VirtualBox"), Some("virtualbox"));
+        assert_eq!(
+            hypervisor("VMware, Inc.", "VMware Virtual Platform"),
+            Some("vmware")
+        );
+        assert_eq!(
+            hypervisor("Microsoft Corporation\n", "Virtual Machine\n"),
+            Some("hyper-v")
+        );
+        assert_eq!(
+            hypervisor("Microsoft Corporation", "Surface Laptop 5"),
+            None
+        );
+        assert_eq!(hypervisor("Dell Inc.", "XPS 13 9310"), None);
+    }
+
+    #[test]
+    fn guest_is_marked() {
+        let root = guest("innotek GmbH", "VirtualBox");
+        assert_eq!(shown(&root).unwrap(), "⬒ virtualbox");
+    }
+
+    #[test]
+    fn virtio_fallback() {
+        let root = guest("", "");
+        assert!(hidden(shown(&root)));
+        root.write("dev/virtio-ports/org.qemu.guest_agent.0", "");
+        assert_eq!(shown(&root).unwrap(), "⬒ vm");
+    }
+
+    #[test]
+    fn bare_metal() {
+        assert!(hidden(shown(&guest("Dell Inc.", "XPS 13 9310"))));
+        // DMI unreadable
+        assert!(hidden(shown(&TempDir::new())));
+    }
+
+    #[test]
+    fn container_takes_precedence() {
+        let root = guest("QEMU", "KVM");
+        root.write(".dockerenv", "");
+        assert!(hidden(shown(&root)));
+        assert_eq!(
+            shown_with(&root, "[segments.vm]\nin_container = true\n").unwrap(),
+            "⬒ kvm"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    config,
+    decorated::{Color, DecoratedString},
+    platform::{self, NetworkInterface, PlatformError},
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const NAME: &str = "vpn";
+
+/// OpenVPN and friends, WireGuard, and Tailscale
+const DEFAULT_INTERFACES: &[&str] = &["tun*", "wg*", "tailscale*"];
+
+/// Where the interfaces come from, so tests can hand over their own
+trait Interfaces {
+    fn list(&self) -> Result<Vec<NetworkInterface>, PlatformError>;
+}
+
+/// The interfaces the system reports
+struct SystemInterfaces;
+
+impl Interfaces for SystemInterfaces {
+    fn list(&self) -> Result<Vec<NetworkInterface>, PlatformError> {
+        platform::network_interfaces()
+    }
+}
+
+/// A WireGuard tunnel, for `promptline preview`
+struct SampleInterfaces;
+
+impl Interfaces for SampleInterfaces {
+    fn list(&self) -> Result<Vec<NetworkInterface>, PlatformError> {
+        Ok(vec![NetworkInterface {
+            name: "wg0".into(),
+            up: true,
+            has_address: true,
+            ipv4: vec![[10, 8, 0, 2].into()],
+        }])
+    }
+}
+
+/// Whether `name` matches `pattern`, a full interface name or a prefix
+/// followed by `*`
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+fn get_vpn(ctx: &Context, interfaces: &dyn Interfaces) -> Result<DecoratedString, SegmentError> {
+    let options = &ctx.config.segment(NAME).options;
+    let invalid = |e| SegmentError::failed(NAME, "invalid config").with_source(e);
+    let patterns = config::get_strings(options, "interfaces")
+        .map_err(invalid)?
+        .unwrap_or_else(|| DEFAULT_INTERFACES.iter().map(|s| s.to_string()).collect());
+    let show_name = config::get_bool(options, "show_name")
+        .map_err(invalid)?
+        .unwrap_or(false);
+
+    let interfaces = interfaces.list().map_err(|e| {
+        SegmentError::not_applicable(NAME, "failed to list interfaces").with_source(e)
+    })?;
+
+    let vpn = interfaces
+        .iter()
+        .filter(|interface| interface.up && interface.has_address)
+        .find(|interface| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(&interface.name, pattern))
+        })
+        .ok_or_else(|| SegmentError::not_applicable(NAME, "no vpn interface up"))?;
+
+    let icon = super::icon(ctx, NAME, "🔒", "")?.unwrap_or_default();
+    let text = DecoratedString::new(format!("{icon}vpn")).colored(Color::Green);
+    if show_name {
+        Ok(text.with_suffix(DecoratedString::new(vpn.name.clone()).dim()))
+    } else {
+        Ok(text)
+    }
+}
+
+/// Shows when a VPN interface is up, from the local interface list alone. Off
+/// unless `enabled = true`
+pub struct Vpn;
+
+impl Segment for Vpn {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn render(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_vpn(ctx, &SystemInterfaces).map(Into::into)
+    }
+
+    fn preview(&self, ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        get_vpn(ctx, &SampleInterfaces).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{args::Args, config::Config, segment::ErrorKind, testing};
+
+    struct Fixed(Vec<NetworkInterface>);
+
+    impl Interfaces for Fixed {
+        fn list(&self) -> Result<Vec<NetworkInterface>, PlatformError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn interface(name: &str, up: bool, has_address: bool) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_string(),
+            up,
+            has_address,
+            ipv4: vec![],
+        }
+    }
+
+    fn hidden(ctx: &Context, interfaces: &[NetworkInterface]) -> bool {
+        matches!(
+            get_vpn(ctx, &Fixed(interfaces.to_vec())),
+            Err(e) if e.kind == ErrorKind::NotApplicable
+        )
+    }
+
+    #[test]
+    fn prefix_patterns() {
+        assert!(matches_pattern("wg0", "wg*"));
+        assert!(matches_pattern("tailscale0", "tailscale*"));
+        assert!(matches_pattern("tun0", "tun0"));
+        assert!(!matches_pattern("tun01", "tun0"));
+        assert!(!matches_pattern("eth0", "wg*"));
+    }
+
+    #[test]
+    fn shows_up_vpn_interface() {
+        let ctx = testing::context(&[], None);
+        let interfaces = Fixed(vec![
+            interface("lo", true, true),
+            interface("wg0", true, true),
+        ]);
+        assert_eq!(get_vpn(&ctx, &interfaces).unwrap().to_plain(), "🔒vpn");
+    }
+
+    #[test]
+    fn needs_up_interface_with_address() {
+        let ctx = testing::context(&[], None);
+        assert!(hidden(&ctx, &[interface("eth0", true, true)]));
+        assert!(hidden(&ctx, &[interface("tun0", false, true)]));
+        assert!(hidden(&ctx, &[interface("tun0", true, false)]));
+    }
+
+    #[test]
+    fn configured_patterns_and_name() {
+        let config = "[segments.vpn]\ninterfaces = [\"ppp*\"]\nshow_name = true\n";
+        let config = Config::from_table(&config::parse(config).unwrap()).unwrap();
+        let ctx = Context::with_env(Args::default(), config, Default::default(), None);
+
+        let interfaces = [interface("wg0", true, true), interface("ppp0", true, true)];
+        let vpn = get_vpn(&ctx, &Fixed(interfaces.to_vec())).unwrap();
+        assert_eq!(vpn.to_plain(), "🔒vpn ppp0");
+        assert!(hidden(&ctx, &interfaces[..1]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lists_system_interfaces() {
+        SystemInterfaces.list().unwrap();
+    }
+}
@@ -0,0 +1,52 @@
+//! Sizes of files and filesystems, written short the way `ls -h` and `df -h`
+//! write them
+
+/// Units after bytes, each 1024 times the one before: KiB, MiB, GiB and so on
+const UNITS: &[&str] = &["K", "M", "G", "T", "P", "E"];
+
+/// `bytes` as `512B`, `4.2G` or `17G`. A tenth of the unit is shown below 10
+/// of it, above that the size is rounded to the unit
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Moves up early where rounding would print `1024K`
+    while size >= 1023.5 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if size < 9.95 {
+        format!("{size:.1}{}", UNITS[unit])
+    } else {
+        format!("{size:.0}{}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(0), "0B");
+        assert_eq!(format_size(1023), "1023B");
+        assert_eq!(format_size(1024), "1.0K");
+        assert_eq!(format_size(1536), "1.5K");
+        assert_eq!(format_size(512 * 1024 * 1024), "512M");
+        assert_eq!(format_size(GIB * 42 / 10), "4.2G");
+        assert_eq!(format_size(GIB * 17), "17G");
+        assert_eq!(format_size(u64::MAX), "16E");
+    }
+
+    #[test]
+    fn rounds_up_into_the_next_unit() {
+        assert_eq!(format_size(1024 * 1024 - 1), "1.0M");
+        assert_eq!(format_size(GIB * 999 / 100), "10G");
+    }
+}
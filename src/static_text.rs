@@ -0,0 +1,146 @@
+//! `[[static]]` segments, fixed text from the config like a `⚠ PROD` marker
+
+use crate::{
+    config::{self, ConfigError, Table},
+    decorated::{Color, DecoratedString},
+    runner::Placement,
+    segment::{RenderedSegment, Segment, SegmentError},
+    Context,
+};
+
+const DEFAULT_NAME: &str = "static";
+
+/// A `[[static]]` entry in the config file
+#[derive(Clone, Debug)]
+pub struct StaticConfig {
+    /// Name for `[segments.<name>]`, `static` unless set
+    pub name: String,
+    pub text: String,
+    pub color: Option<Color>,
+    pub bold: bool,
+    /// From `position`, `first`, `last` or `after=<segment>`. Last if unset
+    pub placement: Placement,
+}
+
+fn parse_placement(s: &str) -> Option<Placement> {
+    match s {
+        "first" => Some(Placement::First),
+        "last" => Some(Placement::Last),
+        _ => s
+            .strip_prefix("after=")
+            .filter(|name| !name.is_empty())
+            .map(|name| Placement::After(name.to_string())),
+    }
+}
+
+impl StaticConfig {
+    pub fn from_table(table: &Table) -> Result<StaticConfig, ConfigError> {
+        let name = config::get_str(table, "name")?
+            .unwrap_or(DEFAULT_NAME)
+            .to_string();
+
+        let text = config::get_str(table, "text")?
+            .ok_or_else(|| ConfigError::invalid_field("static.text", "string"))?
+            .to_string();
+
+        let color = config::get_str(table, "color")?
+            .map(|s| {
+                Color::from_name(s)
+                    .ok_or_else(|| ConfigError::invalid_field("static.color", "color name"))
+            })
+            .transpose()?;
+
+        let bold = config::get_bool(table, "bold")?.unwrap_or(false);
+
+        let placement = config::get_str(table, "position")?
+            .map(|s| {
+                parse_placement(s).ok_or_else(|| {
+                    ConfigError::invalid_field("static.position", "first, last or after=<segment>")
+                })
+            })
+            .transpose()?
+            .unwrap_or(Placement::Last);
+
+        Ok(StaticConfig {
+            name,
+            text,
+            color,
+            bold,
+            placement,
+        })
+    }
+}
+
+/// Shows the text of a `[[static]]` entry as is
+pub struct StaticSegment {
+    config: StaticConfig,
+}
+
+impl StaticSegment {
+    pub fn new(config: StaticConfig) -> StaticSegment {
+        StaticSegment { config }
+    }
+}
+
+impl Segment for StaticSegment {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        let mut text = DecoratedString::new(self.config.text.clone());
+        if let Some(color) = self.config.color {
+            text = text.colored(color);
+        }
+        if self.config.bold {
+            text = text.bold();
+        }
+        Ok(text.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_toml(toml: &str) -> Result<StaticConfig, ConfigError> {
+        StaticConfig::from_table(&config::parse(toml).unwrap())
+    }
+
+    #[test]
+    fn parses_entry() {
+        let config =
+            from_toml("text = \"⚠ PROD\"\ncolor = \"red\"\nbold = true\nposition = \"first\"\n")
+                .unwrap();
+        assert_eq!(config.name, "static");
+        assert_eq!(config.placement, Placement::First);
+
+        let ctx = crate::testing::context(&[], None);
+        let rendered = StaticSegment::new(config).render(&ctx).unwrap();
+        assert_eq!(rendered.to_ansi(), "\x1b[1m\x1b[31m⚠ PROD\x1b[39m\x1b[22m");
+    }
+
+    #[test]
+    fn position_modes() {
+        let placement = |position: &str| {
+            from_toml(&format!("text = \"x\"\nposition = \"{position}\"\n"))
+                .map(|config| config.placement)
+        };
+        assert_eq!(placement("last").unwrap(), Placement::Last);
+        assert_eq!(
+            placement("after=cwd").unwrap(),
+            Placement::After("cwd".into())
+        );
+        assert!(placement("after=").is_err());
+        assert!(placement("middle").is_err());
+        assert_eq!(
+            from_toml("text = \"x\"\n").unwrap().placement,
+            Placement::Last
+        );
+    }
+
+    #[test]
+    fn requires_text() {
+        assert!(from_toml("color = \"red\"\n").is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! Helpers for unit tests of segments
+
+// Which helpers are used depends on the segments compiled in
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{args::Args, config::Config, Context};
+
+/// A context with only the environment variables in `vars` set and `cwd` as
+/// the working directory
+pub fn context(vars: &[(&str, &str)], cwd: Option<&str>) -> Context {
+    let env: HashMap<_, _> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    Context::with_env(
+        Args::default(),
+        Config::default(),
+        env,
+        cwd.map(PathBuf::from),
+    )
+}
+
+/// A fresh directory under the system temp dir, removed on drop
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "promptline-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Creates `relative` and its parent directories, with `contents`
+    pub fn write(&self, relative: &str, contents: &str) {
+        let path = self.path.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
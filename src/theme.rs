@@ -0,0 +1,283 @@
+//! Named sets of styles for the colors segments use, for terminals and eyes
+//! the default colors don't suit. Picked with `theme` in the config or
+//! `$PROMPTLINE_THEME`, and layered under the `colors` of each segment
+
+use crate::{
+    decorated::{Color, DecoratedString, Style},
+    Context,
+};
+
+/// A set of styles for the colors of the segments
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Theme {
+    /// The colors segments pick themselves
+    #[default]
+    Default,
+    /// Darker colors in place of yellow, cyan and white, for light backgrounds
+    Light,
+    /// Blue in place of green, and failures underlined, for red-green color
+    /// blindness
+    Colorblind,
+    /// Bold, dim and underline in place of any color
+    Mono,
+}
+
+/// Names of the themes, in the order of the styles in `SEGMENTS`
+pub const NAMES: &[&str] = &["default", "light", "colorblind", "mono"];
+
+impl Theme {
+    const ALL: [Theme; 4] = [Theme::Default, Theme::Light, Theme::Colorblind, Theme::Mono];
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        let i = NAMES.iter().position(|known| *known == name)?;
+        Some(Theme::ALL[i])
+    }
+
+    pub fn name(self) -> &'static str {
+        NAMES[self as usize]
+    }
+}
+
+/// The style each color of a segment is shown as. Colors without one are left
+/// as they are
+type Palette = &'static [(Color, Style)];
+
+const fn attrs(bold: bool, dim: bool, underline: bool) -> Style {
+    Style {
+        color: None,
+        bold,
+        dim,
+        underline,
+    }
+}
+
+const UNDERLINED_RED: Style = Style {
+    underline: true,
+    ..Style::color(Color::Red)
+};
+
+const KEEP: Palette = &[];
+
+const LIGHT: Palette = &[
+    (Color::Yellow, Style::color(Color::Magenta)),
+    (Color::Cyan, Style::color(Color::Blue)),
+    (Color::White, attrs(false, false, false)),
+];
+
+const COLORBLIND: Palette = &[
+    (Color::Green, Style::color(Color::Blue)),
+    (Color::Red, UNDERLINED_RED),
+];
+
+/// Failures stand out by being underlined and bold, warnings by being
+/// underlined, and everything else loses its color
+const MONO: Palette = &[
+    (Color::Red, attrs(true, false, true)),
+    (Color::Yellow, attrs(false, false, true)),
+    (Color::Green, attrs(false, false, false)),
+    (Color::Blue, attrs(false, false, false)),
+    (Color::Magenta, attrs(false, false, false)),
+    (Color::Cyan, attrs(false, false, false)),
+    (Color::White, attrs(false, false, false)),
+];
+
+/// `MONO` for segments whose cyan is only there to set them apart, which then
+/// fades into the background
+const MONO_QUIET: Palette = &[
+    (Color::Red, attrs(true, false, true)),
+    (Color::Yellow, attrs(false, false, true)),
+    (Color::Cyan, attrs(false, true, false)),
+];
+
+const USUAL: [Palette; 4] = [KEEP, LIGHT, COLORBLIND, MONO];
+const QUIET: [Palette; 4] = [KEEP, LIGHT, COLORBLIND, MONO_QUIET];
+
+/// The palette of every built in segment in each theme, in the order of
+/// `NAMES`, along with the prompt char. Segments missing here, like custom
+/// ones, keep their colors
+const SEGMENTS: &[(&str, [Palette; 4])] = &[
+    ("time", QUIET),
+    ("idle", USUAL),
+    ("docker", USUAL),
+    ("docker_context", USUAL),
+    ("sandbox", USUAL),
+    ("vm", USUAL),
+    ("user", USUAL),
+    ("users", USUAL),
+    ("sudo", USUAL),
+    ("hostname", USUAL),
+    ("uptime", USUAL),
+    ("systemd", USUAL),
+    ("updates", USUAL),
+    ("cwd", USUAL),
+    ("shell", USUAL),
+    ("shlvl", USUAL),
+    ("status", USUAL),
+    ("umask", USUAL),
+    ("hg", USUAL),
+    ("hg_drafts", USUAL),
+    ("git", USUAL),
+    ("unpushed", USUAL),
+    ("repo_size", USUAL),
+    ("vpn", USUAL),
+    ("offline", USUAL),
+    ("local_ip", USUAL),
+    ("conda", USUAL),
+    ("venv", USUAL),
+    ("nix_shell", USUAL),
+    ("nix_path", USUAL),
+    ("guix_env", USUAL),
+    ("rust_toolchain", USUAL),
+    ("cargo", USUAL),
+    ("java", USUAL),
+    ("php", USUAL),
+    ("ruby", USUAL),
+    ("node", USUAL),
+    ("bazel", USUAL),
+    ("cmake", USUAL),
+    ("env_watch", QUIET),
+    ("locale", USUAL),
+    ("mail", USUAL),
+    ("cpu_temp", USUAL),
+    ("vi_mode", USUAL),
+    ("prompt_char", USUAL),
+];
+
+/// The theme `$PROMPTLINE_THEME` names, or else the config's. An unknown name
+/// in the variable is passed over
+pub fn current(ctx: &Context) -> Theme {
+    ctx.var("PROMPTLINE_THEME")
+        .and_then(Theme::from_name)
+        .unwrap_or(ctx.config.theme)
+}
+
+/// The style `color` of the segment `name` is shown as, from the segment's
+/// `colors` or else the theme. `None` to keep the color
+fn style(ctx: &Context, theme: Theme, name: &str, color: Color) -> Option<Style> {
+    let overridden = ctx
+        .config
+        .segments
+        .get(name)
+        .and_then(|config| config.colors.iter().find(|(from, _)| *from == color));
+    if let Some((_, style)) = overridden {
+        return Some(*style);
+    }
+
+    let (_, palettes) = SEGMENTS.iter().find(|(segment, _)| *segment == name)?;
+    let palette = palettes[theme as usize];
+    palette
+        .iter()
+        .find(|(from, _)| *from == color)
+        .map(|(_, style)| *style)
+}
+
+/// What each color of the segment `name` is shown as in the current theme,
+/// for `restyled`
+pub fn styler<'a>(ctx: &'a Context, name: &'a str) -> impl Fn(Color) -> Option<Style> + 'a {
+    let theme = current(ctx);
+    move |color| style(ctx, theme, name, color)
+}
+
+/// `text` of the segment `name` in the current theme's styles
+pub fn restyle(ctx: &Context, name: &str, text: DecoratedString) -> DecoratedString {
+    text.restyled(&styler(ctx, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{self, Config},
+        segments, testing,
+    };
+
+    fn context(vars: &[(&str, &str)], toml: &str) -> Context {
+        let mut ctx = testing::context(vars, None);
+        ctx.config = Config::from_table(&config::parse(toml).unwrap()).unwrap();
+        ctx
+    }
+
+    fn status() -> DecoratedString {
+        DecoratedString::join(
+            vec![
+                DecoratedString::new("0".into()).colored(Color::Green),
+                DecoratedString::new("1".into()).colored(Color::Red),
+            ],
+            "|",
+        )
+        .bold()
+    }
+
+    #[test]
+    fn every_segment_in_every_theme() {
+        let names: Vec<_> = SEGMENTS.iter().map(|(name, _)| *name).collect();
+        for segment in segments::builtin() {
+            assert!(names.contains(&segment.name()), "{}", segment.name());
+        }
+        assert_eq!(NAMES.len(), Theme::ALL.len());
+        for (i, name) in NAMES.iter().enumerate() {
+            assert_eq!(Theme::from_name(name).map(|theme| theme as usize), Some(i));
+        }
+    }
+
+    #[test]
+    fn default_keeps_colors() {
+        let ctx = context(&[], "");
+        assert_eq!(
+            restyle(&ctx, "status", status()).to_ansi(),
+            status().to_ansi()
+        );
+    }
+
+    #[test]
+    fn themes_from_config_and_env() {
+        let ctx = context(&[], "theme = \"colorblind\"\n");
+        assert_eq!(
+            restyle(&ctx, "status", status()).to_ansi(),
+            "\x1b[1m\x1b[34m0\x1b[39m|\x1b[4m\x1b[31m1\x1b[39m\x1b[24m\x1b[22m"
+        );
+
+        let ctx = context(&[("PROMPTLINE_THEME", "mono")], "theme = \"colorblind\"\n");
+        assert_eq!(
+            restyle(&ctx, "status", status()).to_ansi(),
+            "\x1b[1m0|\x1b[1m\x1b[4m1\x1b[24m\x1b[22m\x1b[22m"
+        );
+
+        let ctx = context(&[("PROMPTLINE_THEME", "sepia")], "theme = \"light\"\n");
+        assert_eq!(current(&ctx), Theme::Light);
+        assert!(Config::from_table(&config::parse("theme = \"sepia\"\n").unwrap()).is_err());
+    }
+
+    #[test]
+    fn segment_colors_override_theme() {
+        let toml = "theme = \"mono\"\n[segments.status.colors]\nred = \"magenta bold\"\n";
+        let ctx = context(&[], toml);
+        assert_eq!(
+            restyle(&ctx, "status", status()).to_ansi(),
+            "\x1b[1m0|\x1b[1m\x1b[35m1\x1b[39m\x1b[22m\x1b[22m"
+        );
+
+        // Custom segments keep their colors, unless overridden
+        let toml = "theme = \"mono\"\n[segments.deploy.colors]\ngreen = \"none\"\n";
+        let ctx = context(&[], toml);
+        assert_eq!(
+            restyle(&ctx, "deploy", status()).to_ansi(),
+            "\x1b[1m0|\x1b[31m1\x1b[39m\x1b[22m"
+        );
+    }
+
+    #[test]
+    fn parses_styles() {
+        assert_eq!(
+            Style::from_words("blue underline"),
+            Some(Style {
+                underline: true,
+                ..Style::color(Color::Blue)
+            })
+        );
+        assert_eq!(Style::from_words("none"), Some(Style::default()));
+        assert_eq!(Style::from_words(""), None);
+        assert_eq!(Style::from_words("blue red"), None);
+        assert_eq!(Style::from_words("italic"), None);
+    }
+}
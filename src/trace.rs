@@ -0,0 +1,189 @@
+//! `PROMPTLINE_TRACE=<file>`, which appends a line about every prompt to the
+//! file, for tracking down a prompt that is only slow now and then. A line
+//! holds tab separated fields:
+//!
+//! ```text
+//! <unix time>\t<cwd>\t<total ms>\t<segment>:<outcome>:<ms>\t...
+//! ```
+//!
+//! with tabs, newlines and backslashes in the cwd and segment names escaped
+
+use std::{
+    fmt::Write as _,
+    fs::OpenOptions,
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::segment::{ErrorKind, RenderedSegment, SegmentError};
+
+/// How rendering one segment of a traced prompt went
+pub struct SegmentTrace {
+    pub name: String,
+    pub outcome: &'static str,
+    pub elapsed: Duration,
+}
+
+impl SegmentTrace {
+    pub fn new(
+        name: &str,
+        result: &Result<RenderedSegment, SegmentError>,
+        elapsed: Duration,
+    ) -> SegmentTrace {
+        let outcome = match result {
+            Ok(_) => "shown",
+            Err(e) => match e.kind {
+                ErrorKind::NotApplicable => "not_applicable",
+                ErrorKind::Hidden => "hidden",
+                ErrorKind::OverBudget => "over_budget",
+                ErrorKind::Failed => "failed",
+            },
+        };
+        SegmentTrace {
+            name: name.to_string(),
+            outcome,
+            elapsed,
+        }
+    }
+}
+
+/// One prompt, as written to the trace file
+pub struct Trace {
+    pub time: SystemTime,
+    pub cwd: Option<PathBuf>,
+    /// From promptline starting until the prompt was rendered
+    pub total: Duration,
+    pub segments: Vec<SegmentTrace>,
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+impl Trace {
+    /// The line for the trace file, without the newline
+    pub fn to_line(&self) -> String {
+        let time = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let cwd = self
+            .cwd
+            .as_deref()
+            .map(|cwd| escape(&cwd.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut line = format!("{time:.3}\t{cwd}\t{:.3}", ms(self.total));
+        for segment in &self.segments {
+            let _ = write!(
+                line,
+                "\t{}:{}:{:.3}",
+                escape(&segment.name),
+                segment.outcome,
+                ms(segment.elapsed)
+            );
+        }
+        line
+    }
+}
+
+/// Appends `trace` to the file at `path`, creating it if need be. This is only
+/// ever a debugging aid, so a file that can't be written is ignored. The file
+/// is opened with `O_CLOEXEC`, keeping it from leaking into the commands
+/// custom segments spawn
+pub fn append(path: &Path, trace: &Trace) {
+    let mut options = OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_CLOEXEC);
+    }
+
+    // A single write, so lines from prompts in several shells don't interleave
+    let line = trace.to_line() + "\n";
+    if let Ok(mut file) = options.open(path) {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::TempDir, DecoratedString};
+
+    fn trace() -> Trace {
+        let shown = Ok(DecoratedString::new("main".into()).into());
+        let missing = Err(SegmentError::not_applicable("conda", "no conda env var"));
+        let slow = Err(SegmentError::new("my:cmd", ErrorKind::OverBudget));
+        Trace {
+            time: UNIX_EPOCH + Duration::from_millis(1_700_000_000_250),
+            cwd: Some("/home/me/tab\there".into()),
+            total: Duration::from_micros(12_345),
+            segments: vec![
+                SegmentTrace::new("git", &shown, Duration::from_micros(1_500)),
+                SegmentTrace::new("conda", &missing, Duration::from_micros(20)),
+                SegmentTrace::new("my:cmd", &slow, Duration::from_millis(100)),
+            ],
+        }
+    }
+
+    /// Splits a line back into its time, cwd, total and segments
+    #[allow(clippy::type_complexity)]
+    fn parse(line: &str) -> (f64, String, f64, Vec<(String, String, f64)>) {
+        let mut fields = line.split('\t');
+        let time = fields.next().unwrap().parse().unwrap();
+        let cwd = fields.next().unwrap().replace("\\t", "\t");
+        let total = fields.next().unwrap().parse().unwrap();
+        let segments = fields
+            .map(|field| {
+                let mut parts = field.rsplitn(3, ':');
+                let ms = parts.next().unwrap().parse().unwrap();
+                let outcome = parts.next().unwrap().to_string();
+                let name = parts.next().unwrap().to_string();
+                (name, outcome, ms)
+            })
+            .collect();
+        (time, cwd, total, segments)
+    }
+
+    #[test]
+    fn line_parses_back() {
+        let (time, cwd, total, segments) = parse(&trace().to_line());
+        assert_eq!(time, 1_700_000_000.25);
+        assert_eq!(cwd, "/home/me/tab\there");
+        assert_eq!(total, 12.345);
+        assert_eq!(
+            segments,
+            [
+                ("git".to_string(), "shown".to_string(), 1.5),
+                ("conda".to_string(), "not_applicable".to_string(), 0.02),
+                ("my:cmd".to_string(), "over_budget".to_string(), 100.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn appends_a_line_per_prompt() {
+        let dir = TempDir::new();
+        let path = dir.path().join("trace.log");
+        append(&path, &trace());
+        append(&path, &trace());
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(parse(lines[1]).3.len(), 3);
+
+        // Nowhere to write is no reason to fail the prompt
+        append(&dir.path().join("missing/trace.log"), &trace());
+    }
+}
@@ -0,0 +1,153 @@
+//! Measuring how much room text takes up on the terminal, and getting the text
+//! back out of styled output
+
+/// Where `Scanner` is within a string
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Text,
+    /// Just after an `ESC`
+    Escape,
+    /// In a CSI sequence, `ESC [` up to a final byte in `@`..=`~`
+    Csi,
+    /// In an OSC sequence, `ESC ]` up to a `BEL` or `ST`
+    Osc,
+    /// Just after an `ESC` inside an OSC sequence, which is `ST` if a `\`
+    /// follows
+    OscEscape,
+}
+
+/// Iterates over the chars of a string that are printed, skipping escape
+/// sequences and the markers shells put around them: bash's `\x01` and `\x02`,
+/// and zsh's `%{` and `%}`
+struct Scanner<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    state: State,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(s: &'a str) -> Scanner<'a> {
+        Scanner {
+            chars: s.chars().peekable(),
+            state: State::Text,
+        }
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        loop {
+            let c = self.chars.next()?;
+            self.state = match (self.state, c) {
+                (State::Text, '\x1b') => State::Escape,
+                (State::Text, '\x01') | (State::Text, '\x02') => State::Text,
+                (State::Text, '%') if matches!(self.chars.peek(), Some('{') | Some('}')) => {
+                    self.chars.next();
+                    State::Text
+                }
+                (State::Text, c) => return Some(c),
+                (State::Escape, '[') => State::Csi,
+                (State::Escape, ']') => State::Osc,
+                // Two character sequences like `ESC 7`
+                (State::Escape, _) => State::Text,
+                (State::Csi, '@'..='~') => State::Text,
+                (State::Csi, _) => State::Csi,
+                (State::Osc, '\x07') => State::Text,
+                (State::Osc, '\x1b') => State::OscEscape,
+                (State::Osc, _) => State::Osc,
+                (State::OscEscape, '\\') => State::Text,
+                (State::OscEscape, _) => State::Osc,
+            };
+        }
+    }
+}
+
+/// `s` without escape sequences or the shells' markers around them
+pub fn strip_ansi(s: &str) -> String {
+    Scanner::new(s).collect()
+}
+
+/// The emoji segments print that terminals draw two columns wide. Symbols like
+/// `⚙` and `✉` are left out, as they are drawn as narrow text without a
+/// variation selector
+const WIDE_GLYPHS: &[char] = &[
+    '🐳', '🐍', '🐘', '💎', '📦', '🔒', '⌛', '☕', '🦀', '⏰', '👥',
+];
+
+/// Columns `c` takes up on common terminals. Only the glyphs promptline prints
+/// itself are known, anything else counts as one column
+pub fn char_width(c: char) -> usize {
+    match c {
+        // Zero width joiner and variation selectors
+        '\u{200d}' | '\u{fe00}'..='\u{fe0f}' => 0,
+        c if WIDE_GLYPHS.contains(&c) => 2,
+        _ => 1,
+    }
+}
+
+/// Number of columns `s` takes up when printed, skipping escape sequences.
+/// Wide CJK characters and emoji other than promptline's own are undercounted,
+/// and combining characters overcounted
+pub fn visible_width(s: &str) -> usize {
+    Scanner::new(s).map(char_width).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(visible_width(""), 0);
+        assert_eq!(visible_width("~/work"), 6);
+        assert_eq!(strip_ansi("50% {done}"), "50% {done}");
+    }
+
+    #[test]
+    fn emoji_take_two_columns() {
+        assert_eq!(visible_width("🐍 base"), 7);
+        assert_eq!(visible_width("🐳 alpine 3.19"), 14);
+        assert_eq!(
+            visible_width("┌[\x1b[1m🐳\x1b[22m]-[\x1b[2m🐍 ml\x1b[22m]"),
+            13
+        );
+        // Drawn as narrow text
+        assert_eq!(visible_width("⚙ nightly"), 9);
+        assert_eq!(visible_width("✉"), 1);
+        assert_eq!(visible_width("☕\u{fe0f}"), 2);
+    }
+
+    #[test]
+    fn skips_csi_sequences() {
+        assert_eq!(visible_width("\x1b[1m\x1b[32mmain\x1b[39m\x1b[22m"), 4);
+        assert_eq!(strip_ansi("\x1b[38;5;208mx\x1b[0m"), "x");
+        assert_eq!(strip_ansi("a\x1b7b\x1b8c"), "abc");
+    }
+
+    #[test]
+    fn skips_osc_sequences() {
+        // Window title ended by BEL, hyperlink ended by ST
+        assert_eq!(strip_ansi("\x1b]0;title\x07prompt"), "prompt");
+        assert_eq!(
+            strip_ansi("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"),
+            "link"
+        );
+        assert_eq!(
+            visible_width("\x1b]8;;https://example.com\x07link\x1b]8;;\x07"),
+            4
+        );
+    }
+
+    #[test]
+    fn skips_shell_markers() {
+        assert_eq!(visible_width("\x01\x1b[1m\x02main\x01\x1b[22m\x02"), 4);
+        assert_eq!(strip_ansi("%{\x1b[1m%}main%{\x1b[22m%}"), "main");
+    }
+
+    #[test]
+    fn unterminated_sequence_hides_the_rest() {
+        assert_eq!(strip_ansi("a\x1b[12"), "a");
+        assert_eq!(strip_ansi("a\x1b]0;title"), "a");
+    }
+}
@@ -0,0 +1,28 @@
+//! Keeps the segment feature gates from rotting by building a reduced set
+
+use std::{path::Path, process::Command};
+
+#[test]
+fn reduced_feature_set_builds() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let status = Command::new(env!("CARGO"))
+        .args([
+            "check",
+            "--quiet",
+            "--no-default-features",
+            "--features",
+            "git,cwd,status",
+        ])
+        .current_dir(manifest_dir)
+        // Code only some gated segments use is dead without them
+        .env("RUSTFLAGS", "-Dwarnings")
+        // A separate target dir avoids waiting on the lock of the running build
+        .env(
+            "CARGO_TARGET_DIR",
+            manifest_dir.join("target/feature-check"),
+        )
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+}
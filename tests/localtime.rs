@@ -0,0 +1,26 @@
+//! Sets `$TZ` for the whole process, so it lives in its own test binary with a
+//! single test instead of racing the others
+
+#![cfg(all(unix, feature = "time"))]
+
+use promptline::localtime::LocalTime;
+
+#[test]
+fn follows_dst_in_fixed_zone() {
+    // US eastern time with its DST rules spelled out, so no tzdata is needed
+    std::env::set_var("TZ", "EST5EDT,M3.2.0,M11.1.0");
+
+    // 2024-01-15 17:30:00 UTC, standard time at UTC-5
+    let winter = LocalTime::from_timestamp(1_705_339_800).unwrap();
+    assert_eq!(winter.format("%Y-%m-%d %H:%M"), "2024-01-15 12:30");
+
+    // 2024-07-15 17:30:00 UTC, daylight time at UTC-4
+    let summer = LocalTime::from_timestamp(1_721_064_600).unwrap();
+    assert_eq!(summer.format("%Y-%m-%d %H:%M %p"), "2024-07-15 13:30 PM");
+
+    // The last second before and the first after the switch on 2024-03-10
+    let before = LocalTime::from_timestamp(1_710_053_999).unwrap();
+    assert_eq!(before.format("%H:%M:%S"), "01:59:59");
+    let after = LocalTime::from_timestamp(1_710_054_000).unwrap();
+    assert_eq!(after.format("%H:%M:%S"), "03:00:00");
+}
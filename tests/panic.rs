@@ -0,0 +1,48 @@
+//! A segment added from outside the crate that panics, as a bug in a library
+//! user's segment would, mustn't take the ones rendered next to it down
+
+use std::{collections::HashMap, sync::Arc};
+
+use promptline::{
+    args::Args,
+    config::Config,
+    runner::{self, Registry},
+    Context, DecoratedString, RenderedSegment, Segment, SegmentError,
+};
+
+struct Panics;
+
+impl Segment for Panics {
+    fn name(&self) -> &str {
+        "panics"
+    }
+
+    fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        panic!("segment bug")
+    }
+}
+
+struct Works;
+
+impl Segment for Works {
+    fn name(&self) -> &str {
+        "works"
+    }
+
+    fn render(&self, _ctx: &Context) -> Result<RenderedSegment, SegmentError> {
+        Ok(DecoratedString::new("fine".into()).into())
+    }
+}
+
+#[test]
+fn panicking_segment_fails_alone() {
+    let mut registry = Registry::new(vec![]);
+    registry.push(Arc::new(Panics));
+    registry.push(Arc::new(Works));
+    let ctx = Context::with_env(Args::default(), Config::default(), HashMap::new(), None);
+
+    let results = runner::render_all(registry.iter(), &Arc::new(ctx));
+    let error = results[0].0.as_ref().unwrap_err().to_string();
+    assert!(error.contains("panicked: segment bug"), "{}", error);
+    assert_eq!(results[1].0.as_ref().unwrap().to_plain(), "fine");
+}
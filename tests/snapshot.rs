@@ -0,0 +1,815 @@
+//! Checks the exact bytes promptline prints, so that refactors can't change the
+//! prompt by accident. Expects the default set of segments
+
+#![cfg(all(
+    unix,
+    feature = "time",
+    feature = "idle",
+    feature = "docker",
+    feature = "docker-context",
+    feature = "sandbox",
+    feature = "vm",
+    feature = "user",
+    feature = "users",
+    feature = "sudo",
+    feature = "hostname",
+    feature = "uptime",
+    feature = "systemd",
+    feature = "updates",
+    feature = "cwd",
+    feature = "shell",
+    feature = "shlvl",
+    feature = "status",
+    feature = "umask",
+    feature = "hg",
+    feature = "hg-drafts",
+    feature = "git",
+    feature = "unpushed",
+    feature = "repo-size",
+    feature = "vpn",
+    feature = "offline",
+    feature = "local-ip",
+    feature = "conda",
+    feature = "venv",
+    feature = "nix-shell",
+    feature = "nix-path",
+    feature = "guix-env",
+    feature = "rust-toolchain",
+    feature = "cargo",
+    feature = "java",
+    feature = "php",
+    feature = "ruby",
+    feature = "node",
+    feature = "bazel",
+    feature = "cmake",
+    feature = "env-watch",
+    feature = "locale",
+    feature = "mail",
+    feature = "cpu-temp",
+    feature = "vi-mode"
+))]
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+/// A scratch home directory with a config hiding the segments whose output
+/// depends on the machine the tests run on
+struct Sandbox {
+    home: PathBuf,
+    vars: Vec<(String, String)>,
+    removed_vars: Vec<String>,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Sandbox {
+        let home =
+            std::env::temp_dir().join(format!("promptline-snapshot-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+        fs::create_dir_all(home.join("work")).unwrap();
+
+        let mut config = String::new();
+        for segment in [
+            "user",
+            "docker",
+            "sandbox",
+            "nix_path",
+            "rust_toolchain",
+            "umask",
+        ] {
+            config += &format!("[segments.{segment}]\nonly_when_env = \"PROMPTLINE_NEVER_SET\"\n");
+        }
+        fs::write(home.join("config.toml"), config).unwrap();
+
+        Sandbox {
+            home,
+            vars: vec![],
+            removed_vars: vec![],
+        }
+    }
+
+    /// Adds `toml` to the end of the config file
+    fn config(self, toml: &str) -> Sandbox {
+        let path = self.home.join("config.toml");
+        let config = fs::read_to_string(&path).unwrap() + toml;
+        fs::write(path, config).unwrap();
+        self
+    }
+
+    fn work_dir(&self) -> PathBuf {
+        self.home.join("work")
+    }
+
+    /// Creates `relative` under the work dir, with its parent directories
+    fn write(&self, relative: &str, contents: impl AsRef<[u8]>) {
+        let path = self.work_dir().join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn var(mut self, name: &str, value: &str) -> Sandbox {
+        self.vars.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn without_var(mut self, name: &str) -> Sandbox {
+        self.removed_vars.push(name.to_string());
+        self
+    }
+
+    fn run(&self, args: &[&str]) -> Vec<u8> {
+        let output = self.output(args);
+        assert!(output.status.success(), "{:?}", output);
+        output.stdout
+    }
+
+    /// Runs promptline without expecting it to succeed
+    fn output(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_promptline"));
+        command
+            .args(args)
+            .current_dir(self.work_dir())
+            .env_clear()
+            .env("HOME", &self.home)
+            .env("PWD", self.work_dir())
+            .env("SHELL", "/bin/bash")
+            .env("PROMPTLINE_CONFIG", self.home.join("config.toml"))
+            .env("PROMPTLINE_FAKE_TIME", "12:34")
+            // The tests, rather than bash, are what runs promptline
+            .env("PROMPTLINE_SHOW_SHELL", "1")
+            .envs(self.vars.iter().map(|(k, v)| (k, v)));
+
+        for name in &self.removed_vars {
+            command.env_remove(name);
+        }
+
+        command
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.home);
+    }
+}
+
+fn assert_output(actual: &[u8], expected: &str) {
+    assert_eq!(String::from_utf8_lossy(actual), expected);
+}
+
+#[test]
+fn colored_prompt() {
+    let sandbox = Sandbox::new("colored");
+    assert_output(
+        &sandbox.run(&["0"]),
+        "┌[\x1b[36m\x1b[1m12:34\x1b[22m\x1b[39m]-[\x1b[1m\x1b[34m~/work\x1b[39m\x1b[22m]-[\x1b[1mbash\x1b[22m]-[\x1b[1m\x1b[32m0\x1b[39m\x1b[22m]\n└> \x1b[0m",
+    );
+}
+
+#[test]
+fn plain_prompt() {
+    let sandbox = Sandbox::new("plain");
+    assert_output(
+        &sandbox.run(&["1", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[1]\n└> ",
+    );
+}
+
+#[test]
+fn git_repo() {
+    let sandbox = Sandbox::new("git");
+    sandbox.write(".git/HEAD", "ref: refs/heads/main\n");
+    sandbox.write(
+        ".git/refs/heads/main",
+        "0123456789abcdef0123456789abcdef01234567\n",
+    );
+    assert_output(
+        &sandbox.run(&["0"]),
+        "┌[\x1b[36m\x1b[1m12:34\x1b[22m\x1b[39m]-[\x1b[1m\x1b[34m~/work\x1b[39m\x1b[22m]-[\x1b[1mbash\x1b[22m]-[\x1b[1m\x1b[32m0\x1b[39m\x1b[22m]-[\x1b[1m\x1b[32m\u{e0a0} main 0123456789abcd\x1b[39m\x1b[22m]\n└> \x1b[0m",
+    );
+}
+
+#[test]
+fn symlink_into_git_repo() {
+    let sandbox = Sandbox::new("git-symlink");
+    let repo = sandbox.home.join("repo");
+    fs::create_dir_all(repo.join(".git/refs/heads")).unwrap();
+    fs::create_dir_all(repo.join("src")).unwrap();
+    fs::write(repo.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+    fs::write(
+        repo.join(".git/refs/heads/main"),
+        "0123456789abcdef0123456789abcdef01234567\n",
+    )
+    .unwrap();
+    let link = sandbox.work_dir().join("link");
+    std::os::unix::fs::symlink(repo.join("src"), &link).unwrap();
+
+    let output = sandbox
+        .command(&["0", "--no-color"])
+        .current_dir(&link)
+        .env("PWD", &link)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_output(
+        &output.stdout,
+        "┌[12:34]-[~/work/link]-[bash]-[0]-[\u{e0a0} main 0123456789abcd]\n└> ",
+    );
+}
+
+#[test]
+fn git_detached_head() {
+    let sandbox = Sandbox::new("git-detached");
+    sandbox.write(".git/HEAD", "fedcba9876543210fedcba9876543210fedcba98\n");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]-[\u{e0a0} fedcba98765432]\n└> ",
+    );
+}
+
+#[test]
+fn ascii_labels() {
+    let sandbox = Sandbox::new("ascii-labels");
+    sandbox.write(".promptline.toml", "icons = \"ascii\"\n");
+    sandbox.write(".git/HEAD", "fedcba9876543210fedcba9876543210fedcba98\n");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]-[git: fedcba98765432]\n└> ",
+    );
+
+    let sandbox = sandbox.config("[segments.git]\nicon = \"\"\n");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]-[fedcba98765432]\n└> ",
+    );
+}
+
+#[test]
+fn ascii_icons_everywhere() {
+    let sandbox = Sandbox::new("ascii-icons")
+        .var("CONDA_DEFAULT_ENV", "science")
+        .var("DOCKER_CONTEXT", "remote")
+        .config("[segments.docker_context]\nenabled = true\n")
+        .config("[segments.mail]\nenabled = true\n");
+    let maildir = sandbox.work_dir().join("Maildir");
+    let sandbox = sandbox
+        .var("VIRTUAL_ENV", "/home/ada/envs/tools")
+        .var("MAIL", &maildir.to_string_lossy());
+    sandbox.write(".promptline.toml", "icons = \"ascii\"\n");
+    sandbox.write(".git/HEAD", "fedcba9876543210fedcba9876543210fedcba98\n");
+    sandbox.write("composer.json", "{\n  \"name\": \"acme/shop\"\n}\n");
+    sandbox.write("Maildir/new/1", "Subject: hi\n");
+
+    let stdout = String::from_utf8(sandbox.run(&["0", "--no-color"])).unwrap();
+    // Only the frame, which the icon mode doesn't cover, is left unicode
+    let segments = stdout.replace(['┌', '├', '└'], "");
+    assert!(segments.is_ascii(), "{}", stdout);
+    assert_output(
+        stdout.as_bytes(),
+        "┌[12:34]-[docker-> remote]-[~/work]-[bash]-[0]-[git: fedcba98765432]-[conda: science]-[venv: tools]-[php: acme/shop]-[mail 1]\n└> ",
+    );
+}
+
+#[test]
+fn zsh_ascii_last_line() {
+    let sandbox = Sandbox::new("zsh-ascii-last-line");
+    sandbox.write(
+        ".promptline.toml",
+        "prompt_char = \"❯\"\nzsh_last_line_frame = \"`-\"\n",
+    );
+    let output = sandbox.run(&["0", "--shell", "zsh"]);
+    let stdout = String::from_utf8(output).unwrap();
+    let (first_line, last_line) = stdout.split_once('\n').unwrap();
+    assert!(first_line.starts_with("┌["), "{}", first_line);
+    assert_eq!(last_line, "`-> %{\x1b[0m%}");
+
+    assert_output(
+        &sandbox.run(&["0", "--shell", "bash", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]\n└❯ ",
+    );
+}
+
+#[test]
+fn one_configured_line() {
+    let sandbox = Sandbox::new("one-line");
+    sandbox.write(".promptline.toml", "lines = [[\"status\", \"cwd\"]]\n");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[0]-[~/work]-[12:34]-[bash]\n└> ",
+    );
+}
+
+#[test]
+fn two_configured_lines() {
+    let sandbox = Sandbox::new("two-lines");
+    sandbox.write(
+        ".promptline.toml",
+        "lines = [[\"time\", \"user\", \"hostname\"], [\"cwd\", \"git\", \"conda\"]]\n",
+    );
+    // user, hostname, git and conda don't apply and collapse, the shell and
+    // status go on the last line
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]\n├[~/work]-[bash]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn three_configured_lines() {
+    let sandbox = Sandbox::new("three-lines");
+    sandbox.write(
+        ".promptline.toml",
+        "lines = [[\"time\"], [\"shell\"], [\"cwd\"]]\n",
+    );
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]\n├[bash]\n├[~/work]-[0]\n└> ",
+    );
+    // A line whose segments all collapse takes no room
+    sandbox.write(
+        ".promptline.toml",
+        "lines = [[\"time\"], [\"git\"], [\"cwd\"]]\n",
+    );
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]\n├[~/work]-[bash]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn hg_repo() {
+    let sandbox = Sandbox::new("hg");
+    sandbox.write(".hg/branch", "default\n");
+    sandbox.write(
+        ".hg/dirstate",
+        [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef],
+    );
+    assert_output(
+        &sandbox.run(&["0"]),
+        "┌[\x1b[36m\x1b[1m12:34\x1b[22m\x1b[39m]-[\x1b[1m\x1b[34m~/work\x1b[39m\x1b[22m]-[\x1b[1mbash\x1b[22m]-[\x1b[1m\x1b[32m0\x1b[39m\x1b[22m]-[\x1b[1m\x1b[32m☿ default 0123456789ab\x1b[39m\x1b[22m]\n└> \x1b[0m",
+    );
+}
+
+#[test]
+fn conda_env() {
+    let sandbox = Sandbox::new("conda").var("CONDA_DEFAULT_ENV", "science");
+    assert_output(
+        &sandbox.run(&["0"]),
+        "┌[\x1b[36m\x1b[1m12:34\x1b[22m\x1b[39m]-[\x1b[1m\x1b[34m~/work\x1b[39m\x1b[22m]-[\x1b[1mbash\x1b[22m]-[\x1b[1m\x1b[32m0\x1b[39m\x1b[22m]-[\x1b[1m🐍 science\x1b[22m]\n└> \x1b[0m",
+    );
+}
+
+#[test]
+fn conda_base_hidden() {
+    let sandbox = Sandbox::new("conda-base").var("CONDA_DEFAULT_ENV", "base");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn nix_shell() {
+    let sandbox = Sandbox::new("nix")
+        .var("IN_NIX_SHELL", "impure")
+        .var("name", "dev");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]-[nix: dev]\n└> ",
+    );
+}
+
+#[test]
+fn ssh_shows_hostname_only_over_ssh() {
+    let sandbox = Sandbox::new("ssh").var("SSH_CONNECTION", "10.0.0.1 22 10.0.0.2 22");
+    let output = String::from_utf8(sandbox.run(&["0", "--no-color"])).unwrap();
+    let hostname = nix::unistd::gethostname(&mut [0u8; 256])
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .split('.')
+        .next()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        output,
+        format!("┌[12:34]-[{hostname}]-[~/work]-[bash]-[0]\n└> ")
+    );
+}
+
+#[test]
+fn bash_wraps_escapes() {
+    let sandbox = Sandbox::new("bash");
+    assert_output(
+        &sandbox.run(&["0", "--shell", "bash", "--segment", "status"]),
+        "\x01\x1b[1m\x02\x01\x1b[32m\x020\x01\x1b[39m\x02\x01\x1b[22m\x02\n",
+    );
+}
+
+#[test]
+fn powershell_has_no_wrappers() {
+    let sandbox = Sandbox::new("powershell");
+    assert_output(
+        &sandbox.run(&["0", "--shell", "powershell", "--segment", "status"]),
+        "\x1b[1m\x1b[32m0\x1b[39m\x1b[22m\n",
+    );
+}
+
+#[test]
+fn shell_hidden_in_login_shell() {
+    // The test binary stands in for the shell that runs promptline
+    let comm = fs::read_to_string("/proc/self/comm").unwrap();
+    let sandbox = Sandbox::new("login-shell")
+        .var("SHELL", &format!("/bin/{}", comm.trim_end()))
+        .without_var("PROMPTLINE_SHOW_SHELL");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn shell_depth_follows_shell_name() {
+    let sandbox = Sandbox::new("shlvl")
+        .config("[segments.shlvl]\nenabled = true\n")
+        .var("SHLVL", "3");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash ⇲3]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn shell_depth_off_by_default() {
+    let sandbox = Sandbox::new("shlvl-off").var("SHLVL", "3");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn vi_normal_mode() {
+    let sandbox = Sandbox::new("vi-normal");
+    assert_output(
+        &sandbox.run(&["0", "--vi-mode", "vicmd", "--segment", "vi_mode"]),
+        "\x1b[1m\x1b[33mN\x1b[39m\x1b[22m\n",
+    );
+}
+
+#[test]
+fn vi_unknown_mode_hidden() {
+    let sandbox = Sandbox::new("vi-unknown");
+    assert_output(
+        &sandbox.run(&["0", "--no-color", "--vi-mode", "visual"]),
+        "┌[12:34]-[~/work]-[bash]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn init_prefetches_on_directory_change() {
+    let sandbox = Sandbox::new("init-prefetch");
+    for (shell, hook) in [
+        ("bash", "[[ $PWD != \"$_promptline_pwd\" ]]"),
+        ("zsh", "chpwd_functions+=(_promptline_chpwd)"),
+        ("fish", "--on-variable PWD"),
+    ] {
+        let snippet = String::from_utf8(sandbox.run(&["init", shell])).unwrap();
+        assert!(snippet.contains(hook), "{}", snippet);
+        assert!(snippet.contains("promptline prefetch"), "{}", snippet);
+    }
+}
+
+#[test]
+fn prefetch_outside_of_a_repo() {
+    let sandbox = Sandbox::new("prefetch-no-repo");
+    sandbox.write(".promptline.toml", "prefetch = true\n");
+    let runtime_dir = sandbox.home.join("runtime");
+    let sandbox = sandbox.var("XDG_RUNTIME_DIR", runtime_dir.to_str().unwrap());
+    assert_output(&sandbox.run(&["prefetch"]), "");
+    // Only the roots looked up on the way are cached
+    let cached: Vec<_> = fs::read_dir(runtime_dir.join("promptline"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert!(
+        cached.iter().all(|name| name.starts_with("roots-")),
+        "{:?}",
+        cached
+    );
+}
+
+#[test]
+fn zsh_init_redraws_on_keymap_change() {
+    let sandbox = Sandbox::new("init-zsh");
+    let snippet = String::from_utf8(sandbox.run(&["init", "zsh"])).unwrap();
+    assert!(
+        snippet.contains("--vi-mode \"${KEYMAP:-main}\""),
+        "{}",
+        snippet
+    );
+    assert!(snippet.contains("zle -N zle-keymap-select"), "{}", snippet);
+}
+
+#[test]
+fn branch_name_cannot_restyle_prompt() {
+    let sandbox = Sandbox::new("evil-branch");
+    sandbox.write(".git/HEAD", "ref: refs/heads/evil\x1b[41m\n");
+    sandbox.write(
+        ".git/refs/heads/evil\x1b[41m",
+        "0123456789abcdef0123456789abcdef01234567\n",
+    );
+    assert_output(
+        &sandbox.run(&["0"]),
+        "┌[\x1b[36m\x1b[1m12:34\x1b[22m\x1b[39m]-[\x1b[1m\x1b[34m~/work\x1b[39m\x1b[22m]-[\x1b[1mbash\x1b[22m]-[\x1b[1m\x1b[32m0\x1b[39m\x1b[22m]-[\x1b[1m\x1b[32m\u{e0a0} evil␛[41m 0123456789abcd\x1b[39m\x1b[22m]\n└> \x1b[0m",
+    );
+}
+
+#[test]
+fn embedded_branch_name_is_escaped() {
+    let sandbox = Sandbox::new("embedded-branch");
+    sandbox.write(".git/HEAD", "ref: refs/heads/fix$PATH!`id`\n");
+    sandbox.write(
+        ".git/refs/heads/fix$PATH!`id`",
+        "0123456789abcdef0123456789abcdef01234567\n",
+    );
+    assert_output(
+        &sandbox.run(&["0", "--shell", "bash", "--embedded", "--segment", "git"]),
+        "\x01\x1b[1m\x02\x01\x1b[32m\x02\u{e0a0} fix\\\\$PATH\\041\\\\`id\\\\` 0123456789abcd\x01\x1b[39m\x02\x01\x1b[22m\x02\n",
+    );
+    assert_output(
+        &sandbox.run(&[
+            "0",
+            "--embedded",
+            "--shell",
+            "zsh",
+            "--segment",
+            "git",
+            "--no-color",
+        ]),
+        "\u{e0a0} fix\\$PATH!\\`id\\` 0123456789abcd\n",
+    );
+}
+
+#[test]
+fn zsh_percent_in_cwd_is_escaped() {
+    let sandbox = Sandbox::new("zsh-percent");
+    sandbox.write("a%Bb/.promptline.toml", "prompt_char = \"%#\"\n");
+    let dir = sandbox.work_dir().join("a%Bb");
+    let output = sandbox
+        .command(&["0", "--shell", "zsh", "--no-color"])
+        .current_dir(&dir)
+        .env("PWD", &dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_output(&output.stdout, "┌[12:34]-[~/work/a%%Bb]-[bash]-[0]\n└%%# ");
+}
+
+#[test]
+fn conda_env_cannot_break_frame() {
+    let sandbox = Sandbox::new("conda-newline").var("CONDA_DEFAULT_ENV", "sci\nence\x07");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]-[🐍 sci␊ence␇]\n└> ",
+    );
+}
+
+#[test]
+fn narrow_terminal_drops_time_and_shell() {
+    let sandbox = Sandbox::new("narrow").var("COLUMNS", "19");
+    assert_output(&sandbox.run(&["0", "--no-color"]), "┌[~/work]-[0]\n└> ");
+}
+
+#[test]
+fn slow_phase_prints_only_expensive_segments() {
+    let sandbox = Sandbox::new("slow");
+    sandbox.write(".git/HEAD", "ref: refs/heads/main\n");
+    sandbox.write(
+        ".git/refs/heads/main",
+        "0123456789abcdef0123456789abcdef01234567\n",
+    );
+    assert_output(
+        &sandbox.run(&["0", "--no-color", "--slow"]),
+        "\u{e0a0} main 0123456789abcd",
+    );
+}
+
+#[test]
+fn fast_phase_appends_slow_result() {
+    let sandbox = Sandbox::new("fast");
+    sandbox.write(".git/HEAD", "ref: refs/heads/main\n");
+    sandbox.write(
+        ".git/refs/heads/main",
+        "0123456789abcdef0123456789abcdef01234567\n",
+    );
+    let full = sandbox.run(&["0", "--no-color"]);
+    assert_output(
+        &sandbox.run(&["0", "--no-color", "--fast", "--async-result", ""]),
+        "┌[12:34]-[~/work]-[bash]-[0]\n└> ",
+    );
+    assert_output(
+        &sandbox.run(&[
+            "0",
+            "--no-color",
+            "--fast",
+            "--async-result",
+            "\u{e0a0} main 0123456789abcd",
+        ]),
+        &String::from_utf8(full).unwrap(),
+    );
+}
+
+#[test]
+fn transient_prompt() {
+    let sandbox = Sandbox::new("transient");
+    assert_output(
+        &sandbox.run(&["--transient", "1", "--shell", "zsh"]),
+        "%{\x1b[1m%}%{\x1b[31m%}>%{\x1b[39m%}%{\x1b[22m%} %{\x1b[0m%}",
+    );
+
+    let snippet = String::from_utf8(sandbox.run(&["init", "zsh"])).unwrap();
+    assert!(snippet.contains("zle -N accept-line"), "{}", snippet);
+}
+
+#[test]
+fn local_config_overrides_global() {
+    let sandbox = Sandbox::new("local-config").config("[segments.shell]\nenabled = false\n");
+    sandbox.write(
+        ".promptline.toml",
+        "prompt_char = \"$\"\n[segments.time]\nenabled = false\n",
+    );
+    assert_output(&sandbox.run(&["0", "--no-color"]), "┌[~/work]-[0]\n└$ ");
+}
+
+#[test]
+fn pipeline_statuses() {
+    let sandbox = Sandbox::new("pipestatus");
+    assert_output(
+        &sandbox.run(&["0 141", "--segment", "status"]),
+        "\x1b[1m\x1b[32m0\x1b[39m\x1b[22m|\x1b[1m\x1b[31m141\x1b[39m\x1b[22m\n",
+    );
+}
+
+#[test]
+fn static_markers() {
+    let sandbox = Sandbox::new("static").config(
+        "[[static]]\ntext = \"⚠ PROD\"\nposition = \"first\"\n\
+         [[static]]\nname = \"team\"\ntext = \"infra\"\nposition = \"after=cwd\"\n",
+    );
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[⚠ PROD]-[12:34]-[~/work]-[infra]-[bash]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn profile_from_env() {
+    let sandbox = Sandbox::new("profile")
+        .config("[profiles.demo.segments.time]\nenabled = false\n")
+        .var("PROMPTLINE_PROFILE", "demo");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[~/work]-[bash]-[0]\n└> ",
+    );
+
+    let sandbox = sandbox.var("PROMPTLINE_PROFILE", "talk");
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[bash]-[0]\n└> ",
+    );
+}
+
+#[test]
+fn broken_config_marker() {
+    let sandbox = Sandbox::new("broken-config").config("[segments.git\n");
+    assert_output(
+        &sandbox.run(&["0", "--segment", "config_error"]),
+        "\x1b[33mcfg!\x1b[39m\n",
+    );
+}
+
+#[test]
+fn preview_uses_sample_data() {
+    let sandbox = Sandbox::new("preview")
+        .config("[profiles.demo.segments.time]\nenabled = false\n")
+        .var("CONDA_DEFAULT_ENV", "real");
+    assert_output(
+        &sandbox.run(&["preview", "--no-color"]),
+        "┌[12:34]-[devbox]-[~/src/shop]-[zsh]-[1]-[\u{e0a0} foo 3f9a1c2e7b4d5a]-[🐍 ml]-[nix: shop-env]-[💎 3.2.2]\n└> \n",
+    );
+    assert_output(
+        &sandbox.run(&["preview", "--no-color", "--profile", "demo"]),
+        "┌[devbox]-[~/src/shop]-[zsh]-[1]-[\u{e0a0} foo 3f9a1c2e7b4d5a]-[🐍 ml]-[nix: shop-env]-[💎 3.2.2]\n└> \n",
+    );
+}
+
+#[test]
+fn unknown_flag_still_prints_a_prompt() {
+    let sandbox = Sandbox::new("unknown-flag");
+    let output = sandbox.output(&["0", "--rigth"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_output(&output.stdout, "└> ");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "promptline: unknown flag --rigth, see promptline --help\n"
+    );
+}
+
+#[test]
+fn bad_value_still_prints_a_prompt() {
+    let sandbox = Sandbox::new("bad-value");
+    for (args, error) in [
+        (
+            &["0", "--shell", "fish"][..],
+            "invalid value fish for --shell",
+        ),
+        (&["0", "--segment"], "--segment requires a value"),
+    ] {
+        let output = sandbox.output(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert_output(&output.stdout, "└> ");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            format!("promptline: {error}, see promptline --help\n")
+        );
+    }
+}
+
+#[test]
+fn help_and_version() {
+    let sandbox = Sandbox::new("help");
+    let help = String::from_utf8(sandbox.run(&["0", "--help", "--rigth"])).unwrap();
+    assert!(help.starts_with("Usage: promptline"), "{}", help);
+    assert_output(
+        &sandbox.run(&["-V"]),
+        concat!("promptline ", env!("CARGO_PKG_VERSION"), "\n"),
+    );
+}
+
+#[test]
+fn completions_offer_custom_segments() {
+    let sandbox =
+        Sandbox::new("completions").config("[[custom]]\nname = \"my_clock\"\ncommand = \"date\"\n");
+    for shell in ["bash", "zsh", "fish"] {
+        let script = String::from_utf8(sandbox.run(&["completions", shell])).unwrap();
+        assert!(script.contains("my_clock"), "{}", script);
+        assert!(script.contains("check-config"), "{}", script);
+    }
+
+    let output = sandbox.output(&["completions", "tcsh"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn closed_stdout_exits_with_3() {
+    use std::os::unix::io::FromRawFd;
+
+    let sandbox = Sandbox::new("closed-stdout");
+    for args in [&["0"][..], &["--list-segments"], &["explain"]] {
+        // Like a shell that has stopped reading, the read end is gone before
+        // promptline writes anything
+        let (read, write) = nix::unistd::pipe().unwrap();
+        nix::unistd::close(read).unwrap();
+        let output = sandbox
+            .command(args)
+            .stdout(unsafe { Stdio::from_raw_fd(write) })
+            .stderr(Stdio::piped())
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(3), "{:?}", args);
+        assert_output(&output.stderr, "");
+    }
+}
+
+#[test]
+fn disable_everything() {
+    let sandbox = Sandbox::new("disable")
+        .config("prompt_char = \n")
+        .var("PROMPTLINE_DISABLE", "1");
+    sandbox.write(".git/HEAD", "ref: refs/heads/main\n");
+    assert_output(&sandbox.run(&["0"]), "$ ");
+    assert_output(&sandbox.run(&["--transient", "1", "--shell", "zsh"]), "$ ");
+    assert_output(&sandbox.run(&["0", "--slow"]), "");
+}
+
+#[test]
+fn disable_some_segments() {
+    let sandbox = Sandbox::new("disable-some").var("PROMPTLINE_DISABLE", "git, shell,kube");
+    sandbox.write(".git/HEAD", "ref: refs/heads/main\n");
+    sandbox.write(
+        ".git/refs/heads/main",
+        "0123456789abcdef0123456789abcdef01234567\n",
+    );
+    assert_output(
+        &sandbox.run(&["0", "--no-color"]),
+        "┌[12:34]-[~/work]-[0]\n└> ",
+    );
+}